@@ -0,0 +1,87 @@
+//! Compares walking the boxed tree built by the parser with scanning its arena form.
+//!
+//! The arena form is lowered from the tree after parsing, so it only ever adds to the cost of a
+//! parse; what it can save is time spent walking a module that has already been read. Both forms
+//! are built before timing starts, and only the walks are timed.
+//!
+//! The input is a generated data module like the ones that motivated the arena: many constant
+//! declarations of nested records and lists, with a few functions over them.
+
+use std::ops::ControlFlow;
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use seglisp::parse::ParseNode;
+use serendipity_parser::{
+    arena::{ArenaModule, Expr},
+    incremental::parse_module,
+    visit::{self, Visit},
+    Expression,
+};
+
+/// A module of roughly `lines` lines.
+fn data_module(lines: usize) -> String {
+    let mut source = String::new();
+
+    for idx in 0..lines / 5 {
+        source.push_str(&format!(
+            "const row{idx} = {{\n  id: {idx},\n  label: \"row {idx}\",\n  \
+             tags: [\"a\", \"b\", row{prev}.id + 1]\n}};\n",
+            prev = idx.saturating_sub(1),
+        ));
+
+        if idx % 50 == 0 {
+            source.push_str(&format!(
+                "fn pick{idx}(r) -> if r.id > {idx} then r.label else row{idx}.label;\n"
+            ));
+        }
+    }
+
+    source.push_str("main pick0(row0);\n");
+    source
+}
+
+struct NameCounter(usize);
+
+impl<'ast> Visit<'ast> for NameCounter {
+    fn visit_expression(&mut self, node: &'ast ParseNode<Expression<'ast>>) -> ControlFlow<()> {
+        if let Expression::Name(_) = node.value {
+            self.0 += 1;
+        }
+        visit::walk_expression(self, node)
+    }
+}
+
+fn walk(c: &mut Criterion) {
+    let mut group = c.benchmark_group("walk");
+
+    for lines in [1_000, 10_000, 50_000] {
+        let source = data_module(lines);
+        let parsed = parse_module(&source);
+        let module = parsed.module.as_ref().unwrap();
+        let (arena, _) = ArenaModule::lower(module);
+
+        group.throughput(Throughput::Bytes(source.len() as u64));
+
+        group.bench_with_input(BenchmarkId::new("tree", lines), module, |b, module| {
+            b.iter(|| {
+                let mut counter = NameCounter(0);
+                let _ = counter.visit_module(black_box(module));
+                counter.0
+            })
+        });
+
+        group.bench_with_input(BenchmarkId::new("arena", lines), &arena, |b, arena| {
+            b.iter(|| {
+                black_box(arena)
+                    .exprs()
+                    .filter(|(_, expr)| matches!(expr, Expr::Name(_)))
+                    .count()
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, walk);
+criterion_main!(benches);
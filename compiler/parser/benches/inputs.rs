@@ -0,0 +1,143 @@
+//! Measures how reading and parsing a module scales, from small real programs to pathological
+//! inputs: deep nesting, long operator chains, and huge list literals.
+//!
+//! Criterion reports time and throughput. Peak heap use is not something it measures, so it is
+//! recorded by a counting allocator and printed once for each input before it is timed.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use serendipity_parser::incremental::{parse_module, MAX_NESTING};
+
+struct Counting;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let now = CURRENT.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(now, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+/// The most heap that parsing `source` has in use at once, beyond what was in use before.
+fn peak_memory(source: &str) -> usize {
+    let base = CURRENT.load(Ordering::Relaxed);
+    PEAK.store(base, Ordering::Relaxed);
+    drop(black_box(parse_module(source)));
+    PEAK.load(Ordering::Relaxed) - base
+}
+
+fn report(name: &str, source: &str) {
+    let peak = peak_memory(source);
+    eprintln!(
+        "{name}: {} bytes of source, {peak} bytes peak heap ({:.1}x)",
+        source.len(),
+        peak as f64 / source.len() as f64
+    );
+}
+
+/// A data module of roughly `lines` lines, like the one in the `parse` benchmark.
+fn data_module(lines: usize) -> String {
+    let mut source = String::new();
+
+    for idx in 0..lines / 5 {
+        source.push_str(&format!(
+            "const row{idx} = {{\n  id: {idx},\n  label: \"row {idx}\",\n  \
+             tags: [\"a\", \"b\", row{prev}.id + 1]\n}};\n",
+            prev = idx.saturating_sub(1),
+        ));
+    }
+
+    source.push_str("main row0;\n");
+    source
+}
+
+/// `main` of an expression nested `depth` parentheses deep.
+fn nested(depth: usize) -> String {
+    format!("main {}1{};\n", "(".repeat(depth), ")".repeat(depth))
+}
+
+/// `main` of a chain of `length` additions.
+fn chain(length: usize) -> String {
+    format!("main 1{};\n", " + 1".repeat(length))
+}
+
+/// `main` of a list literal with `length` elements.
+fn list(length: usize) -> String {
+    let elements: Vec<_> = (0..length).map(|idx| idx.to_string()).collect();
+    format!("main [{}];\n", elements.join(", "))
+}
+
+fn programs(c: &mut Criterion) {
+    let mut group = c.benchmark_group("programs");
+
+    let inputs = [
+        (
+            "hello_world",
+            include_str!("../../../cli/slipr/examples/hello_world.sdp").to_string(),
+        ),
+        (
+            "core",
+            include_str!("../../../lib/core/lib.sdp").to_string(),
+        ),
+        ("data_1000", data_module(1_000)),
+        ("data_10000", data_module(10_000)),
+    ];
+
+    for (name, source) in &inputs {
+        report(&format!("programs/{name}"), source);
+        group.throughput(Throughput::Bytes(source.len() as u64));
+        group.bench_with_input(*name, source, |b, source| {
+            b.iter(|| parse_module(black_box(source)))
+        });
+    }
+
+    group.finish();
+}
+
+fn pathological(c: &mut Criterion) {
+    type Generator = fn(usize) -> String;
+
+    // Nesting stops at the limit: each level of parentheses still recurses through the reader, so
+    // anything deeper is refused before it is read.
+    let cases: [(&str, Generator, &[usize]); 3] = [
+        ("nested", nested, &[8, 32, MAX_NESTING]),
+        ("chain", chain, &[100, 1_000, 10_000]),
+        ("list", list, &[1_000, 10_000, 100_000]),
+    ];
+
+    for (name, generate, sizes) in cases {
+        let mut group = c.benchmark_group(name);
+
+        for &size in sizes {
+            let source = generate(size);
+            report(&format!("{name}/{size}"), &source);
+            group.throughput(Throughput::Bytes(source.len() as u64));
+            group.bench_with_input(BenchmarkId::from_parameter(size), &source, |b, source| {
+                b.iter(|| parse_module(black_box(source)))
+            });
+        }
+
+        group.finish();
+    }
+}
+
+criterion_group!(benches, programs, pathological);
+criterion_main!(benches);
@@ -0,0 +1,14 @@
+//! Feeds arbitrary input through everything `parse_bytes` does: reading, parsing, export checks,
+//! and lints. Run it from `compiler/parser` with `cargo +nightly fuzz run parse_bytes`; the
+//! example programs make a good seed corpus.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    // `parse_bytes` is only defined for UTF-8, and says so by panicking.
+    if std::str::from_utf8(data).is_ok() {
+        serendipity_parser::with_checked_module(data, |_| ());
+    }
+});
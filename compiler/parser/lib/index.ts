@@ -1,15 +1,32 @@
 export * from "./index.gen";
+export { IncrementalParser, NodeIdTracker } from "../dist-bundler/serendipity_parser";
 import * as bg from "../dist-bundler/serendipity_parser";
 
-import { Module, ParsedDocument, ParseNode } from "./index.gen";
+import {
+  EditOutcome,
+  ExportTable,
+  FixableDiagnostic,
+  HoleCompletions,
+  Module,
+  NodeInfo,
+  ParsedDocument,
+  ParseNode,
+} from "./index.gen";
 
+/**
+ * Parses a module. The diagnostics include those of the export checks and, unless `lints` is
+ * false, the findings of the built-in lints at their default levels; use `lint` to run them with a
+ * project's configuration instead. Empty tuples are reported either way, as they were before the
+ * lints existed.
+ */
 export function parse(
-  input: string | Uint8Array
+  input: string | Uint8Array,
+  { lints = true }: { lints?: boolean } = {}
 ): ParsedDocument<ParseNode<Module>> {
   if (typeof input === "string") {
-    return bg.parse_bytes(new TextEncoder().encode(input));
+    return bg.parse_bytes(new TextEncoder().encode(input), lints);
   } else if (input instanceof Uint8Array) {
-    return bg.parse_bytes(input);
+    return bg.parse_bytes(input, lints);
   } else {
     throw new Error("unsupported input, try 'Uint8Array' or 'string'");
   }
@@ -24,3 +41,200 @@ export function printParse(input: string | Uint8Array): string {
     throw new Error("unsupported input, try 'Uint8Array' or 'string'");
   }
 }
+
+export function moduleExports(input: string | Uint8Array): ExportTable | null {
+  return bg.module_exports(toBytes(input));
+}
+
+function toBytes(input: string | Uint8Array): Uint8Array {
+  if (typeof input === "string") {
+    return new TextEncoder().encode(input);
+  } else if (input instanceof Uint8Array) {
+    return input;
+  } else {
+    throw new Error("unsupported input, try 'Uint8Array' or 'string'");
+  }
+}
+
+/**
+ * Returns the chain of nodes enclosing a position, outermost first.
+ *
+ * @param input the source text of the module
+ * @param position a byte offset, or a zero-based line and a column in UTF-16 code units, as
+ *   Monaco reports it
+ */
+export function nodesAt(
+  input: string | Uint8Array,
+  position: number | { line: number; column: number }
+): NodeInfo[] | null {
+  const bytes = toBytes(input);
+
+  if (typeof position === "number") {
+    return bg.nodes_at(bytes, position);
+  } else {
+    return bg.nodes_at_position(bytes, position.line, position.column);
+  }
+}
+
+/**
+ * Like `nodesAt`, but each node carries its stable `id` from `tracker`. `input` must be the text
+ * last passed to `tracker.update`.
+ */
+export function trackedNodesAt(
+  tracker: bg.NodeIdTracker,
+  input: string | Uint8Array,
+  offset: number
+): NodeInfo[] | null {
+  return tracker.nodes_at(toBytes(input), offset);
+}
+
+/**
+ * Returns the range of the node at `path` (e.g. `declarations[0].body`), or null if there is no
+ * such node.
+ */
+export function nodeRange(input: string | Uint8Array, path: string): ParseNode<unknown>["range"] | null {
+  return bg.node_range(toBytes(input), path);
+}
+
+/**
+ * Returns ranked candidates to fill the hole (`@`) at a position, or null if there is no hole there.
+ *
+ * Each candidate has a `snippet` for text editors and a parsed `fragment` for structural editors.
+ *
+ * @param input the source text of the module
+ * @param position a byte offset, or a zero-based line and a column in UTF-16 code units, as
+ *   Monaco reports it
+ */
+export function holeCompletions(
+  input: string | Uint8Array,
+  position: number | { line: number; column: number }
+): HoleCompletions | null {
+  const bytes = toBytes(input);
+
+  if (typeof position === "number") {
+    return bg.hole_completions(bytes, position);
+  } else {
+    return bg.hole_completions_at_position(bytes, position.line, position.column);
+  }
+}
+
+/**
+ * Replaces the node at `path` with `text`. Like every edit below, this returns the edited source
+ * and its parse, or an `error` if the edit could not be made or its result does not parse.
+ * Formatting and comments outside the edited node are preserved.
+ */
+export function replaceNode(input: string | Uint8Array, path: string, text: string): EditOutcome {
+  return bg.replace_node(toBytes(input), path, text);
+}
+
+/**
+ * Inserts a statement into the body of the procedure at `path`, before the statement at `index`.
+ * An `index` equal to the number of statements appends it.
+ */
+export function insertStatement(
+  input: string | Uint8Array,
+  path: string,
+  index: number,
+  text: string
+): EditOutcome {
+  return bg.insert_statement(toBytes(input), path, index, text);
+}
+
+/** Deletes the statement at `index` from the body of the procedure at `path`. */
+export function deleteStatement(input: string | Uint8Array, path: string, index: number): EditOutcome {
+  return bg.delete_statement_at(toBytes(input), path, index);
+}
+
+export type Wrapper =
+  | { kind: "if"; condition: string }
+  | { kind: "with"; name: string; value: string }
+  | { kind: "fn"; parameters: string[] };
+
+/** Wraps the expression at `path` in an `if`, `with`, or `fn` expression. */
+export function wrapExpression(input: string | Uint8Array, path: string, wrapper: Wrapper): EditOutcome {
+  const bytes = toBytes(input);
+
+  switch (wrapper.kind) {
+    case "if":
+      return bg.wrap_in_if(bytes, path, wrapper.condition);
+    case "with":
+      return bg.wrap_in_with(bytes, path, wrapper.name, wrapper.value);
+    case "fn":
+      return bg.wrap_in_fn(bytes, path, wrapper.parameters.join(", "));
+  }
+}
+
+/**
+ * Inserts a parameter into the function at `path`, before the parameter at `index`. An `index`
+ * equal to the number of parameters appends it.
+ */
+export function addParameter(
+  input: string | Uint8Array,
+  path: string,
+  index: number,
+  text: string
+): EditOutcome {
+  return bg.add_parameter(toBytes(input), path, index, text);
+}
+
+/** Removes the parameter at `index` from the function at `path`. */
+export function removeParameter(input: string | Uint8Array, path: string, index: number): EditOutcome {
+  return bg.remove_parameter(toBytes(input), path, index);
+}
+
+/**
+ * Lints a module with the built-in rules. `config` is the text of an `sdp.toml` project file whose
+ * `[lint]` table sets rule levels. Each diagnostic comes with the suggested edits that fix it.
+ * Returns `null` if the module does not parse or the configuration is invalid.
+ */
+export function lint(input: string | Uint8Array, config: string = ""): FixableDiagnostic[] | null {
+  return bg.lint_bytes(toBytes(input), config);
+}
+
+/**
+ * Applies lint fixes to a module and returns the fixed source. Only fixes that cannot change what
+ * the program means are applied, unless `maybeIncorrect` is set. Returns `null` if the
+ * configuration is invalid.
+ */
+export function fix(
+  input: string | Uint8Array,
+  config: string = "",
+  maybeIncorrect: boolean = false
+): string | null {
+  return bg.fix_bytes(toBytes(input), config, maybeIncorrect);
+}
+
+/**
+ * Returns the structural patch that turns `old` into `updated`, as text, or `null` if either does
+ * not parse. Patches name the declarations and statements they change by content as well as by
+ * position, so they can be applied to a module that others have changed elsewhere.
+ */
+export function diff(old: string | Uint8Array, updated: string | Uint8Array): string | null {
+  return bg.diff_bytes(toBytes(old), toBytes(updated));
+}
+
+/**
+ * Applies a patch written by `diff`. Fails with an `error` if a node the patch changes has itself
+ * changed since the patch was made.
+ */
+export function applyPatch(input: string | Uint8Array, patch: string): EditOutcome {
+  return bg.patch_bytes(toBytes(input), patch);
+}
+
+export interface GraphOptions {
+  /** The graph language to write. Defaults to `"dot"`. */
+  format?: "dot" | "mermaid";
+  /** The path of the node to draw, e.g. `declarations[0].body`. Defaults to the whole module. */
+  path?: string;
+  /** Whether to add the span of each node to its label. */
+  spans?: boolean;
+}
+
+/**
+ * Draws the tree of a module as a Graphviz DOT or Mermaid graph. Nodes are labelled with their
+ * variant and operator, name, or literal, and edges with the field that holds the child. Returns
+ * `null` if the module does not parse or there is no node at `path`.
+ */
+export function astGraph(input: string | Uint8Array, options: GraphOptions = {}): string | null {
+  return bg.ast_graph(toBytes(input), options.path ?? "", options.format ?? "dot", options.spans ?? false);
+}
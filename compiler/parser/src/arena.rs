@@ -0,0 +1,1129 @@
+//! A compact form of a parsed module, lowered from the tree after parsing.
+//!
+//! The tree built by the parser boxes every subexpression, keeps a full [`seglisp::Range`] on every
+//! node, and borrows each name from the source. An [`ArenaModule`] stores the same module in a few
+//! flat pools instead. Nodes refer to one another by index ([`ExprId`], [`StmtId`], ...), lists of
+//! children are [`Slice`]s of a shared pool, spans are pairs of `u32` byte offsets, and names and
+//! literals are interned as [`Symbol`]s. Keyword and punctuation tokens are not kept.
+//!
+//! The parser does not build this form: [`ArenaModule::lower`] walks a tree the parser has already
+//! built, so getting an `ArenaModule` costs a parse and then a lowering. It pays off for a module
+//! that is kept and walked many times, since it is smaller than the tree and its pools can be
+//! scanned without chasing pointers. An `ArenaModule` owns all of its data, so it can outlive the
+//! source and the tree it was lowered from. Lower several modules with one [`Interner`] so that
+//! their symbols can be compared with one another.
+
+use std::{collections::HashMap, fmt, marker::PhantomData};
+
+use seglisp::parse::ParseNode;
+
+use crate::{
+    ArithmeticOp, Assignment, BindingPattern, CompareOp, Declaration, Expression, GenericParameter,
+    InterfaceField, Module, ParameterDeclaration, ParsedVec, RecordBindingElement, RecordElement,
+    Statement, Type, TypeConstraint, UnaryOp, Verbatim,
+};
+
+/// An interned string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Symbol(u32);
+
+/// Deduplicates strings, handing out a [`Symbol`] for each distinct one.
+#[derive(Debug, Clone, Default)]
+pub struct Interner {
+    ids: HashMap<Box<str>, Symbol>,
+    names: Vec<Box<str>>,
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn intern(&mut self, name: &str) -> Symbol {
+        if let Some(symbol) = self.ids.get(name) {
+            return *symbol;
+        }
+
+        let symbol = Symbol(self.names.len() as u32);
+        self.names.push(name.into());
+        self.ids.insert(name.into(), symbol);
+        symbol
+    }
+
+    /// The symbol of `name`, if it has been interned.
+    pub fn get(&self, name: &str) -> Option<Symbol> {
+        self.ids.get(name).copied()
+    }
+
+    pub fn resolve(&self, symbol: Symbol) -> &str {
+        &self.names[symbol.0 as usize]
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+/// The byte range `start..end` of the source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub start: u32,
+    pub end: u32,
+}
+
+impl Span {
+    fn of(range: &seglisp::Range) -> Self {
+        Span {
+            start: range.0.absolute as u32,
+            end: range.1.absolute as u32,
+        }
+    }
+}
+
+macro_rules! ids {
+    ($($(#[$attr:meta])* $name:ident),* $(,)?) => {
+        $(
+            $(#[$attr])*
+            #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+            pub struct $name(u32);
+
+            impl $name {
+                pub fn index(&self) -> usize {
+                    self.0 as usize
+                }
+            }
+        )*
+    };
+}
+
+ids! {
+    /// The index of an expression in an [`ArenaModule`].
+    ExprId,
+    /// The index of a statement in an [`ArenaModule`].
+    StmtId,
+    /// The index of a type in an [`ArenaModule`].
+    TypeId,
+    /// The index of a binding pattern in an [`ArenaModule`].
+    PatternId,
+}
+
+/// A run of consecutive items in one of the pools of an [`ArenaModule`]. Read it with
+/// [`ArenaModule::list`].
+pub struct Slice<T> {
+    start: u32,
+    len: u32,
+    _item: PhantomData<fn() -> T>,
+}
+
+impl<T> Slice<T> {
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+// Derives would require `T` to implement these traits too.
+impl<T> Clone for Slice<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Slice<T> {}
+
+impl<T> PartialEq for Slice<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.start == other.start && self.len == other.len
+    }
+}
+
+impl<T> Eq for Slice<T> {}
+
+impl<T> fmt::Debug for Slice<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Slice({}..{})", self.start, self.start + self.len)
+    }
+}
+
+/// A name, with the span it was written at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ident {
+    pub symbol: Symbol,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Generic {
+    pub name: Ident,
+    pub constraint: Option<TypeId>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Param {
+    pub name: Ident,
+    pub type_: Option<TypeId>,
+}
+
+/// A `name = value` binding of a `with` expression or a `let` or `set` statement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Binding {
+    pub name: Ident,
+    pub value: ExprId,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Field {
+    pub name: Ident,
+    pub type_: TypeId,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordElem {
+    KeyValuePair { key: Ident, value: ExprId },
+    Identifier(Ident),
+    Spread(ExprId),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatternElem {
+    Identifier(Ident),
+    KeyValuePair { name: Ident, pattern: PatternId },
+    Rest(Ident),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decl {
+    Main {
+        body: ExprId,
+    },
+    Const {
+        name: Ident,
+        type_: Option<TypeId>,
+        value: ExprId,
+    },
+    Function {
+        name: Ident,
+        generics: Slice<Generic>,
+        parameters: Slice<Param>,
+        return_type: Option<TypeId>,
+        body: ExprId,
+    },
+    Import {
+        pattern: PatternId,
+        specifier: Ident,
+    },
+    Export {
+        elements: Slice<RecordElem>,
+    },
+    TypeAlias {
+        name: Ident,
+        generics: Slice<Generic>,
+        value: TypeId,
+    },
+    Interface {
+        name: Ident,
+        generics: Slice<Generic>,
+        extends: Option<TypeId>,
+        fields: Slice<Field>,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub enum Expr {
+    Number(Symbol),
+    String(Symbol),
+    Boolean(bool),
+    Name(Symbol),
+    Hole,
+    None,
+    As {
+        expr: ExprId,
+        type_: TypeId,
+    },
+    Unary {
+        operator: UnaryOp,
+        expression: ExprId,
+    },
+    Compare {
+        operator: CompareOp,
+        left: ExprId,
+        right: ExprId,
+    },
+    Arithmetic {
+        operator: ArithmeticOp,
+        left: ExprId,
+        right: ExprId,
+    },
+    Accessor {
+        accessee: ExprId,
+        index: ExprId,
+    },
+    Function {
+        name: Option<Ident>,
+        generics: Slice<Generic>,
+        parameters: Slice<Param>,
+        return_type: Option<TypeId>,
+        body: ExprId,
+    },
+    Call {
+        callee: ExprId,
+        arguments: Slice<ExprId>,
+    },
+    With {
+        bindings: Slice<Binding>,
+        body: ExprId,
+    },
+    Tuple(Slice<ExprId>),
+    List(Slice<ExprId>),
+    Procedure(Slice<StmtId>),
+    If {
+        condition: ExprId,
+        then: ExprId,
+        _else: ExprId,
+    },
+    Record(Slice<RecordElem>),
+    FieldAccess {
+        accessee: ExprId,
+        field: Ident,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stmt {
+    Let(Binding),
+    Set(Binding),
+    If {
+        condition: ExprId,
+        then: StmtId,
+        _else: Option<StmtId>,
+    },
+    ForIn {
+        binding: Ident,
+        iterator: ExprId,
+        body: StmtId,
+    },
+    Forever(StmtId),
+    Do(ExprId),
+    Break,
+    Continue,
+    Pass,
+    Expression(ExprId),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ty {
+    Kind,
+    Never,
+    Unknown,
+    Reference {
+        name: Ident,
+        arguments: Slice<TypeId>,
+    },
+    Union {
+        left: TypeId,
+        right: TypeId,
+    },
+    Tuple(Slice<TypeId>),
+    Function {
+        parameters: Slice<TypeId>,
+        return_type: TypeId,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pattern {
+    Identifier(Ident),
+    Tuple(Slice<PatternId>),
+    Record(Slice<PatternElem>),
+}
+
+/// A module stored in flat pools. See the [module documentation](self).
+#[derive(Debug, Clone, Default)]
+pub struct ArenaModule {
+    pub declarations: Vec<(Decl, Span)>,
+    exprs: Vec<(Expr, Span)>,
+    stmts: Vec<(Stmt, Span)>,
+    types: Vec<(Ty, Span)>,
+    patterns: Vec<(Pattern, Span)>,
+    expr_lists: Vec<ExprId>,
+    stmt_lists: Vec<StmtId>,
+    type_lists: Vec<TypeId>,
+    pattern_lists: Vec<PatternId>,
+    generics: Vec<Generic>,
+    params: Vec<Param>,
+    bindings: Vec<Binding>,
+    fields: Vec<Field>,
+    record_elems: Vec<RecordElem>,
+    pattern_elems: Vec<PatternElem>,
+}
+
+/// The pools of an [`ArenaModule`] that [`Slice`]s index into.
+pub trait Pool<T> {
+    fn list(&self, slice: Slice<T>) -> &[T];
+}
+
+macro_rules! pools {
+    ($($item:ty => $field:ident),* $(,)?) => {
+        $(
+            impl Pool<$item> for ArenaModule {
+                fn list(&self, slice: Slice<$item>) -> &[$item] {
+                    &self.$field[slice.start as usize..(slice.start + slice.len) as usize]
+                }
+            }
+        )*
+    };
+}
+
+pools! {
+    ExprId => expr_lists,
+    StmtId => stmt_lists,
+    TypeId => type_lists,
+    PatternId => pattern_lists,
+    Generic => generics,
+    Param => params,
+    Binding => bindings,
+    Field => fields,
+    RecordElem => record_elems,
+    PatternElem => pattern_elems,
+}
+
+impl ArenaModule {
+    /// Lowers `module` with a fresh interner, which is returned alongside.
+    pub fn lower(module: &ParseNode<Module>) -> (Self, Interner) {
+        let mut interner = Interner::new();
+        let arena = Self::lower_with(module, &mut interner);
+        (arena, interner)
+    }
+
+    /// Lowers `module`, interning its names and literals in `interner`.
+    pub fn lower_with(module: &ParseNode<Module>, interner: &mut Interner) -> Self {
+        let mut lowerer = Lowerer {
+            arena: ArenaModule::default(),
+            interner,
+        };
+
+        for decl in &module.value.declarations {
+            let lowered = lowerer.declaration(&decl.value);
+            lowerer
+                .arena
+                .declarations
+                .push((lowered, Span::of(&decl.range)));
+        }
+
+        lowerer.arena
+    }
+
+    /// The items of `slice`.
+    pub fn list<T>(&self, slice: Slice<T>) -> &[T]
+    where
+        Self: Pool<T>,
+    {
+        Pool::list(self, slice)
+    }
+
+    pub fn expr(&self, id: ExprId) -> &Expr {
+        &self.exprs[id.index()].0
+    }
+
+    pub fn expr_span(&self, id: ExprId) -> Span {
+        self.exprs[id.index()].1
+    }
+
+    pub fn stmt(&self, id: StmtId) -> &Stmt {
+        &self.stmts[id.index()].0
+    }
+
+    pub fn stmt_span(&self, id: StmtId) -> Span {
+        self.stmts[id.index()].1
+    }
+
+    pub fn ty(&self, id: TypeId) -> &Ty {
+        &self.types[id.index()].0
+    }
+
+    pub fn type_span(&self, id: TypeId) -> Span {
+        self.types[id.index()].1
+    }
+
+    pub fn pattern(&self, id: PatternId) -> &Pattern {
+        &self.patterns[id.index()].0
+    }
+
+    pub fn pattern_span(&self, id: PatternId) -> Span {
+        self.patterns[id.index()].1
+    }
+
+    /// Every expression of the module, in the order they were lowered: children before parents.
+    pub fn exprs(&self) -> impl Iterator<Item = (ExprId, &Expr)> {
+        self.exprs
+            .iter()
+            .enumerate()
+            .map(|(idx, (expr, _))| (ExprId(idx as u32), expr))
+    }
+
+    /// Every statement of the module, children before parents.
+    pub fn stmts(&self) -> impl Iterator<Item = (StmtId, &Stmt)> {
+        self.stmts
+            .iter()
+            .enumerate()
+            .map(|(idx, (stmt, _))| (StmtId(idx as u32), stmt))
+    }
+}
+
+struct Lowerer<'i> {
+    arena: ArenaModule,
+    interner: &'i mut Interner,
+}
+
+/// Appends `items` to `pool` and returns the slice they occupy.
+fn extend<T>(pool: &mut Vec<T>, items: Vec<T>) -> Slice<T> {
+    let start = pool.len() as u32;
+    let len = items.len() as u32;
+    pool.extend(items);
+    Slice {
+        start,
+        len,
+        _item: PhantomData,
+    }
+}
+
+impl Lowerer<'_> {
+    fn ident(&mut self, node: &Verbatim) -> Ident {
+        Ident {
+            symbol: self.interner.intern(node.value),
+            span: Span::of(&node.range),
+        }
+    }
+
+    fn constraint(&mut self, node: &Option<ParseNode<TypeConstraint>>) -> Option<TypeId> {
+        node.as_ref().map(|c| self.type_(&c.value.type_))
+    }
+
+    fn generics(&mut self, node: &Option<ParsedVec<GenericParameter>>) -> Slice<Generic> {
+        let generics = node
+            .iter()
+            .flat_map(|list| &list.value)
+            .map(|g| Generic {
+                name: self.ident(&g.value.name),
+                constraint: self.constraint(&g.value.constraint),
+            })
+            .collect();
+        extend(&mut self.arena.generics, generics)
+    }
+
+    fn params(&mut self, node: &ParsedVec<ParameterDeclaration>) -> Slice<Param> {
+        let params = node
+            .value
+            .iter()
+            .map(|p| Param {
+                name: self.ident(&p.value.name),
+                type_: self.constraint(&p.value.type_),
+            })
+            .collect();
+        extend(&mut self.arena.params, params)
+    }
+
+    fn binding(&mut self, node: &Assignment) -> Binding {
+        Binding {
+            name: self.ident(&node.symbol),
+            value: self.expression(&node.value),
+        }
+    }
+
+    fn record_elements(&mut self, node: &ParsedVec<RecordElement>) -> Slice<RecordElem> {
+        let elements = node
+            .value
+            .iter()
+            .map(|element| match &element.value {
+                RecordElement::KeyValuePair { key, value } => RecordElem::KeyValuePair {
+                    key: self.ident(key),
+                    value: self.expression(value),
+                },
+                RecordElement::Identifier { name } => RecordElem::Identifier(self.ident(name)),
+                RecordElement::Spread { value } => RecordElem::Spread(self.expression(value)),
+            })
+            .collect();
+        extend(&mut self.arena.record_elems, elements)
+    }
+
+    fn expressions(&mut self, nodes: &[ParseNode<Expression>]) -> Slice<ExprId> {
+        let ids = nodes.iter().map(|e| self.expression(e)).collect();
+        extend(&mut self.arena.expr_lists, ids)
+    }
+
+    fn types(&mut self, nodes: &[ParseNode<Type>]) -> Slice<TypeId> {
+        let ids = nodes.iter().map(|t| self.type_(t)).collect();
+        extend(&mut self.arena.type_lists, ids)
+    }
+
+    fn declaration(&mut self, decl: &Declaration) -> Decl {
+        match decl {
+            Declaration::Main { body, .. } => Decl::Main {
+                body: self.expression(body),
+            },
+            Declaration::Const {
+                identifier,
+                type_,
+                value,
+                ..
+            } => Decl::Const {
+                name: self.ident(identifier),
+                type_: self.constraint(type_),
+                value: self.expression(value),
+            },
+            Declaration::Function {
+                identifier,
+                generic_parameters,
+                parameters,
+                constraint,
+                body,
+                ..
+            } => Decl::Function {
+                name: self.ident(identifier),
+                generics: self.generics(generic_parameters),
+                parameters: self.params(parameters),
+                return_type: self.constraint(constraint),
+                body: self.expression(body),
+            },
+            Declaration::Import {
+                pattern,
+                module_specifier,
+                ..
+            } => Decl::Import {
+                pattern: self.pattern(pattern),
+                specifier: self.ident(module_specifier),
+            },
+            Declaration::Export { elements, .. } => Decl::Export {
+                elements: self.record_elements(elements),
+            },
+            Declaration::TypeAlias {
+                name,
+                generic_parameters,
+                value,
+                ..
+            } => Decl::TypeAlias {
+                name: self.ident(name),
+                generics: self.generics(generic_parameters),
+                value: self.type_(value),
+            },
+            Declaration::Interface {
+                name,
+                generic_parameters,
+                constraint,
+                body,
+                ..
+            } => {
+                let name = self.ident(name);
+                let generics = self.generics(generic_parameters);
+                let extends = self.constraint(constraint);
+                let fields = body
+                    .value
+                    .iter()
+                    .map(|f: &ParseNode<InterfaceField>| Field {
+                        name: self.ident(&f.value.name),
+                        type_: self.type_(&f.value.constraint.value.type_),
+                    })
+                    .collect();
+
+                Decl::Interface {
+                    name,
+                    generics,
+                    extends,
+                    fields: extend(&mut self.arena.fields, fields),
+                }
+            }
+        }
+    }
+
+    fn expression(&mut self, node: &ParseNode<Expression>) -> ExprId {
+        let expr = match &node.value {
+            Expression::Number(n) => Expr::Number(self.interner.intern(n)),
+            Expression::String(s) => Expr::String(self.interner.intern(s)),
+            Expression::Boolean(b) => Expr::Boolean(*b),
+            Expression::Name(n) => Expr::Name(self.interner.intern(n)),
+            Expression::Hole => Expr::Hole,
+            Expression::None => Expr::None,
+            Expression::As { expr, type_, .. } => Expr::As {
+                expr: self.expression(expr),
+                type_: self.type_(type_),
+            },
+            Expression::Unary {
+                operator,
+                expression,
+            } => Expr::Unary {
+                operator: operator.value.clone(),
+                expression: self.expression(expression),
+            },
+            Expression::Compare {
+                operator,
+                left,
+                right,
+            } => Expr::Compare {
+                operator: operator.value.clone(),
+                left: self.expression(left),
+                right: self.expression(right),
+            },
+            Expression::Arithmetic {
+                operator,
+                left,
+                right,
+            } => Expr::Arithmetic {
+                operator: operator.value.clone(),
+                left: self.expression(left),
+                right: self.expression(right),
+            },
+            Expression::Accessor { accessee, index } => Expr::Accessor {
+                accessee: self.expression(accessee),
+                index: self.expression(index),
+            },
+            Expression::Function {
+                name,
+                generic_parameters,
+                parameters,
+                constraint,
+                body,
+                ..
+            } => Expr::Function {
+                name: name.as_ref().map(|n| self.ident(n)),
+                generics: self.generics(generic_parameters),
+                parameters: self.params(parameters),
+                return_type: self.constraint(constraint),
+                body: self.expression(body),
+            },
+            Expression::Call { callee, parameters } => Expr::Call {
+                callee: self.expression(callee),
+                arguments: self.expressions(&parameters.value),
+            },
+            Expression::With { bindings, body, .. } => {
+                let lowered = bindings
+                    .value
+                    .iter()
+                    .map(|b| self.binding(&b.value))
+                    .collect();
+
+                Expr::With {
+                    bindings: extend(&mut self.arena.bindings, lowered),
+                    body: self.expression(body),
+                }
+            }
+            Expression::Tuple { elements } => Expr::Tuple(self.expressions(&elements.value)),
+            Expression::List { elements } => Expr::List(self.expressions(&elements.value)),
+            Expression::Procedure { body } => {
+                let ids = body.value.iter().map(|s| self.statement(s)).collect();
+                Expr::Procedure(extend(&mut self.arena.stmt_lists, ids))
+            }
+            Expression::If {
+                condition,
+                then,
+                _else,
+                ..
+            } => Expr::If {
+                condition: self.expression(condition),
+                then: self.expression(then),
+                _else: self.expression(_else),
+            },
+            Expression::Record { elements } => Expr::Record(self.record_elements(elements)),
+            Expression::FieldAccess { accessee, field } => Expr::FieldAccess {
+                accessee: self.expression(accessee),
+                field: self.ident(field),
+            },
+        };
+
+        self.arena.exprs.push((expr, Span::of(&node.range)));
+        ExprId(self.arena.exprs.len() as u32 - 1)
+    }
+
+    fn statement(&mut self, node: &ParseNode<Statement>) -> StmtId {
+        let stmt = match &node.value {
+            Statement::Let { assignment, .. } => Stmt::Let(self.binding(&assignment.value)),
+            Statement::Set(assignment) => Stmt::Set(self.binding(&assignment.value)),
+            Statement::If {
+                condition,
+                then,
+                _else,
+                ..
+            } => Stmt::If {
+                condition: self.expression(condition),
+                then: self.statement(then),
+                _else: _else.as_ref().map(|s| self.statement(s)),
+            },
+            Statement::ForIn {
+                binding,
+                iterator,
+                body,
+                ..
+            } => Stmt::ForIn {
+                binding: self.ident(binding),
+                iterator: self.expression(iterator),
+                body: self.statement(body),
+            },
+            Statement::Forever(body) => Stmt::Forever(self.statement(body)),
+            Statement::Do(expr) => Stmt::Do(self.expression(expr)),
+            Statement::Break => Stmt::Break,
+            Statement::Continue => Stmt::Continue,
+            Statement::Pass => Stmt::Pass,
+            Statement::Expression(expr) => Stmt::Expression(self.expression(expr)),
+        };
+
+        self.arena.stmts.push((stmt, Span::of(&node.range)));
+        StmtId(self.arena.stmts.len() as u32 - 1)
+    }
+
+    fn type_(&mut self, node: &ParseNode<Type>) -> TypeId {
+        let ty = match &node.value {
+            Type::Kind => Ty::Kind,
+            Type::Never => Ty::Never,
+            Type::Unknown => Ty::Unknown,
+            Type::Reference {
+                name,
+                generic_parameters,
+            } => Ty::Reference {
+                name: self.ident(name),
+                arguments: self.types(
+                    generic_parameters
+                        .as_ref()
+                        .map_or(&[][..], |g| g.value.as_slice()),
+                ),
+            },
+            Type::Union { left, right } => Ty::Union {
+                left: self.type_(left),
+                right: self.type_(right),
+            },
+            Type::Tuple { members } => Ty::Tuple(self.types(&members.value)),
+            Type::Function {
+                parameters,
+                return_type,
+                ..
+            } => Ty::Function {
+                parameters: self.types(&parameters.value),
+                return_type: self.type_(return_type),
+            },
+        };
+
+        self.arena.types.push((ty, Span::of(&node.range)));
+        TypeId(self.arena.types.len() as u32 - 1)
+    }
+
+    fn pattern(&mut self, node: &ParseNode<BindingPattern>) -> PatternId {
+        let pattern = match &node.value {
+            BindingPattern::Identifier { name } => Pattern::Identifier(self.ident(name)),
+            BindingPattern::Tuple { patterns } => {
+                let ids = patterns.value.iter().map(|p| self.pattern(p)).collect();
+                Pattern::Tuple(extend(&mut self.arena.pattern_lists, ids))
+            }
+            BindingPattern::Record { elements } => {
+                let lowered = elements
+                    .value
+                    .iter()
+                    .map(|element| match &element.value {
+                        RecordBindingElement::Identifier { name } => {
+                            PatternElem::Identifier(self.ident(name))
+                        }
+                        RecordBindingElement::KeyValuePair { name, pattern } => {
+                            PatternElem::KeyValuePair {
+                                name: self.ident(name),
+                                pattern: self.pattern(pattern),
+                            }
+                        }
+                        RecordBindingElement::Rest { name } => PatternElem::Rest(self.ident(name)),
+                    })
+                    .collect();
+                Pattern::Record(extend(&mut self.arena.pattern_elems, lowered))
+            }
+        };
+
+        self.arena.patterns.push((pattern, Span::of(&node.range)));
+        PatternId(self.arena.patterns.len() as u32 - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeSet, ops::ControlFlow};
+
+    use super::*;
+    use crate::{
+        incremental::parse_module,
+        visit::{self, Visit},
+    };
+
+    /// Every kind of node the parser can build. `as` expressions and the `_` type are missing: the
+    /// parser reads neither.
+    const EVERY_KIND: &str = "\
+type Pair[T] = (T, T);
+type Maybe[T] = T | none;
+interface Named { name: string };
+fn first[T: Named](p: Pair[T]): T -> p[0].name;
+fn kind(k: *) -> k;
+fn never(t: fn() -> !): ! -> t();
+import { a, b: (c, d), ...e } = use(\"./a\");
+export { first, pair: 1, ...e };
+const s: string = \"s\";
+const g = fn (v) -> if v then none else (v, false);
+main with (x = -1, y = { x, z: true, ...@ }) #[
+  let n = 1 + 2 * 3 < 4;
+  if (!x) set x = x % 2;
+  for i in [1, 2] do f(i);
+  loop break;
+  loop continue;
+  pass;
+  f(n);
+];
+";
+
+    /// The name of the variant a node's `Debug` output starts with.
+    fn variant(node: &impl fmt::Debug) -> String {
+        format!("{node:?}")
+            .chars()
+            .take_while(|c| c.is_alphanumeric())
+            .collect()
+    }
+
+    fn span(range: &seglisp::Range) -> Span {
+        Span::of(range)
+    }
+
+    /// The kind and span of every node of a tree, in the order they were visited.
+    #[derive(Default)]
+    struct Nodes {
+        declarations: Vec<(String, Span)>,
+        exprs: Vec<(String, Span)>,
+        stmts: Vec<(String, Span)>,
+        types: Vec<(String, Span)>,
+        patterns: Vec<(String, Span)>,
+    }
+
+    impl<'ast> Visit<'ast> for Nodes {
+        fn visit_declaration(
+            &mut self,
+            node: &'ast ParseNode<Declaration<'ast>>,
+        ) -> ControlFlow<()> {
+            self.declarations
+                .push((variant(&node.value), span(&node.range)));
+            visit::walk_declaration(self, node)
+        }
+
+        fn visit_expression(&mut self, node: &'ast ParseNode<Expression<'ast>>) -> ControlFlow<()> {
+            self.exprs.push((variant(&node.value), span(&node.range)));
+            visit::walk_expression(self, node)
+        }
+
+        fn visit_statement(&mut self, node: &'ast ParseNode<Statement<'ast>>) -> ControlFlow<()> {
+            self.stmts.push((variant(&node.value), span(&node.range)));
+            visit::walk_statement(self, node)
+        }
+
+        fn visit_type(&mut self, node: &'ast ParseNode<Type<'ast>>) -> ControlFlow<()> {
+            self.types.push((variant(&node.value), span(&node.range)));
+            visit::walk_type(self, node)
+        }
+
+        fn visit_binding_pattern(
+            &mut self,
+            node: &'ast ParseNode<BindingPattern<'ast>>,
+        ) -> ControlFlow<()> {
+            self.patterns
+                .push((variant(&node.value), span(&node.range)));
+            visit::walk_binding_pattern(self, node)
+        }
+    }
+
+    /// The kinds and spans of `pool`, sorted so that they can be compared regardless of order.
+    fn sorted<T: fmt::Debug>(pool: &[(T, Span)]) -> Vec<(String, (u32, u32))> {
+        let mut nodes: Vec<_> = pool
+            .iter()
+            .map(|(node, span)| (variant(node), (span.start, span.end)))
+            .collect();
+        nodes.sort();
+        nodes
+    }
+
+    fn sorted_tree(nodes: &[(String, Span)]) -> Vec<(String, (u32, u32))> {
+        let mut nodes: Vec<_> = nodes
+            .iter()
+            .map(|(kind, span)| (kind.clone(), (span.start, span.end)))
+            .collect();
+        nodes.sort();
+        nodes
+    }
+
+    fn kinds(nodes: &[(String, Span)]) -> BTreeSet<&str> {
+        nodes.iter().map(|(kind, _)| kind.as_str()).collect()
+    }
+
+    #[test]
+    fn every_node_is_lowered_with_its_kind_and_span() {
+        let parsed = parse_module(EVERY_KIND);
+        assert!(parsed.diagnostics.is_empty(), "{:?}", parsed.diagnostics);
+        let module = parsed.module.as_ref().unwrap();
+
+        let mut tree = Nodes::default();
+        let _ = tree.visit_module(module);
+        let (arena, interner) = ArenaModule::lower(module);
+
+        assert_eq!(
+            kinds(&tree.declarations),
+            BTreeSet::from([
+                "Const",
+                "Export",
+                "Function",
+                "Import",
+                "Interface",
+                "Main",
+                "TypeAlias"
+            ])
+        );
+        assert_eq!(
+            kinds(&tree.exprs),
+            BTreeSet::from([
+                "Accessor",
+                "Arithmetic",
+                "Boolean",
+                "Call",
+                "Compare",
+                "FieldAccess",
+                "Function",
+                "Hole",
+                "If",
+                "List",
+                "Name",
+                "None",
+                "Number",
+                "Procedure",
+                "Record",
+                "String",
+                "Tuple",
+                "Unary",
+                "With"
+            ])
+        );
+        assert_eq!(
+            kinds(&tree.stmts),
+            BTreeSet::from([
+                "Break",
+                "Continue",
+                "Do",
+                "Expression",
+                "ForIn",
+                "Forever",
+                "If",
+                "Let",
+                "Pass",
+                "Set"
+            ])
+        );
+        assert_eq!(
+            kinds(&tree.types),
+            BTreeSet::from(["Function", "Kind", "Never", "Reference", "Tuple", "Union"])
+        );
+        assert_eq!(
+            kinds(&tree.patterns),
+            BTreeSet::from(["Identifier", "Record", "Tuple"])
+        );
+
+        assert_eq!(sorted(&arena.declarations), sorted_tree(&tree.declarations));
+        assert_eq!(sorted(&arena.exprs), sorted_tree(&tree.exprs));
+        assert_eq!(sorted(&arena.stmts), sorted_tree(&tree.stmts));
+        assert_eq!(sorted(&arena.types), sorted_tree(&tree.types));
+        assert_eq!(sorted(&arena.patterns), sorted_tree(&tree.patterns));
+
+        // Names and literals resolve to the text they were read from.
+        for (id, expr) in arena.exprs() {
+            let Span { start, end } = arena.expr_span(id);
+            let text = &EVERY_KIND[start as usize..end as usize];
+
+            match expr {
+                Expr::Name(symbol) | Expr::Number(symbol) => {
+                    assert_eq!(interner.resolve(*symbol), text)
+                }
+                Expr::String(symbol) => {
+                    assert_eq!(format!("\"{}\"", interner.resolve(*symbol)), text)
+                }
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn interning_dedupes_names() {
+        let mut interner = Interner::new();
+        let a = interner.intern("a");
+
+        assert_eq!(interner.intern("a"), a);
+        assert_ne!(interner.intern("b"), a);
+        assert_eq!(interner.len(), 2);
+        assert_eq!(interner.get("a"), Some(a));
+        assert_eq!(interner.get("c"), None);
+        assert_eq!(interner.resolve(a), "a");
+
+        // Modules lowered with one interner share their symbols.
+        let first = parse_module("const x = y;");
+        let second = parse_module("main y(x);");
+        ArenaModule::lower_with(first.module.as_ref().unwrap(), &mut interner);
+        let arena = ArenaModule::lower_with(second.module.as_ref().unwrap(), &mut interner);
+
+        assert_eq!(interner.len(), 4);
+        let names: Vec<_> = arena
+            .exprs()
+            .filter_map(|(_, expr)| match expr {
+                Expr::Name(symbol) => Some(*symbol),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            names,
+            [interner.get("y").unwrap(), interner.get("x").unwrap()]
+        );
+    }
+
+    #[test]
+    fn slices_index_their_own_items() {
+        let parsed = parse_module("main f([[1], [2, 3]], ());");
+        let (arena, interner) = ArenaModule::lower(parsed.module.as_ref().unwrap());
+
+        let Decl::Main { body } = arena.declarations[0].0 else {
+            panic!("not a main declaration");
+        };
+        let Expr::Call { arguments, .. } = arena.expr(body) else {
+            panic!("not a call");
+        };
+        let arguments = arena.list(*arguments);
+        assert_eq!(arguments.len(), 2);
+
+        let Expr::Tuple(empty) = arena.expr(arguments[1]) else {
+            panic!("not a tuple");
+        };
+        assert!(empty.is_empty());
+        assert!(arena.list(*empty).is_empty());
+
+        let Expr::List(outer) = arena.expr(arguments[0]) else {
+            panic!("not a list");
+        };
+        let numbers: Vec<Vec<&str>> = arena
+            .list(*outer)
+            .iter()
+            .map(|inner| {
+                let Expr::List(inner) = arena.expr(*inner) else {
+                    panic!("not a list");
+                };
+                arena
+                    .list(*inner)
+                    .iter()
+                    .map(|id| match arena.expr(*id) {
+                        Expr::Number(symbol) => interner.resolve(*symbol),
+                        other => panic!("{other:?} is not a number"),
+                    })
+                    .collect()
+            })
+            .collect();
+
+        assert_eq!(numbers, [vec!["1"], vec!["2", "3"]]);
+    }
+}
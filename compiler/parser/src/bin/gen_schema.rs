@@ -0,0 +1,7 @@
+use serendipity_parser::json;
+
+pub fn main() {
+    let schema = serde_json::to_string_pretty(&json::schema()).expect("failed to write schema");
+
+    println!("{schema}");
+}
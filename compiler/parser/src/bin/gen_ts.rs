@@ -1,12 +1,228 @@
-use std::io::Write;
+use std::{fmt::Write as _, io::Write};
 
 use seglisp::parse::ParsedDocument;
-use serendipity_parser::Module;
+use serendipity_parser::{
+    edit::EditOutcome,
+    fix::FixableDiagnostic,
+    holes::HoleCompletions,
+    ids::NodeIdEntry,
+    incremental::ParsedModule,
+    query::NodeInfo,
+    schema::{js_field_name, Field, FieldKind, Shape, TypeKind, TypeSchema, Variant, TYPES},
+    ExportTable, Module,
+};
 
 pub fn main() {
-    let types = seglisp::ts_type_roots!(ParsedDocument<Module>);
+    let mut types = seglisp::ts_type_roots!(
+        ParsedDocument<Module>,
+        ExportTable,
+        NodeInfo,
+        NodeIdEntry,
+        ParsedModule,
+        HoleCompletions,
+        EditOutcome,
+        FixableDiagnostic
+    );
+
+    write_guards(&mut types);
+    write_visitors(&mut types);
+    write_walker(&mut types);
+    write_builders(&mut types);
 
     std::io::stdout()
         .write_all(types.as_bytes())
         .expect("failed to write types");
 }
+
+/// `Expression` → `expression`, for names of walker callbacks and builder namespaces.
+fn lower_first(name: &str) -> String {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .map(|first| first.to_lowercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+fn enums() -> impl Iterator<Item = (&'static TypeSchema, &'static [Variant])> {
+    TYPES.iter().filter_map(|ty| match ty.kind {
+        TypeKind::Enum(variants) => Some((ty, variants)),
+        TypeKind::Struct(_) => None,
+    })
+}
+
+fn write_guards(out: &mut String) {
+    for (ty, variants) in enums() {
+        for variant in variants {
+            let (enum_name, name) = (ty.name, variant.name);
+            writeln!(
+                out,
+                "\nexport function is{name}{enum_name}(node: {enum_name}): node is {name}{enum_name} {{\n  \
+                   return node.kind === \"{name}\";\n}}"
+            )
+            .unwrap();
+        }
+    }
+}
+
+fn write_visitors(out: &mut String) {
+    for (ty, variants) in enums() {
+        let enum_name = ty.name;
+
+        writeln!(out, "\nexport interface {enum_name}Visitor<R> {{").unwrap();
+        for variant in variants {
+            writeln!(out, "  {0}(node: {0}{enum_name}): R;", variant.name).unwrap();
+        }
+        writeln!(out, "}}").unwrap();
+
+        writeln!(
+            out,
+            "\nexport function visit{enum_name}<R>(node: {enum_name}, visitor: {enum_name}Visitor<R>): R {{\n  \
+               switch (node.kind) {{"
+        )
+        .unwrap();
+        for variant in variants {
+            writeln!(
+                out,
+                "    case \"{0}\":\n      return visitor.{0}(node);",
+                variant.name
+            )
+            .unwrap();
+        }
+        writeln!(out, "  }}\n}}").unwrap();
+    }
+}
+
+/// The statement that walks the child or children in `expr`, if it holds any AST nodes.
+fn walk_field(expr: &str, kind: FieldKind) -> Option<String> {
+    let is_ast = |name| TYPES.iter().any(|ty| ty.name == name);
+
+    match kind {
+        FieldKind::Token | FieldKind::Text | FieldKind::Boolean => None,
+        FieldKind::Node(name) => is_ast(name).then(|| format!("walk{name}({expr}, walker);")),
+        FieldKind::List(name) => is_ast(name)
+            .then(|| format!("for (const child of {expr}.value) walk{name}(child, walker);")),
+        FieldKind::Items(name) => {
+            is_ast(name).then(|| format!("for (const child of {expr}) walk{name}(child, walker);"))
+        }
+        FieldKind::Optional(kind) => {
+            walk_field(expr, *kind).map(|walk| format!("if ({expr} != null) {walk}"))
+        }
+    }
+}
+
+fn walk_fields(out: &mut String, value: &str, fields: &[Field], tuple: bool, indent: &str) {
+    for (index, field) in fields.iter().enumerate() {
+        let expr = if tuple {
+            format!("{value}[{index}]")
+        } else {
+            format!("{value}.{}", js_field_name(field.name))
+        };
+
+        if let Some(walk) = walk_field(&expr, field.kind) {
+            writeln!(out, "{indent}{walk}").unwrap();
+        }
+    }
+}
+
+fn write_walker(out: &mut String) {
+    writeln!(
+        out,
+        "\n/**\n * Callbacks for `walk` functions, called on each node before its children. A callback \
+         that\n * returns `false` skips the children of its node.\n */\nexport interface Walker {{"
+    )
+    .unwrap();
+    for ty in TYPES {
+        writeln!(
+            out,
+            "  {}?(node: ParseNode<{}>): boolean | void;",
+            lower_first(ty.name),
+            ty.name
+        )
+        .unwrap();
+    }
+    writeln!(out, "}}").unwrap();
+
+    for ty in TYPES {
+        let name = ty.name;
+        writeln!(
+            out,
+            "\nexport function walk{name}(node: ParseNode<{name}>, walker: Walker): void {{\n  \
+               if (walker.{}?.(node) === false) return;\n  \
+               const value = node.value;",
+            lower_first(name)
+        )
+        .unwrap();
+
+        match ty.kind {
+            TypeKind::Struct(fields) => walk_fields(out, "value", fields, false, "  "),
+            TypeKind::Enum(variants) => {
+                writeln!(out, "  switch (value.kind) {{").unwrap();
+                for variant in variants {
+                    let mut body = String::new();
+                    match variant.shape {
+                        Shape::Unit => {}
+                        Shape::Tuple(fields) => {
+                            walk_fields(&mut body, "value", fields, true, "      ")
+                        }
+                        Shape::Struct(fields) => {
+                            walk_fields(&mut body, "value", fields, false, "      ")
+                        }
+                    }
+
+                    if !body.is_empty() {
+                        writeln!(out, "    case \"{}\":\n{body}      break;", variant.name)
+                            .unwrap();
+                    }
+                }
+                writeln!(out, "  }}").unwrap();
+            }
+        }
+
+        writeln!(out, "}}").unwrap();
+    }
+}
+
+fn write_builders(out: &mut String) {
+    for (ty, variants) in enums() {
+        let enum_name = ty.name;
+
+        writeln!(out, "\nexport const {} = {{", lower_first(enum_name)).unwrap();
+        for variant in variants {
+            let (name, full) = (variant.name, format!("{}{enum_name}", variant.name));
+
+            match variant.shape {
+                Shape::Unit => writeln!(
+                    out,
+                    "  {name}(): {full} {{\n    return {{ kind: \"{name}\" }} as {full};\n  }},"
+                ),
+                Shape::Tuple(fields) => {
+                    let parameters = (0..fields.len())
+                        .map(|i| format!("_{i}: {full}[{i}]"))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    let elements = (0..fields.len())
+                        .map(|i| format!("_{i}"))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    let element_types = (0..fields.len())
+                        .map(|i| format!("{full}[{i}]"))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+
+                    writeln!(
+                        out,
+                        "  {name}({parameters}): {full} {{\n    \
+                           return Object.assign([{elements}] as [{element_types}], {{ kind: \"{name}\" }} as const);\n  }},"
+                    )
+                }
+                Shape::Struct(_) => writeln!(
+                    out,
+                    "  {name}(fields: Omit<{full}, \"kind\">): {full} {{\n    \
+                       return {{ kind: \"{name}\", ...fields }};\n  }},"
+                ),
+            }
+            .unwrap();
+        }
+        writeln!(out, "}};").unwrap();
+    }
+}
@@ -0,0 +1,398 @@
+use std::{
+    io::{BufRead, Write},
+    path::Path,
+};
+
+use seglisp::{DiagnosticLocation, DiagnosticSeverity};
+use serendipity_parser::{
+    diagram::{self, Format, Options},
+    eval::StdHost,
+    fix::{fix, Applicability, FixableDiagnostic},
+    incremental::parse_module,
+    lint::{check, LintConfig, Registry},
+    node::NodePath,
+    patch::{apply, diff_sources, Patch},
+    repl::{Repl, Reply},
+    sexpr,
+};
+
+const USAGE: &str = "\
+usage: sdp <command>
+
+commands:
+  repl                               start an interactive session
+  lint <file>...                     report lint findings, configured by the nearest sdp.toml
+  lint --rules                       list the lint rules and their default levels
+  fix [--maybe-incorrect] <file>...  apply lint fixes in place; only those that cannot change
+                                     what the program means unless --maybe-incorrect is given
+  parse --json [--pretty] <file>     print the parse of a file as JSON (see docs/parse-json.md)
+  parse --sexpr <file>               print the tree of a file as an S-expression
+  diff <old> <new>                   print the structural patch that turns one file into another
+  patch <file> <patch>               apply a patch written by sdp diff to a file in place
+  graph [--mermaid] [--spans] <file> [<path>]
+                                     print the tree of a file, or of the node at a path such as
+                                     declarations[0].body, as a Graphviz DOT or Mermaid graph";
+
+fn repl() {
+    let mut repl = Repl::new(StdHost);
+    let stdin = std::io::stdin();
+
+    println!("Serendipity REPL. Type :help for help.");
+
+    loop {
+        print!("{}", repl.prompt());
+        let _ = std::io::stdout().flush();
+
+        let mut line = String::new();
+        match stdin.lock().read_line(&mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+
+        match repl.feed(&line) {
+            Reply::Incomplete => {}
+            Reply::Output(text) if text.is_empty() => {}
+            Reply::Output(text) => println!("{text}"),
+            Reply::Quit => break,
+        }
+    }
+}
+
+/// Lints each file and prints its diagnostics. Returns whether any of them was an error.
+fn lint_files(paths: &[String]) -> bool {
+    let registry = Registry::default();
+    let mut failed = false;
+
+    for path in paths {
+        let source = match std::fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(error) => {
+                eprintln!("{path}: {error}");
+                failed = true;
+                continue;
+            }
+        };
+
+        let Some(config) = project_config(path) else {
+            failed = true;
+            continue;
+        };
+
+        for rule in config.unknown_rules(&registry) {
+            eprintln!("{path}: warning: unknown lint rule '{rule}'");
+        }
+
+        let parsed = parse_module(&source);
+        let mut diagnostics: Vec<FixableDiagnostic> = parsed
+            .diagnostics
+            .iter()
+            .cloned()
+            .map(FixableDiagnostic::from)
+            .collect();
+        if let Some(module) = &parsed.module {
+            diagnostics.extend(check(&source, module, &registry, &config));
+        }
+
+        for FixableDiagnostic {
+            diagnostic,
+            suggestions,
+        } in diagnostics
+        {
+            let (line, column) = match &diagnostic.location {
+                DiagnosticLocation::Range((start, _)) => (start.line + 1, start.column + 1),
+                _ => (1, 1),
+            };
+            let severity = match diagnostic.severity {
+                DiagnosticSeverity::Error => {
+                    failed = true;
+                    "error"
+                }
+                DiagnosticSeverity::Warning => "warning",
+                _ => "info",
+            };
+
+            println!("{path}:{line}:{column}: {severity}: {}", diagnostic.message);
+            if let Some(note) = &diagnostic.note {
+                println!("  note: {note}");
+            }
+            for suggestion in suggestions {
+                println!("  fix: {}", suggestion.message);
+            }
+        }
+    }
+
+    failed
+}
+
+/// Applies lint fixes to each file in place. Returns whether any file could not be fixed.
+fn fix_files(paths: &[String], allowed: Applicability) -> bool {
+    let registry = Registry::default();
+    let mut failed = false;
+
+    for path in paths {
+        let source = match std::fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(error) => {
+                eprintln!("{path}: {error}");
+                failed = true;
+                continue;
+            }
+        };
+
+        let Some(config) = project_config(path) else {
+            failed = true;
+            continue;
+        };
+
+        let fixed = fix(&source, &registry, &config, allowed);
+        if fixed.applied == 0 {
+            continue;
+        }
+
+        match std::fs::write(path, &fixed.source) {
+            Ok(()) => println!("{path}: applied {} fixes", fixed.applied),
+            Err(error) => {
+                eprintln!("{path}: {error}");
+                failed = true;
+            }
+        }
+    }
+
+    failed
+}
+
+/// The lint configuration for the file at `path`, or `None` after reporting why it is invalid.
+fn project_config(path: &str) -> Option<LintConfig> {
+    let directory = Path::new(path).parent().unwrap_or(Path::new("."));
+
+    match LintConfig::discover(directory) {
+        Ok((config, _)) => Some(config),
+        Err(error) => {
+            eprintln!("{error}");
+            None
+        }
+    }
+}
+
+fn list_rules() {
+    for rule in Registry::default().rules() {
+        println!(
+            "{:<20} {:<6} {}",
+            rule.name(),
+            rule.default_level().as_str(),
+            rule.description()
+        );
+    }
+}
+
+/// Prints the JSON parse of the file at `path`. Returns whether it could not be read.
+#[cfg(feature = "serde")]
+fn parse_json(path: &str, pretty: bool) -> bool {
+    match std::fs::read_to_string(path) {
+        Ok(source) => {
+            println!("{}", serendipity_parser::json::to_json(&source, pretty));
+            false
+        }
+        Err(error) => {
+            eprintln!("{path}: {error}");
+            true
+        }
+    }
+}
+
+#[cfg(not(feature = "serde"))]
+fn parse_json(_path: &str, _pretty: bool) -> bool {
+    eprintln!("sdp was built without the `serde` feature, so it cannot write JSON");
+    true
+}
+
+/// Prints the S-expression form of the file at `path`. Returns whether it could not be parsed.
+fn parse_sexpr(path: &str) -> bool {
+    let result = std::fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|source| {
+            parse_module(&source)
+                .module
+                .map(|module| sexpr::print_module(&module.value))
+                .ok_or_else(|| "the module does not parse".to_string())
+        });
+
+    match result {
+        Ok(text) => {
+            print!("{text}");
+            false
+        }
+        Err(error) => {
+            eprintln!("{path}: {error}");
+            true
+        }
+    }
+}
+
+/// Prints the patch that turns the file at `old` into the one at `new`. Returns whether it could
+/// not be made.
+fn diff_files(old: &str, new: &str) -> bool {
+    let read = |path: &str| std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}"));
+
+    let result = read(old).and_then(|old_source| {
+        let new_source = read(new)?;
+        diff_sources(&old_source, &new_source).map_err(|e| e.to_string())
+    });
+
+    match result {
+        Ok(patch) => {
+            print!("{patch}");
+            false
+        }
+        Err(error) => {
+            eprintln!("{error}");
+            true
+        }
+    }
+}
+
+/// Applies the patch at `patch` to the file at `path` in place. Returns whether it could not be
+/// applied.
+fn patch_file(path: &str, patch: &str) -> bool {
+    let read = |path: &str| std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}"));
+
+    let result = read(patch).and_then(|text| {
+        let patch: Patch = text.parse().map_err(|e| format!("{patch}: {e}"))?;
+        let source = read(path)?;
+        let patched = apply(&source, &patch).map_err(|e| format!("{path}: {e}"))?;
+        std::fs::write(path, patched).map_err(|e| format!("{path}: {e}"))
+    });
+
+    match result {
+        Ok(()) => false,
+        Err(error) => {
+            eprintln!("{error}");
+            true
+        }
+    }
+}
+
+/// Prints a graph of the node at `path` in the file at `file`. Returns whether it could not be
+/// drawn.
+fn graph_file(file: &str, path: &str, options: Options) -> bool {
+    let result = std::fs::read_to_string(file)
+        .map_err(|e| e.to_string())
+        .and_then(|source| {
+            let path = path.parse::<NodePath>().map_err(|e| e.to_string())?;
+            diagram::render_source(&source, &path, options).map_err(|e| e.to_string())
+        });
+
+    match result {
+        Ok(graph) => {
+            print!("{graph}");
+            false
+        }
+        Err(error) => {
+            eprintln!("{file}: {error}");
+            true
+        }
+    }
+}
+
+pub fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    match args.first().map(String::as_str) {
+        Some("repl") => repl(),
+        Some("lint") if args.get(1).map(String::as_str) == Some("--rules") => list_rules(),
+        Some("lint") if args.len() > 1 => {
+            if lint_files(&args[1..]) {
+                std::process::exit(1);
+            }
+        }
+        Some("fix") => {
+            let mut allowed = Applicability::MachineApplicable;
+            let mut paths = Vec::new();
+            for arg in &args[1..] {
+                match arg.as_str() {
+                    "--maybe-incorrect" => allowed = Applicability::MaybeIncorrect,
+                    flag if flag.starts_with("--") => {
+                        eprintln!("{USAGE}");
+                        std::process::exit(2);
+                    }
+                    _ => paths.push(arg.clone()),
+                }
+            }
+
+            if paths.is_empty() {
+                eprintln!("{USAGE}");
+                std::process::exit(2);
+            }
+            if fix_files(&paths, allowed) {
+                std::process::exit(1);
+            }
+        }
+        Some("parse") if args.get(1).map(String::as_str) == Some("--json") => {
+            let (pretty, rest) = match args.get(2).map(String::as_str) {
+                Some("--pretty") => (true, &args[3..]),
+                _ => (false, &args[2..]),
+            };
+            let [path] = rest else {
+                eprintln!("{USAGE}");
+                std::process::exit(2);
+            };
+
+            if parse_json(path, pretty) {
+                std::process::exit(1);
+            }
+        }
+        Some("parse") if args.get(1).map(String::as_str) == Some("--sexpr") => {
+            let [_, _, path] = args.as_slice() else {
+                eprintln!("{USAGE}");
+                std::process::exit(2);
+            };
+
+            if parse_sexpr(path) {
+                std::process::exit(1);
+            }
+        }
+        Some("diff") if args.len() == 3 => {
+            if diff_files(&args[1], &args[2]) {
+                std::process::exit(1);
+            }
+        }
+        Some("patch") if args.len() == 3 => {
+            if patch_file(&args[1], &args[2]) {
+                std::process::exit(1);
+            }
+        }
+        Some("graph") => {
+            let mut options = Options {
+                format: Format::Dot,
+                spans: false,
+            };
+            let mut rest = &args[1..];
+            while let Some((flag, tail)) = rest.split_first() {
+                match flag.as_str() {
+                    "--mermaid" => options.format = Format::Mermaid,
+                    "--spans" => options.spans = true,
+                    _ => break,
+                }
+                rest = tail;
+            }
+
+            let (file, path) = match rest {
+                [file] => (file, ""),
+                [file, path] => (file, path.as_str()),
+                _ => {
+                    eprintln!("{USAGE}");
+                    std::process::exit(2);
+                }
+            };
+
+            if graph_file(file, path, options) {
+                std::process::exit(1);
+            }
+        }
+        Some("-h" | "--help" | "help") => println!("{USAGE}"),
+        _ => {
+            eprintln!("{USAGE}");
+            std::process::exit(2);
+        }
+    }
+}
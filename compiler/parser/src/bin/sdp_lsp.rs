@@ -0,0 +1,12 @@
+use serendipity_parser::lsp::Server;
+
+pub fn main() {
+    let mut server = Server::new();
+
+    if let Err(error) = server.run(std::io::stdin().lock(), std::io::stdout().lock()) {
+        eprintln!("sdp-lsp: failed to communicate with the client: {error}");
+        std::process::exit(1);
+    }
+
+    std::process::exit(server.exit_code());
+}
@@ -0,0 +1,871 @@
+//! A module that several people can edit at the same time.
+//!
+//! A [`Document`] is a conflict-free replicated data type whose state is a module. Each client
+//! keeps its own replica, edits it locally, and sends the [`Op`] each edit returns to every other
+//! replica. Replicas that have applied the same ops have the same module, whatever order the ops
+//! arrived in. An op that depends on one that has not arrived yet is held back until it has.
+//!
+//! The module is held as a tree of fragments of source text with two kinds of slot in them:
+//!
+//! - A hole (`@`) is a last-writer-wins register. Filling it sets it to a new fragment, and when
+//!   two people fill the same hole at once, one fill wins on every replica.
+//! - The statements of a procedure, and the declarations of the module, form a sequence, ordered as
+//!   a replicated growable array. Concurrent insertions are all kept. Moving an element gives it a
+//!   new place and keeps the last move made, so an element is never duplicated or lost by moves.
+//!
+//! Edits inside different holes or different statements never interfere, and can be made inside
+//! text that someone else has just inserted. The source of the module is rendered from the tree,
+//! with statements on their own lines. Comments between declarations and statements are not kept.
+//!
+//! With the `serde` feature, ops can be serialized to send them between clients.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+use seglisp::{parse::ParseNode, DiagnosticSeverity};
+
+use crate::{
+    edit::{line_indent, span},
+    incremental::parse_module,
+    node::{NodePath, NodeRef, PathSegment},
+    owned::OwnedModule,
+    Expression, Module,
+};
+
+const INDENT: &str = "  ";
+
+/// Identifies a replica. Every replica of a document needs its own.
+pub type ReplicaId = u32;
+
+/// Identifies an op, or a hole, sequence, or element created by one.
+///
+/// Ids are ordered by Lamport timestamp, so an op is always greater than every op its replica had
+/// seen when it was made.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Id {
+    pub counter: u64,
+    pub replica: ReplicaId,
+    /// Numbers the holes, sequences, and elements an op creates, in the order they appear in its
+    /// fragment. The op itself is `0`.
+    pub sub: u32,
+}
+
+/// Source text with holes and sequences in it, as carried by an op.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Fragment {
+    pub pieces: Vec<Piece>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Piece {
+    /// Text, with the indentation of its first line taken off the lines after it.
+    Text(String),
+    /// An empty hole.
+    Hole,
+    /// The statements between the brackets of a procedure.
+    Sequence(Vec<Fragment>),
+}
+
+/// A change to a document, to be applied by every replica.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Op {
+    pub id: Id,
+    pub kind: OpKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OpKind {
+    /// Inserts `fragment` into `sequence` right after the place `after`, or at the front.
+    Insert {
+        sequence: Id,
+        after: Option<Id>,
+        fragment: Fragment,
+    },
+    Delete {
+        element: Id,
+    },
+    /// Moves `element` to right after the place `after` in its sequence, or to the front.
+    Move {
+        element: Id,
+        after: Option<Id>,
+    },
+    /// Fills `hole` with `fragment`, or empties it.
+    Fill {
+        hole: Id,
+        fragment: Option<Fragment>,
+    },
+}
+
+/// Something in a document that can be edited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Object {
+    Hole(Id),
+    /// The declarations of the module, or the statements of a procedure.
+    Sequence(Id),
+    /// A declaration or statement in a sequence.
+    Element(Id),
+}
+
+/// The error returned when a local edit cannot be made.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrdtError(pub String);
+
+impl fmt::Display for CrdtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for CrdtError {}
+
+impl Fragment {
+    /// The fragment of `node`, which was parsed from `source`.
+    fn of(source: &str, node: NodeRef) -> Fragment {
+        let (start, end) = span(node.range());
+        let mut builder = FragmentBuilder {
+            source,
+            indent: line_indent(source, start),
+            cursor: start,
+            pieces: Vec::new(),
+        };
+
+        builder.visit(node);
+        builder.text(end);
+
+        Fragment {
+            pieces: builder.pieces,
+        }
+    }
+
+    /// Parses `text` as a single declaration.
+    pub fn declaration(text: &str) -> Result<Fragment, CrdtError> {
+        let text = text.trim().trim_end_matches(';');
+        parse_checked(text, |module| match &module.value.declarations[..] {
+            [declaration] => Some(NodeRef::Declaration(declaration)),
+            _ => None,
+        })
+        .ok_or_else(|| CrdtError(format!("'{text}' is not a declaration")))
+    }
+
+    /// Parses `text` as a single statement.
+    pub fn statement(text: &str) -> Result<Fragment, CrdtError> {
+        let text = text.trim().trim_end_matches(';');
+        let wrapped = format!("main #[\n{text};\n];");
+
+        parse_checked(&wrapped, |module| {
+            match NodeRef::Module(module).descendant(&main_body())? {
+                NodeRef::Expression(ParseNode {
+                    value: Expression::Procedure { body },
+                    ..
+                }) => match &body.value[..] {
+                    [statement] => Some(NodeRef::Statement(statement)),
+                    _ => None,
+                },
+                _ => None,
+            }
+        })
+        .ok_or_else(|| CrdtError(format!("'{text}' is not a statement")))
+    }
+
+    /// Parses `text` as an expression, parenthesized if it would not otherwise stay whole as an
+    /// operand.
+    pub fn expression(text: &str) -> Result<Fragment, CrdtError> {
+        let text = text.trim();
+        let wrapped = format!("main {text};");
+
+        let mut compound = false;
+        let fragment = parse_checked(&wrapped, |module| {
+            let body = NodeRef::Module(module).descendant(&main_body())?;
+            compound = matches!(
+                body.kind(),
+                "As" | "Unary" | "Compare" | "Arithmetic" | "Function" | "With" | "If"
+            );
+            Some(body)
+        })
+        .ok_or_else(|| CrdtError(format!("'{text}' is not an expression")))?;
+
+        Ok(if compound {
+            let mut pieces = vec![Piece::Text("(".into())];
+            pieces.extend(fragment.pieces);
+            pieces.push(Piece::Text(")".into()));
+            Fragment { pieces }
+        } else {
+            fragment
+        })
+    }
+}
+
+/// The path to the body of `main` in a module of a single `main` declaration.
+fn main_body() -> NodePath {
+    NodePath(vec![
+        PathSegment::indexed("declarations", 0),
+        PathSegment::field("body"),
+    ])
+}
+
+/// Parses `source`, which must have no errors, and takes the fragment of the node `find` picks.
+fn parse_checked(
+    source: &str,
+    find: impl for<'a, 'ast> FnOnce(&'a ParseNode<Module<'ast>>) -> Option<NodeRef<'a, 'ast>>,
+) -> Option<Fragment> {
+    let parsed = parse_module(source);
+    if parsed
+        .diagnostics
+        .iter()
+        .any(|d| matches!(d.severity, DiagnosticSeverity::Error))
+    {
+        return None;
+    }
+
+    find(parsed.module.as_ref()?).map(|node| Fragment::of(source, node))
+}
+
+struct FragmentBuilder<'s> {
+    source: &'s str,
+    indent: &'s str,
+    cursor: usize,
+    pieces: Vec<Piece>,
+}
+
+impl FragmentBuilder<'_> {
+    /// Takes the text from the cursor up to `to`.
+    fn text(&mut self, to: usize) {
+        if to > self.cursor {
+            let text = &self.source[self.cursor..to];
+            let text = if self.indent.is_empty() {
+                text.to_string()
+            } else {
+                text.replace(&format!("\n{}", self.indent), "\n")
+            };
+            self.pieces.push(Piece::Text(text));
+        }
+        self.cursor = to.max(self.cursor);
+    }
+
+    fn visit(&mut self, node: NodeRef) {
+        match node {
+            NodeRef::Expression(ParseNode {
+                value: Expression::Hole,
+                range,
+                ..
+            }) => {
+                let (start, end) = span(*range);
+                self.text(start);
+                self.pieces.push(Piece::Hole);
+                self.cursor = end;
+            }
+            NodeRef::Expression(ParseNode {
+                value: Expression::Procedure { body },
+                range,
+                ..
+            }) => {
+                let (start, end) = span(*range);
+
+                // The range may or may not include the brackets.
+                let open = self.source[start..end]
+                    .find('[')
+                    .map(|i| start + i)
+                    .or_else(|| self.source[..start].rfind('['))
+                    .unwrap_or(start);
+                let close = self.source[open..end]
+                    .rfind(']')
+                    .map(|i| open + i)
+                    .or_else(|| self.source[end..].find(']').map(|i| end + i))
+                    .unwrap_or(end);
+
+                self.text(open + 1);
+                self.pieces.push(Piece::Sequence(
+                    body.value
+                        .iter()
+                        .map(|statement| Fragment::of(self.source, NodeRef::Statement(statement)))
+                        .collect(),
+                ));
+                self.cursor = close;
+            }
+            _ => {
+                for (_, child) in node.children() {
+                    self.visit(child);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Part {
+    Text(String),
+    Slot(Id),
+}
+
+#[derive(Debug, Clone)]
+enum Slot {
+    /// The fill with the greatest id wins.
+    Hole {
+        fill: Option<(Id, Option<Id>)>,
+    },
+    Sequence(Sequence),
+}
+
+/// The places of a sequence form a tree, each place following the one it was inserted after. The
+/// order of the sequence is a pre-order walk of the tree, with later places first among siblings.
+#[derive(Debug, Clone, Default)]
+struct Sequence {
+    places: BTreeSet<Id>,
+    /// The places inserted right after each place, or at the front, in ascending order.
+    following: BTreeMap<Option<Id>, Vec<Id>>,
+    /// The elements that are, or once were, in the sequence.
+    members: BTreeSet<Id>,
+}
+
+impl Sequence {
+    fn add_place(&mut self, place: Id, after: Option<Id>) {
+        let following = self.following.entry(after).or_default();
+        let idx = following.partition_point(|p| *p < place);
+        following.insert(idx, place);
+        self.places.insert(place);
+    }
+
+    fn order(&self) -> Vec<Id> {
+        let mut order = Vec::with_capacity(self.places.len());
+        let mut stack: Vec<Id> = self.following.get(&None).cloned().unwrap_or_default();
+
+        while let Some(place) = stack.pop() {
+            order.push(place);
+            if let Some(following) = self.following.get(&Some(place)) {
+                stack.extend(following);
+            }
+        }
+
+        order
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Element {
+    sequence: Id,
+    fragment: Id,
+    deleted: bool,
+    /// The place the element is at. A move makes a new place whose id is that of the move, so the
+    /// greatest place is the latest.
+    place: Id,
+}
+
+/// A replica of a module being edited by several people.
+#[derive(Debug, Clone)]
+pub struct Document {
+    replica: ReplicaId,
+    clock: u64,
+    root: Id,
+    fragments: BTreeMap<Id, Vec<Part>>,
+    slots: BTreeMap<Id, Slot>,
+    elements: BTreeMap<Id, Element>,
+    applied: BTreeSet<Id>,
+    /// Ops that have arrived before something they refer to.
+    pending: Vec<Op>,
+}
+
+/// The source of a document, with where each object was rendered.
+struct Rendered {
+    source: String,
+    spans: Vec<(Object, usize, usize)>,
+}
+
+impl Document {
+    /// Parses `source` as the starting state of a replica. Every replica of a document must start
+    /// from the same source.
+    pub fn new(replica: ReplicaId, source: &str) -> Result<Document, CrdtError> {
+        let parsed = parse_module(source);
+        if parsed
+            .diagnostics
+            .iter()
+            .any(|d| matches!(d.severity, DiagnosticSeverity::Error))
+        {
+            return Err(CrdtError("the module does not parse".into()));
+        }
+        let module = parsed
+            .module
+            .as_ref()
+            .ok_or_else(|| CrdtError("the module does not parse".into()))?;
+
+        let declarations = module
+            .value
+            .declarations
+            .iter()
+            .map(|declaration| Fragment::of(source, NodeRef::Declaration(declaration)))
+            .collect();
+
+        let mut document = Document {
+            replica,
+            clock: 0,
+            root: Id {
+                counter: 0,
+                replica: 0,
+                sub: 0,
+            },
+            fragments: BTreeMap::new(),
+            slots: BTreeMap::new(),
+            elements: BTreeMap::new(),
+            applied: BTreeSet::new(),
+            pending: Vec::new(),
+        };
+
+        // The starting state is the same on every replica, so it is made by the same op on each.
+        let start = document.root;
+        let mut sub = 0;
+        let fragment = document.instantiate(
+            start,
+            &Fragment {
+                pieces: vec![Piece::Sequence(declarations)],
+            },
+            &mut sub,
+        );
+        let Some(Part::Slot(root)) = document.fragments[&fragment].first() else {
+            unreachable!("the root fragment is a single sequence");
+        };
+        document.root = *root;
+
+        Ok(document)
+    }
+
+    /// A copy of this replica with another id, for a new client to start from.
+    pub fn fork(&self, replica: ReplicaId) -> Document {
+        Document {
+            replica,
+            ..self.clone()
+        }
+    }
+
+    pub fn replica(&self) -> ReplicaId {
+        self.replica
+    }
+
+    /// The declarations of the module.
+    pub fn root(&self) -> Id {
+        self.root
+    }
+
+    /// The number of ops that have arrived but are waiting for ones they depend on.
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// The source of the module.
+    pub fn source(&self) -> String {
+        self.render().source
+    }
+
+    /// The module, parsed from [`Document::source`].
+    pub fn module(&self) -> OwnedModule {
+        OwnedModule::parse(self.source())
+    }
+
+    /// The elements in `sequence`, in order.
+    pub fn elements(&self, sequence: Id) -> Vec<Id> {
+        let Some(Slot::Sequence(sequence)) = self.slots.get(&sequence) else {
+            return Vec::new();
+        };
+
+        let at: BTreeMap<Id, Id> = sequence
+            .members
+            .iter()
+            .filter(|id| !self.elements[id].deleted)
+            .map(|id| (self.elements[id].place, *id))
+            .collect();
+
+        sequence
+            .order()
+            .into_iter()
+            .filter_map(|place| at.get(&place).copied())
+            .collect()
+    }
+
+    /// The sequences in the module, in source order, starting with the module's declarations.
+    pub fn sequences(&self) -> Vec<Id> {
+        self.render()
+            .spans
+            .into_iter()
+            .filter_map(|(object, ..)| match object {
+                Object::Sequence(id) => Some(id),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The empty holes in the module, in source order.
+    pub fn holes(&self) -> Vec<Id> {
+        self.render()
+            .spans
+            .into_iter()
+            .filter_map(|(object, ..)| match object {
+                Object::Hole(id) => Some(id),
+                _ => None,
+            })
+            .filter(|id| match self.slots[id] {
+                Slot::Hole { fill } => fill.is_none_or(|(_, fragment)| fragment.is_none()),
+                Slot::Sequence(_) => false,
+            })
+            .collect()
+    }
+
+    /// The object at `path` in [`Document::module`]: the element of a declaration or statement, the
+    /// sequence of a procedure or the module, or the hole a filled or empty hole expression is in.
+    pub fn find(&self, path: &NodePath) -> Option<Object> {
+        let rendered = self.render();
+        let parsed = parse_module(&rendered.source);
+        let module = parsed.module.as_ref()?;
+        let node = NodeRef::Module(module).descendant(path)?;
+        let (start, end) = span(node.range());
+
+        let exact = |kind: fn(&Object) -> bool| {
+            rendered
+                .spans
+                .iter()
+                .find(|(object, s, e)| kind(object) && (*s, *e) == (start, end))
+                .map(|(object, ..)| *object)
+        };
+
+        match node {
+            NodeRef::Module(_) => Some(Object::Sequence(self.root)),
+            NodeRef::Declaration(_) | NodeRef::Statement(_) => {
+                exact(|o| matches!(o, Object::Element(_)))
+            }
+            NodeRef::Expression(ParseNode {
+                value: Expression::Procedure { .. },
+                ..
+            }) => rendered
+                .spans
+                .iter()
+                .find(|(object, s, e)| {
+                    matches!(object, Object::Sequence(_)) && start <= *s && *e <= end
+                })
+                .map(|(object, ..)| *object),
+            NodeRef::Expression(_) => exact(|o| matches!(o, Object::Hole(_))),
+            _ => None,
+        }
+    }
+
+    fn next_id(&mut self) -> Id {
+        self.clock += 1;
+        Id {
+            counter: self.clock,
+            replica: self.replica,
+            sub: 0,
+        }
+    }
+
+    /// Applies a local op and returns it, to be sent to the other replicas.
+    fn local(&mut self, kind: OpKind) -> Op {
+        let op = Op {
+            id: self.next_id(),
+            kind,
+        };
+        self.integrate(&op);
+        op
+    }
+
+    fn element(&self, element: Id) -> Result<&Element, CrdtError> {
+        self.elements
+            .get(&element)
+            .filter(|e| !e.deleted)
+            .ok_or_else(|| CrdtError(format!("there is no element {element:?}")))
+    }
+
+    /// The place of `after`, which must be an element of `sequence`.
+    fn place_after(&self, sequence: Id, after: Option<Id>) -> Result<Option<Id>, CrdtError> {
+        after
+            .map(|after| {
+                let element = self.element(after)?;
+                if element.sequence != sequence {
+                    return Err(CrdtError(format!(
+                        "element {after:?} is not in sequence {sequence:?}"
+                    )));
+                }
+                Ok(element.place)
+            })
+            .transpose()
+    }
+
+    /// Fills `hole` with the expression `text`, or empties it if `text` is `None`.
+    pub fn fill(&mut self, hole: Id, text: Option<&str>) -> Result<Op, CrdtError> {
+        if !matches!(self.slots.get(&hole), Some(Slot::Hole { .. })) {
+            return Err(CrdtError(format!("there is no hole {hole:?}")));
+        }
+        let fragment = text.map(Fragment::expression).transpose()?;
+
+        Ok(self.local(OpKind::Fill { hole, fragment }))
+    }
+
+    /// Inserts `text` into `sequence` right after the element `after`, or at the front. `text` is
+    /// a declaration if `sequence` is the module's, and a statement otherwise.
+    pub fn insert(&mut self, sequence: Id, after: Option<Id>, text: &str) -> Result<Op, CrdtError> {
+        if !matches!(self.slots.get(&sequence), Some(Slot::Sequence(_))) {
+            return Err(CrdtError(format!("there is no sequence {sequence:?}")));
+        }
+        let after = self.place_after(sequence, after)?;
+        let fragment = if sequence == self.root {
+            Fragment::declaration(text)?
+        } else {
+            Fragment::statement(text)?
+        };
+
+        Ok(self.local(OpKind::Insert {
+            sequence,
+            after,
+            fragment,
+        }))
+    }
+
+    pub fn delete(&mut self, element: Id) -> Result<Op, CrdtError> {
+        self.element(element)?;
+        Ok(self.local(OpKind::Delete { element }))
+    }
+
+    /// Moves `element` to right after the element `after` of the same sequence, or to the front.
+    pub fn move_after(&mut self, element: Id, after: Option<Id>) -> Result<Op, CrdtError> {
+        let sequence = self.element(element)?.sequence;
+        if after == Some(element) {
+            return Err(CrdtError(format!(
+                "element {element:?} cannot be moved after itself"
+            )));
+        }
+        let after = self.place_after(sequence, after)?;
+
+        Ok(self.local(OpKind::Move { element, after }))
+    }
+
+    /// Applies an op from another replica. Ops that were already applied are ignored, and ops
+    /// that refer to something that has not arrived yet wait for it.
+    pub fn apply(&mut self, op: Op) {
+        if self.applied.contains(&op.id) || self.pending.iter().any(|p| p.id == op.id) {
+            return;
+        }
+        self.pending.push(op);
+
+        while let Some(idx) = self.pending.iter().position(|op| self.ready(op)) {
+            let op = self.pending.remove(idx);
+            self.integrate(&op);
+        }
+    }
+
+    fn has_place(&self, sequence: Id, place: Option<Id>) -> bool {
+        match self.slots.get(&sequence) {
+            Some(Slot::Sequence(sequence)) => place.is_none_or(|p| sequence.places.contains(&p)),
+            _ => false,
+        }
+    }
+
+    /// Whether everything `op` refers to has arrived.
+    fn ready(&self, op: &Op) -> bool {
+        match &op.kind {
+            OpKind::Insert {
+                sequence, after, ..
+            } => self.has_place(*sequence, *after),
+            OpKind::Delete { element } => self.elements.contains_key(element),
+            OpKind::Move { element, after } => self
+                .elements
+                .get(element)
+                .is_some_and(|e| self.has_place(e.sequence, *after)),
+            OpKind::Fill { hole, .. } => matches!(self.slots.get(hole), Some(Slot::Hole { .. })),
+        }
+    }
+
+    fn integrate(&mut self, op: &Op) {
+        self.clock = self.clock.max(op.id.counter);
+        self.applied.insert(op.id);
+
+        match &op.kind {
+            OpKind::Insert {
+                sequence,
+                after,
+                fragment,
+            } => {
+                let mut sub = 0;
+                let fragment = self.instantiate(op.id, fragment, &mut sub);
+                self.add_element(*sequence, op.id, *after, fragment);
+            }
+            OpKind::Delete { element } => {
+                if let Some(element) = self.elements.get_mut(element) {
+                    element.deleted = true;
+                }
+            }
+            OpKind::Move { element, after } => {
+                let Some(sequence) = self.elements.get(element).map(|e| e.sequence) else {
+                    return;
+                };
+                if let Some(Slot::Sequence(sequence)) = self.slots.get_mut(&sequence) {
+                    sequence.add_place(op.id, *after);
+                }
+                if let Some(element) = self.elements.get_mut(element) {
+                    element.place = element.place.max(op.id);
+                }
+            }
+            OpKind::Fill { hole, fragment } => {
+                let mut sub = 0;
+                let fragment = fragment
+                    .as_ref()
+                    .map(|fragment| self.instantiate(op.id, fragment, &mut sub));
+
+                if let Some(Slot::Hole { fill }) = self.slots.get_mut(hole) {
+                    if fill.is_none_or(|(id, _)| id < op.id) {
+                        *fill = Some((op.id, fragment));
+                    }
+                }
+            }
+        }
+    }
+
+    fn add_element(&mut self, sequence: Id, element: Id, after: Option<Id>, fragment: Id) {
+        if let Some(Slot::Sequence(sequence)) = self.slots.get_mut(&sequence) {
+            sequence.add_place(element, after);
+            sequence.members.insert(element);
+        }
+
+        self.elements.insert(
+            element,
+            Element {
+                sequence,
+                fragment,
+                deleted: false,
+                place: element,
+            },
+        );
+    }
+
+    /// Creates the fragment, holes, sequences, and elements of `fragment`, numbering their ids
+    /// from `sub` under `op`. Returns the id of the fragment.
+    fn instantiate(&mut self, op: Id, fragment: &Fragment, sub: &mut u32) -> Id {
+        let id = next(op, sub);
+
+        let mut parts = Vec::with_capacity(fragment.pieces.len());
+        for piece in &fragment.pieces {
+            match piece {
+                Piece::Text(text) => parts.push(Part::Text(text.clone())),
+                Piece::Hole => {
+                    let hole = next(op, sub);
+                    self.slots.insert(hole, Slot::Hole { fill: None });
+                    parts.push(Part::Slot(hole));
+                }
+                Piece::Sequence(statements) => {
+                    let sequence = next(op, sub);
+                    self.slots
+                        .insert(sequence, Slot::Sequence(Sequence::default()));
+
+                    let mut after = None;
+                    for statement in statements {
+                        let element = next(op, sub);
+                        let fragment = self.instantiate(op, statement, sub);
+                        self.add_element(sequence, element, after, fragment);
+                        after = Some(element);
+                    }
+                    parts.push(Part::Slot(sequence));
+                }
+            }
+        }
+
+        self.fragments.insert(id, parts);
+        id
+    }
+
+    fn render(&self) -> Rendered {
+        let mut renderer = Renderer {
+            document: self,
+            rendered: Rendered {
+                source: String::new(),
+                spans: vec![(Object::Sequence(self.root), 0, 0)],
+            },
+        };
+
+        for (idx, element) in self.elements(self.root).into_iter().enumerate() {
+            if idx > 0 {
+                renderer.rendered.source.push('\n');
+            }
+            renderer.element(element, 0);
+            renderer.rendered.source.push_str(";\n");
+        }
+
+        renderer.rendered.spans[0].2 = renderer.rendered.source.len();
+        renderer.rendered
+    }
+}
+
+/// The next id under `op`.
+fn next(op: Id, sub: &mut u32) -> Id {
+    *sub += 1;
+    Id { sub: *sub, ..op }
+}
+
+struct Renderer<'d> {
+    document: &'d Document,
+    rendered: Rendered,
+}
+
+impl Renderer<'_> {
+    fn len(&self) -> usize {
+        self.rendered.source.len()
+    }
+
+    /// Records `object` as starting here, returning where to record its end.
+    fn open(&mut self, object: Object) -> usize {
+        let start = self.len();
+        self.rendered.spans.push((object, start, start));
+        self.rendered.spans.len() - 1
+    }
+
+    fn close(&mut self, idx: usize) {
+        self.rendered.spans[idx].2 = self.len();
+    }
+
+    fn fragment(&mut self, fragment: Id, depth: usize) {
+        let document = self.document;
+
+        for part in &document.fragments[&fragment] {
+            match part {
+                Part::Text(text) => {
+                    let indent = INDENT.repeat(depth);
+                    self.rendered
+                        .source
+                        .push_str(&text.replace('\n', &format!("\n{indent}")));
+                }
+                Part::Slot(id) => match &document.slots[id] {
+                    Slot::Hole { fill } => {
+                        let span = self.open(Object::Hole(*id));
+                        match fill.and_then(|(_, fragment)| fragment) {
+                            Some(fragment) => self.fragment(fragment, depth),
+                            None => self.rendered.source.push('@'),
+                        }
+                        self.close(span);
+                    }
+                    Slot::Sequence(_) => {
+                        let span = self.open(Object::Sequence(*id));
+                        let elements = document.elements(*id);
+
+                        for element in &elements {
+                            self.rendered.source.push('\n');
+                            self.rendered.source.push_str(&INDENT.repeat(depth + 1));
+                            self.element(*element, depth + 1);
+                            self.rendered.source.push(';');
+                        }
+                        if !elements.is_empty() {
+                            self.rendered.source.push('\n');
+                            self.rendered.source.push_str(&INDENT.repeat(depth));
+                        }
+                        self.close(span);
+                    }
+                },
+            }
+        }
+    }
+
+    fn element(&mut self, element: Id, depth: usize) {
+        let span = self.open(Object::Element(element));
+        self.fragment(self.document.elements[&element].fragment, depth);
+        self.close(span);
+    }
+}
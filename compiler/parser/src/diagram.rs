@@ -0,0 +1,235 @@
+//! Diagrams of the surface AST, for teaching and debugging.
+//!
+//! [`render`] draws a module or any subtree of it as a Graphviz DOT or Mermaid graph. Each node is
+//! labelled with its variant and the token that tells it apart from its siblings (an operator, a
+//! name, or a literal), and each edge with the field that holds the child, e.g. `left` or
+//! `parameters[0]`. Spans can be added to the labels as one-based `line:column` pairs.
+
+use std::{fmt, str::FromStr};
+
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    incremental::parse_module,
+    node::{NodePath, NodeRef},
+    BindingPattern, Declaration, Expression, RecordBindingElement, RecordElement, Statement, Type,
+};
+
+/// The graph language to write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Dot,
+    Mermaid,
+}
+
+impl FromStr for Format {
+    type Err = DiagramError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dot" => Ok(Format::Dot),
+            "mermaid" => Ok(Format::Mermaid),
+            _ => Err(DiagramError(format!(
+                "unknown diagram format `{s}`, expected `dot` or `mermaid`"
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Options {
+    pub format: Format,
+    /// Whether to add the span of each node to its label.
+    pub spans: bool,
+}
+
+/// An error drawing a diagram of a source file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiagramError(pub String);
+
+impl fmt::Display for DiagramError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for DiagramError {}
+
+/// The label of `node`: its variant, followed by its operator, name, or literal if it has one.
+pub fn label(node: &NodeRef) -> String {
+    match token(node) {
+        Some(token) => format!("{} {token}", node.kind()),
+        None => node.kind().to_string(),
+    }
+}
+
+fn token(node: &NodeRef) -> Option<String> {
+    let text = |t: &str| Some(t.to_string());
+
+    match node {
+        NodeRef::Declaration(node) => match &node.value {
+            Declaration::Const { identifier, .. } | Declaration::Function { identifier, .. } => {
+                text(identifier.value)
+            }
+            Declaration::TypeAlias { name, .. } | Declaration::Interface { name, .. } => {
+                text(name.value)
+            }
+            Declaration::Import {
+                module_specifier, ..
+            } => text(module_specifier.value),
+            Declaration::Main { .. } | Declaration::Export { .. } => None,
+        },
+        NodeRef::Expression(node) => match &node.value {
+            Expression::Number(n) | Expression::Name(n) => Some(n.to_string()),
+            Expression::String(s) => Some(format!("{s:?}")),
+            Expression::Boolean(b) => Some(b.to_string()),
+            Expression::Unary { operator, .. } => Some(operator.value.to_string()),
+            Expression::Compare { operator, .. } => Some(operator.value.to_string()),
+            Expression::Arithmetic { operator, .. } => Some(operator.value.to_string()),
+            Expression::Function { name, .. } => name.as_ref().and_then(|name| text(name.value)),
+            Expression::FieldAccess { field, .. } => Some(format!(".{}", field.value)),
+            _ => None,
+        },
+        NodeRef::Type(node) => match &node.value {
+            Type::Reference { name, .. } => text(name.value),
+            _ => None,
+        },
+        NodeRef::GenericParameter(node) => text(node.value.name.value),
+        NodeRef::InterfaceField(node) => text(node.value.name.value),
+        NodeRef::Parameter(node) => text(node.value.name.value),
+        NodeRef::Assignment(node) => text(node.value.symbol.value),
+        NodeRef::RecordElement(node) => match &node.value {
+            RecordElement::KeyValuePair { key, .. } => text(key.value),
+            RecordElement::Identifier { name } => text(name.value),
+            RecordElement::Spread { .. } => None,
+        },
+        NodeRef::Statement(node) => match &node.value {
+            Statement::ForIn { binding, .. } => text(binding.value),
+            _ => None,
+        },
+        NodeRef::BindingPattern(node) => match &node.value {
+            BindingPattern::Identifier { name } => text(name.value),
+            _ => None,
+        },
+        NodeRef::RecordBindingElement(node) => match &node.value {
+            RecordBindingElement::Identifier { name }
+            | RecordBindingElement::KeyValuePair { name, .. }
+            | RecordBindingElement::Rest { name } => text(name.value),
+        },
+        NodeRef::Identifier(node) => text(node.value),
+        NodeRef::Module(_) | NodeRef::TypeConstraint(_) => None,
+    }
+}
+
+/// Draws `root` and everything below it.
+pub fn render(root: NodeRef, options: Options) -> String {
+    let mut out = String::new();
+    let mut count = 0;
+
+    match options.format {
+        Format::Dot => out.push_str("digraph ast {\n  node [shape=box, fontname=\"monospace\"];\n"),
+        Format::Mermaid => out.push_str("graph TD\n"),
+    }
+    draw(&mut out, root, &mut count, options);
+    if options.format == Format::Dot {
+        out.push_str("}\n");
+    }
+
+    out
+}
+
+/// Draws `node` and its descendants, numbering them in pre-order. Returns the number of `node`.
+fn draw(out: &mut String, node: NodeRef, count: &mut usize, options: Options) -> usize {
+    let id = *count;
+    *count += 1;
+
+    let mut lines = vec![label(&node)];
+    if options.spans {
+        let (start, end) = node.range();
+        lines.push(format!(
+            "{}:{}-{}:{}",
+            start.line + 1,
+            start.column + 1,
+            end.line + 1,
+            end.column + 1
+        ));
+    }
+
+    match options.format {
+        Format::Dot => out.push_str(&format!(
+            "  n{id} [label=\"{}\"];\n",
+            lines
+                .iter()
+                .map(|l| escape_dot(l))
+                .collect::<Vec<_>>()
+                .join("\\n")
+        )),
+        Format::Mermaid => out.push_str(&format!(
+            "  n{id}[\"{}\"]\n",
+            lines
+                .iter()
+                .map(|l| escape_mermaid(l))
+                .collect::<Vec<_>>()
+                .join("<br/>")
+        )),
+    }
+
+    for (segment, child) in node.children() {
+        let child_id = draw(out, child, count, options);
+        let field = segment.to_string();
+
+        match options.format {
+            Format::Dot => out.push_str(&format!(
+                "  n{id} -> n{child_id} [label=\"{}\"];\n",
+                escape_dot(&field)
+            )),
+            Format::Mermaid => out.push_str(&format!(
+                "  n{id} -->|\"{}\"| n{child_id}\n",
+                escape_mermaid(&field)
+            )),
+        }
+    }
+
+    id
+}
+
+fn escape_dot(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Mermaid labels are HTML, and take entities in Mermaid's own `#name;` syntax.
+fn escape_mermaid(text: &str) -> String {
+    text.replace('#', "#35;")
+        .replace('"', "#quot;")
+        .replace('<', "#lt;")
+        .replace('>', "#gt;")
+}
+
+/// Parses `source` and draws the node at `path`.
+pub fn render_source(
+    source: &str,
+    path: &NodePath,
+    options: Options,
+) -> Result<String, DiagramError> {
+    let parsed = parse_module(source);
+    let module = parsed
+        .module
+        .as_ref()
+        .ok_or_else(|| DiagramError("the module does not parse".into()))?;
+
+    NodeRef::Module(module)
+        .descendant(path)
+        .map(|node| render(node, options))
+        .ok_or_else(|| DiagramError(format!("there is no node at `{path}`")))
+}
+
+/// Draws the node at `path` (the whole module if it is empty) as a `dot` or `mermaid` graph.
+/// Returns `null` if the module does not parse or there is no such node.
+#[wasm_bindgen]
+pub fn ast_graph(data: &[u8], path: &str, format: &str, spans: bool) -> Option<String> {
+    let source = core::str::from_utf8(data).expect("data was not valid UTF-8");
+    let path = path.parse::<NodePath>().ok()?;
+    let format = format.parse().ok()?;
+
+    render_source(source, &path, Options { format, spans }).ok()
+}
@@ -0,0 +1,526 @@
+//! Structured editing of a module's source.
+//!
+//! Each [`Edit`] names its target by a [`NodePath`] and is carried out as a small set of
+//! [`TextEdit`]s on the source, so that formatting and comments outside the edited node are left
+//! exactly as they were. The edited source is parsed again before it is returned, and an edit whose
+//! result does not parse is refused.
+//!
+//! Inserted text that spans several lines is indented to match the line it is inserted on.
+
+use std::fmt;
+
+use wasm_bindgen::prelude::*;
+
+use seglisp::{
+    js_interop::{JsInterop, JsValue},
+    parse::ParseNode,
+    DiagnosticSeverity,
+};
+
+use crate::{
+    incremental::{apply_edits, parse_module, ParsedModule, TextEdit},
+    node::{NodePath, NodeRef},
+    Declaration, Expression, Module, ParameterDeclaration, ParsedVec, Statement,
+};
+
+const INDENT: &str = "  ";
+
+/// An expression to wrap another in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Wrapper {
+    /// `if condition then <expr> else @`
+    If { condition: String },
+    /// `with (name = value) <expr>`
+    With { name: String, value: String },
+    /// `fn (parameters...) -> <expr>`
+    Function { parameters: Vec<String> },
+}
+
+/// A structured edit of a module.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Edit {
+    /// Replaces the node at `path` with `text`.
+    Replace { path: NodePath, text: String },
+    /// Inserts the statement `text` into the body of the procedure at `path`, before the statement
+    /// at `index`, or at the end if `index` is the number of statements.
+    InsertStatement {
+        path: NodePath,
+        index: usize,
+        text: String,
+    },
+    /// Deletes the statement at `index` from the body of the procedure at `path`.
+    DeleteStatement { path: NodePath, index: usize },
+    /// Wraps the expression at `path`.
+    Wrap { path: NodePath, wrapper: Wrapper },
+    /// Inserts the parameter `text` into the function at `path`, before the parameter at `index`,
+    /// or at the end if `index` is the number of parameters.
+    AddParameter {
+        path: NodePath,
+        index: usize,
+        text: String,
+    },
+    /// Removes the parameter at `index` from the function at `path`.
+    RemoveParameter { path: NodePath, index: usize },
+}
+
+/// An edit that could not be made.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EditError(pub String);
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for EditError {}
+
+/// The result of a successful edit.
+#[derive(Debug, Clone)]
+pub struct Edited {
+    /// The edited source.
+    pub source: String,
+    /// The changes made to the original source.
+    pub edits: Vec<TextEdit>,
+}
+
+impl Edited {
+    /// Parses the edited source.
+    pub fn parse(&self) -> ParsedModule<'_> {
+        parse_module(&self.source)
+    }
+}
+
+/// Applies `edit` to `source`, checking that the result still parses.
+pub fn edit(source: &str, edit: &Edit) -> Result<Edited, EditError> {
+    let parsed = parse_module(source);
+    let module = parsed
+        .module
+        .as_ref()
+        .ok_or_else(|| EditError("the module could not be parsed".into()))?;
+
+    let edits = text_edits(source, module, edit)?;
+    let source = apply_edits(source, &edits).map_err(|e| EditError(e.0))?;
+
+    let errors: Vec<String> = parse_module(&source)
+        .diagnostics
+        .iter()
+        .filter(|d| matches!(d.severity, DiagnosticSeverity::Error))
+        .map(|d| d.message.clone())
+        .collect();
+
+    if !errors.is_empty() {
+        return Err(EditError(format!(
+            "the edited module does not parse: {}",
+            errors.join("; ")
+        )));
+    }
+
+    Ok(Edited { source, edits })
+}
+
+/// Works out the changes to `source`, which `module` was parsed from, that carry out `edit`. The
+/// result is not checked.
+pub fn text_edits(
+    source: &str,
+    module: &ParseNode<Module>,
+    edit: &Edit,
+) -> Result<Vec<TextEdit>, EditError> {
+    match edit {
+        Edit::Replace { path, text } => {
+            let (start, end) = span(find(module, path)?.range());
+            Ok(vec![TextEdit {
+                start,
+                end,
+                text: reindent(text, line_indent(source, start)),
+            }])
+        }
+        Edit::InsertStatement { path, index, text } => {
+            let (range, statements) = procedure_body(module, path)?;
+            let spans: Vec<_> = statements.iter().map(|s| span(s.range)).collect();
+            let text = text.trim_end().trim_end_matches(';');
+
+            let list = List {
+                span: span(range),
+                open: "[",
+                separator: ";",
+            };
+            insert_into_list(source, path, &list, &spans, *index, text).map(|e| vec![e])
+        }
+        Edit::DeleteStatement { path, index } => {
+            let (_, statements) = procedure_body(module, path)?;
+            let statement = statements
+                .get(*index)
+                .ok_or_else(|| out_of_range("statement", path, *index))?;
+
+            Ok(vec![delete_statement(source, span(statement.range))])
+        }
+        Edit::Wrap { path, wrapper } => {
+            let NodeRef::Expression(expr) = find(module, path)? else {
+                return Err(EditError(format!("'{path}' is not an expression")));
+            };
+
+            let (start, end) = span(expr.range);
+            let inner = &source[start..end];
+            let mut text = match wrapper {
+                Wrapper::If { condition } => format!("if {condition} then {inner} else @"),
+                Wrapper::With { name, value } => format!("with ({name} = {value}) {inner}"),
+                Wrapper::Function { parameters } => {
+                    format!("fn ({}) -> {inner}", parameters.join(", "))
+                }
+            };
+
+            // Wrappers extend as far to the right as they can, so they need parentheses as an
+            // operand.
+            if is_operand(module, path) {
+                text = format!("({text})");
+            }
+
+            Ok(vec![TextEdit { start, end, text }])
+        }
+        Edit::AddParameter { path, index, text } => {
+            let parameters = parameters(module, path)?;
+            let spans: Vec<_> = parameters.value.iter().map(|p| span(p.range)).collect();
+
+            let list = List {
+                span: span(parameters.range),
+                open: "(",
+                separator: ",",
+            };
+            insert_into_list(source, path, &list, &spans, *index, text.trim()).map(|e| vec![e])
+        }
+        Edit::RemoveParameter { path, index } => {
+            let spans: Vec<_> = parameters(module, path)?
+                .value
+                .iter()
+                .map(|p| span(p.range))
+                .collect();
+
+            if *index >= spans.len() {
+                return Err(out_of_range("parameter", path, *index));
+            }
+
+            // Take the separator after the parameter with it, or the one before if it is last.
+            let (start, end) = if *index + 1 < spans.len() {
+                (spans[*index].0, spans[*index + 1].0)
+            } else if *index > 0 {
+                (spans[*index - 1].1, spans[*index].1)
+            } else {
+                spans[*index]
+            };
+
+            Ok(vec![TextEdit {
+                start,
+                end,
+                text: String::new(),
+            }])
+        }
+    }
+}
+
+pub(crate) fn span(range: seglisp::Range) -> (usize, usize) {
+    (range.0.absolute, range.1.absolute)
+}
+
+fn out_of_range(what: &str, path: &NodePath, index: usize) -> EditError {
+    EditError(format!("'{path}' has no {what} at index {index}"))
+}
+
+fn find<'a, 'ast>(
+    module: &'a ParseNode<Module<'ast>>,
+    path: &NodePath,
+) -> Result<NodeRef<'a, 'ast>, EditError> {
+    NodeRef::Module(module)
+        .descendant(path)
+        .ok_or_else(|| EditError(format!("there is no node at '{path}'")))
+}
+
+fn procedure_body<'a, 'ast>(
+    module: &'a ParseNode<Module<'ast>>,
+    path: &NodePath,
+) -> Result<(seglisp::Range, &'a [ParseNode<Statement<'ast>>]), EditError> {
+    match find(module, path)? {
+        NodeRef::Expression(ParseNode {
+            value: Expression::Procedure { body },
+            range,
+            ..
+        }) => Ok((*range, &body.value)),
+        _ => Err(EditError(format!("'{path}' is not a procedure"))),
+    }
+}
+
+fn parameters<'a, 'ast>(
+    module: &'a ParseNode<Module<'ast>>,
+    path: &NodePath,
+) -> Result<&'a ParsedVec<ParameterDeclaration<'ast>>, EditError> {
+    match find(module, path)? {
+        NodeRef::Declaration(ParseNode {
+            value: Declaration::Function { parameters, .. },
+            ..
+        })
+        | NodeRef::Expression(ParseNode {
+            value: Expression::Function { parameters, .. },
+            ..
+        }) => Ok(parameters),
+        _ => Err(EditError(format!("'{path}' is not a function"))),
+    }
+}
+
+/// Returns true if the expression at `path` is an operand of another expression, and so must be
+/// parenthesized if it extends to the right.
+fn is_operand(module: &ParseNode<Module>, path: &NodePath) -> bool {
+    let parent = path
+        .parent()
+        .and_then(|p| NodeRef::Module(module).descendant(&p));
+
+    matches!(parent, Some(NodeRef::Expression(_)))
+        && matches!(
+            path.segments().last().map(|s| s.field),
+            Some("left" | "right" | "expression" | "callee" | "accessee" | "expr")
+        )
+}
+
+/// A delimited list of statements or parameters.
+pub(crate) struct List {
+    /// The span of the list, or of the node it belongs to.
+    pub span: (usize, usize),
+    pub open: &'static str,
+    pub separator: &'static str,
+}
+
+/// Inserts `text` as the element at `index` of `list`, whose elements span `spans`.
+///
+/// An element is placed on its own line if the element it is inserted next to is, and on the same
+/// line otherwise.
+pub(crate) fn insert_into_list(
+    source: &str,
+    path: &NodePath,
+    list: &List,
+    spans: &[(usize, usize)],
+    index: usize,
+    text: &str,
+) -> Result<TextEdit, EditError> {
+    if index > spans.len() {
+        return Err(out_of_range("element", path, index));
+    }
+
+    let Some(&(start, end)) = spans.get(index).or_else(|| spans.last()) else {
+        return Ok(insert_into_empty_list(source, list, text));
+    };
+
+    let separator = list.separator;
+
+    let on_own_line = source[..start]
+        .rsplit('\n')
+        .next()
+        .is_some_and(|before| before.trim().is_empty());
+
+    let indent = line_indent(source, start);
+    let text = reindent(text, indent);
+    let gap = if on_own_line {
+        format!("\n{indent}")
+    } else {
+        " ".into()
+    };
+
+    Ok(if index < spans.len() {
+        TextEdit {
+            start,
+            end: start,
+            text: format!("{text}{separator}{gap}"),
+        }
+    } else {
+        // The separator that followed the last element, if any, now follows the new one.
+        TextEdit {
+            start: end,
+            end,
+            text: format!("{separator}{gap}{text}"),
+        }
+    })
+}
+
+/// Inserts `text` as the only element of an empty list, replacing any whitespace between its
+/// delimiters. Statements are placed on their own line.
+fn insert_into_empty_list(source: &str, list: &List, text: &str) -> TextEdit {
+    let (start, end) = list.span;
+
+    // The span may or may not include the opening delimiter.
+    let open = source[start..end]
+        .find(list.open)
+        .map(|i| start + i)
+        .or_else(|| source[..start].rfind(list.open))
+        .unwrap_or(start);
+
+    let interior = open + list.open.len();
+    let rest = &source[interior..];
+    let whitespace = rest.len() - rest.trim_start().len();
+
+    let text = if list.separator == ";" {
+        let indent = line_indent(source, open);
+        let inner = format!("{indent}{INDENT}");
+        format!("\n{inner}{};\n{indent}", reindent(text, &inner))
+    } else {
+        text.to_string()
+    };
+
+    TextEdit {
+        start: interior,
+        end: interior + whitespace,
+        text,
+    }
+}
+
+/// Removes a statement along with its `;` and the rest of its line, if nothing else is on it.
+pub(crate) fn delete_statement(source: &str, (start, end): (usize, usize)) -> TextEdit {
+    let mut end = end;
+    let rest = &source[end..];
+    let trimmed = rest.trim_start_matches([' ', '\t']);
+    if trimmed.starts_with(';') {
+        end += rest.len() - trimmed.len() + 1;
+    }
+
+    let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+    let after = &source[end..];
+    let line_end = after.find('\n').map_or(source.len(), |i| end + i + 1);
+
+    let (start, end) =
+        if source[line_start..start].trim().is_empty() && source[end..line_end].trim().is_empty() {
+            (line_start, line_end)
+        } else {
+            let spaces = after.len() - after.trim_start_matches([' ', '\t']).len();
+            (start, end + spaces)
+        };
+
+    TextEdit {
+        start,
+        end,
+        text: String::new(),
+    }
+}
+
+/// The whitespace at the start of the line containing `offset`.
+pub(crate) fn line_indent(source: &str, offset: usize) -> &str {
+    let line_start = source[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let line = &source[line_start..];
+    &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+}
+
+/// Indents every line of `text` after the first by `indent`.
+pub(crate) fn reindent(text: &str, indent: &str) -> String {
+    text.replace('\n', &format!("\n{indent}"))
+}
+
+/// The result of an edit, as exposed to JavaScript: either the edited source and its tree, or the
+/// reason the edit could not be made.
+#[derive(Debug, Clone, JsInterop)]
+pub struct EditOutcome<'a> {
+    pub error: Option<String>,
+    pub source: Option<&'a str>,
+    pub parsed: Option<ParsedModule<'a>>,
+}
+
+fn edit_bytes(data: &[u8], path: &str, make: impl FnOnce(NodePath) -> Edit) -> JsValue {
+    let source = core::str::from_utf8(data).expect("data was not valid UTF-8");
+
+    let result = path
+        .parse::<NodePath>()
+        .map_err(|e| EditError(e.0))
+        .and_then(|path| edit(source, &make(path)));
+
+    match result {
+        Ok(edited) => EditOutcome {
+            error: None,
+            source: Some(&edited.source),
+            parsed: Some(edited.parse()),
+        }
+        .to_js_value(),
+        Err(e) => EditOutcome {
+            error: Some(e.0),
+            source: None,
+            parsed: None,
+        }
+        .to_js_value(),
+    }
+}
+
+/// Replaces the node at `path` with `text`, returning an `EditOutcome`.
+#[wasm_bindgen]
+pub fn replace_node(data: &[u8], path: &str, text: &str) -> JsValue {
+    edit_bytes(data, path, |path| Edit::Replace {
+        path,
+        text: text.into(),
+    })
+}
+
+/// Inserts a statement into the procedure at `path`, returning an `EditOutcome`.
+#[wasm_bindgen]
+pub fn insert_statement(data: &[u8], path: &str, index: usize, text: &str) -> JsValue {
+    edit_bytes(data, path, |path| Edit::InsertStatement {
+        path,
+        index,
+        text: text.into(),
+    })
+}
+
+/// Deletes a statement from the procedure at `path`, returning an `EditOutcome`.
+#[wasm_bindgen]
+pub fn delete_statement_at(data: &[u8], path: &str, index: usize) -> JsValue {
+    edit_bytes(data, path, |path| Edit::DeleteStatement { path, index })
+}
+
+/// Wraps the expression at `path` in `if condition then <expr> else @`, returning an
+/// `EditOutcome`.
+#[wasm_bindgen]
+pub fn wrap_in_if(data: &[u8], path: &str, condition: &str) -> JsValue {
+    edit_bytes(data, path, |path| Edit::Wrap {
+        path,
+        wrapper: Wrapper::If {
+            condition: condition.into(),
+        },
+    })
+}
+
+/// Wraps the expression at `path` in `with (name = value) <expr>`, returning an `EditOutcome`.
+#[wasm_bindgen]
+pub fn wrap_in_with(data: &[u8], path: &str, name: &str, value: &str) -> JsValue {
+    edit_bytes(data, path, |path| Edit::Wrap {
+        path,
+        wrapper: Wrapper::With {
+            name: name.into(),
+            value: value.into(),
+        },
+    })
+}
+
+/// Wraps the expression at `path` in `fn (parameters) -> <expr>`, returning an `EditOutcome`.
+/// `parameters` is a comma-separated list, which may be empty.
+#[wasm_bindgen]
+pub fn wrap_in_fn(data: &[u8], path: &str, parameters: &str) -> JsValue {
+    let parameters = parameters
+        .split(',')
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .map(String::from)
+        .collect();
+
+    edit_bytes(data, path, |path| Edit::Wrap {
+        path,
+        wrapper: Wrapper::Function { parameters },
+    })
+}
+
+/// Adds a parameter to the function at `path`, returning an `EditOutcome`.
+#[wasm_bindgen]
+pub fn add_parameter(data: &[u8], path: &str, index: usize, text: &str) -> JsValue {
+    edit_bytes(data, path, |path| Edit::AddParameter {
+        path,
+        index,
+        text: text.into(),
+    })
+}
+
+/// Removes a parameter from the function at `path`, returning an `EditOutcome`.
+#[wasm_bindgen]
+pub fn remove_parameter(data: &[u8], path: &str, index: usize) -> JsValue {
+    edit_bytes(data, path, |path| Edit::RemoveParameter { path, index })
+}
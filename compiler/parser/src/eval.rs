@@ -0,0 +1,1023 @@
+//! A tree-walking interpreter for the surface AST.
+//!
+//! This follows the semantics of `@serendipity/interpreter`, which runs the lowered abstract syntax,
+//! closely enough to evaluate programs without lowering them first: tuple elements and call
+//! arguments are evaluated lazily, `none` and `false` are the only false values, and `__core`
+//! provides the same intrinsics. Procedures are values that perform their statements when they are
+//! run as a statement (or as the body of `main`), rather than being compiled to continuations.
+
+use std::{cell::RefCell, collections::HashMap, fmt, rc::Rc};
+
+use seglisp::parse::ParseNode;
+
+use crate::{
+    ArithmeticOp, CompareOp, Declaration, Expression, Module, RecordElement, Statement, UnaryOp,
+};
+
+/// How many function calls may be nested before evaluation is abandoned.
+const MAX_CALL_DEPTH: usize = 1000;
+
+/// How deeply nested values are printed before they are elided.
+const MAX_PRINT_DEPTH: usize = 64;
+
+/// An error raised while evaluating a program.
+#[derive(Debug, Clone)]
+pub struct EvalError {
+    pub message: String,
+    /// The innermost expression that was being evaluated when the error was raised.
+    pub range: Option<seglisp::Range>,
+}
+
+impl EvalError {
+    fn new(message: impl Into<String>) -> Self {
+        EvalError {
+            message: message.into(),
+            range: None,
+        }
+    }
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.range {
+            Some(range) => write!(
+                f,
+                "{}:{}: {}",
+                range.0.line + 1,
+                range.0.column + 1,
+                self.message
+            ),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+type EvalResult<T> = Result<T, EvalError>;
+
+/// The functions of `__core`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Intrinsic {
+    /// `__core` itself, which maps the name of an intrinsic to the intrinsic.
+    Core,
+    Import,
+    PrintStmt,
+    ReadLine,
+    StrSplit,
+    StrCat,
+    ToStr,
+    Err,
+}
+
+impl Intrinsic {
+    fn by_name(name: &str) -> Option<Intrinsic> {
+        Some(match name {
+            "import" => Intrinsic::Import,
+            "print_stmt" => Intrinsic::PrintStmt,
+            "read_line" => Intrinsic::ReadLine,
+            "str_split" => Intrinsic::StrSplit,
+            "str_cat" => Intrinsic::StrCat,
+            "to_str" => Intrinsic::ToStr,
+            "err" => Intrinsic::Err,
+            _ => return None,
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Intrinsic::Core => "__core",
+            Intrinsic::Import => "import",
+            Intrinsic::PrintStmt => "print_stmt",
+            Intrinsic::ReadLine => "read_line",
+            Intrinsic::StrSplit => "str_split",
+            Intrinsic::StrCat => "str_cat",
+            Intrinsic::ToStr => "to_str",
+            Intrinsic::Err => "err",
+        }
+    }
+
+    fn arity(&self) -> usize {
+        match self {
+            Intrinsic::StrSplit | Intrinsic::StrCat => 2,
+            _ => 1,
+        }
+    }
+}
+
+/// A runtime value.
+#[derive(Clone)]
+pub enum Value<'ast> {
+    Number(f64),
+    String(Rc<str>),
+    Boolean(bool),
+    None,
+    Tuple(Rc<[Thunk<'ast>]>),
+    Record(Rc<[(Rc<str>, Thunk<'ast>)]>),
+    Function(Rc<Function<'ast>>),
+    Intrinsic(Intrinsic, Rc<[Value<'ast>]>),
+    Action(Rc<Action<'ast>>),
+}
+
+impl Value<'_> {
+    /// A short description of the kind of value, for error messages.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Value::Number(_) => "number",
+            Value::String(_) => "string",
+            Value::Boolean(_) => "boolean",
+            Value::None => "none",
+            Value::Tuple(_) => "tuple",
+            Value::Record(_) => "record",
+            Value::Function(_) | Value::Intrinsic(..) => "function",
+            Value::Action(_) => "procedure",
+        }
+    }
+
+    fn is_truthy(&self) -> bool {
+        !matches!(self, Value::None | Value::Boolean(false))
+    }
+}
+
+/// A closure, possibly applied to some of its parameters.
+pub struct Function<'ast> {
+    name: Option<&'ast str>,
+    parameters: Vec<&'ast str>,
+    body: &'ast ParseNode<Expression<'ast>>,
+    env: Rc<Env<'ast>>,
+    applied: Vec<Thunk<'ast>>,
+}
+
+/// Something a procedure does when it is run.
+pub enum Action<'ast> {
+    Procedure {
+        body: &'ast [ParseNode<Statement<'ast>>],
+        env: Rc<Env<'ast>>,
+    },
+    Print(Value<'ast>),
+}
+
+enum ThunkState<'ast> {
+    Pending(&'ast ParseNode<Expression<'ast>>, Rc<Env<'ast>>),
+    Forcing,
+    Forced(Value<'ast>),
+}
+
+/// A lazily evaluated value, evaluated at most once.
+#[derive(Clone)]
+pub struct Thunk<'ast>(Rc<RefCell<ThunkState<'ast>>>);
+
+impl<'ast> Thunk<'ast> {
+    pub fn value(value: Value<'ast>) -> Self {
+        Thunk(Rc::new(RefCell::new(ThunkState::Forced(value))))
+    }
+
+    fn pending(expr: &'ast ParseNode<Expression<'ast>>, env: &Rc<Env<'ast>>) -> Self {
+        Thunk(Rc::new(RefCell::new(ThunkState::Pending(
+            expr,
+            env.clone(),
+        ))))
+    }
+}
+
+/// A scope of bindings.
+#[derive(Default)]
+pub struct Env<'ast> {
+    bindings: RefCell<HashMap<&'ast str, Thunk<'ast>>>,
+    parent: Option<Rc<Env<'ast>>>,
+}
+
+impl<'ast> Env<'ast> {
+    pub fn child(parent: &Rc<Env<'ast>>) -> Rc<Self> {
+        Rc::new(Env {
+            bindings: Default::default(),
+            parent: Some(parent.clone()),
+        })
+    }
+
+    /// Binds `name` in this scope, shadowing any binding of the same name.
+    pub fn define(&self, name: &'ast str, thunk: Thunk<'ast>) {
+        self.bindings.borrow_mut().insert(name, thunk);
+    }
+
+    pub fn lookup(&self, name: &str) -> Option<Thunk<'ast>> {
+        match self.bindings.borrow().get(name) {
+            Some(thunk) => Some(thunk.clone()),
+            None => self.parent.as_ref().and_then(|p| p.lookup(name)),
+        }
+    }
+
+    /// Replaces the value of the nearest existing binding of `name`.
+    fn assign(&self, name: &str, value: Value<'ast>) -> bool {
+        if let Some(thunk) = self.bindings.borrow_mut().get_mut(name) {
+            *thunk = Thunk::value(value);
+            return true;
+        }
+
+        self.parent
+            .as_ref()
+            .is_some_and(|parent| parent.assign(name, value))
+    }
+}
+
+/// How control leaves a statement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Flow {
+    Normal,
+    Break,
+    Continue,
+}
+
+/// The interpreter's connection to the outside world.
+pub trait Host {
+    /// Prints a line of output.
+    fn print(&mut self, text: &str);
+
+    /// Reads a line of input, after printing `prompt` if there is one.
+    fn read_line(&mut self, prompt: Option<&str>) -> String;
+}
+
+/// A host that uses standard input and output.
+#[derive(Debug, Default)]
+pub struct StdHost;
+
+impl Host for StdHost {
+    fn print(&mut self, text: &str) {
+        println!("{text}");
+    }
+
+    fn read_line(&mut self, prompt: Option<&str>) -> String {
+        use std::io::Write;
+
+        if let Some(prompt) = prompt {
+            print!("{prompt}");
+            let _ = std::io::stdout().flush();
+        }
+
+        let mut line = String::new();
+        let _ = std::io::stdin().read_line(&mut line);
+        line
+    }
+}
+
+/// Evaluates declarations, expressions, and statements against a global scope.
+pub struct Interpreter<'ast, H: Host> {
+    globals: Rc<Env<'ast>>,
+    host: H,
+    depth: usize,
+}
+
+impl<'ast, H: Host> Interpreter<'ast, H> {
+    pub fn new(host: H) -> Self {
+        let globals = Rc::new(Env::default());
+        globals.define(
+            "__core",
+            Thunk::value(Value::Intrinsic(Intrinsic::Core, Rc::new([]))),
+        );
+
+        Interpreter {
+            globals,
+            host,
+            depth: 0,
+        }
+    }
+
+    /// The global scope, which holds top-level declarations.
+    pub fn globals(&self) -> &Rc<Env<'ast>> {
+        &self.globals
+    }
+
+    pub fn host(&mut self) -> &mut H {
+        &mut self.host
+    }
+
+    /// Binds the name a declaration introduces in the global scope. Declarations that do not
+    /// introduce a value, and `main`, are ignored.
+    pub fn declare(&mut self, decl: &'ast ParseNode<Declaration<'ast>>) -> EvalResult<()> {
+        match &decl.value {
+            Declaration::Const {
+                identifier, value, ..
+            } => {
+                self.globals
+                    .define(identifier.value, Thunk::pending(value, &self.globals));
+            }
+            Declaration::Function {
+                identifier,
+                parameters,
+                body,
+                ..
+            } => {
+                let function = Function {
+                    name: Some(identifier.value),
+                    parameters: parameters
+                        .value
+                        .iter()
+                        .map(|p| p.value.name.value)
+                        .collect(),
+                    body,
+                    env: self.globals.clone(),
+                    applied: vec![],
+                };
+
+                self.globals.define(
+                    identifier.value,
+                    Thunk::value(Value::Function(Rc::new(function))),
+                );
+            }
+            Declaration::Import { .. } => {
+                return Err(EvalError {
+                    message: "imports are not supported by this interpreter".into(),
+                    range: Some(decl.range),
+                })
+            }
+            Declaration::Main { .. }
+            | Declaration::Export { .. }
+            | Declaration::TypeAlias { .. }
+            | Declaration::Interface { .. } => {}
+        }
+
+        Ok(())
+    }
+
+    /// Declares every declaration of `module`, then runs its `main` declaration, if it has one.
+    pub fn run_module(&mut self, module: &'ast ParseNode<Module<'ast>>) -> EvalResult<()> {
+        for decl in &module.value.declarations {
+            self.declare(decl)?;
+        }
+
+        for decl in &module.value.declarations {
+            if let Declaration::Main { body, .. } = &decl.value {
+                let globals = self.globals.clone();
+                let value = self.eval(body, &globals)?;
+                self.run(value)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Runs `value` if it is a procedure or another action. Other values are ignored.
+    pub fn run(&mut self, value: Value<'ast>) -> EvalResult<()> {
+        match self.perform(value)? {
+            Flow::Normal => Ok(()),
+            Flow::Break => Err(EvalError::new("`break` outside of a loop")),
+            Flow::Continue => Err(EvalError::new("`continue` outside of a loop")),
+        }
+    }
+
+    /// Runs statements directly in `env`, so that their `let` bindings remain visible afterwards.
+    pub fn run_statements(
+        &mut self,
+        statements: &'ast [ParseNode<Statement<'ast>>],
+        env: &Rc<Env<'ast>>,
+    ) -> EvalResult<()> {
+        match self.exec_block(statements, env)? {
+            Flow::Normal => Ok(()),
+            Flow::Break => Err(EvalError::new("`break` outside of a loop")),
+            Flow::Continue => Err(EvalError::new("`continue` outside of a loop")),
+        }
+    }
+
+    pub fn force(&mut self, thunk: &Thunk<'ast>) -> EvalResult<Value<'ast>> {
+        let state = std::mem::replace(&mut *thunk.0.borrow_mut(), ThunkState::Forcing);
+
+        match state {
+            ThunkState::Forced(value) => {
+                *thunk.0.borrow_mut() = ThunkState::Forced(value.clone());
+                Ok(value)
+            }
+            ThunkState::Forcing => Err(EvalError::new("value depends on itself")),
+            ThunkState::Pending(expr, env) => match self.eval(expr, &env) {
+                Ok(value) => {
+                    *thunk.0.borrow_mut() = ThunkState::Forced(value.clone());
+                    Ok(value)
+                }
+                Err(e) => {
+                    *thunk.0.borrow_mut() = ThunkState::Pending(expr, env);
+                    Err(e)
+                }
+            },
+        }
+    }
+
+    /// Evaluates `expr` in `env`.
+    pub fn eval(
+        &mut self,
+        expr: &'ast ParseNode<Expression<'ast>>,
+        env: &Rc<Env<'ast>>,
+    ) -> EvalResult<Value<'ast>> {
+        self.eval_inner(expr, env).map_err(|mut e| {
+            e.range.get_or_insert(expr.range);
+            e
+        })
+    }
+
+    fn eval_inner(
+        &mut self,
+        expr: &'ast ParseNode<Expression<'ast>>,
+        env: &Rc<Env<'ast>>,
+    ) -> EvalResult<Value<'ast>> {
+        Ok(match &expr.value {
+            Expression::Number(n) => Value::Number(
+                n.parse()
+                    .map_err(|_| EvalError::new(format!("invalid number '{n}'")))?,
+            ),
+            Expression::String(s) => Value::String(s.as_str().into()),
+            Expression::Boolean(b) => Value::Boolean(*b),
+            Expression::None => Value::None,
+            Expression::Hole => return Err(EvalError::new("encountered a hole in the program")),
+            Expression::Name(name) => {
+                let thunk = env
+                    .lookup(name)
+                    .ok_or_else(|| EvalError::new(format!("name not found: {name}")))?;
+                self.force(&thunk)?
+            }
+            Expression::As { expr, .. } => self.eval(expr, env)?,
+            Expression::Unary {
+                operator,
+                expression,
+            } => {
+                let value = self.eval(expression, env)?;
+                match (operator.value, value) {
+                    (UnaryOp::Negate, value) => Value::Boolean(!value.is_truthy()),
+                    (UnaryOp::Minus, Value::Number(n)) => Value::Number(-n),
+                    (UnaryOp::Minus, value) => {
+                        return Err(EvalError::new(format!(
+                            "attempted unary minus on a {}",
+                            value.kind()
+                        )))
+                    }
+                }
+            }
+            Expression::Arithmetic {
+                operator,
+                left,
+                right,
+            } => {
+                let left = self.eval(left, env)?;
+                let right = self.eval(right, env)?;
+
+                let (Value::Number(l), Value::Number(r)) = (&left, &right) else {
+                    return Err(EvalError::new(format!(
+                        "attempted arithmetic on a {} and a {}",
+                        left.kind(),
+                        right.kind()
+                    )));
+                };
+
+                Value::Number(match operator.value {
+                    ArithmeticOp::Add => l + r,
+                    ArithmeticOp::Subtract => l - r,
+                    ArithmeticOp::Multiply => l * r,
+                    ArithmeticOp::Divide => l / r,
+                    ArithmeticOp::Modulus => l % r,
+                })
+            }
+            Expression::Compare {
+                operator,
+                left,
+                right,
+            } => {
+                let left = self.eval(left, env)?;
+                let right = self.eval(right, env)?;
+                Value::Boolean(compare(operator.value, &left, &right)?)
+            }
+            Expression::Accessor { accessee, index } => {
+                let accessee = self.eval(accessee, env)?;
+                let index = self.eval(index, env)?;
+                self.index(accessee, index)?
+            }
+            Expression::FieldAccess { accessee, field } => {
+                let accessee = self.eval(accessee, env)?;
+                self.index(accessee, Value::String(field.value.into()))?
+            }
+            Expression::Function {
+                name,
+                parameters,
+                body,
+                ..
+            } => {
+                let parameters = parameters
+                    .value
+                    .iter()
+                    .map(|p| p.value.name.value)
+                    .collect();
+
+                match name {
+                    // A named function expression can refer to itself.
+                    Some(name) => {
+                        let scope = Env::child(env);
+                        let function = Rc::new(Function {
+                            name: Some(name.value),
+                            parameters,
+                            body,
+                            env: scope.clone(),
+                            applied: vec![],
+                        });
+                        scope.define(name.value, Thunk::value(Value::Function(function.clone())));
+                        Value::Function(function)
+                    }
+                    None => Value::Function(Rc::new(Function {
+                        name: None,
+                        parameters,
+                        body,
+                        env: env.clone(),
+                        applied: vec![],
+                    })),
+                }
+            }
+            Expression::Call { callee, parameters } => {
+                let callee = self.eval(callee, env)?;
+                let arguments = parameters
+                    .value
+                    .iter()
+                    .map(|p| Thunk::pending(p, env))
+                    .collect();
+                self.call(callee, arguments)?
+            }
+            Expression::With { bindings, body, .. } => {
+                // Bindings are visible in their own values, which gives them `letrec` semantics.
+                let scope = Env::child(env);
+                for binding in &bindings.value {
+                    scope.define(
+                        binding.value.symbol.value,
+                        Thunk::pending(&binding.value.value, &scope),
+                    );
+                }
+                self.eval(body, &scope)?
+            }
+            Expression::Tuple { elements } => Value::Tuple(
+                elements
+                    .value
+                    .iter()
+                    .map(|e| Thunk::pending(e, env))
+                    .collect(),
+            ),
+            Expression::List { elements } => {
+                // Lists are sequences of nested pairs, terminated by `none`.
+                let mut list = Value::None;
+                for element in elements.value.iter().rev() {
+                    list =
+                        Value::Tuple(Rc::new([Thunk::pending(element, env), Thunk::value(list)]));
+                }
+                list
+            }
+            Expression::Record { elements } => {
+                let mut fields: Vec<(Rc<str>, Thunk<'ast>)> = Vec::new();
+
+                // Later elements replace earlier ones with the same key.
+                fn set<'ast>(
+                    fields: &mut Vec<(Rc<str>, Thunk<'ast>)>,
+                    key: Rc<str>,
+                    thunk: Thunk<'ast>,
+                ) {
+                    fields.retain(|(k, _)| *k != key);
+                    fields.push((key, thunk));
+                }
+
+                for element in &elements.value {
+                    match &element.value {
+                        RecordElement::KeyValuePair { key, value } => {
+                            set(&mut fields, key.value.into(), Thunk::pending(value, env))
+                        }
+                        RecordElement::Identifier { name } => {
+                            let thunk = env.lookup(name.value).ok_or_else(|| {
+                                EvalError::new(format!("name not found: {}", name.value))
+                            })?;
+                            set(&mut fields, name.value.into(), thunk)
+                        }
+                        RecordElement::Spread { value } => match self.eval(value, env)? {
+                            Value::Record(spread) => {
+                                for (key, thunk) in spread.iter() {
+                                    set(&mut fields, key.clone(), thunk.clone())
+                                }
+                            }
+                            value => {
+                                return Err(EvalError::new(format!(
+                                    "cannot spread a {} into a record",
+                                    value.kind()
+                                )))
+                            }
+                        },
+                    }
+                }
+
+                Value::Record(fields.into())
+            }
+            Expression::Procedure { body } => Value::Action(Rc::new(Action::Procedure {
+                body: &body.value,
+                env: env.clone(),
+            })),
+            Expression::If {
+                condition,
+                then,
+                _else,
+                ..
+            } => {
+                if self.eval(condition, env)?.is_truthy() {
+                    self.eval(then, env)?
+                } else {
+                    self.eval(_else, env)?
+                }
+            }
+        })
+    }
+
+    fn index(&mut self, accessee: Value<'ast>, index: Value<'ast>) -> EvalResult<Value<'ast>> {
+        match (&accessee, &index) {
+            (Value::Tuple(elements), Value::Number(n)) => {
+                let element = (n.fract() == 0.0 && *n >= 0.0)
+                    .then(|| elements.get(*n as usize))
+                    .flatten()
+                    .ok_or_else(|| EvalError::new(format!("index {n} is out of bounds")))?;
+                self.force(&element.clone())
+            }
+            (Value::Record(fields), Value::String(key)) => {
+                match fields.iter().find(|(k, _)| k == key) {
+                    Some((_, thunk)) => self.force(&thunk.clone()),
+                    None => Ok(Value::None),
+                }
+            }
+            (Value::Intrinsic(..), _) => self.call(accessee, vec![Thunk::value(index)]),
+            _ => Err(EvalError::new(format!(
+                "cannot index a {} with a {}",
+                accessee.kind(),
+                index.kind()
+            ))),
+        }
+    }
+
+    /// Calls `callee` with `arguments`. Functions are curried, so supplying fewer arguments than a
+    /// function has parameters produces a function of the rest.
+    pub fn call(
+        &mut self,
+        callee: Value<'ast>,
+        arguments: Vec<Thunk<'ast>>,
+    ) -> EvalResult<Value<'ast>> {
+        match callee {
+            Value::Function(function) => {
+                let mut applied = function.applied.clone();
+                applied.extend(arguments);
+
+                if applied.len() < function.parameters.len() {
+                    return Ok(Value::Function(Rc::new(Function {
+                        name: function.name,
+                        parameters: function.parameters.clone(),
+                        body: function.body,
+                        env: function.env.clone(),
+                        applied,
+                    })));
+                }
+
+                let rest = applied.split_off(function.parameters.len());
+
+                let scope = Env::child(&function.env);
+                for (name, argument) in function.parameters.iter().zip(applied) {
+                    if *name != "_" {
+                        scope.define(name, argument);
+                    }
+                }
+
+                if self.depth >= MAX_CALL_DEPTH {
+                    return Err(EvalError::new("maximum call depth exceeded"));
+                }
+                self.depth += 1;
+                let result = self.eval(function.body, &scope);
+                self.depth -= 1;
+
+                let result = result?;
+                if rest.is_empty() {
+                    Ok(result)
+                } else {
+                    self.call(result, rest)
+                }
+            }
+            Value::Intrinsic(intrinsic, applied) => {
+                let mut values = applied.to_vec();
+                let mut arguments = arguments.into_iter();
+
+                while values.len() < intrinsic.arity() {
+                    match arguments.next() {
+                        Some(argument) => values.push(self.force(&argument)?),
+                        None if values.is_empty() && intrinsic == Intrinsic::ReadLine => {
+                            values.push(Value::None)
+                        }
+                        None => return Ok(Value::Intrinsic(intrinsic, values.into())),
+                    }
+                }
+
+                let result = self.intrinsic(intrinsic, values)?;
+                let rest: Vec<_> = arguments.collect();
+
+                if rest.is_empty() {
+                    Ok(result)
+                } else {
+                    self.call(result, rest)
+                }
+            }
+            Value::Record(_) if arguments.len() == 1 => {
+                // Records can be called with a key, like the closures they lower to.
+                let key = self.force(&arguments[0])?;
+                self.index(callee, key)
+            }
+            callee => Err(EvalError::new(format!("cannot call a {}", callee.kind()))),
+        }
+    }
+
+    fn intrinsic(
+        &mut self,
+        intrinsic: Intrinsic,
+        mut arguments: Vec<Value<'ast>>,
+    ) -> EvalResult<Value<'ast>> {
+        let string = |value: &Value, what: &str| match value {
+            Value::String(s) => Ok(s.clone()),
+            value => Err(EvalError::new(format!(
+                "{}: {what} must be a string, not a {}",
+                intrinsic.name(),
+                value.kind()
+            ))),
+        };
+
+        Ok(match intrinsic {
+            Intrinsic::Core => {
+                let name = string(&arguments[0], "key")?;
+                let intrinsic = Intrinsic::by_name(&name)
+                    .ok_or_else(|| EvalError::new(format!("unimplemented intrinsic {name}")))?;
+                Value::Intrinsic(intrinsic, Rc::new([]))
+            }
+            Intrinsic::Import => {
+                return Err(EvalError::new(
+                    "imports are not supported by this interpreter",
+                ))
+            }
+            Intrinsic::PrintStmt => Value::Action(Rc::new(Action::Print(arguments.remove(0)))),
+            Intrinsic::ReadLine => {
+                let prompt = match &arguments[0] {
+                    Value::None => None,
+                    value => Some(string(value, "prompt")?),
+                };
+                Value::String(self.host.read_line(prompt.as_deref()).into())
+            }
+            Intrinsic::StrSplit => {
+                let s = string(&arguments[0], "the string to split")?;
+                let (left, right) = match &arguments[1] {
+                    Value::String(on) => s
+                        .split_once(&**on)
+                        .ok_or_else(|| EvalError::new("str_split: split delimiter not found"))?,
+                    Value::Number(n) => {
+                        let idx = *n as usize;
+                        if !s.is_char_boundary(idx) || idx > s.len() {
+                            return Err(EvalError::new("str_split: index out of bounds"));
+                        }
+                        s.split_at(idx)
+                    }
+                    value => {
+                        return Err(EvalError::new(format!(
+                            "str_split: cannot split on a {}",
+                            value.kind()
+                        )))
+                    }
+                };
+
+                Value::Tuple(Rc::new([
+                    Thunk::value(Value::String(left.into())),
+                    Thunk::value(Value::String(right.into())),
+                ]))
+            }
+            Intrinsic::StrCat => {
+                // As in the reference interpreter, the second argument comes first.
+                let right = string(&arguments[0], "right")?;
+                let left = string(&arguments[1], "left")?;
+                Value::String(format!("{left}{right}").into())
+            }
+            Intrinsic::ToStr => Value::String(self.to_str(&arguments[0])?.into()),
+            Intrinsic::Err => {
+                return Err(EvalError::new(format!(
+                    "panic: {}",
+                    self.to_str(&arguments[0])?
+                )))
+            }
+        })
+    }
+
+    /// Performs an action, returning how control leaves it.
+    fn perform(&mut self, value: Value<'ast>) -> EvalResult<Flow> {
+        match value {
+            Value::Action(action) => match &*action {
+                Action::Procedure { body, env } => {
+                    let scope = Env::child(env);
+                    self.exec_block(body, &scope)
+                }
+                Action::Print(value) => {
+                    let text = self.to_str(value)?;
+                    self.host.print(&text);
+                    Ok(Flow::Normal)
+                }
+            },
+            _ => Ok(Flow::Normal),
+        }
+    }
+
+    fn exec_block(
+        &mut self,
+        statements: &'ast [ParseNode<Statement<'ast>>],
+        env: &Rc<Env<'ast>>,
+    ) -> EvalResult<Flow> {
+        for statement in statements {
+            match self.exec(statement, env)? {
+                Flow::Normal => {}
+                flow => return Ok(flow),
+            }
+        }
+
+        Ok(Flow::Normal)
+    }
+
+    fn exec(
+        &mut self,
+        statement: &'ast ParseNode<Statement<'ast>>,
+        env: &Rc<Env<'ast>>,
+    ) -> EvalResult<Flow> {
+        Ok(match &statement.value {
+            Statement::Let { assignment, .. } => {
+                let value = self.eval(&assignment.value.value, env)?;
+                env.define(assignment.value.symbol.value, Thunk::value(value));
+                Flow::Normal
+            }
+            Statement::Set(assignment) => {
+                let value = self.eval(&assignment.value.value, env)?;
+                if !env.assign(assignment.value.symbol.value, value) {
+                    return Err(EvalError {
+                        message: format!(
+                            "cannot assign to undeclared name {}",
+                            assignment.value.symbol.value
+                        ),
+                        range: Some(assignment.range),
+                    });
+                }
+                Flow::Normal
+            }
+            Statement::If {
+                condition,
+                then,
+                _else,
+                ..
+            } => {
+                if self.eval(condition, env)?.is_truthy() {
+                    self.exec(then, &Env::child(env))?
+                } else if let Some(_else) = _else {
+                    self.exec(_else, &Env::child(env))?
+                } else {
+                    Flow::Normal
+                }
+            }
+            Statement::ForIn {
+                binding,
+                iterator,
+                body,
+                ..
+            } => {
+                let mut sequence = self.eval(iterator, env)?;
+
+                loop {
+                    let (head, tail) = match &sequence {
+                        Value::None => break,
+                        Value::Tuple(pair) if pair.len() == 2 => (pair[0].clone(), pair[1].clone()),
+                        value => {
+                            return Err(EvalError {
+                                message: format!("cannot iterate over a {}", value.kind()),
+                                range: Some(iterator.range),
+                            })
+                        }
+                    };
+
+                    let scope = Env::child(env);
+                    scope.define(binding.value, head);
+
+                    if self.exec(body, &scope)? == Flow::Break {
+                        break;
+                    }
+
+                    sequence = self.force(&tail)?;
+                }
+
+                Flow::Normal
+            }
+            Statement::Forever(body) => loop {
+                if self.exec(body, &Env::child(env))? == Flow::Break {
+                    break Flow::Normal;
+                }
+            },
+            Statement::Do(e) | Statement::Expression(e) => {
+                let value = self.eval(e, env)?;
+                self.perform(value)?
+            }
+            Statement::Break => Flow::Break,
+            Statement::Continue => Flow::Continue,
+            Statement::Pass => Flow::Normal,
+        })
+    }
+
+    /// Converts a value to the string `print` would show for it.
+    pub fn to_str(&mut self, value: &Value<'ast>) -> EvalResult<String> {
+        self.show(value, false, 0)
+    }
+
+    /// Converts a value to a string that reads back as the value where possible, for echoing
+    /// results in a REPL. Strings are quoted.
+    pub fn show_value(&mut self, value: &Value<'ast>) -> EvalResult<String> {
+        self.show(value, true, 0)
+    }
+
+    fn show(&mut self, value: &Value<'ast>, quote: bool, depth: usize) -> EvalResult<String> {
+        if depth > MAX_PRINT_DEPTH {
+            return Ok("...".into());
+        }
+
+        Ok(match value {
+            Value::Number(n) if n.is_infinite() => {
+                if *n > 0.0 { "Infinity" } else { "-Infinity" }.into()
+            }
+            Value::Number(n) => n.to_string(),
+            Value::String(s) if quote => crate::printer::escape_string(s),
+            Value::String(s) => s.to_string(),
+            Value::Boolean(b) => b.to_string(),
+            Value::None => "none".into(),
+            Value::Tuple(elements) => {
+                let mut parts = Vec::with_capacity(elements.len());
+                for element in elements.iter() {
+                    let value = self.force(element)?;
+                    parts.push(self.show(&value, quote, depth + 1)?);
+                }
+                format!("({})", parts.join(", "))
+            }
+            Value::Record(fields) => {
+                let mut parts = Vec::with_capacity(fields.len());
+                for (key, thunk) in fields.iter() {
+                    let value = self.force(thunk)?;
+                    parts.push(format!("{key}: {}", self.show(&value, quote, depth + 1)?));
+                }
+                if parts.is_empty() {
+                    "{}".into()
+                } else {
+                    format!("{{ {} }}", parts.join(", "))
+                }
+            }
+            Value::Function(function) => match function.name {
+                Some(name) => format!("<function {name}>"),
+                None => "<function>".into(),
+            },
+            Value::Intrinsic(intrinsic, _) => format!("<intrinsic {}>", intrinsic.name()),
+            Value::Action(_) => "<procedure>".into(),
+        })
+    }
+}
+
+fn compare(operator: CompareOp, left: &Value, right: &Value) -> EvalResult<bool> {
+    if std::mem::discriminant(left) != std::mem::discriminant(right) {
+        return Ok(false);
+    }
+
+    let equal = match (left, right) {
+        (Value::None, Value::None) => true,
+        (Value::Number(l), Value::Number(r)) => l == r,
+        (Value::String(l), Value::String(r)) => l == r,
+        (Value::Boolean(l), Value::Boolean(r)) => l == r,
+        (Value::Tuple(l), Value::Tuple(r)) => Rc::ptr_eq(l, r),
+        (Value::Record(l), Value::Record(r)) => Rc::ptr_eq(l, r),
+        (Value::Function(l), Value::Function(r)) => Rc::ptr_eq(l, r),
+        (Value::Action(l), Value::Action(r)) => Rc::ptr_eq(l, r),
+        (Value::Intrinsic(l, _), Value::Intrinsic(r, _)) => l == r,
+        _ => false,
+    };
+
+    Ok(match operator {
+        CompareOp::Equal => equal,
+        CompareOp::NotEqual => !equal,
+        ordered => {
+            let ordering = match (left, right) {
+                (Value::None, Value::None) => std::cmp::Ordering::Equal,
+                (Value::Number(l), Value::Number(r)) => match l.partial_cmp(r) {
+                    Some(ordering) => ordering,
+                    // Comparisons involving NaN are false.
+                    None => return Ok(false),
+                },
+                (Value::String(l), Value::String(r)) => l.cmp(r),
+                (Value::Boolean(_), _) => return Err(EvalError::new("cannot compare booleans")),
+                _ => {
+                    return Err(EvalError::new(format!(
+                        "cannot compare a {} with a {}",
+                        left.kind(),
+                        right.kind()
+                    )))
+                }
+            };
+
+            match ordered {
+                CompareOp::LessThan => ordering.is_lt(),
+                CompareOp::LessThanOrEqual => ordering.is_le(),
+                CompareOp::GreaterThan => ordering.is_gt(),
+                CompareOp::GreaterThanOrEqual => ordering.is_ge(),
+                CompareOp::Equal | CompareOp::NotEqual => unreachable!(),
+            }
+        }
+    })
+}
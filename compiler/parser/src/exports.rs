@@ -0,0 +1,267 @@
+use seglisp::{
+    js_interop::JsInterop, parse::ParseNode, Diagnostic, DiagnosticLocation, DiagnosticPhase,
+    DiagnosticSeverity,
+};
+
+use crate::{
+    resolve::free_names, BindingPattern, Declaration, Expression, Module, RecordBindingElement,
+    RecordElement, Verbatim,
+};
+
+/// The kind of top-level binding that an export refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, JsInterop)]
+pub enum BindingKind {
+    Const,
+    Function,
+    TypeAlias,
+    Interface,
+    Import,
+}
+
+/// A single top-level binding declared by a module.
+#[derive(Debug, Clone, JsInterop)]
+pub struct ModuleBinding<'ast> {
+    pub name: Verbatim<'ast>,
+    pub kind: BindingKind,
+}
+
+/// A single entry in a module's export table.
+#[derive(Debug, Clone, JsInterop)]
+pub enum Export<'ast> {
+    /// `export { name }`, exporting a top-level binding under its own name.
+    Binding {
+        name: Verbatim<'ast>,
+        kind: BindingKind,
+    },
+    /// `export { name: <expression> }`, exporting the value of an expression under `name`.
+    Value {
+        name: Verbatim<'ast>,
+        value: ParseNode<Expression<'ast>>,
+    },
+}
+
+impl<'ast> Export<'ast> {
+    /// The name under which this entry is exported.
+    pub fn name(&self) -> &Verbatim<'ast> {
+        match self {
+            Export::Binding { name, .. } | Export::Value { name, .. } => name,
+        }
+    }
+}
+
+/// The export table of a module, computed from all of its `export { ... }` declarations.
+#[derive(Debug, Clone, Default, JsInterop)]
+pub struct ExportTable<'ast> {
+    /// The exported names, in declaration order.
+    pub exports: Vec<Export<'ast>>,
+    /// Import bindings whose contents are re-exported wholesale (`export { ...name }`).
+    pub reexports: Vec<Verbatim<'ast>>,
+}
+
+impl<'ast> ExportTable<'ast> {
+    /// Looks up an exported entry by name.
+    pub fn get(&self, name: &str) -> Option<&Export<'ast>> {
+        self.exports.iter().find(|e| e.name().value == name)
+    }
+}
+
+impl<'ast> Module<'ast> {
+    /// Collects the names bound at the top level of this module.
+    pub fn bindings(&self) -> Vec<ModuleBinding<'ast>> {
+        let mut bindings = Vec::new();
+
+        for decl in &self.declarations {
+            match &decl.value {
+                Declaration::Const { identifier, .. } => bindings.push(ModuleBinding {
+                    name: identifier.clone(),
+                    kind: BindingKind::Const,
+                }),
+                Declaration::Function { identifier, .. } => bindings.push(ModuleBinding {
+                    name: identifier.clone(),
+                    kind: BindingKind::Function,
+                }),
+                Declaration::TypeAlias { name, .. } => bindings.push(ModuleBinding {
+                    name: name.clone(),
+                    kind: BindingKind::TypeAlias,
+                }),
+                Declaration::Interface { name, .. } => bindings.push(ModuleBinding {
+                    name: name.clone(),
+                    kind: BindingKind::Interface,
+                }),
+                Declaration::Import { pattern, .. } => {
+                    collect_pattern_names(&pattern.value, &mut |name| {
+                        bindings.push(ModuleBinding {
+                            name: name.clone(),
+                            kind: BindingKind::Import,
+                        })
+                    });
+                }
+                Declaration::Main { .. } | Declaration::Export { .. } => {}
+            }
+        }
+
+        bindings
+    }
+
+    /// Computes the export table of this module, ignoring any diagnostics.
+    pub fn exports(&self) -> ExportTable<'ast> {
+        check_exports(self).0
+    }
+}
+
+/// Calls `f` with every name bound by a binding pattern.
+pub(crate) fn collect_pattern_names<'p, 'ast: 'p>(
+    pattern: &'p BindingPattern<'ast>,
+    f: &mut impl FnMut(&'p Verbatim<'ast>),
+) {
+    match pattern {
+        BindingPattern::Identifier { name } => f(name),
+        BindingPattern::Tuple { patterns } => {
+            for p in &patterns.value {
+                collect_pattern_names(&p.value, f);
+            }
+        }
+        BindingPattern::Record { elements } => {
+            for element in &elements.value {
+                match &element.value {
+                    RecordBindingElement::Identifier { name }
+                    | RecordBindingElement::Rest { name } => f(name),
+                    RecordBindingElement::KeyValuePair { pattern, .. } => {
+                        collect_pattern_names(&pattern.value, f)
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Computes the export table of a module and checks its `export` declarations.
+///
+/// Reports exports of names that are not bound in the module, exported values that use names
+/// which are neither top-level bindings nor bound within the value, names that are exported more
+/// than once, spreads of anything other than an imported module, and modules with more than one
+/// `export` declaration. Entries that produce an error are left out of the table.
+pub fn check_exports<'ast>(module: &Module<'ast>) -> (ExportTable<'ast>, Vec<Diagnostic>) {
+    let bindings = module.bindings();
+    let lookup = |name: &str| bindings.iter().find(|b| b.name.value == name);
+
+    let mut table = ExportTable::default();
+    let mut diagnostics = Vec::new();
+    let mut seen_export_block = false;
+
+    for decl in &module.declarations {
+        let Declaration::Export {
+            export_keyword,
+            elements,
+        } = &decl.value
+        else {
+            continue;
+        };
+
+        if seen_export_block {
+            diagnostics.push(diagnostic(
+                export_keyword.range,
+                DiagnosticSeverity::Warning,
+                "module has more than one 'export' declaration (merge them into one)".into(),
+            ));
+        }
+        seen_export_block = true;
+
+        for element in &elements.value {
+            let name = match &element.value {
+                RecordElement::Identifier { name } => name,
+                RecordElement::KeyValuePair { key, .. } => key,
+                RecordElement::Spread { value } => {
+                    match &value.value {
+                        &Expression::Name(n) => match lookup(n) {
+                            Some(ModuleBinding {
+                                kind: BindingKind::Import,
+                                ..
+                            }) => table.reexports.push(value.clone().map(|_| n)),
+                            Some(_) => diagnostics.push(diagnostic(
+                                value.range,
+                                DiagnosticSeverity::Error,
+                                format!("cannot spread '{n}' in an export, only imported modules can be re-exported"),
+                            )),
+                            None => diagnostics.push(diagnostic(
+                                value.range,
+                                DiagnosticSeverity::Error,
+                                format!("unknown export '{n}', no such binding in this module"),
+                            )),
+                        },
+                        _ => diagnostics.push(diagnostic(
+                            value.range,
+                            DiagnosticSeverity::Error,
+                            "cannot spread an arbitrary expression in an export".into(),
+                        )),
+                    }
+                    continue;
+                }
+            };
+
+            if table.get(name.value).is_some() {
+                diagnostics.push(diagnostic(
+                    name.range,
+                    DiagnosticSeverity::Error,
+                    format!("duplicate export '{}'", name.value),
+                ));
+                continue;
+            }
+
+            match &element.value {
+                RecordElement::Identifier { name } => match lookup(name.value) {
+                    Some(binding) => table.exports.push(Export::Binding {
+                        name: name.clone(),
+                        kind: binding.kind,
+                    }),
+                    None => diagnostics.push(diagnostic(
+                        name.range,
+                        DiagnosticSeverity::Error,
+                        format!(
+                            "unknown export '{}', no such binding in this module",
+                            name.value
+                        ),
+                    )),
+                },
+                RecordElement::KeyValuePair { key, value } => {
+                    let unknown: Vec<_> = free_names(value)
+                        .into_iter()
+                        .filter(|r| lookup(r.name).is_none())
+                        .collect();
+
+                    if !unknown.is_empty() {
+                        diagnostics.extend(unknown.iter().map(|r| {
+                            diagnostic(
+                                r.range,
+                                DiagnosticSeverity::Error,
+                                format!("unknown name '{}' in export of '{}'", r.name, key.value),
+                            )
+                        }));
+                        continue;
+                    }
+
+                    table.exports.push(Export::Value {
+                        name: key.clone(),
+                        value: value.clone(),
+                    })
+                }
+                RecordElement::Spread { .. } => unreachable!(),
+            }
+        }
+    }
+
+    (table, diagnostics)
+}
+
+fn diagnostic(range: seglisp::Range, severity: DiagnosticSeverity, message: String) -> Diagnostic {
+    Diagnostic {
+        abridged: false,
+        inner_diagnostics: None,
+        location: DiagnosticLocation::Range(range),
+        message,
+        note: None,
+        phase: DiagnosticPhase::Parse,
+        severity,
+        subject: None,
+    }
+}
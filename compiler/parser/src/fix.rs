@@ -0,0 +1,203 @@
+//! Suggested fixes for diagnostics.
+//!
+//! A [`FixableDiagnostic`] is a diagnostic with [`Suggestion`]s that resolve it. Each suggestion is a
+//! set of text edits and an [`Applicability`], which says whether the edits can be applied without
+//! a person looking at them. [`fix`] applies suggestions to a source text until none are left, or
+//! until applying them would break the parse.
+
+use wasm_bindgen::prelude::*;
+
+use seglisp::{
+    js_interop::{JsInterop, JsValue},
+    Diagnostic, DiagnosticSeverity,
+};
+
+use crate::{
+    incremental::{apply_edits, check_edits, parse_module, TextEdit},
+    lint::{check, LintConfig, Registry},
+};
+
+/// The most passes [`fix`] makes over a source text. Each pass may enable new fixes, but a fix
+/// that keeps enabling itself must not loop forever.
+const MAX_PASSES: usize = 16;
+
+/// How confident a suggestion is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, JsInterop)]
+pub enum Applicability {
+    /// The edits certainly preserve what the program means, and can be applied automatically.
+    MachineApplicable,
+    /// The edits are probably what was intended, but may change what the program means.
+    MaybeIncorrect,
+}
+
+impl Applicability {
+    /// Whether a suggestion of this applicability is applied when `allowed` ones are.
+    pub fn is_allowed_by(&self, allowed: Applicability) -> bool {
+        *self == Applicability::MachineApplicable || allowed == Applicability::MaybeIncorrect
+    }
+}
+
+/// Edits that resolve a diagnostic.
+#[derive(Debug, Clone, JsInterop)]
+pub struct Suggestion {
+    /// What the edits do, phrased as an action, e.g. "replace with `none`".
+    pub message: String,
+    pub applicability: Applicability,
+    /// Non-overlapping edits, in terms of the source the diagnostic was reported for.
+    pub edits: Vec<TextEdit>,
+}
+
+impl Suggestion {
+    /// A suggestion that replaces the bytes `start..end` with `text`.
+    pub fn replace(
+        message: impl Into<String>,
+        applicability: Applicability,
+        (start, end): (usize, usize),
+        text: impl Into<String>,
+    ) -> Self {
+        Suggestion {
+            message: message.into(),
+            applicability,
+            edits: vec![TextEdit {
+                start,
+                end,
+                text: text.into(),
+            }],
+        }
+    }
+}
+
+/// A diagnostic and the suggestions that resolve it, in order of preference.
+#[derive(Debug, Clone, JsInterop)]
+pub struct FixableDiagnostic {
+    pub diagnostic: Diagnostic,
+    pub suggestions: Vec<Suggestion>,
+}
+
+impl From<Diagnostic> for FixableDiagnostic {
+    fn from(diagnostic: Diagnostic) -> Self {
+        FixableDiagnostic {
+            diagnostic,
+            suggestions: Vec::new(),
+        }
+    }
+}
+
+/// Applies the first allowed suggestion of each diagnostic to `source`. A suggestion whose edits
+/// do not fit `source`, or overlap those of one already taken, is skipped. Returns the edited source
+/// and the number of suggestions applied.
+pub fn apply_suggestions(
+    source: &str,
+    diagnostics: &[FixableDiagnostic],
+    allowed: Applicability,
+) -> (String, usize) {
+    let mut taken: Vec<TextEdit> = Vec::new();
+    let mut applied = 0;
+
+    for diagnostic in diagnostics {
+        let Some(suggestion) = diagnostic
+            .suggestions
+            .iter()
+            .find(|s| s.applicability.is_allowed_by(allowed))
+        else {
+            continue;
+        };
+
+        let overlaps = suggestion.edits.iter().any(|edit| {
+            taken
+                .iter()
+                .any(|t| (edit.start < t.end && t.start < edit.end) || edit.start == t.start)
+        });
+
+        if !overlaps && check_edits(source, &suggestion.edits).is_ok() {
+            taken.extend(suggestion.edits.iter().cloned());
+            applied += 1;
+        }
+    }
+
+    let source = apply_edits(source, &taken).expect("taken suggestions fit and do not overlap");
+    (source, applied)
+}
+
+/// The result of [`fix`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fixed {
+    pub source: String,
+    /// The number of suggestions applied.
+    pub applied: usize,
+}
+
+/// Returns true if `source` parses without errors.
+fn parses_cleanly(source: &str) -> bool {
+    let parsed = parse_module(source);
+
+    parsed.module.is_some()
+        && !parsed
+            .diagnostics
+            .iter()
+            .any(|d| matches!(d.severity, DiagnosticSeverity::Error))
+}
+
+/// Lints `source` and applies the allowed suggestions, over and over until there are none left. A
+/// pass whose edits leave the source with parse errors is dropped, and the source as it was before
+/// that pass is returned.
+pub fn fix(
+    source: &str,
+    registry: &Registry,
+    config: &LintConfig,
+    allowed: Applicability,
+) -> Fixed {
+    let mut fixed = Fixed {
+        source: source.to_string(),
+        applied: 0,
+    };
+
+    for _ in 0..MAX_PASSES {
+        let parsed = parse_module(&fixed.source);
+        let Some(module) = &parsed.module else {
+            break;
+        };
+
+        let diagnostics = check(&fixed.source, module, registry, config);
+        let (source, applied) = apply_suggestions(&fixed.source, &diagnostics, allowed);
+        drop(parsed);
+
+        if applied == 0 || !parses_cleanly(&source) {
+            break;
+        }
+
+        fixed.source = source;
+        fixed.applied += applied;
+    }
+
+    fixed
+}
+
+/// Applies lint fixes to a document, with the built-in rules configured by the text of a project
+/// file. Only machine-applicable fixes are applied unless `maybe_incorrect` is set. Returns the
+/// fixed source, or `None` if the configuration is invalid or the document is not valid UTF-8.
+///
+/// This is everything [`fix_bytes`] does short of converting the result to JS, so it can be run
+/// natively.
+pub fn fix_document(data: &[u8], config: &str, maybe_incorrect: bool) -> Option<String> {
+    let config = LintConfig::parse(config).ok()?;
+    let source = core::str::from_utf8(data).ok()?;
+
+    let allowed = if maybe_incorrect {
+        Applicability::MaybeIncorrect
+    } else {
+        Applicability::MachineApplicable
+    };
+
+    Some(fix(source, &Registry::default(), &config, allowed).source)
+}
+
+/// Applies lint fixes to a document, as [`fix_document`] does. Returns the fixed source, or `null`
+/// if the configuration is invalid or the document is not valid UTF-8.
+#[wasm_bindgen]
+pub fn fix_bytes(data: &[u8], config: &str, maybe_incorrect: bool) -> JsValue {
+    match fix_document(data, config, maybe_incorrect) {
+        Some(source) => source.to_js_value(),
+        None => JsValue::NULL,
+    }
+}
@@ -0,0 +1,887 @@
+//! Owned, rebuilding traversal of the surface AST.
+//!
+//! A [`Fold`] consumes a tree and produces a new one. The default implementation of each method
+//! calls the matching `walk_*` function, which folds the node's children and rebuilds the node
+//! around them, keeping its original span. Implementors override the methods for the nodes they
+//! want to replace.
+//!
+//! A [`TryFold`] is the fallible form of a fold: each method returns a `Result`, and the first
+//! error stops the traversal and is returned from it. [`Fold`] is built on top of it, so both
+//! visit the children of a node in the same order.
+
+use std::convert::Infallible;
+
+use seglisp::parse::ParseNode;
+
+use crate::{
+    Assignment, BindingPattern, Declaration, Expression, GenericParameter, InterfaceField, Module,
+    ParameterDeclaration, ParsedVec, RecordBindingElement, RecordElement, Statement, Type,
+    TypeConstraint, Verbatim,
+};
+
+pub trait Fold<'ast> {
+    fn fold_module(&mut self, node: ParseNode<Module<'ast>>) -> ParseNode<Module<'ast>> {
+        walk_module(self, node)
+    }
+
+    fn fold_declaration(
+        &mut self,
+        node: ParseNode<Declaration<'ast>>,
+    ) -> ParseNode<Declaration<'ast>> {
+        walk_declaration(self, node)
+    }
+
+    fn fold_generic_parameter(
+        &mut self,
+        node: ParseNode<GenericParameter<'ast>>,
+    ) -> ParseNode<GenericParameter<'ast>> {
+        walk_generic_parameter(self, node)
+    }
+
+    fn fold_interface_field(
+        &mut self,
+        node: ParseNode<InterfaceField<'ast>>,
+    ) -> ParseNode<InterfaceField<'ast>> {
+        walk_interface_field(self, node)
+    }
+
+    fn fold_expression(
+        &mut self,
+        node: ParseNode<Expression<'ast>>,
+    ) -> ParseNode<Expression<'ast>> {
+        walk_expression(self, node)
+    }
+
+    fn fold_type_constraint(
+        &mut self,
+        node: ParseNode<TypeConstraint<'ast>>,
+    ) -> ParseNode<TypeConstraint<'ast>> {
+        walk_type_constraint(self, node)
+    }
+
+    fn fold_type(&mut self, node: ParseNode<Type<'ast>>) -> ParseNode<Type<'ast>> {
+        walk_type(self, node)
+    }
+
+    fn fold_parameter(
+        &mut self,
+        node: ParseNode<ParameterDeclaration<'ast>>,
+    ) -> ParseNode<ParameterDeclaration<'ast>> {
+        walk_parameter(self, node)
+    }
+
+    fn fold_assignment(
+        &mut self,
+        node: ParseNode<Assignment<'ast>>,
+    ) -> ParseNode<Assignment<'ast>> {
+        walk_assignment(self, node)
+    }
+
+    fn fold_record_element(
+        &mut self,
+        node: ParseNode<RecordElement<'ast>>,
+    ) -> ParseNode<RecordElement<'ast>> {
+        walk_record_element(self, node)
+    }
+
+    fn fold_statement(&mut self, node: ParseNode<Statement<'ast>>) -> ParseNode<Statement<'ast>> {
+        walk_statement(self, node)
+    }
+
+    fn fold_binding_pattern(
+        &mut self,
+        node: ParseNode<BindingPattern<'ast>>,
+    ) -> ParseNode<BindingPattern<'ast>> {
+        walk_binding_pattern(self, node)
+    }
+
+    fn fold_record_binding_element(
+        &mut self,
+        node: ParseNode<RecordBindingElement<'ast>>,
+    ) -> ParseNode<RecordBindingElement<'ast>> {
+        walk_record_binding_element(self, node)
+    }
+
+    /// Called for every identifier token that is not itself an expression. See
+    /// [`Visit::visit_identifier`](crate::visit::Visit::visit_identifier).
+    fn fold_identifier(&mut self, node: Verbatim<'ast>) -> Verbatim<'ast> {
+        node
+    }
+}
+
+pub trait TryFold<'ast> {
+    /// The error that stops the fold.
+    type Error;
+
+    fn try_fold_module(
+        &mut self,
+        node: ParseNode<Module<'ast>>,
+    ) -> Result<ParseNode<Module<'ast>>, Self::Error> {
+        try_walk_module(self, node)
+    }
+
+    fn try_fold_declaration(
+        &mut self,
+        node: ParseNode<Declaration<'ast>>,
+    ) -> Result<ParseNode<Declaration<'ast>>, Self::Error> {
+        try_walk_declaration(self, node)
+    }
+
+    fn try_fold_generic_parameter(
+        &mut self,
+        node: ParseNode<GenericParameter<'ast>>,
+    ) -> Result<ParseNode<GenericParameter<'ast>>, Self::Error> {
+        try_walk_generic_parameter(self, node)
+    }
+
+    fn try_fold_interface_field(
+        &mut self,
+        node: ParseNode<InterfaceField<'ast>>,
+    ) -> Result<ParseNode<InterfaceField<'ast>>, Self::Error> {
+        try_walk_interface_field(self, node)
+    }
+
+    fn try_fold_expression(
+        &mut self,
+        node: ParseNode<Expression<'ast>>,
+    ) -> Result<ParseNode<Expression<'ast>>, Self::Error> {
+        try_walk_expression(self, node)
+    }
+
+    fn try_fold_type_constraint(
+        &mut self,
+        node: ParseNode<TypeConstraint<'ast>>,
+    ) -> Result<ParseNode<TypeConstraint<'ast>>, Self::Error> {
+        try_walk_type_constraint(self, node)
+    }
+
+    fn try_fold_type(
+        &mut self,
+        node: ParseNode<Type<'ast>>,
+    ) -> Result<ParseNode<Type<'ast>>, Self::Error> {
+        try_walk_type(self, node)
+    }
+
+    fn try_fold_parameter(
+        &mut self,
+        node: ParseNode<ParameterDeclaration<'ast>>,
+    ) -> Result<ParseNode<ParameterDeclaration<'ast>>, Self::Error> {
+        try_walk_parameter(self, node)
+    }
+
+    fn try_fold_assignment(
+        &mut self,
+        node: ParseNode<Assignment<'ast>>,
+    ) -> Result<ParseNode<Assignment<'ast>>, Self::Error> {
+        try_walk_assignment(self, node)
+    }
+
+    fn try_fold_record_element(
+        &mut self,
+        node: ParseNode<RecordElement<'ast>>,
+    ) -> Result<ParseNode<RecordElement<'ast>>, Self::Error> {
+        try_walk_record_element(self, node)
+    }
+
+    fn try_fold_statement(
+        &mut self,
+        node: ParseNode<Statement<'ast>>,
+    ) -> Result<ParseNode<Statement<'ast>>, Self::Error> {
+        try_walk_statement(self, node)
+    }
+
+    fn try_fold_binding_pattern(
+        &mut self,
+        node: ParseNode<BindingPattern<'ast>>,
+    ) -> Result<ParseNode<BindingPattern<'ast>>, Self::Error> {
+        try_walk_binding_pattern(self, node)
+    }
+
+    fn try_fold_record_binding_element(
+        &mut self,
+        node: ParseNode<RecordBindingElement<'ast>>,
+    ) -> Result<ParseNode<RecordBindingElement<'ast>>, Self::Error> {
+        try_walk_record_binding_element(self, node)
+    }
+
+    /// Called for every identifier token that is not itself an expression. See
+    /// [`Visit::visit_identifier`](crate::visit::Visit::visit_identifier).
+    fn try_fold_identifier(&mut self, node: Verbatim<'ast>) -> Result<Verbatim<'ast>, Self::Error> {
+        Ok(node)
+    }
+}
+
+/// Runs a [`Fold`] as a [`TryFold`] that never fails.
+struct Infallibly<'f, F: ?Sized>(&'f mut F);
+
+impl<'ast, F: Fold<'ast> + ?Sized> TryFold<'ast> for Infallibly<'_, F> {
+    type Error = Infallible;
+
+    fn try_fold_module(
+        &mut self,
+        node: ParseNode<Module<'ast>>,
+    ) -> Result<ParseNode<Module<'ast>>, Infallible> {
+        Ok(self.0.fold_module(node))
+    }
+
+    fn try_fold_declaration(
+        &mut self,
+        node: ParseNode<Declaration<'ast>>,
+    ) -> Result<ParseNode<Declaration<'ast>>, Infallible> {
+        Ok(self.0.fold_declaration(node))
+    }
+
+    fn try_fold_generic_parameter(
+        &mut self,
+        node: ParseNode<GenericParameter<'ast>>,
+    ) -> Result<ParseNode<GenericParameter<'ast>>, Infallible> {
+        Ok(self.0.fold_generic_parameter(node))
+    }
+
+    fn try_fold_interface_field(
+        &mut self,
+        node: ParseNode<InterfaceField<'ast>>,
+    ) -> Result<ParseNode<InterfaceField<'ast>>, Infallible> {
+        Ok(self.0.fold_interface_field(node))
+    }
+
+    fn try_fold_expression(
+        &mut self,
+        node: ParseNode<Expression<'ast>>,
+    ) -> Result<ParseNode<Expression<'ast>>, Infallible> {
+        Ok(self.0.fold_expression(node))
+    }
+
+    fn try_fold_type_constraint(
+        &mut self,
+        node: ParseNode<TypeConstraint<'ast>>,
+    ) -> Result<ParseNode<TypeConstraint<'ast>>, Infallible> {
+        Ok(self.0.fold_type_constraint(node))
+    }
+
+    fn try_fold_type(
+        &mut self,
+        node: ParseNode<Type<'ast>>,
+    ) -> Result<ParseNode<Type<'ast>>, Infallible> {
+        Ok(self.0.fold_type(node))
+    }
+
+    fn try_fold_parameter(
+        &mut self,
+        node: ParseNode<ParameterDeclaration<'ast>>,
+    ) -> Result<ParseNode<ParameterDeclaration<'ast>>, Infallible> {
+        Ok(self.0.fold_parameter(node))
+    }
+
+    fn try_fold_assignment(
+        &mut self,
+        node: ParseNode<Assignment<'ast>>,
+    ) -> Result<ParseNode<Assignment<'ast>>, Infallible> {
+        Ok(self.0.fold_assignment(node))
+    }
+
+    fn try_fold_record_element(
+        &mut self,
+        node: ParseNode<RecordElement<'ast>>,
+    ) -> Result<ParseNode<RecordElement<'ast>>, Infallible> {
+        Ok(self.0.fold_record_element(node))
+    }
+
+    fn try_fold_statement(
+        &mut self,
+        node: ParseNode<Statement<'ast>>,
+    ) -> Result<ParseNode<Statement<'ast>>, Infallible> {
+        Ok(self.0.fold_statement(node))
+    }
+
+    fn try_fold_binding_pattern(
+        &mut self,
+        node: ParseNode<BindingPattern<'ast>>,
+    ) -> Result<ParseNode<BindingPattern<'ast>>, Infallible> {
+        Ok(self.0.fold_binding_pattern(node))
+    }
+
+    fn try_fold_record_binding_element(
+        &mut self,
+        node: ParseNode<RecordBindingElement<'ast>>,
+    ) -> Result<ParseNode<RecordBindingElement<'ast>>, Infallible> {
+        Ok(self.0.fold_record_binding_element(node))
+    }
+
+    fn try_fold_identifier(&mut self, node: Verbatim<'ast>) -> Result<Verbatim<'ast>, Infallible> {
+        Ok(self.0.fold_identifier(node))
+    }
+}
+
+fn infallible<T>(result: Result<T, Infallible>) -> T {
+    match result {
+        Ok(value) => value,
+        Err(never) => match never {},
+    }
+}
+
+pub fn walk_module<'ast, F: Fold<'ast> + ?Sized>(
+    f: &mut F,
+    node: ParseNode<Module<'ast>>,
+) -> ParseNode<Module<'ast>> {
+    infallible(try_walk_module(&mut Infallibly(f), node))
+}
+
+pub fn walk_declaration<'ast, F: Fold<'ast> + ?Sized>(
+    f: &mut F,
+    node: ParseNode<Declaration<'ast>>,
+) -> ParseNode<Declaration<'ast>> {
+    infallible(try_walk_declaration(&mut Infallibly(f), node))
+}
+
+pub fn walk_generic_parameter<'ast, F: Fold<'ast> + ?Sized>(
+    f: &mut F,
+    node: ParseNode<GenericParameter<'ast>>,
+) -> ParseNode<GenericParameter<'ast>> {
+    infallible(try_walk_generic_parameter(&mut Infallibly(f), node))
+}
+
+pub fn walk_interface_field<'ast, F: Fold<'ast> + ?Sized>(
+    f: &mut F,
+    node: ParseNode<InterfaceField<'ast>>,
+) -> ParseNode<InterfaceField<'ast>> {
+    infallible(try_walk_interface_field(&mut Infallibly(f), node))
+}
+
+pub fn walk_expression<'ast, F: Fold<'ast> + ?Sized>(
+    f: &mut F,
+    node: ParseNode<Expression<'ast>>,
+) -> ParseNode<Expression<'ast>> {
+    infallible(try_walk_expression(&mut Infallibly(f), node))
+}
+
+pub fn walk_type_constraint<'ast, F: Fold<'ast> + ?Sized>(
+    f: &mut F,
+    node: ParseNode<TypeConstraint<'ast>>,
+) -> ParseNode<TypeConstraint<'ast>> {
+    infallible(try_walk_type_constraint(&mut Infallibly(f), node))
+}
+
+pub fn walk_type<'ast, F: Fold<'ast> + ?Sized>(
+    f: &mut F,
+    node: ParseNode<Type<'ast>>,
+) -> ParseNode<Type<'ast>> {
+    infallible(try_walk_type(&mut Infallibly(f), node))
+}
+
+pub fn walk_parameter<'ast, F: Fold<'ast> + ?Sized>(
+    f: &mut F,
+    node: ParseNode<ParameterDeclaration<'ast>>,
+) -> ParseNode<ParameterDeclaration<'ast>> {
+    infallible(try_walk_parameter(&mut Infallibly(f), node))
+}
+
+pub fn walk_assignment<'ast, F: Fold<'ast> + ?Sized>(
+    f: &mut F,
+    node: ParseNode<Assignment<'ast>>,
+) -> ParseNode<Assignment<'ast>> {
+    infallible(try_walk_assignment(&mut Infallibly(f), node))
+}
+
+pub fn walk_record_element<'ast, F: Fold<'ast> + ?Sized>(
+    f: &mut F,
+    node: ParseNode<RecordElement<'ast>>,
+) -> ParseNode<RecordElement<'ast>> {
+    infallible(try_walk_record_element(&mut Infallibly(f), node))
+}
+
+pub fn walk_statement<'ast, F: Fold<'ast> + ?Sized>(
+    f: &mut F,
+    node: ParseNode<Statement<'ast>>,
+) -> ParseNode<Statement<'ast>> {
+    infallible(try_walk_statement(&mut Infallibly(f), node))
+}
+
+pub fn walk_binding_pattern<'ast, F: Fold<'ast> + ?Sized>(
+    f: &mut F,
+    node: ParseNode<BindingPattern<'ast>>,
+) -> ParseNode<BindingPattern<'ast>> {
+    infallible(try_walk_binding_pattern(&mut Infallibly(f), node))
+}
+
+pub fn walk_record_binding_element<'ast, F: Fold<'ast> + ?Sized>(
+    f: &mut F,
+    node: ParseNode<RecordBindingElement<'ast>>,
+) -> ParseNode<RecordBindingElement<'ast>> {
+    infallible(try_walk_record_binding_element(&mut Infallibly(f), node))
+}
+
+fn try_map<T, U, E>(
+    node: ParseNode<T>,
+    f: impl FnOnce(T) -> Result<U, E>,
+) -> Result<ParseNode<U>, E> {
+    Ok(ParseNode {
+        value: f(node.value)?,
+        range: node.range,
+        has_error: node.has_error,
+    })
+}
+
+fn fold_list<T, E>(
+    list: ParsedVec<T>,
+    f: impl FnMut(ParseNode<T>) -> Result<ParseNode<T>, E>,
+) -> Result<ParsedVec<T>, E> {
+    try_map(list, |nodes| nodes.into_iter().map(f).collect())
+}
+
+fn fold_boxed<T, E>(
+    node: Box<ParseNode<T>>,
+    f: impl FnOnce(ParseNode<T>) -> Result<ParseNode<T>, E>,
+) -> Result<Box<ParseNode<T>>, E> {
+    f(*node).map(Box::new)
+}
+
+fn fold_generics<'ast, F: TryFold<'ast> + ?Sized>(
+    f: &mut F,
+    generics: Option<ParsedVec<GenericParameter<'ast>>>,
+) -> Result<Option<ParsedVec<GenericParameter<'ast>>>, F::Error> {
+    generics
+        .map(|generics| fold_list(generics, |p| f.try_fold_generic_parameter(p)))
+        .transpose()
+}
+
+fn fold_constraint<'ast, F: TryFold<'ast> + ?Sized>(
+    f: &mut F,
+    constraint: Option<ParseNode<TypeConstraint<'ast>>>,
+) -> Result<Option<ParseNode<TypeConstraint<'ast>>>, F::Error> {
+    constraint
+        .map(|constraint| f.try_fold_type_constraint(constraint))
+        .transpose()
+}
+
+pub fn try_walk_module<'ast, F: TryFold<'ast> + ?Sized>(
+    f: &mut F,
+    node: ParseNode<Module<'ast>>,
+) -> Result<ParseNode<Module<'ast>>, F::Error> {
+    try_map(node, |module| {
+        Ok(Module {
+            declarations: module
+                .declarations
+                .into_iter()
+                .map(|decl| f.try_fold_declaration(decl))
+                .collect::<Result<_, _>>()?,
+        })
+    })
+}
+
+pub fn try_walk_declaration<'ast, F: TryFold<'ast> + ?Sized>(
+    f: &mut F,
+    node: ParseNode<Declaration<'ast>>,
+) -> Result<ParseNode<Declaration<'ast>>, F::Error> {
+    try_map(node, |decl| {
+        Ok(match decl {
+            Declaration::Main { main_keyword, body } => Declaration::Main {
+                main_keyword,
+                body: fold_boxed(body, |e| f.try_fold_expression(e))?,
+            },
+            Declaration::Const {
+                const_keyword,
+                identifier,
+                type_,
+                equals_token,
+                value,
+            } => Declaration::Const {
+                const_keyword,
+                identifier: f.try_fold_identifier(identifier)?,
+                type_: fold_constraint(f, type_)?,
+                equals_token,
+                value: fold_boxed(value, |e| f.try_fold_expression(e))?,
+            },
+            Declaration::Function {
+                function_keyword,
+                identifier,
+                generic_parameters,
+                parameters,
+                constraint,
+                arrow_token,
+                body,
+            } => Declaration::Function {
+                function_keyword,
+                identifier: f.try_fold_identifier(identifier)?,
+                generic_parameters: fold_generics(f, generic_parameters)?,
+                parameters: fold_list(parameters, |p| f.try_fold_parameter(p))?,
+                constraint: fold_constraint(f, constraint)?,
+                arrow_token,
+                body: fold_boxed(body, |e| f.try_fold_expression(e))?,
+            },
+            Declaration::Import {
+                import_keyword,
+                pattern,
+                equal_token,
+                use_keyword,
+                module_specifier,
+            } => Declaration::Import {
+                import_keyword,
+                pattern: f.try_fold_binding_pattern(pattern)?,
+                equal_token,
+                use_keyword,
+                module_specifier,
+            },
+            Declaration::Export {
+                export_keyword,
+                elements,
+            } => Declaration::Export {
+                export_keyword,
+                elements: fold_list(elements, |e| f.try_fold_record_element(e))?,
+            },
+            Declaration::TypeAlias {
+                type_keyword,
+                name,
+                generic_parameters,
+                equals_token,
+                value,
+            } => Declaration::TypeAlias {
+                type_keyword,
+                name: f.try_fold_identifier(name)?,
+                generic_parameters: fold_generics(f, generic_parameters)?,
+                equals_token,
+                value: f.try_fold_type(value)?,
+            },
+            Declaration::Interface {
+                interface_keyword,
+                name,
+                generic_parameters,
+                constraint,
+                body,
+            } => Declaration::Interface {
+                interface_keyword,
+                name: f.try_fold_identifier(name)?,
+                generic_parameters: fold_generics(f, generic_parameters)?,
+                constraint: fold_constraint(f, constraint)?,
+                body: fold_list(body, |field| f.try_fold_interface_field(field))?,
+            },
+        })
+    })
+}
+
+pub fn try_walk_generic_parameter<'ast, F: TryFold<'ast> + ?Sized>(
+    f: &mut F,
+    node: ParseNode<GenericParameter<'ast>>,
+) -> Result<ParseNode<GenericParameter<'ast>>, F::Error> {
+    try_map(node, |p| {
+        Ok(GenericParameter {
+            name: f.try_fold_identifier(p.name)?,
+            constraint: fold_constraint(f, p.constraint)?,
+        })
+    })
+}
+
+pub fn try_walk_interface_field<'ast, F: TryFold<'ast> + ?Sized>(
+    f: &mut F,
+    node: ParseNode<InterfaceField<'ast>>,
+) -> Result<ParseNode<InterfaceField<'ast>>, F::Error> {
+    try_map(node, |field| {
+        Ok(InterfaceField {
+            name: f.try_fold_identifier(field.name)?,
+            constraint: f.try_fold_type_constraint(field.constraint)?,
+        })
+    })
+}
+
+pub fn try_walk_expression<'ast, F: TryFold<'ast> + ?Sized>(
+    f: &mut F,
+    node: ParseNode<Expression<'ast>>,
+) -> Result<ParseNode<Expression<'ast>>, F::Error> {
+    try_map(node, |expr| {
+        Ok(match expr {
+            e @ (Expression::Number(_)
+            | Expression::String(_)
+            | Expression::Boolean(_)
+            | Expression::Name(_)
+            | Expression::Hole
+            | Expression::None) => e,
+            Expression::As {
+                expr,
+                as_token,
+                type_,
+            } => Expression::As {
+                expr: fold_boxed(expr, |e| f.try_fold_expression(e))?,
+                as_token,
+                type_: fold_boxed(type_, |t| f.try_fold_type(t))?,
+            },
+            Expression::Unary {
+                operator,
+                expression,
+            } => Expression::Unary {
+                operator,
+                expression: fold_boxed(expression, |e| f.try_fold_expression(e))?,
+            },
+            Expression::Compare {
+                operator,
+                left,
+                right,
+            } => Expression::Compare {
+                operator,
+                left: fold_boxed(left, |e| f.try_fold_expression(e))?,
+                right: fold_boxed(right, |e| f.try_fold_expression(e))?,
+            },
+            Expression::Arithmetic {
+                operator,
+                left,
+                right,
+            } => Expression::Arithmetic {
+                operator,
+                left: fold_boxed(left, |e| f.try_fold_expression(e))?,
+                right: fold_boxed(right, |e| f.try_fold_expression(e))?,
+            },
+            Expression::Accessor { accessee, index } => Expression::Accessor {
+                accessee: fold_boxed(accessee, |e| f.try_fold_expression(e))?,
+                index: fold_boxed(index, |e| f.try_fold_expression(e))?,
+            },
+            Expression::Function {
+                fn_keyword,
+                name,
+                generic_parameters,
+                parameters,
+                constraint,
+                arrow_token,
+                body,
+            } => Expression::Function {
+                fn_keyword,
+                name: name.map(|name| f.try_fold_identifier(name)).transpose()?,
+                generic_parameters: fold_generics(f, generic_parameters)?,
+                parameters: fold_list(parameters, |p| f.try_fold_parameter(p))?,
+                constraint: fold_constraint(f, constraint)?,
+                arrow_token,
+                body: fold_boxed(body, |e| f.try_fold_expression(e))?,
+            },
+            Expression::Call { callee, parameters } => Expression::Call {
+                callee: fold_boxed(callee, |e| f.try_fold_expression(e))?,
+                parameters: fold_list(parameters, |e| f.try_fold_expression(e))?,
+            },
+            Expression::With {
+                with_keyword,
+                bindings,
+                body,
+            } => Expression::With {
+                with_keyword,
+                bindings: fold_list(bindings, |b| f.try_fold_assignment(b))?,
+                body: fold_boxed(body, |e| f.try_fold_expression(e))?,
+            },
+            Expression::Tuple { elements } => Expression::Tuple {
+                elements: fold_list(elements, |e| f.try_fold_expression(e))?,
+            },
+            Expression::List { elements } => Expression::List {
+                elements: fold_list(elements, |e| f.try_fold_expression(e))?,
+            },
+            Expression::Procedure { body } => Expression::Procedure {
+                body: fold_list(body, |s| f.try_fold_statement(s))?,
+            },
+            Expression::If {
+                if_keyword,
+                condition,
+                then_keyword,
+                then,
+                else_keyword,
+                _else,
+            } => Expression::If {
+                if_keyword,
+                condition: fold_boxed(condition, |e| f.try_fold_expression(e))?,
+                then_keyword,
+                then: fold_boxed(then, |e| f.try_fold_expression(e))?,
+                else_keyword,
+                _else: fold_boxed(_else, |e| f.try_fold_expression(e))?,
+            },
+            Expression::Record { elements } => Expression::Record {
+                elements: fold_list(elements, |e| f.try_fold_record_element(e))?,
+            },
+            Expression::FieldAccess { accessee, field } => Expression::FieldAccess {
+                accessee: fold_boxed(accessee, |e| f.try_fold_expression(e))?,
+                field: f.try_fold_identifier(field)?,
+            },
+        })
+    })
+}
+
+pub fn try_walk_type_constraint<'ast, F: TryFold<'ast> + ?Sized>(
+    f: &mut F,
+    node: ParseNode<TypeConstraint<'ast>>,
+) -> Result<ParseNode<TypeConstraint<'ast>>, F::Error> {
+    try_map(node, |c| {
+        Ok(TypeConstraint {
+            colon_token: c.colon_token,
+            type_: fold_boxed(c.type_, |t| f.try_fold_type(t))?,
+        })
+    })
+}
+
+pub fn try_walk_type<'ast, F: TryFold<'ast> + ?Sized>(
+    f: &mut F,
+    node: ParseNode<Type<'ast>>,
+) -> Result<ParseNode<Type<'ast>>, F::Error> {
+    try_map(node, |t| {
+        Ok(match t {
+            t @ (Type::Kind | Type::Never | Type::Unknown) => t,
+            Type::Reference {
+                name,
+                generic_parameters,
+            } => Type::Reference {
+                name: f.try_fold_identifier(name)?,
+                generic_parameters: generic_parameters
+                    .map(|parameters| fold_list(parameters, |t| f.try_fold_type(t)))
+                    .transpose()?,
+            },
+            Type::Union { left, right } => Type::Union {
+                left: fold_boxed(left, |t| f.try_fold_type(t))?,
+                right: fold_boxed(right, |t| f.try_fold_type(t))?,
+            },
+            Type::Tuple { members } => Type::Tuple {
+                members: fold_list(members, |t| f.try_fold_type(t))?,
+            },
+            Type::Function {
+                fn_keyword,
+                parameters,
+                arrow_token,
+                return_type,
+            } => Type::Function {
+                fn_keyword,
+                parameters: fold_list(parameters, |t| f.try_fold_type(t))?,
+                arrow_token,
+                return_type: fold_boxed(return_type, |t| f.try_fold_type(t))?,
+            },
+        })
+    })
+}
+
+pub fn try_walk_parameter<'ast, F: TryFold<'ast> + ?Sized>(
+    f: &mut F,
+    node: ParseNode<ParameterDeclaration<'ast>>,
+) -> Result<ParseNode<ParameterDeclaration<'ast>>, F::Error> {
+    try_map(node, |p| {
+        Ok(ParameterDeclaration {
+            name: f.try_fold_identifier(p.name)?,
+            type_: fold_constraint(f, p.type_)?,
+        })
+    })
+}
+
+pub fn try_walk_assignment<'ast, F: TryFold<'ast> + ?Sized>(
+    f: &mut F,
+    node: ParseNode<Assignment<'ast>>,
+) -> Result<ParseNode<Assignment<'ast>>, F::Error> {
+    try_map(node, |a| {
+        Ok(Assignment {
+            symbol: f.try_fold_identifier(a.symbol)?,
+            equal_token: a.equal_token,
+            value: f.try_fold_expression(a.value)?,
+        })
+    })
+}
+
+pub fn try_walk_record_element<'ast, F: TryFold<'ast> + ?Sized>(
+    f: &mut F,
+    node: ParseNode<RecordElement<'ast>>,
+) -> Result<ParseNode<RecordElement<'ast>>, F::Error> {
+    try_map(node, |element| {
+        Ok(match element {
+            RecordElement::KeyValuePair { key, value } => RecordElement::KeyValuePair {
+                key: f.try_fold_identifier(key)?,
+                value: f.try_fold_expression(value)?,
+            },
+            RecordElement::Identifier { name } => RecordElement::Identifier {
+                name: f.try_fold_identifier(name)?,
+            },
+            RecordElement::Spread { value } => RecordElement::Spread {
+                value: f.try_fold_expression(value)?,
+            },
+        })
+    })
+}
+
+pub fn try_walk_statement<'ast, F: TryFold<'ast> + ?Sized>(
+    f: &mut F,
+    node: ParseNode<Statement<'ast>>,
+) -> Result<ParseNode<Statement<'ast>>, F::Error> {
+    try_map(node, |stmt| {
+        Ok(match stmt {
+            Statement::Let {
+                let_keyword,
+                assignment,
+            } => Statement::Let {
+                let_keyword,
+                assignment: f.try_fold_assignment(assignment)?,
+            },
+            Statement::Set(assignment) => Statement::Set(f.try_fold_assignment(assignment)?),
+            Statement::If {
+                if_keyword,
+                condition,
+                then,
+                _else,
+            } => Statement::If {
+                if_keyword,
+                condition: fold_boxed(condition, |e| f.try_fold_expression(e))?,
+                then: fold_boxed(then, |s| f.try_fold_statement(s))?,
+                _else: _else
+                    .map(|s| fold_boxed(s, |s| f.try_fold_statement(s)))
+                    .transpose()?,
+            },
+            Statement::ForIn {
+                for_keyword,
+                binding,
+                in_keyword,
+                iterator,
+                body,
+            } => Statement::ForIn {
+                for_keyword,
+                binding: f.try_fold_identifier(binding)?,
+                in_keyword,
+                iterator: fold_boxed(iterator, |e| f.try_fold_expression(e))?,
+                body: fold_boxed(body, |s| f.try_fold_statement(s))?,
+            },
+            Statement::Forever(body) => {
+                Statement::Forever(fold_boxed(body, |s| f.try_fold_statement(s))?)
+            }
+            Statement::Do(body) => Statement::Do(fold_boxed(body, |e| f.try_fold_expression(e))?),
+            Statement::Expression(body) => {
+                Statement::Expression(fold_boxed(body, |e| f.try_fold_expression(e))?)
+            }
+            s @ (Statement::Break | Statement::Continue | Statement::Pass) => s,
+        })
+    })
+}
+
+pub fn try_walk_binding_pattern<'ast, F: TryFold<'ast> + ?Sized>(
+    f: &mut F,
+    node: ParseNode<BindingPattern<'ast>>,
+) -> Result<ParseNode<BindingPattern<'ast>>, F::Error> {
+    try_map(node, |pattern| {
+        Ok(match pattern {
+            BindingPattern::Identifier { name } => BindingPattern::Identifier {
+                name: f.try_fold_identifier(name)?,
+            },
+            BindingPattern::Tuple { patterns } => BindingPattern::Tuple {
+                patterns: fold_list(patterns, |p| f.try_fold_binding_pattern(p))?,
+            },
+            BindingPattern::Record { elements } => BindingPattern::Record {
+                elements: fold_list(elements, |e| f.try_fold_record_binding_element(e))?,
+            },
+        })
+    })
+}
+
+pub fn try_walk_record_binding_element<'ast, F: TryFold<'ast> + ?Sized>(
+    f: &mut F,
+    node: ParseNode<RecordBindingElement<'ast>>,
+) -> Result<ParseNode<RecordBindingElement<'ast>>, F::Error> {
+    try_map(node, |element| {
+        Ok(match element {
+            RecordBindingElement::Identifier { name } => RecordBindingElement::Identifier {
+                name: f.try_fold_identifier(name)?,
+            },
+            RecordBindingElement::KeyValuePair { name, pattern } => {
+                RecordBindingElement::KeyValuePair {
+                    name: f.try_fold_identifier(name)?,
+                    pattern: f.try_fold_binding_pattern(pattern)?,
+                }
+            }
+            RecordBindingElement::Rest { name } => RecordBindingElement::Rest {
+                name: f.try_fold_identifier(name)?,
+            },
+        })
+    })
+}
@@ -0,0 +1,130 @@
+//! A set of modules that import one another.
+//!
+//! Modules are identified by `/`-separated paths. An import specifier is resolved the way the
+//! interpreter resolves it: relative to the directory of the importing module, with `.` and `..`
+//! segments normalized away. Modules that are imported but not part of the graph are simply not
+//! followed.
+
+use seglisp::parse::ParseNode;
+
+use crate::{BindingPattern, Declaration, Module};
+
+/// A module in a [`ModuleGraph`].
+#[derive(Debug, Clone, Copy)]
+pub struct GraphModule<'a, 'ast> {
+    pub path: &'a str,
+    pub source: &'ast str,
+    pub module: &'ast ParseNode<Module<'ast>>,
+}
+
+/// An import declaration and the module it refers to.
+#[derive(Debug, Clone, Copy)]
+pub struct Import<'ast> {
+    pub pattern: &'ast ParseNode<BindingPattern<'ast>>,
+    pub specifier: &'ast ParseNode<&'ast str>,
+    /// The index of the imported module, if it is part of the graph.
+    pub target: Option<usize>,
+}
+
+/// The modules of a project, indexed in the order they were added.
+#[derive(Debug, Clone, Default)]
+pub struct ModuleGraph<'ast> {
+    modules: Vec<(String, &'ast str, &'ast ParseNode<Module<'ast>>)>,
+}
+
+impl<'ast> ModuleGraph<'ast> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the module at `path`, parsed from `source`, and returns its index.
+    pub fn add(
+        &mut self,
+        path: &str,
+        source: &'ast str,
+        module: &'ast ParseNode<Module<'ast>>,
+    ) -> usize {
+        self.modules.push((normalize(path), source, module));
+        self.modules.len() - 1
+    }
+
+    pub fn len(&self) -> usize {
+        self.modules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.modules.is_empty()
+    }
+
+    pub fn get(&self, idx: usize) -> GraphModule<'_, 'ast> {
+        let (path, source, module) = &self.modules[idx];
+        GraphModule {
+            path,
+            source,
+            module,
+        }
+    }
+
+    /// The index of the module at `path`, if it is part of the graph.
+    pub fn find(&self, path: &str) -> Option<usize> {
+        let path = normalize(path);
+        self.modules.iter().position(|(p, ..)| *p == path)
+    }
+
+    /// The index of the module that `specifier` refers to when imported from module `from`.
+    pub fn resolve_specifier(&self, from: usize, specifier: &str) -> Option<usize> {
+        let (path, ..) = &self.modules[from];
+
+        match path.rsplit_once('/') {
+            _ if specifier.starts_with('/') => self.find(specifier),
+            Some((directory, _)) => self.find(&format!("{directory}/{specifier}")),
+            None => self.find(specifier),
+        }
+    }
+
+    /// The import declarations of module `idx`.
+    pub fn imports(&self, idx: usize) -> Vec<Import<'ast>> {
+        let module = self.modules[idx].2;
+
+        module
+            .value
+            .declarations
+            .iter()
+            .filter_map(|decl| match &decl.value {
+                Declaration::Import {
+                    pattern,
+                    module_specifier,
+                    ..
+                } => Some(Import {
+                    pattern,
+                    specifier: module_specifier,
+                    target: self.resolve_specifier(idx, module_specifier.value),
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// Removes empty and `.` segments from a path, and `..` segments along with the segment before
+/// them.
+fn normalize(path: &str) -> String {
+    let mut segments: Vec<&str> = Vec::new();
+
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+
+    let normalized = segments.join("/");
+    if path.starts_with('/') {
+        format!("/{normalized}")
+    } else {
+        normalized
+    }
+}
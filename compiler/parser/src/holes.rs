@@ -0,0 +1,680 @@
+//! Completion suggestions for typed holes.
+//!
+//! A hole (`@`) stands for an expression that has not been written yet. [`complete_hole`] finds the
+//! hole at a position, works out the type its context expects and the names in scope there, and
+//! returns ranked candidates to fill it: bindings whose type fits, calls to functions whose result
+//! fits, literals, tuple, list, and record constructors, and `if`/`with`/`fn` templates.
+//!
+//! Each candidate carries a snippet, in the placeholder syntax shared by LSP and Monaco, and the
+//! plain source text it stands for. [`HoleCompletion::fragment`] parses that text into the AST
+//! fragment that replaces the hole, for structured editors such as Camino.
+
+use wasm_bindgen::prelude::*;
+
+use seglisp::{
+    js_interop::{JsInterop, JsValue},
+    parse::ParseNode,
+};
+
+use crate::{
+    incremental::{parse_module, relocate_expression},
+    node::{NodePath, NodeRef},
+    query::{nodes_at_offset, offset_of_position},
+    types::{Ty, TypeEnv},
+    with_parsed_module, ArithmeticOp, Declaration, Expression, Module, Statement, UnaryOp,
+};
+
+/// What a completion candidate inserts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompletionKind {
+    /// A name in scope.
+    Binding,
+    /// A call to a function in scope.
+    Call,
+    Literal,
+    /// A tuple, list, record, or procedure.
+    Constructor,
+    /// An `if`, `with`, or `fn` expression.
+    Template,
+}
+
+impl CompletionKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CompletionKind::Binding => "binding",
+            CompletionKind::Call => "call",
+            CompletionKind::Literal => "literal",
+            CompletionKind::Constructor => "constructor",
+            CompletionKind::Template => "template",
+        }
+    }
+}
+
+/// A candidate to fill a hole.
+#[derive(Debug, Clone)]
+pub struct HoleCompletion {
+    pub label: String,
+    pub kind: CompletionKind,
+    /// The type of the candidate, if it is known.
+    pub detail: Option<String>,
+    /// The text to insert, with `${n:default}` placeholders.
+    pub snippet: String,
+    /// The text to insert, with every placeholder replaced by its default.
+    pub text: String,
+    /// Higher scores are better candidates.
+    pub score: i32,
+    /// `text` as the body of a `main` declaration, which is the smallest module it parses in.
+    declaration: String,
+}
+
+/// What precedes a candidate's text in the declaration its fragment is parsed from.
+const FRAGMENT_PREFIX: &str = "main ";
+
+impl HoleCompletion {
+    fn new(
+        label: impl Into<String>,
+        kind: CompletionKind,
+        detail: Option<String>,
+        parts: Vec<Part>,
+        score: i32,
+    ) -> Self {
+        let (snippet, text) = snippet(parts);
+
+        HoleCompletion {
+            label: label.into(),
+            kind,
+            detail,
+            declaration: format!("{FRAGMENT_PREFIX}{text};"),
+            snippet,
+            text,
+            score,
+        }
+    }
+
+    /// Parses the candidate's text into the expression that replaces the hole. Ranges in the
+    /// fragment count from the start of `text`.
+    pub fn fragment(&self) -> Option<ParseNode<Expression<'_>>> {
+        let parsed = parse_module(&self.declaration);
+        let module = parsed.module.filter(|m| !m.has_error)?;
+
+        match module.value.declarations.into_iter().next()?.value {
+            Declaration::Main { body, .. } => {
+                relocate_expression(*body, &self.declaration, &self.text, FRAGMENT_PREFIX.len())
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A hole and what its context expects of it.
+#[derive(Debug, Clone)]
+pub struct Hole {
+    pub path: NodePath,
+    pub range: seglisp::Range,
+    /// The type the hole's context expects, or [`Ty::Unknown`] if it accepts anything.
+    pub expected: Ty,
+}
+
+/// Finds the hole at byte `offset`, or immediately before it, so that a cursor placed just after an
+/// `@` still finds it.
+pub fn find_hole(module: &ParseNode<Module>, offset: usize) -> Option<Hole> {
+    locate(module, offset).map(|(hole, _)| hole)
+}
+
+/// Finds the hole at or immediately before byte `offset`, with the names in scope there.
+fn locate(module: &ParseNode<Module>, offset: usize) -> Option<(Hole, TypeEnv)> {
+    [Some(offset), offset.checked_sub(1)]
+        .into_iter()
+        .flatten()
+        .find_map(|offset| {
+            let chain = nodes_at_offset(module, offset);
+
+            match chain.last() {
+                Some((path, NodeRef::Expression(expr)))
+                    if matches!(expr.value, Expression::Hole) =>
+                {
+                    let (env, expected) = context(module, &chain);
+
+                    Some((
+                        Hole {
+                            path: path.clone(),
+                            range: expr.range,
+                            expected,
+                        },
+                        env,
+                    ))
+                }
+                _ => None,
+            }
+        })
+}
+
+/// Walks the chain of nodes from the module to a hole, returning the types of the names in scope
+/// at the hole and the type expected of it.
+fn context(module: &ParseNode<Module>, chain: &[(NodePath, NodeRef)]) -> (TypeEnv, Ty) {
+    let mut env = TypeEnv::for_declarations(module.value.declarations.iter().map(|d| &d.value));
+    let mut expected = Ty::Unknown;
+
+    for window in chain.windows(2) {
+        let (_, parent) = &window[0];
+        let (path, _) = &window[1];
+        let segment = path.segments().last().expect("child has no path segment");
+
+        match (parent, segment.field) {
+            (NodeRef::Declaration(decl), field) => match &decl.value {
+                Declaration::Function {
+                    identifier,
+                    parameters,
+                    constraint,
+                    ..
+                } if field == "body" => {
+                    let types = match env.lookup(identifier.value) {
+                        Some(Ty::Function { parameters, .. }) => parameters.clone(),
+                        _ => vec![],
+                    };
+
+                    env.push_scope();
+                    for (idx, p) in parameters.value.iter().enumerate() {
+                        env.bind(
+                            p.value.name.value,
+                            types.get(idx).cloned().unwrap_or(Ty::Unknown),
+                        );
+                    }
+
+                    expected = constraint
+                        .as_ref()
+                        .map_or(Ty::Unknown, |c| Ty::from_type(&c.value.type_.value));
+                }
+                Declaration::Const {
+                    type_: Some(type_), ..
+                } if field == "value" => {
+                    expected = Ty::from_type(&type_.value.type_.value);
+                }
+                _ => expected = Ty::Unknown,
+            },
+            (NodeRef::Expression(expr), field) => {
+                expected = match &expr.value {
+                    Expression::Function {
+                        name,
+                        parameters,
+                        constraint,
+                        ..
+                    } if field == "body" => {
+                        let function = env.infer(&expr.value);
+
+                        env.push_scope();
+                        if let Some(name) = name {
+                            env.bind(name.value, function.clone());
+                        }
+                        let types = match function {
+                            Ty::Function { parameters, .. } => parameters,
+                            _ => vec![],
+                        };
+                        for (idx, p) in parameters.value.iter().enumerate() {
+                            env.bind(
+                                p.value.name.value,
+                                types.get(idx).cloned().unwrap_or(Ty::Unknown),
+                            );
+                        }
+
+                        constraint
+                            .as_ref()
+                            .map_or(Ty::Unknown, |c| Ty::from_type(&c.value.type_.value))
+                    }
+                    Expression::With { bindings, .. } => {
+                        env.push_scope();
+                        for b in &bindings.value {
+                            env.bind(b.value.symbol.value, Ty::Unknown);
+                        }
+                        for b in &bindings.value {
+                            let ty = env.infer(&b.value.value.value);
+                            env.bind(b.value.symbol.value, ty);
+                        }
+                        Ty::Unknown
+                    }
+                    Expression::Procedure { body } => {
+                        env.push_scope();
+                        let before = segment.index.unwrap_or(0);
+                        for stmt in &body.value[..before] {
+                            env.infer_statement(&stmt.value);
+                        }
+                        Ty::Unknown
+                    }
+                    Expression::Call { callee, .. } if field == "parameters" => {
+                        match env.infer(&callee.value) {
+                            Ty::Function { parameters, .. } => segment
+                                .index
+                                .and_then(|idx| parameters.get(idx).cloned())
+                                .unwrap_or(Ty::Unknown),
+                            _ => Ty::Unknown,
+                        }
+                    }
+                    Expression::Arithmetic {
+                        operator,
+                        left,
+                        right,
+                    } => {
+                        let other = if field == "left" { right } else { left };
+                        match (&operator.value, env.infer(&other.value)) {
+                            (ArithmeticOp::Add, Ty::String) => Ty::String,
+                            _ => Ty::Number,
+                        }
+                    }
+                    Expression::Compare { left, right, .. } => {
+                        let other = if field == "left" { right } else { left };
+                        env.infer(&other.value)
+                    }
+                    Expression::Unary { operator, .. } => match &operator.value {
+                        UnaryOp::Minus => Ty::Number,
+                        UnaryOp::Negate => Ty::Unknown,
+                    },
+                    Expression::Accessor { .. } if field == "index" => Ty::Number,
+                    Expression::If { .. } if field == "condition" => Ty::Boolean,
+                    Expression::If { then, _else, .. } => {
+                        // A branch should have the type of the other branch, or failing that,
+                        // whatever is expected of the `if` itself.
+                        let other = if field == "then" { _else } else { then };
+                        match env.infer(&other.value) {
+                            Ty::Unknown => expected,
+                            ty => ty,
+                        }
+                    }
+                    Expression::As { type_, .. } => Ty::from_type(&type_.value),
+                    _ => Ty::Unknown,
+                };
+            }
+            (NodeRef::Statement(stmt), field) => {
+                expected = match &stmt.value {
+                    Statement::If { .. } if field == "condition" => Ty::Boolean,
+                    Statement::Set(assignment) => env
+                        .lookup(assignment.value.symbol.value)
+                        .cloned()
+                        .unwrap_or(Ty::Unknown),
+                    Statement::ForIn {
+                        binding, iterator, ..
+                    } if field == "body" => {
+                        let element = match env.infer(&iterator.value) {
+                            Ty::List(element) => *element,
+                            _ => Ty::Unknown,
+                        };
+                        env.push_scope();
+                        env.bind(binding.value, element);
+                        Ty::Unknown
+                    }
+                    Statement::Do(_) => Ty::Procedure,
+                    _ => Ty::Unknown,
+                };
+            }
+            // The expected type of an assignment's value was set by its statement.
+            (NodeRef::Assignment(_), _) => {}
+            _ => expected = Ty::Unknown,
+        }
+    }
+
+    (env, expected)
+}
+
+/// Returns the hole at byte `offset` and the candidates to fill it, best first, or `None` if
+/// there is no hole there.
+pub fn complete_hole(
+    module: &ParseNode<Module>,
+    offset: usize,
+) -> Option<(Hole, Vec<HoleCompletion>)> {
+    let (hole, env) = locate(module, offset)?;
+
+    let mut completions = Vec::new();
+    bindings(&env, &hole.expected, &mut completions);
+    literals(&env, &hole.expected, &mut completions);
+    constructors(&env, &hole.expected, &mut completions);
+    templates(&env, &hole.expected, &mut completions);
+
+    // Stable, so that equally good candidates keep the order they were suggested in.
+    completions.sort_by(|a, b| b.score.cmp(&a.score));
+
+    Some((hole, completions))
+}
+
+/// A piece of a candidate's text: either literal text or a placeholder with its default.
+enum Part {
+    Text(String),
+    Placeholder(String),
+}
+
+fn text(s: impl Into<String>) -> Part {
+    Part::Text(s.into())
+}
+
+fn placeholder(default: impl Into<String>) -> Part {
+    Part::Placeholder(default.into())
+}
+
+/// `open`, then a placeholder for each of `count` elements separated by commas, then `close`.
+fn delimited(open: &str, count: usize, close: &str) -> Vec<Part> {
+    let mut parts = vec![text(open)];
+    for idx in 0..count {
+        if idx > 0 {
+            parts.push(text(", "));
+        }
+        parts.push(placeholder("@"));
+    }
+    parts.push(text(close));
+    parts
+}
+
+/// Builds a snippet and its plain text from parts.
+fn snippet(parts: Vec<Part>) -> (String, String) {
+    let mut snippet = String::new();
+    let mut plain = String::new();
+    let mut placeholders = 0;
+
+    for part in parts {
+        match part {
+            Part::Text(literal) => {
+                snippet.push_str(&escape_snippet(&literal));
+                plain.push_str(&literal);
+            }
+            Part::Placeholder(default) => {
+                placeholders += 1;
+                snippet.push_str(&format!("${{{placeholders}:{}}}", escape_snippet(&default)));
+                plain.push_str(&default);
+            }
+        }
+    }
+
+    (snippet, plain)
+}
+
+fn escape_snippet(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('$', "\\$")
+        .replace('}', "\\}")
+}
+
+/// How well a candidate of type `ty` fits `expected`: 2 for an exact match, 1 if it fits, 0 if too
+/// little is known to tell, and `None` if it is known not to fit.
+fn fitness(env: &TypeEnv, ty: &Ty, expected: &Ty) -> Option<i32> {
+    if !env.fits(ty, expected) {
+        None
+    } else if ty.is_unknown() || expected.is_unknown() {
+        Some(0)
+    } else if env.expand(ty.clone()) == env.expand(expected.clone()) {
+        Some(2)
+    } else {
+        Some(1)
+    }
+}
+
+fn bindings(env: &TypeEnv, expected: &Ty, out: &mut Vec<HoleCompletion>) {
+    // Bindings are listed innermost first, and nearer bindings are slightly preferred.
+    for (distance, (name, ty)) in env.bindings().into_iter().enumerate() {
+        let nearness = -(distance.min(50) as i32);
+        let detail = (!ty.is_unknown()).then(|| ty.to_string());
+
+        if let Some(fit) = fitness(env, ty, expected) {
+            out.push(HoleCompletion::new(
+                name,
+                CompletionKind::Binding,
+                detail.clone(),
+                vec![text(name)],
+                300 + fit * 100 + nearness,
+            ));
+        }
+
+        // Calls are only suggested when their result is known to fit, since otherwise every
+        // function would be suggested twice.
+        if let Ty::Function { parameters, result } = ty {
+            if let (false, Some(fit @ 1..)) =
+                (parameters.is_empty(), fitness(env, result, expected))
+            {
+                let mut parts = vec![text(name)];
+                parts.extend(delimited("(", parameters.len(), ")"));
+
+                out.push(HoleCompletion::new(
+                    format!("{name}(…)"),
+                    CompletionKind::Call,
+                    detail,
+                    parts,
+                    200 + fit * 100 + nearness,
+                ));
+            }
+        }
+    }
+}
+
+fn literals(env: &TypeEnv, expected: &Ty, out: &mut Vec<HoleCompletion>) {
+    let candidates = [
+        ("0", Ty::Number, vec![placeholder("0")]),
+        (
+            "\"\"",
+            Ty::String,
+            vec![text("\""), placeholder(""), text("\"")],
+        ),
+        ("true", Ty::Boolean, vec![text("true")]),
+        ("false", Ty::Boolean, vec![text("false")]),
+        ("none", Ty::None, vec![text("none")]),
+    ];
+
+    for (label, ty, parts) in candidates {
+        // Literals are only worth suggesting when the context asks for their type.
+        if let Some(fit @ 1..) = fitness(env, &ty, expected) {
+            out.push(HoleCompletion::new(
+                label,
+                CompletionKind::Literal,
+                Some(ty.to_string()),
+                parts,
+                250 + fit * 100,
+            ));
+        }
+    }
+}
+
+fn procedure() -> Vec<Part> {
+    vec![text("#[\n  "), placeholder("pass"), text(";\n]")]
+}
+
+fn constructors(env: &TypeEnv, expected: &Ty, out: &mut Vec<HoleCompletion>) {
+    let mut push = |label: &str, parts: Vec<Part>, score: i32| {
+        out.push(HoleCompletion::new(
+            label,
+            CompletionKind::Constructor,
+            (!expected.is_unknown()).then(|| expected.to_string()),
+            parts,
+            score,
+        ));
+    };
+
+    // A constructor shaped like the expected type is the best candidate there is. Generic shapes
+    // are only offered when nothing is known.
+    match env.expand(expected.clone()) {
+        Ty::Tuple(members) if !members.is_empty() => {
+            push("(…)", delimited("(", members.len(), ")"), 450)
+        }
+        Ty::Record(fields) if fields.is_empty() => push("{}", vec![text("{}")], 450),
+        Ty::Record(fields) => {
+            let mut parts = vec![text("{ ")];
+            for (idx, (name, _)) in fields.iter().enumerate() {
+                if idx > 0 {
+                    parts.push(text(", "));
+                }
+                parts.push(text(format!("{name}: ")));
+                parts.push(placeholder("@"));
+            }
+            parts.push(text(" }"));
+
+            push("{ … }", parts, 450);
+        }
+        Ty::List(_) => push("[…]", delimited("[", 1, "]"), 450),
+        Ty::Procedure => push("#[ … ]", procedure(), 450),
+        Ty::Unknown => {
+            push("(…)", delimited("(", 2, ")"), 150);
+            push("[…]", delimited("[", 1, "]"), 150);
+            push(
+                "{ … }",
+                vec![
+                    text("{ "),
+                    placeholder("key"),
+                    text(": "),
+                    placeholder("@"),
+                    text(" }"),
+                ],
+                150,
+            );
+            push("#[ … ]", procedure(), 150);
+        }
+        _ => {}
+    }
+}
+
+fn templates(env: &TypeEnv, expected: &Ty, out: &mut Vec<HoleCompletion>) {
+    let mut push = |label: &str, parts: Vec<Part>, score: i32| {
+        out.push(HoleCompletion::new(
+            label,
+            CompletionKind::Template,
+            None,
+            parts,
+            score,
+        ));
+    };
+
+    // `if` and `with` can produce a value of any type.
+    push(
+        "if … then … else …",
+        vec![
+            text("if "),
+            placeholder("@"),
+            text(" then "),
+            placeholder("@"),
+            text(" else "),
+            placeholder("@"),
+        ],
+        100,
+    );
+    push(
+        "with (…) …",
+        vec![
+            text("with ("),
+            placeholder("name"),
+            text(" = "),
+            placeholder("@"),
+            text(") "),
+            placeholder("@"),
+        ],
+        90,
+    );
+
+    let parameters = match env.expand(expected.clone()) {
+        Ty::Function { parameters, .. } => parameters.len(),
+        Ty::Unknown => 1,
+        _ => return,
+    };
+
+    let mut parts = vec![text("fn (")];
+    for idx in 0..parameters {
+        if idx > 0 {
+            parts.push(text(", "));
+        }
+        parts.push(placeholder(parameter_name(idx, parameters)));
+    }
+    parts.push(text(") -> "));
+    parts.push(placeholder("@"));
+
+    let score = if expected.is_unknown() { 80 } else { 450 };
+    push("fn (…) -> …", parts, score);
+}
+
+/// A name for the `idx`th of `count` parameters of a function template.
+fn parameter_name(idx: usize, count: usize) -> String {
+    if count == 1 {
+        "x".into()
+    } else {
+        ((b'a' + (idx % 26) as u8) as char).to_string()
+    }
+}
+
+/// A completion candidate as exposed to JavaScript.
+#[derive(Debug, Clone, JsInterop)]
+pub struct HoleCandidate<'a> {
+    pub label: &'a str,
+    pub kind: &'static str,
+    pub detail: Option<&'a str>,
+    pub snippet: &'a str,
+    pub text: &'a str,
+    pub score: usize,
+    /// The expression that replaces the hole. Its ranges count from the start of `text`.
+    pub fragment: Option<ParseNode<Expression<'a>>>,
+}
+
+/// The completions for a hole, as exposed to JavaScript.
+#[derive(Debug, Clone, JsInterop)]
+pub struct HoleCompletions<'a> {
+    pub path: String,
+    pub range: seglisp::Range,
+    pub expected: String,
+    pub candidates: Vec<HoleCandidate<'a>>,
+}
+
+/// Returns the `HoleCompletions` for the hole at byte `offset`, or `null` if there is no hole
+/// there, the document could not be parsed, or it is not valid UTF-8.
+#[wasm_bindgen]
+pub fn hole_completions(data: &[u8], offset: usize) -> JsValue {
+    if core::str::from_utf8(data).is_err() {
+        return JsValue::NULL;
+    }
+
+    with_parsed_module(data, |doc| {
+        let Some((hole, completions)) = doc
+            .result
+            .as_ref()
+            .ok()
+            .and_then(|module| complete_hole(module, offset))
+        else {
+            return JsValue::NULL;
+        };
+
+        HoleCompletions {
+            path: hole.path.to_string(),
+            range: hole.range,
+            expected: hole.expected.to_string(),
+            candidates: completions
+                .iter()
+                .map(|c| HoleCandidate {
+                    label: &c.label,
+                    kind: c.kind.as_str(),
+                    detail: c.detail.as_deref(),
+                    snippet: &c.snippet,
+                    text: &c.text,
+                    score: c.score.max(0) as usize,
+                    fragment: c.fragment(),
+                })
+                .collect(),
+        }
+        .to_js_value()
+    })
+}
+
+/// Like `hole_completions`, but for a zero-based line and UTF-16 column.
+#[wasm_bindgen]
+pub fn hole_completions_at_position(data: &[u8], line: usize, column: usize) -> JsValue {
+    let Ok(source) = core::str::from_utf8(data) else {
+        return JsValue::NULL;
+    };
+
+    match offset_of_position(source, line, column) {
+        Some(offset) => hole_completions(data, offset),
+        None => JsValue::NULL,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snippets_escape_their_text_but_not_their_placeholders() {
+        assert_eq!(escape_snippet("a\\b$c}d{"), "a\\\\b\\$c\\}d{");
+
+        let (snippet, plain) = snippet(vec![text("${ "), placeholder("}"), text(" }")]);
+        assert_eq!(snippet, "\\${ ${1:\\}} \\}");
+        assert_eq!(plain, "${ } }");
+    }
+}
@@ -0,0 +1,376 @@
+//! Stable node identifiers that survive reparsing.
+//!
+//! [`ParseNode`] belongs to `seglisp` and carries no identity, so every reparse produces a tree whose
+//! nodes can only be told apart by position. A [`NodeIdTree`] assigns an identifier to every node of
+//! a module and keeps a lightweight snapshot of the tree's shape. When the module is reparsed, the
+//! new tree is matched against the snapshot by structure (category, kind, and names) and position,
+//! and matched nodes keep their identifiers. Edits in one declaration therefore leave the
+//! identifiers of every other declaration untouched.
+//!
+//! The identifiers are kept beside the tree rather than on its nodes. Storing them on the nodes
+//! would mean replacing `ParseNode` with a type of our own throughout the AST, and every consumer of
+//! the tree would pay for identity it does not use. Instead each identifier is looked up by the
+//! [`NodePath`] of its node in the current parse, and is handed to JavaScript with the node it
+//! belongs to: every [`NodeIdEntry`] describes its node, and [`NodeIdTracker::nodes_at`] answers
+//! position queries with a [`NodeInfo`] whose `id` is set.
+
+use wasm_bindgen::prelude::*;
+
+use seglisp::{
+    js_interop::{JsInterop, JsValue},
+    parse::ParseNode,
+};
+
+use crate::{
+    node::{NodePath, NodeRef, PathSegment},
+    query::{nodes_at_offset, NodeInfo},
+    with_parsed_module, Declaration, Expression, Module, Statement,
+};
+
+/// An identifier for a node, stable across reparses of the same document.
+pub type NodeId = usize;
+
+/// The identifier assigned to the node at `path`, with the node's category, kind, and range in the
+/// parse it was assigned in.
+#[derive(Debug, Clone, JsInterop)]
+pub struct NodeIdEntry {
+    pub path: String,
+    pub id: NodeId,
+    pub category: &'static str,
+    pub kind: &'static str,
+    pub range: seglisp::Range,
+}
+
+#[derive(Debug, Clone)]
+struct IdNode {
+    id: NodeId,
+    category: &'static str,
+    kind: &'static str,
+    range: seglisp::Range,
+    label: Option<String>,
+    children: Vec<(PathSegment, IdNode)>,
+}
+
+impl IdNode {
+    fn matches(&self, node: &NodeRef) -> bool {
+        self.category == node.category() && self.kind == node.kind()
+    }
+
+    fn find(&self, path: &[PathSegment]) -> Option<&IdNode> {
+        match path.split_first() {
+            None => Some(self),
+            Some((segment, rest)) => self
+                .children
+                .iter()
+                .find(|(s, _)| s == segment)
+                .and_then(|(_, child)| child.find(rest)),
+        }
+    }
+}
+
+/// Identifiers for every node of a module, carried over from one parse to the next.
+#[derive(Debug, Clone)]
+pub struct NodeIdTree {
+    root: IdNode,
+    next_id: NodeId,
+}
+
+impl NodeIdTree {
+    /// Assigns fresh identifiers to every node of `module`.
+    pub fn new(module: &ParseNode<Module>) -> Self {
+        let mut next_id = 0;
+        let root = fresh(NodeRef::Module(module), &mut next_id);
+
+        NodeIdTree { root, next_id }
+    }
+
+    /// Replaces the tracked tree with `module`, a reparse of the same document.
+    ///
+    /// Nodes that match a node of the previous tree keep its identifier, and all other nodes receive
+    /// fresh identifiers. Identifiers are never reused.
+    pub fn update(&mut self, module: &ParseNode<Module>) {
+        self.root = rematch(&self.root, NodeRef::Module(module), &mut self.next_id);
+    }
+
+    /// The identifier of the node at `path`.
+    pub fn id_at(&self, path: &NodePath) -> Option<NodeId> {
+        self.root.find(path.segments()).map(|node| node.id)
+    }
+
+    /// The path of the node with identifier `id`.
+    pub fn path_of(&self, id: NodeId) -> Option<NodePath> {
+        fn search(node: &IdNode, id: NodeId, path: &mut Vec<PathSegment>) -> bool {
+            if node.id == id {
+                return true;
+            }
+
+            for (segment, child) in &node.children {
+                path.push(*segment);
+                if search(child, id, path) {
+                    return true;
+                }
+                path.pop();
+            }
+
+            false
+        }
+
+        let mut path = Vec::new();
+        search(&self.root, id, &mut path).then(|| NodePath(path))
+    }
+
+    /// Every node's identifier, in depth-first source order.
+    pub fn entries(&self) -> Vec<NodeIdEntry> {
+        fn collect(node: &IdNode, path: &NodePath, entries: &mut Vec<NodeIdEntry>) {
+            entries.push(NodeIdEntry {
+                path: path.to_string(),
+                id: node.id,
+                category: node.category,
+                kind: node.kind,
+                range: node.range,
+            });
+
+            for (segment, child) in &node.children {
+                collect(child, &path.child(*segment), entries);
+            }
+        }
+
+        let mut entries = Vec::new();
+        collect(&self.root, &NodePath::root(), &mut entries);
+        entries
+    }
+}
+
+/// A short string that distinguishes nodes of the same kind from one another, such as the name a
+/// declaration introduces.
+fn label(node: &NodeRef) -> Option<String> {
+    match node {
+        NodeRef::Declaration(decl) => match &decl.value {
+            Declaration::Const { identifier, .. } | Declaration::Function { identifier, .. } => {
+                Some(identifier.value.to_string())
+            }
+            Declaration::TypeAlias { name, .. } | Declaration::Interface { name, .. } => {
+                Some(name.value.to_string())
+            }
+            Declaration::Import {
+                module_specifier, ..
+            } => Some(module_specifier.value.to_string()),
+            Declaration::Main { .. } | Declaration::Export { .. } => None,
+        },
+        NodeRef::Expression(expr) => match &expr.value {
+            Expression::Name(name) | Expression::Number(name) => Some(name.to_string()),
+            Expression::String(s) => Some(s.clone()),
+            _ => None,
+        },
+        NodeRef::Statement(stmt) => match &stmt.value {
+            Statement::Let { assignment, .. } | Statement::Set(assignment) => {
+                Some(assignment.value.symbol.value.to_string())
+            }
+            _ => None,
+        },
+        NodeRef::Parameter(p) => Some(p.value.name.value.to_string()),
+        NodeRef::Assignment(a) => Some(a.value.symbol.value.to_string()),
+        NodeRef::Identifier(i) => Some(i.value.to_string()),
+        _ => None,
+    }
+}
+
+fn fresh(node: NodeRef, next_id: &mut NodeId) -> IdNode {
+    let id = *next_id;
+    *next_id += 1;
+
+    IdNode {
+        id,
+        category: node.category(),
+        kind: node.kind(),
+        range: node.range(),
+        label: label(&node),
+        children: node
+            .children()
+            .into_iter()
+            .map(|(segment, child)| (segment, fresh(child, next_id)))
+            .collect(),
+    }
+}
+
+fn rematch(old: &IdNode, node: NodeRef, next_id: &mut NodeId) -> IdNode {
+    let new_children = node.children();
+
+    let mut children = Vec::with_capacity(new_children.len());
+    let mut idx = 0;
+
+    // Children are grouped by field. Scalar fields are matched directly, and list fields are
+    // aligned so that insertions and deletions do not shift the identity of later elements.
+    while idx < new_children.len() {
+        let field = new_children[idx].0.field;
+
+        let end = new_children[idx..]
+            .iter()
+            .position(|(s, _)| s.field != field)
+            .map_or(new_children.len(), |n| idx + n);
+
+        let old_group: Vec<&IdNode> = old
+            .children
+            .iter()
+            .filter(|(s, _)| s.field == field)
+            .map(|(_, c)| c)
+            .collect();
+
+        let new_group = &new_children[idx..end];
+
+        if new_group.len() == 1 && new_group[0].0.index.is_none() {
+            let (segment, child) = new_group[0];
+            let matched = old_group.first().filter(|o| o.matches(&child));
+
+            children.push((
+                segment,
+                match matched {
+                    Some(o) => rematch(o, child, next_id),
+                    None => fresh(child, next_id),
+                },
+            ));
+        } else {
+            let pairs = align(&old_group, new_group);
+
+            for ((segment, child), matched) in new_group.iter().zip(pairs) {
+                children.push((
+                    *segment,
+                    match matched {
+                        Some(o) => rematch(old_group[o], *child, next_id),
+                        None => fresh(*child, next_id),
+                    },
+                ));
+            }
+        }
+
+        idx = end;
+    }
+
+    IdNode {
+        id: old.id,
+        category: node.category(),
+        kind: node.kind(),
+        range: node.range(),
+        label: label(&node),
+        children,
+    }
+}
+
+/// Aligns the elements of a list against the elements of its previous version, returning the
+/// index of the matching old element (if any) for each new element.
+///
+/// Elements are first anchored by a longest common subsequence over their kind and label. New
+/// elements that fall between two anchors are then matched, in order, with old elements of the same
+/// kind between the same anchors, which keeps the identity of a node whose name was edited.
+fn align(old: &[&IdNode], new: &[(PathSegment, NodeRef)]) -> Vec<Option<usize>> {
+    let new_labels: Vec<_> = new.iter().map(|(_, n)| label(n)).collect();
+
+    let same = |o: usize, n: usize| old[o].matches(&new[n].1) && old[o].label == new_labels[n];
+
+    // lcs[o][n] is the length of the longest common subsequence of old[o..] and new[n..].
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for o in (0..old.len()).rev() {
+        for n in (0..new.len()).rev() {
+            lcs[o][n] = if same(o, n) {
+                lcs[o + 1][n + 1] + 1
+            } else {
+                lcs[o + 1][n].max(lcs[o][n + 1])
+            };
+        }
+    }
+
+    let mut result = vec![None; new.len()];
+    let mut anchors = Vec::new();
+    let (mut o, mut n) = (0, 0);
+    while o < old.len() && n < new.len() {
+        if same(o, n) {
+            result[n] = Some(o);
+            anchors.push((o, n));
+            o += 1;
+            n += 1;
+        } else if lcs[o + 1][n] >= lcs[o][n + 1] {
+            o += 1;
+        } else {
+            n += 1;
+        }
+    }
+    anchors.push((old.len(), new.len()));
+
+    let (mut old_start, mut new_start) = (0, 0);
+    for (old_end, new_end) in anchors {
+        let mut candidates = old_start..old_end;
+
+        for n in new_start..new_end {
+            if let Some(o) = candidates.clone().find(|o| old[*o].matches(&new[n].1)) {
+                result[n] = Some(o);
+                candidates = o + 1..old_end;
+            }
+        }
+
+        old_start = old_end + 1;
+        new_start = new_end + 1;
+    }
+
+    result
+}
+
+/// Tracks stable node identifiers for a document across reparses.
+#[wasm_bindgen]
+#[derive(Debug, Default)]
+pub struct NodeIdTracker {
+    tree: Option<NodeIdTree>,
+}
+
+#[wasm_bindgen]
+impl NodeIdTracker {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses `data`, matches it against the previously tracked parse, and returns an array of
+    /// `NodeIdEntry` giving the identifier of every node, or `null` if the document could not be
+    /// parsed. The previous identifiers are kept if parsing fails.
+    pub fn update(&mut self, data: &[u8]) -> JsValue {
+        with_parsed_module(data, |doc| match &doc.result {
+            Ok(module) => {
+                let tree = match self.tree.take() {
+                    Some(mut tree) => {
+                        tree.update(module);
+                        tree
+                    }
+                    None => NodeIdTree::new(module),
+                };
+
+                let entries = tree.entries();
+                self.tree = Some(tree);
+                entries.to_js_value()
+            }
+            Err(_) => JsValue::NULL,
+        })
+    }
+
+    /// Like `nodes_at`, but gives each node in the chain its identifier. `data` must be the text
+    /// last passed to `update`. Returns `null` if the document could not be parsed.
+    pub fn nodes_at(&self, data: &[u8], offset: usize) -> JsValue {
+        with_parsed_module(data, |doc| match &doc.result {
+            Ok(module) => nodes_at_offset(module, offset)
+                .into_iter()
+                .map(|(path, node)| {
+                    let id = self.tree.as_ref().and_then(|tree| tree.id_at(&path));
+                    NodeInfo::new(&path, node, id)
+                })
+                .collect::<Vec<_>>()
+                .to_js_value(),
+            Err(_) => JsValue::NULL,
+        })
+    }
+
+    /// The path of the node with identifier `id` in the most recent parse, if it still exists.
+    pub fn path_of(&self, id: NodeId) -> Option<String> {
+        self.tree
+            .as_ref()
+            .and_then(|tree| tree.path_of(id))
+            .map(|path| path.to_string())
+    }
+}
@@ -0,0 +1,1087 @@
+//! Incremental reparsing of a module after text edits.
+//!
+//! A module is split into top-level declarations, and each declaration owns the text from its first
+//! token up to the first token of the next declaration (its _extent_). When a document is edited,
+//! only the declarations whose extents touch an edit are read and parsed again. Every other
+//! declaration is carried over from the previous parse: its spans are shifted to account for the
+//! edits before it, and its borrowed text is moved over to the identical text in the new source.
+//! Diagnostics are reused in the same way.
+//!
+//! An edit can change how the text after it is read: opening a bracket, string or comment, or
+//! removing the `;` after a declaration, runs the edited declaration on into the next. When the
+//! reparsed region does not end cleanly after a `;`, or reports an error, the region is extended to
+//! the end of the source. Since the region always starts where a declaration does, this reads the
+//! same declarations that parsing the whole source would. Only whole top-level declarations are
+//! reparsed: a bracketed list inside one is read in the context of its parent, so it is not
+//! reparsed on its own.
+//!
+//! The AST borrows from its source text, so [`reparse`] takes the previous source and the new
+//! source side by side and returns a tree that borrows from the new one. [`OwnedModule`] keeps the
+//! two together for callers that hold on to a document as it changes.
+
+use std::{fmt, ops::Range};
+
+use wasm_bindgen::prelude::*;
+
+use seglisp::{
+    js_interop::{JsInterop, JsValue},
+    parse::ParseNode,
+    Diagnostic, DiagnosticLocation, DiagnosticPhase, DiagnosticSeverity, Location,
+};
+
+use crate::{
+    check_exports,
+    owned::OwnedModule,
+    scan::{scan, Kind, Lexeme},
+    Assignment, BindingPattern, Declaration, Expression, GenericParameter, InterfaceField, Module,
+    ParameterDeclaration, ParsedVec, RecordBindingElement, RecordElement, Statement, Type,
+    TypeConstraint, Verbatim,
+};
+
+/// A replacement of the bytes `start..end` of a document with `text`.
+#[derive(Debug, Clone, PartialEq, Eq, JsInterop)]
+pub struct TextEdit {
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+}
+
+/// Edits that cannot be applied to a source text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEditError(pub String);
+
+impl fmt::Display for TextEditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for TextEditError {}
+
+/// Checks that `edits` can be applied to `source`: each one lies within it, starts and ends on a
+/// character boundary, and does not overlap any other.
+pub fn check_edits(source: &str, edits: &[TextEdit]) -> Result<(), TextEditError> {
+    let mut edits = edits.iter().collect::<Vec<_>>();
+    edits.sort_by_key(|e| e.start);
+
+    let mut cursor = 0;
+
+    for edit in edits {
+        let TextEdit { start, end, .. } = *edit;
+
+        if start > end || end > source.len() {
+            return Err(TextEditError(format!(
+                "the edit {start}..{end} is out of bounds for a source of {} bytes",
+                source.len()
+            )));
+        }
+        if !source.is_char_boundary(start) || !source.is_char_boundary(end) {
+            return Err(TextEditError(format!(
+                "the edit {start}..{end} does not start and end on character boundaries"
+            )));
+        }
+        if start < cursor {
+            return Err(TextEditError(format!(
+                "the edit {start}..{end} overlaps another edit"
+            )));
+        }
+
+        cursor = end;
+    }
+
+    Ok(())
+}
+
+/// Applies `edits` to `source`. The edits are given in terms of the original source and must not
+/// overlap.
+pub fn apply_edits(source: &str, edits: &[TextEdit]) -> Result<String, TextEditError> {
+    check_edits(source, edits)?;
+
+    let mut edits = edits.iter().collect::<Vec<_>>();
+    edits.sort_by_key(|e| e.start);
+
+    let mut result = String::with_capacity(source.len());
+    let mut cursor = 0;
+
+    for edit in edits {
+        result.push_str(&source[cursor..edit.start]);
+        result.push_str(&edit.text);
+        cursor = edit.end;
+    }
+
+    result.push_str(&source[cursor..]);
+    Ok(result)
+}
+
+/// The result of parsing a module, with the diagnostics reported while reading and parsing it.
+#[derive(Debug, Clone, JsInterop)]
+pub struct ParsedModule<'ast> {
+    pub module: Option<ParseNode<Module<'ast>>>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// How deeply brackets may nest. The reader recurses once per level of `(`, `[` or `{`, and so
+/// does the parser, so a source nested deeper than this is refused before it is read, rather than
+/// overflowing the 1 MiB stack of a WASM build. `tests/stack.rs` checks that a source nested twice
+/// this deep still fits on such a stack, and measures how deep one can go.
+pub const MAX_NESTING: usize = 256;
+
+/// Reports the first bracket of `source` that opens more than [`MAX_NESTING`] levels deep.
+pub(crate) fn check_nesting(source: &str) -> Option<Diagnostic> {
+    let mut depth = 0usize;
+
+    for Lexeme { kind, range } in scan(source) {
+        match kind {
+            Kind::Open => depth += 1,
+            Kind::Close => depth = depth.saturating_sub(1),
+            _ => continue,
+        }
+
+        if depth > MAX_NESTING {
+            let location = |absolute| {
+                let (line, column) = position_of(source, absolute);
+                Location {
+                    absolute,
+                    line,
+                    column,
+                }
+            };
+
+            return Some(Diagnostic {
+                abridged: false,
+                inner_diagnostics: None,
+                location: DiagnosticLocation::Range((location(range.start), location(range.end))),
+                message: format!("brackets are nested more than {MAX_NESTING} levels deep"),
+                note: None,
+                phase: DiagnosticPhase::Parse,
+                severity: DiagnosticSeverity::Error,
+                subject: None,
+            });
+        }
+    }
+
+    None
+}
+
+/// Reads and parses all of `source` as a module. A source nested deeper than [`MAX_NESTING`] is
+/// not read, and has no module.
+pub fn parse_module(source: &str) -> ParsedModule<'_> {
+    if let Some(diagnostic) = check_nesting(source) {
+        return ParsedModule {
+            module: None,
+            diagnostics: vec![diagnostic],
+        };
+    }
+
+    let result = seglisp::read_str(&Default::default(), source);
+
+    let host = seglisp::parse::ParseHost::default();
+
+    let result = result.parse::<Module>(&host);
+
+    ParsedModule {
+        module: result.result.ok(),
+        diagnostics: result.diagnostics,
+    }
+}
+
+/// Reparses `new_source`, which is `old_source` with `edits` applied, reusing every declaration of
+/// `previous` that the edits did not touch.
+///
+/// Falls back to parsing the whole of `new_source` if the previous parse failed, if `edits` do not
+/// fit `old_source` or do not produce `new_source`'s length, if a diagnostic has no range, or if the
+/// edited region cannot be parsed on its own.
+pub fn reparse<'old, 'new>(
+    previous: ParsedModule<'old>,
+    old_source: &'old str,
+    new_source: &'new str,
+    edits: &[TextEdit],
+) -> ParsedModule<'new> {
+    try_reparse(previous, old_source, new_source, edits).unwrap_or_else(|| parse_module(new_source))
+}
+
+fn try_reparse<'old, 'new>(
+    previous: ParsedModule<'old>,
+    old_source: &'old str,
+    new_source: &'new str,
+    edits: &[TextEdit],
+) -> Option<ParsedModule<'new>> {
+    let module = previous.module?;
+
+    if edits.is_empty() || module.value.declarations.is_empty() {
+        return None;
+    }
+
+    check_edits(old_source, edits).ok()?;
+
+    let lo = edits.iter().map(|e| e.start).min()?;
+    let hi = edits.iter().map(|e| e.end).max()?;
+    let delta = new_source.len() as isize - old_source.len() as isize;
+
+    let expected_delta: isize = edits
+        .iter()
+        .map(|e| e.text.len() as isize - (e.end - e.start) as isize)
+        .sum();
+    if delta != expected_delta {
+        return None;
+    }
+
+    // The extent of each declaration runs from its start to the start of the next declaration. The
+    // first extent also covers any leading text, and the last extent runs to the end of the source.
+    let starts = module
+        .value
+        .declarations
+        .iter()
+        .enumerate()
+        .map(|(idx, decl)| if idx == 0 { 0 } else { decl.range.0.absolute })
+        .collect::<Vec<_>>();
+    let extent_end = |idx: usize| starts.get(idx + 1).copied().unwrap_or(old_source.len());
+
+    let first_dirty = (0..starts.len()).find(|idx| extent_end(*idx) >= lo)?;
+    let mut last_dirty = (first_dirty..starts.len())
+        .take_while(|idx| starts[*idx] <= hi)
+        .last()
+        .unwrap_or(first_dirty);
+
+    let regions = |last_dirty: usize| -> Option<(Range<usize>, Range<usize>)> {
+        let old_region = starts[first_dirty]..extent_end(last_dirty);
+        let new_end: usize = (old_region.end as isize + delta).try_into().ok()?;
+        let new_region = old_region.start..new_end;
+
+        Some((old_region, new_region))
+    };
+
+    let (mut old_region, mut new_region) = regions(last_dirty)?;
+    let mut reparsed = parse_module(new_source.get(new_region.clone())?);
+
+    // An edit that opens a bracket, string or comment, or removes a `;`, makes the region run on
+    // into the declarations after it. They are then read again as well, up to the end of the
+    // source, which is what parsing the whole source would do from the start of the region.
+    if new_region.end < new_source.len()
+        && !ends_cleanly(&new_source[new_region.clone()], &reparsed)
+    {
+        last_dirty = starts.len() - 1;
+        (old_region, new_region) = regions(last_dirty)?;
+        reparsed = parse_module(new_source.get(new_region.clone())?);
+    }
+
+    let region_start = position_of(new_source, new_region.start);
+    let region_module = reparsed.module?;
+
+    let into_region = Shift {
+        absolute: new_region.start as isize,
+        line: region_start.0 as isize,
+        boundary_line: 0,
+        column: region_start.1 as isize,
+    };
+
+    let (old_end_line, old_end_column) = position_of(old_source, old_region.end);
+    let (new_end_line, new_end_column) = position_of(new_source, new_region.end);
+    let after_region = Shift {
+        absolute: delta,
+        line: new_end_line as isize - old_end_line as isize,
+        boundary_line: old_end_line,
+        column: new_end_column as isize - old_end_column as isize,
+    };
+
+    // Declarations in the reparsed region already borrow from the new source, so they are only
+    // moved from the region's coordinates into the document's.
+    let region = Relocator {
+        old_source: new_source,
+        new_source,
+        offset: 0,
+        shift: into_region,
+    };
+    let before = Relocator {
+        old_source,
+        new_source,
+        offset: 0,
+        shift: Shift::NONE,
+    };
+    let after = Relocator {
+        old_source,
+        new_source,
+        offset: delta,
+        shift: after_region,
+    };
+
+    let ParseNode {
+        value: Module { declarations },
+        range: module_range,
+        ..
+    } = module;
+
+    let mut new_declarations = Vec::with_capacity(declarations.len());
+    let mut region_range = region_module.range;
+    into_region.apply_range(&mut region_range);
+    let mut region_declarations = Some(region_module.value.declarations);
+
+    for (idx, decl) in declarations.into_iter().enumerate() {
+        if idx < first_dirty {
+            new_declarations.push(before.declaration(decl)?);
+        } else if idx == first_dirty {
+            for decl in region_declarations.take()? {
+                new_declarations.push(region.declaration(decl)?);
+            }
+        } else if idx > last_dirty {
+            new_declarations.push(after.declaration(decl)?);
+        }
+    }
+
+    // A diagnostic at the very end of the source belongs to the last declaration, so it is reported
+    // again if that declaration is reparsed.
+    let reaches_end = old_region.end == old_source.len();
+
+    // Diagnostics without a range cannot be placed before, in, or after the region, so the whole
+    // source is parsed again instead.
+    let mut diagnostics = Vec::new();
+    for mut diagnostic in previous.diagnostics {
+        let start = start_of(&diagnostic)?;
+
+        if start < old_region.start {
+            diagnostics.push(diagnostic);
+        } else if start >= old_region.end && !reaches_end {
+            shift_diagnostic(&mut diagnostic, &after_region);
+            diagnostics.push(diagnostic);
+        }
+    }
+    for mut diagnostic in reparsed.diagnostics {
+        start_of(&diagnostic)?;
+        shift_diagnostic(&mut diagnostic, &into_region);
+        diagnostics.push(diagnostic);
+    }
+    diagnostics.sort_by_key(|d| start_of(d).unwrap_or(0));
+
+    // The module spans the reparsed region's tokens where the region is at either end of it.
+    let mut range = module_range;
+    if first_dirty == 0 {
+        range.0 = region_range.0;
+    }
+    if reaches_end {
+        range.1 = region_range.1;
+    } else {
+        after_region.apply(&mut range.1);
+    }
+
+    let has_error = new_declarations.iter().any(|d| d.has_error);
+
+    Some(ParsedModule {
+        module: Some(ParseNode {
+            value: Module {
+                declarations: new_declarations,
+            },
+            range,
+            has_error,
+        }),
+        diagnostics,
+    })
+}
+
+/// Whether `region`, read and parsed on its own as `parsed`, ends where a declaration can start.
+/// When it does, the text after it reads the same as it did before the edit.
+fn ends_cleanly(region: &str, parsed: &ParsedModule) -> bool {
+    let Some(module) = &parsed.module else {
+        return false;
+    };
+
+    if parsed
+        .diagnostics
+        .iter()
+        .any(|d| matches!(d.severity, DiagnosticSeverity::Error))
+    {
+        return false;
+    }
+
+    // The last declaration must be closed by a `;`, and a line comment after it must end within the
+    // region.
+    let rest = match module.value.declarations.last() {
+        Some(decl) => {
+            let end = decl.range.1.absolute;
+            if region[..end].ends_with(';') {
+                &region[end..]
+            } else {
+                match region[end..].trim_start().strip_prefix(';') {
+                    Some(rest) => rest,
+                    None => return false,
+                }
+            }
+        }
+        None => region,
+    };
+
+    rest.trim().is_empty() || region.ends_with('\n')
+}
+
+/// The byte offset a diagnostic starts at, if it has a range.
+fn start_of(diagnostic: &Diagnostic) -> Option<usize> {
+    match &diagnostic.location {
+        DiagnosticLocation::Range(range) => Some(range.0.absolute),
+        _ => None,
+    }
+}
+
+/// Moves a module parsed from `old_source` over to `new_source`, an identical copy of it. Fails if
+/// any string in the module does not point into `old_source`.
+pub(crate) fn relocate<'old, 'new>(
+    module: ParseNode<Module<'old>>,
+    old_source: &'old str,
+    new_source: &'new str,
+) -> Option<ParseNode<Module<'new>>> {
+    let relocator = Relocator {
+        old_source,
+        new_source,
+        offset: 0,
+        shift: Shift::NONE,
+    };
+
+    relocator.node(module, |Module { declarations }| {
+        Some(Module {
+            declarations: declarations
+                .into_iter()
+                .map(|decl| relocator.declaration(decl))
+                .collect::<Option<_>>()?,
+        })
+    })
+}
+
+/// Moves an expression parsed from `old_source` over to `new_source`, the text of `old_source` from
+/// byte `start` on, so that its spans count from the start of `new_source`. The text before `start`
+/// must not contain a line break. Fails as [`relocate`] does.
+pub(crate) fn relocate_expression<'old, 'new>(
+    expr: ParseNode<Expression<'old>>,
+    old_source: &'old str,
+    new_source: &'new str,
+    start: usize,
+) -> Option<ParseNode<Expression<'new>>> {
+    let prefix = old_source.get(..start)?;
+    if prefix.contains('\n') {
+        return None;
+    }
+
+    let relocator = Relocator {
+        old_source,
+        new_source,
+        offset: -(start as isize),
+        shift: Shift {
+            absolute: -(start as isize),
+            line: 0,
+            boundary_line: 0,
+            column: -(prefix.chars().count() as isize),
+        },
+    };
+
+    relocator.expression(expr)
+}
+
+/// Computes the zero-based line and column of the byte `offset` in `source`.
+fn position_of(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset];
+    let line = before.matches('\n').count();
+    let line_start = before.rfind('\n').map_or(0, |idx| idx + 1);
+
+    (line, before[line_start..].chars().count())
+}
+
+/// A translation of source locations.
+///
+/// Every location is moved by `absolute` bytes and `line` lines. Locations on `boundary_line` (in
+/// the original coordinates) are also moved by `column` columns, since the text before them on the
+/// same line was edited.
+#[derive(Debug, Clone, Copy)]
+struct Shift {
+    absolute: isize,
+    line: isize,
+    boundary_line: usize,
+    column: isize,
+}
+
+impl Shift {
+    const NONE: Shift = Shift {
+        absolute: 0,
+        line: 0,
+        boundary_line: 0,
+        column: 0,
+    };
+
+    fn apply(&self, location: &mut seglisp::Location) {
+        if location.line == self.boundary_line {
+            location.column = (location.column as isize + self.column) as usize;
+        }
+        location.line = (location.line as isize + self.line) as usize;
+        location.absolute = (location.absolute as isize + self.absolute) as usize;
+    }
+
+    fn apply_range(&self, range: &mut seglisp::Range) {
+        self.apply(&mut range.0);
+        self.apply(&mut range.1);
+    }
+}
+
+fn shift_diagnostic(diagnostic: &mut Diagnostic, shift: &Shift) {
+    if let DiagnosticLocation::Range(range) = &mut diagnostic.location {
+        shift.apply_range(range);
+    }
+
+    for inner in diagnostic.inner_diagnostics.iter_mut().flatten() {
+        shift_diagnostic(inner, shift);
+    }
+}
+
+/// Moves a subtree that borrows from the old source over to the new source, shifting every span by
+/// `shift` and every borrowed string by `offset` bytes.
+///
+/// Fails if any string in the subtree does not point into the old source, or if the text at its
+/// new position differs.
+struct Relocator<'old, 'new> {
+    old_source: &'old str,
+    new_source: &'new str,
+    offset: isize,
+    shift: Shift,
+}
+
+impl<'old, 'new> Relocator<'old, 'new> {
+    fn str(&self, s: &'old str) -> Option<&'new str> {
+        let base = self.old_source.as_ptr() as usize;
+        let ptr = s.as_ptr() as usize;
+
+        if ptr < base || ptr + s.len() > base + self.old_source.len() {
+            return None;
+        }
+
+        let start = ((ptr - base) as isize + self.offset) as usize;
+        let moved = self.new_source.get(start..start + s.len())?;
+
+        (moved == s).then_some(moved)
+    }
+
+    fn node<T, U>(
+        &self,
+        mut node: ParseNode<T>,
+        f: impl FnOnce(T) -> Option<U>,
+    ) -> Option<ParseNode<U>> {
+        self.shift.apply_range(&mut node.range);
+
+        let node = node.map(f);
+        node.value.as_ref()?;
+        Some(node.map(|v| v.unwrap()))
+    }
+
+    fn verbatim(&self, node: Verbatim<'old>) -> Option<Verbatim<'new>> {
+        self.node(node, |s| self.str(s))
+    }
+
+    fn opt_verbatim(&self, node: Option<Verbatim<'old>>) -> Option<Option<Verbatim<'new>>> {
+        node.map(|n| self.verbatim(n)).transpose()
+    }
+
+    fn list<T, U>(
+        &self,
+        list: ParsedVec<T>,
+        f: impl Fn(ParseNode<T>) -> Option<ParseNode<U>>,
+    ) -> Option<ParsedVec<U>> {
+        self.node(list, |nodes| nodes.into_iter().map(f).collect())
+    }
+
+    fn boxed<T, U>(
+        &self,
+        node: Box<ParseNode<T>>,
+        f: impl FnOnce(ParseNode<T>) -> Option<ParseNode<U>>,
+    ) -> Option<Box<ParseNode<U>>> {
+        f(*node).map(Box::new)
+    }
+
+    fn generics(
+        &self,
+        generics: Option<ParsedVec<GenericParameter<'old>>>,
+    ) -> Option<Option<ParsedVec<GenericParameter<'new>>>> {
+        generics
+            .map(|g| self.list(g, |p| self.generic_parameter(p)))
+            .transpose()
+    }
+
+    fn constraint(
+        &self,
+        constraint: Option<ParseNode<TypeConstraint<'old>>>,
+    ) -> Option<Option<ParseNode<TypeConstraint<'new>>>> {
+        constraint.map(|c| self.type_constraint(c)).transpose()
+    }
+
+    fn expr(
+        &self,
+        node: Box<ParseNode<Expression<'old>>>,
+    ) -> Option<Box<ParseNode<Expression<'new>>>> {
+        self.boxed(node, |e| self.expression(e))
+    }
+
+    fn stmt(
+        &self,
+        node: Box<ParseNode<Statement<'old>>>,
+    ) -> Option<Box<ParseNode<Statement<'new>>>> {
+        self.boxed(node, |s| self.statement(s))
+    }
+
+    fn ty(&self, node: Box<ParseNode<Type<'old>>>) -> Option<Box<ParseNode<Type<'new>>>> {
+        self.boxed(node, |t| self.type_(t))
+    }
+
+    fn declaration(
+        &self,
+        node: ParseNode<Declaration<'old>>,
+    ) -> Option<ParseNode<Declaration<'new>>> {
+        self.node(node, |decl| {
+            Some(match decl {
+                Declaration::Main { main_keyword, body } => Declaration::Main {
+                    main_keyword: self.verbatim(main_keyword)?,
+                    body: self.expr(body)?,
+                },
+                Declaration::Const {
+                    const_keyword,
+                    identifier,
+                    type_,
+                    equals_token,
+                    value,
+                } => Declaration::Const {
+                    const_keyword: self.verbatim(const_keyword)?,
+                    identifier: self.verbatim(identifier)?,
+                    type_: self.constraint(type_)?,
+                    equals_token: self.verbatim(equals_token)?,
+                    value: self.expr(value)?,
+                },
+                Declaration::Function {
+                    function_keyword,
+                    identifier,
+                    generic_parameters,
+                    parameters,
+                    constraint,
+                    arrow_token,
+                    body,
+                } => Declaration::Function {
+                    function_keyword: self.verbatim(function_keyword)?,
+                    identifier: self.verbatim(identifier)?,
+                    generic_parameters: self.generics(generic_parameters)?,
+                    parameters: self.list(parameters, |p| self.parameter(p))?,
+                    constraint: self.constraint(constraint)?,
+                    arrow_token: self.verbatim(arrow_token)?,
+                    body: self.expr(body)?,
+                },
+                Declaration::Import {
+                    import_keyword,
+                    pattern,
+                    equal_token,
+                    use_keyword,
+                    module_specifier,
+                } => Declaration::Import {
+                    import_keyword: self.verbatim(import_keyword)?,
+                    pattern: self.binding_pattern(pattern)?,
+                    equal_token: self.verbatim(equal_token)?,
+                    use_keyword: self.verbatim(use_keyword)?,
+                    module_specifier: self.verbatim(module_specifier)?,
+                },
+                Declaration::Export {
+                    export_keyword,
+                    elements,
+                } => Declaration::Export {
+                    export_keyword: self.verbatim(export_keyword)?,
+                    elements: self.list(elements, |e| self.record_element(e))?,
+                },
+                Declaration::TypeAlias {
+                    type_keyword,
+                    name,
+                    generic_parameters,
+                    equals_token,
+                    value,
+                } => Declaration::TypeAlias {
+                    type_keyword: self.verbatim(type_keyword)?,
+                    name: self.verbatim(name)?,
+                    generic_parameters: self.generics(generic_parameters)?,
+                    equals_token: self.verbatim(equals_token)?,
+                    value: self.type_(value)?,
+                },
+                Declaration::Interface {
+                    interface_keyword,
+                    name,
+                    generic_parameters,
+                    constraint,
+                    body,
+                } => Declaration::Interface {
+                    interface_keyword: self.verbatim(interface_keyword)?,
+                    name: self.verbatim(name)?,
+                    generic_parameters: self.generics(generic_parameters)?,
+                    constraint: self.constraint(constraint)?,
+                    body: self.list(body, |f| self.interface_field(f))?,
+                },
+            })
+        })
+    }
+
+    fn generic_parameter(
+        &self,
+        node: ParseNode<GenericParameter<'old>>,
+    ) -> Option<ParseNode<GenericParameter<'new>>> {
+        self.node(node, |p| {
+            Some(GenericParameter {
+                name: self.verbatim(p.name)?,
+                constraint: self.constraint(p.constraint)?,
+            })
+        })
+    }
+
+    fn interface_field(
+        &self,
+        node: ParseNode<InterfaceField<'old>>,
+    ) -> Option<ParseNode<InterfaceField<'new>>> {
+        self.node(node, |f| {
+            Some(InterfaceField {
+                name: self.verbatim(f.name)?,
+                constraint: self.type_constraint(f.constraint)?,
+            })
+        })
+    }
+
+    fn type_constraint(
+        &self,
+        node: ParseNode<TypeConstraint<'old>>,
+    ) -> Option<ParseNode<TypeConstraint<'new>>> {
+        self.node(node, |c| {
+            Some(TypeConstraint {
+                colon_token: self.verbatim(c.colon_token)?,
+                type_: self.ty(c.type_)?,
+            })
+        })
+    }
+
+    fn parameter(
+        &self,
+        node: ParseNode<ParameterDeclaration<'old>>,
+    ) -> Option<ParseNode<ParameterDeclaration<'new>>> {
+        self.node(node, |p| {
+            Some(ParameterDeclaration {
+                name: self.verbatim(p.name)?,
+                type_: self.constraint(p.type_)?,
+            })
+        })
+    }
+
+    fn assignment(&self, node: ParseNode<Assignment<'old>>) -> Option<ParseNode<Assignment<'new>>> {
+        self.node(node, |a| {
+            Some(Assignment {
+                symbol: self.verbatim(a.symbol)?,
+                equal_token: self.verbatim(a.equal_token)?,
+                value: self.expression(a.value)?,
+            })
+        })
+    }
+
+    fn expression(&self, node: ParseNode<Expression<'old>>) -> Option<ParseNode<Expression<'new>>> {
+        self.node(node, |expr| {
+            Some(match expr {
+                Expression::Number(n) => Expression::Number(self.str(n)?),
+                Expression::String(s) => Expression::String(s),
+                Expression::Boolean(b) => Expression::Boolean(b),
+                Expression::Name(n) => Expression::Name(self.str(n)?),
+                Expression::Hole => Expression::Hole,
+                Expression::None => Expression::None,
+                Expression::As {
+                    expr,
+                    as_token,
+                    type_,
+                } => Expression::As {
+                    expr: self.expr(expr)?,
+                    as_token: self.verbatim(as_token)?,
+                    type_: self.ty(type_)?,
+                },
+                Expression::Unary {
+                    operator,
+                    expression,
+                } => Expression::Unary {
+                    operator: self.node(operator, Some)?,
+                    expression: self.expr(expression)?,
+                },
+                Expression::Compare {
+                    operator,
+                    left,
+                    right,
+                } => Expression::Compare {
+                    operator: self.node(operator, Some)?,
+                    left: self.expr(left)?,
+                    right: self.expr(right)?,
+                },
+                Expression::Arithmetic {
+                    operator,
+                    left,
+                    right,
+                } => Expression::Arithmetic {
+                    operator: self.node(operator, Some)?,
+                    left: self.expr(left)?,
+                    right: self.expr(right)?,
+                },
+                Expression::Accessor { accessee, index } => Expression::Accessor {
+                    accessee: self.expr(accessee)?,
+                    index: self.expr(index)?,
+                },
+                Expression::Function {
+                    fn_keyword,
+                    name,
+                    generic_parameters,
+                    parameters,
+                    constraint,
+                    arrow_token,
+                    body,
+                } => Expression::Function {
+                    fn_keyword: self.verbatim(fn_keyword)?,
+                    name: self.opt_verbatim(name)?,
+                    generic_parameters: self.generics(generic_parameters)?,
+                    parameters: self.list(parameters, |p| self.parameter(p))?,
+                    constraint: self.constraint(constraint)?,
+                    arrow_token: self.verbatim(arrow_token)?,
+                    body: self.expr(body)?,
+                },
+                Expression::Call { callee, parameters } => Expression::Call {
+                    callee: self.expr(callee)?,
+                    parameters: self.list(parameters, |e| self.expression(e))?,
+                },
+                Expression::With {
+                    with_keyword,
+                    bindings,
+                    body,
+                } => Expression::With {
+                    with_keyword: self.verbatim(with_keyword)?,
+                    bindings: self.list(bindings, |b| self.assignment(b))?,
+                    body: self.expr(body)?,
+                },
+                Expression::Tuple { elements } => Expression::Tuple {
+                    elements: self.list(elements, |e| self.expression(e))?,
+                },
+                Expression::List { elements } => Expression::List {
+                    elements: self.list(elements, |e| self.expression(e))?,
+                },
+                Expression::Procedure { body } => Expression::Procedure {
+                    body: self.list(body, |s| self.statement(s))?,
+                },
+                Expression::If {
+                    if_keyword,
+                    condition,
+                    then_keyword,
+                    then,
+                    else_keyword,
+                    _else,
+                } => Expression::If {
+                    if_keyword: self.verbatim(if_keyword)?,
+                    condition: self.expr(condition)?,
+                    then_keyword: self.verbatim(then_keyword)?,
+                    then: self.expr(then)?,
+                    else_keyword: self.verbatim(else_keyword)?,
+                    _else: self.expr(_else)?,
+                },
+                Expression::Record { elements } => Expression::Record {
+                    elements: self.list(elements, |e| self.record_element(e))?,
+                },
+                Expression::FieldAccess { accessee, field } => Expression::FieldAccess {
+                    accessee: self.expr(accessee)?,
+                    field: self.verbatim(field)?,
+                },
+            })
+        })
+    }
+
+    fn type_(&self, node: ParseNode<Type<'old>>) -> Option<ParseNode<Type<'new>>> {
+        self.node(node, |t| {
+            Some(match t {
+                Type::Kind => Type::Kind,
+                Type::Never => Type::Never,
+                Type::Unknown => Type::Unknown,
+                Type::Reference {
+                    name,
+                    generic_parameters,
+                } => Type::Reference {
+                    name: self.verbatim(name)?,
+                    generic_parameters: generic_parameters
+                        .map(|g| self.list(g, |t| self.type_(t)))
+                        .transpose()?,
+                },
+                Type::Union { left, right } => Type::Union {
+                    left: self.ty(left)?,
+                    right: self.ty(right)?,
+                },
+                Type::Tuple { members } => Type::Tuple {
+                    members: self.list(members, |t| self.type_(t))?,
+                },
+                Type::Function {
+                    fn_keyword,
+                    parameters,
+                    arrow_token,
+                    return_type,
+                } => Type::Function {
+                    fn_keyword: self.verbatim(fn_keyword)?,
+                    parameters: self.list(parameters, |t| self.type_(t))?,
+                    arrow_token: self.verbatim(arrow_token)?,
+                    return_type: self.ty(return_type)?,
+                },
+            })
+        })
+    }
+
+    fn record_element(
+        &self,
+        node: ParseNode<RecordElement<'old>>,
+    ) -> Option<ParseNode<RecordElement<'new>>> {
+        self.node(node, |e| {
+            Some(match e {
+                RecordElement::KeyValuePair { key, value } => RecordElement::KeyValuePair {
+                    key: self.verbatim(key)?,
+                    value: self.expression(value)?,
+                },
+                RecordElement::Identifier { name } => RecordElement::Identifier {
+                    name: self.verbatim(name)?,
+                },
+                RecordElement::Spread { value } => RecordElement::Spread {
+                    value: self.expression(value)?,
+                },
+            })
+        })
+    }
+
+    fn statement(&self, node: ParseNode<Statement<'old>>) -> Option<ParseNode<Statement<'new>>> {
+        self.node(node, |s| {
+            Some(match s {
+                Statement::Let {
+                    let_keyword,
+                    assignment,
+                } => Statement::Let {
+                    let_keyword: self.verbatim(let_keyword)?,
+                    assignment: self.assignment(assignment)?,
+                },
+                Statement::Set(assignment) => Statement::Set(self.assignment(assignment)?),
+                Statement::If {
+                    if_keyword,
+                    condition,
+                    then,
+                    _else,
+                } => Statement::If {
+                    if_keyword: self.verbatim(if_keyword)?,
+                    condition: self.expr(condition)?,
+                    then: self.stmt(then)?,
+                    _else: _else.map(|e| self.stmt(e)).transpose()?,
+                },
+                Statement::ForIn {
+                    for_keyword,
+                    binding,
+                    in_keyword,
+                    iterator,
+                    body,
+                } => Statement::ForIn {
+                    for_keyword: self.verbatim(for_keyword)?,
+                    binding: self.verbatim(binding)?,
+                    in_keyword: self.verbatim(in_keyword)?,
+                    iterator: self.expr(iterator)?,
+                    body: self.stmt(body)?,
+                },
+                Statement::Forever(body) => Statement::Forever(self.stmt(body)?),
+                Statement::Do(body) => Statement::Do(self.expr(body)?),
+                Statement::Break => Statement::Break,
+                Statement::Continue => Statement::Continue,
+                Statement::Pass => Statement::Pass,
+                Statement::Expression(e) => Statement::Expression(self.expr(e)?),
+            })
+        })
+    }
+
+    fn binding_pattern(
+        &self,
+        node: ParseNode<BindingPattern<'old>>,
+    ) -> Option<ParseNode<BindingPattern<'new>>> {
+        self.node(node, |p| {
+            Some(match p {
+                BindingPattern::Identifier { name } => BindingPattern::Identifier {
+                    name: self.verbatim(name)?,
+                },
+                BindingPattern::Tuple { patterns } => BindingPattern::Tuple {
+                    patterns: self.list(patterns, |p| self.binding_pattern(p))?,
+                },
+                BindingPattern::Record { elements } => BindingPattern::Record {
+                    elements: self.list(elements, |e| self.record_binding_element(e))?,
+                },
+            })
+        })
+    }
+
+    fn record_binding_element(
+        &self,
+        node: ParseNode<RecordBindingElement<'old>>,
+    ) -> Option<ParseNode<RecordBindingElement<'new>>> {
+        self.node(node, |e| {
+            Some(match e {
+                RecordBindingElement::Identifier { name } => RecordBindingElement::Identifier {
+                    name: self.verbatim(name)?,
+                },
+                RecordBindingElement::KeyValuePair { name, pattern } => {
+                    RecordBindingElement::KeyValuePair {
+                        name: self.verbatim(name)?,
+                        pattern: self.binding_pattern(pattern)?,
+                    }
+                }
+                RecordBindingElement::Rest { name } => RecordBindingElement::Rest {
+                    name: self.verbatim(name)?,
+                },
+            })
+        })
+    }
+}
+
+/// Keeps the source and parse of a document so that it can be reparsed incrementally as it is
+/// edited.
+#[wasm_bindgen]
+pub struct IncrementalParser {
+    module: OwnedModule,
+    pending: Vec<TextEdit>,
+}
+
+#[wasm_bindgen]
+impl IncrementalParser {
+    /// Parses `data` in full and starts tracking it.
+    #[wasm_bindgen(constructor)]
+    pub fn new(data: &[u8]) -> Self {
+        let source = core::str::from_utf8(data).expect("data was not valid UTF-8");
+
+        IncrementalParser {
+            module: OwnedModule::parse(source),
+            pending: Vec::new(),
+        }
+    }
+
+    /// Records the replacement of the bytes `start..end` of the current source with `text`. Edits
+    /// are applied together by the next call to `update`, and their offsets are all relative to the
+    /// source as of the previous `update`.
+    ///
+    /// Returns why the edit was rejected if it does not fit in the source or overlaps another
+    /// pending edit, in which case it is not recorded.
+    pub fn edit(&mut self, start: usize, end: usize, text: &str) -> Option<String> {
+        self.pending.push(TextEdit {
+            start,
+            end,
+            text: text.to_string(),
+        });
+
+        let error = check_edits(self.module.source(), &self.pending).err()?;
+        self.pending.pop();
+        Some(error.0)
+    }
+
+    /// Applies the pending edits, reparses the affected declarations, and returns the updated
+    /// `ParsedModule`.
+    pub fn update(&mut self) -> JsValue {
+        if !self.pending.is_empty() {
+            let edits = std::mem::take(&mut self.pending);
+            self.module
+                .apply_edits(&edits)
+                .expect("pending edits are checked as they are recorded");
+        }
+
+        let mut parsed = self.module.parsed().clone();
+        if let Some(module) = &parsed.module {
+            parsed.diagnostics.extend(check_exports(&module.value).1);
+        }
+
+        parsed.to_js_value()
+    }
+}
@@ -0,0 +1,356 @@
+//! The JSON form of a parse, for tools outside of JavaScript.
+//!
+//! With the `serde` feature, every node of the AST implements `Serialize` and `Deserialize`. A
+//! whole parse is written as a [`ParseOutput`], whose shape is described by the JSON schema in
+//! `schema/parse-output.schema.json` and documented in `docs/parse-json.md`. The schema is
+//! versioned by [`SCHEMA_VERSION`], which changes whenever the shape of the output does. It is
+//! generated from the AST by [`schema()`], and written out by `cargo run --features serde --bin
+//! gen_schema`.
+//!
+//! Names and tokens in the tree are borrowed strings. When a parse is read back, they borrow from
+//! the decoded JSON held by an [`OwnedParseOutput`], so they may be written with escape sequences,
+//! as JSON writers such as Python's `json.dumps` do for every character outside ASCII.
+
+use std::sync::Arc;
+
+use seglisp::{parse::ParseNode, Diagnostic, DiagnosticPhase, DiagnosticSeverity};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use crate::{
+    check_exports,
+    incremental::parse_module,
+    lint,
+    schema::{Field, FieldKind, Shape, TypeKind, TypeSchema, OPERATORS, TYPES},
+    Module,
+};
+
+/// The version of the JSON schema that [`ParseOutput`] follows.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// A parsed module and its diagnostics, as written by `sdp parse --json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound(deserialize = "'de: 'ast"))]
+pub struct ParseOutput<'ast> {
+    /// Always [`SCHEMA_VERSION`] when written by this crate.
+    pub version: u32,
+    /// The module, or `null` if the source could not be parsed.
+    pub module: Option<ParseNode<Module<'ast>>>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl<'ast> ParseOutput<'ast> {
+    /// Parses `source` and collects every diagnostic a parse reports in the editor: parse errors,
+    /// export checks, and lints with the default configuration.
+    pub fn parse(source: &'ast str) -> Self {
+        let parsed = parse_module(source);
+        let mut diagnostics = parsed.diagnostics;
+
+        if let Some(module) = &parsed.module {
+            diagnostics.extend(check_exports(&module.value).1);
+            diagnostics.extend(lint::lint(
+                source,
+                module,
+                &lint::Registry::default(),
+                &lint::LintConfig::default(),
+            ));
+        }
+
+        ParseOutput {
+            version: SCHEMA_VERSION,
+            module: parsed.module,
+            diagnostics,
+        }
+    }
+}
+
+/// An error reading a [`ParseOutput`].
+#[derive(Debug)]
+pub enum ReadError {
+    Json(serde_json::Error),
+    /// The output follows a different version of the schema.
+    Version(u32),
+}
+
+impl std::fmt::Display for ReadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReadError::Json(error) => write!(f, "{error}"),
+            ReadError::Version(version) => write!(
+                f,
+                "parse output has schema version {version}, expected {SCHEMA_VERSION}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ReadError {}
+
+/// Writes a parse of `source` as JSON, pretty-printed if `pretty` is set.
+pub fn to_json(source: &str, pretty: bool) -> String {
+    let output = ParseOutput::parse(source);
+
+    let json = if pretty {
+        serde_json::to_string_pretty(&output)
+    } else {
+        serde_json::to_string(&output)
+    };
+    json.expect("the AST always serializes")
+}
+
+/// A [`ParseOutput`] read back by [`from_json`], and the decoded JSON its strings borrow from.
+#[derive(Debug, Clone)]
+pub struct OwnedParseOutput {
+    // `output` borrows from `json`, so it is declared first to be dropped first. The `'static`
+    // lifetime never leaves this struct: `output` shortens it to the borrow of `self`. Clones
+    // share the same `json`, so their trees stay valid too.
+    output: ParseOutput<'static>,
+    json: Arc<Value>,
+}
+
+impl OwnedParseOutput {
+    pub fn output(&self) -> &ParseOutput<'_> {
+        &self.output
+    }
+
+    /// The module, or `None` if the source could not be parsed.
+    pub fn module(&self) -> Option<&ParseNode<Module<'_>>> {
+        self.output.module.as_ref()
+    }
+
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.output.diagnostics
+    }
+}
+
+/// Reads a parse written by [`to_json`]. Fails if it follows a different version of the schema.
+///
+/// The JSON is decoded before the tree is read from it, so names and tokens may contain escape
+/// sequences.
+pub fn from_json(json: &str) -> Result<OwnedParseOutput, ReadError> {
+    // The version is checked first, since output of another version may not deserialize at all.
+    #[derive(Deserialize)]
+    struct Versioned {
+        version: u32,
+    }
+
+    let json: Arc<Value> = Arc::new(serde_json::from_str(json).map_err(ReadError::Json)?);
+
+    let Versioned { version } = Versioned::deserialize(json.as_ref()).map_err(ReadError::Json)?;
+    if version != SCHEMA_VERSION {
+        return Err(ReadError::Version(version));
+    }
+
+    // SAFETY: `value` points into the allocation behind `json`, and the tree borrows only from the
+    // strings inside it, which outlive every use of the tree:
+    // - `Arc<Value>` only gives shared access, so the strings are never mutated or moved, even
+    //   when the `OwnedParseOutput` itself is moved;
+    // - the allocation is freed only when the last clone of the `Arc` is dropped, and each
+    //   `OwnedParseOutput` holds a clone for as long as it holds the tree, dropping the tree first;
+    // - the `'static` lifetime never leaves the struct, as every accessor shortens it to the
+    //   borrow of `self`.
+    let value: &'static Value = unsafe { &*Arc::as_ptr(&json) };
+    let output = ParseOutput::deserialize(value).map_err(ReadError::Json)?;
+
+    Ok(OwnedParseOutput { output, json })
+}
+
+/// Returns the JSON schema of [`ParseOutput`].
+pub fn schema() -> Value {
+    let mut defs = Map::new();
+
+    defs.insert(
+        "Location".into(),
+        object(json!({
+            "absolute": { "type": "integer", "minimum": 0 },
+            "line": { "type": "integer", "minimum": 0 },
+            "column": { "type": "integer", "minimum": 0 },
+        })),
+    );
+    defs.insert(
+        "Range".into(),
+        json!({
+            "type": "array",
+            "prefixItems": [reference("Location"), reference("Location")],
+            "items": false,
+            "minItems": 2,
+        }),
+    );
+    defs.insert("Verbatim".into(), node(json!({ "type": "string" })));
+
+    for ty in TYPES {
+        defs.insert(format!("{}Node", ty.name), node(reference(ty.name)));
+        defs.insert(
+            format!("{}List", ty.name),
+            node(json!({ "type": "array", "items": reference(&format!("{}Node", ty.name)) })),
+        );
+    }
+    for ty in OPERATORS {
+        defs.insert(format!("{}Node", ty.name), node(reference(ty.name)));
+    }
+    for ty in TYPES.iter().chain(OPERATORS) {
+        defs.insert(ty.name.into(), type_schema(ty));
+    }
+
+    // Diagnostics come from `seglisp`. Their severities and phases are the ones this crate
+    // reports; a location is a range, or another variant of `DiagnosticLocation` written the way
+    // `serde` writes enums.
+    defs.insert(
+        "Diagnostic".into(),
+        json!({
+            "type": "object",
+            "required": ["message", "severity", "phase", "location"],
+            "additionalProperties": false,
+            "properties": {
+                "message": { "type": "string" },
+                "note": nullable(json!({ "type": "string" })),
+                "severity": {
+                    "enum": names([DiagnosticSeverity::Error, DiagnosticSeverity::Warning]),
+                },
+                "phase": { "enum": names([DiagnosticPhase::Parse, DiagnosticPhase::Lint]) },
+                "location": {
+                    "oneOf": [
+                        object(json!({ "Range": reference("Range") })),
+                        { "type": "string" },
+                        {
+                            "type": "object",
+                            "minProperties": 1,
+                            "maxProperties": 1,
+                            "not": { "required": ["Range"] },
+                        },
+                    ],
+                },
+                "inner_diagnostics": nullable(json!({
+                    "type": "array",
+                    "items": reference("Diagnostic"),
+                })),
+                "abridged": { "type": "boolean" },
+                "subject": { "type": "null" },
+            },
+        }),
+    );
+
+    let mut schema = object(json!({
+        "version": { "const": SCHEMA_VERSION },
+        "module": nullable(reference("ModuleNode")),
+        "diagnostics": { "type": "array", "items": reference("Diagnostic") },
+    }));
+
+    let top = schema.as_object_mut().unwrap();
+    top.insert(
+        "$schema".into(),
+        "https://json-schema.org/draft/2020-12/schema".into(),
+    );
+    top.insert(
+        "$id".into(),
+        format!("https://serendipitous.dev/schema/parse-output/v{SCHEMA_VERSION}.json").into(),
+    );
+    top.insert("title".into(), "Serendipity parse output".into());
+    top.insert(
+        "description".into(),
+        "The output of `sdp parse --json`: a parsed module and its diagnostics. See \
+         docs/parse-json.md."
+            .into(),
+    );
+    top.insert("$defs".into(), Value::Object(defs));
+    schema
+}
+
+/// The names `serde` writes the unit variants `variants` as.
+fn names<T: Serialize>(variants: impl IntoIterator<Item = T>) -> Vec<Value> {
+    variants
+        .into_iter()
+        .map(|variant| serde_json::to_value(variant).expect("unit variants serialize as names"))
+        .collect()
+}
+
+fn reference(name: &str) -> Value {
+    json!({ "$ref": format!("#/$defs/{name}") })
+}
+
+fn nullable(schema: Value) -> Value {
+    json!({ "oneOf": [schema, { "type": "null" }] })
+}
+
+/// An object with exactly the given properties, all of them required.
+fn object(properties: Value) -> Value {
+    let required: Vec<_> = properties.as_object().unwrap().keys().cloned().collect();
+
+    json!({
+        "type": "object",
+        "required": required,
+        "additionalProperties": false,
+        "properties": properties,
+    })
+}
+
+/// A `ParseNode` whose value follows `value`.
+fn node(value: Value) -> Value {
+    object(json!({
+        "value": value,
+        "range": reference("Range"),
+        "has_error": { "type": "boolean" },
+    }))
+}
+
+fn field(kind: FieldKind) -> Value {
+    match kind {
+        FieldKind::Token => reference("Verbatim"),
+        FieldKind::Text => json!({ "type": "string" }),
+        FieldKind::Boolean => json!({ "type": "boolean" }),
+        FieldKind::Node(name) => reference(&format!("{name}Node")),
+        FieldKind::List(name) => reference(&format!("{name}List")),
+        FieldKind::Items(name) => {
+            json!({ "type": "array", "items": reference(&format!("{name}Node")) })
+        }
+        FieldKind::Optional(kind) => nullable(field(*kind)),
+    }
+}
+
+fn fields(fields: &[Field]) -> Value {
+    object(Value::Object(
+        fields
+            .iter()
+            .map(|f| (f.name.to_string(), field(f.kind)))
+            .collect(),
+    ))
+}
+
+/// Enums are written the way `serde` writes them by default: unit variants as their name, and
+/// other variants as an object whose only key is their name.
+fn type_schema(ty: &TypeSchema) -> Value {
+    let variants = match ty.kind {
+        TypeKind::Struct(struct_fields) => return fields(struct_fields),
+        TypeKind::Enum(variants) => variants,
+    };
+
+    let units: Vec<_> = variants
+        .iter()
+        .filter(|v| v.shape == Shape::Unit)
+        .map(|v| v.name)
+        .collect();
+    let mut alternatives = Vec::new();
+
+    if !units.is_empty() {
+        alternatives.push(json!({ "enum": units }));
+    }
+    for variant in variants {
+        let value = match variant.shape {
+            Shape::Unit => continue,
+            Shape::Tuple([single]) => field(single.kind),
+            Shape::Tuple(elements) => json!({
+                "type": "array",
+                "prefixItems": elements.iter().map(|f| field(f.kind)).collect::<Vec<_>>(),
+                "items": false,
+                "minItems": elements.len(),
+            }),
+            Shape::Struct(struct_fields) => fields(struct_fields),
+        };
+        alternatives.push(object(json!({ variant.name: value })));
+    }
+
+    match alternatives.len() {
+        1 => alternatives.pop().unwrap(),
+        _ => json!({ "oneOf": alternatives }),
+    }
+}
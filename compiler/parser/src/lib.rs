@@ -13,6 +13,42 @@ use seglisp::{
     SegLisp, SegLispNode, Segment,
 };
 
+#[cfg(feature = "arena")]
+pub mod arena;
+pub mod crdt;
+pub mod diagram;
+pub mod edit;
+pub mod eval;
+mod exports;
+pub mod fix;
+pub mod fold;
+pub mod graph;
+pub mod holes;
+pub mod ids;
+pub mod incremental;
+#[cfg(feature = "serde")]
+pub mod json;
+pub mod lint;
+#[cfg(feature = "lsp")]
+pub mod lsp;
+pub mod node;
+pub mod owned;
+pub mod patch;
+pub mod printer;
+pub mod query;
+pub mod rename;
+pub mod repl;
+pub mod resolve;
+mod scan;
+pub mod schema;
+pub mod sexpr;
+pub mod structural;
+pub mod types;
+pub mod visit;
+pub mod visit_mut;
+
+pub use exports::{check_exports, BindingKind, Export, ExportTable, ModuleBinding};
+
 macro_rules! set {
     {$($e:expr),*} => {
         {
@@ -26,6 +62,11 @@ macro_rules! set {
 }
 
 #[derive(Debug, Clone, JsInterop)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound(deserialize = "'de: 'ast"))
+)]
 pub struct Module<'ast> {
     pub declarations: Vec<ParseNode<Declaration<'ast>>>,
 }
@@ -39,6 +80,11 @@ impl<'ast> Parse<'ast, Body<'ast>> for Module<'ast> {
 }
 
 #[derive(Debug, Clone, JsInterop)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound(deserialize = "'de: 'ast"))
+)]
 pub struct GenericParameter<'ast> {
     name: Verbatim<'ast>,
     constraint: Option<ParseNode<TypeConstraint<'ast>>>,
@@ -54,6 +100,11 @@ impl_parse! {
 }
 
 #[derive(Debug, Clone, JsInterop)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound(deserialize = "'de: 'ast"))
+)]
 pub enum Declaration<'ast> {
     Main {
         main_keyword: Verbatim<'ast>,
@@ -202,6 +253,11 @@ impl<'ast> Parse<'ast, Segment<'ast>> for Declaration<'ast> {
 }
 
 #[derive(Debug, Clone, JsInterop)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound(deserialize = "'de: 'ast"))
+)]
 pub struct InterfaceField<'ast> {
     name: Verbatim<'ast>,
     constraint: ParseNode<TypeConstraint<'ast>>,
@@ -221,6 +277,11 @@ pub type ParsedVec<T> = ParseNode<Vec<ParseNode<T>>>;
 pub type Verbatim<'ast> = ParseNode<&'ast str>;
 
 #[derive(Debug, Clone, JsInterop)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound(deserialize = "'de: 'ast"))
+)]
 pub enum Expression<'ast> {
     // Elemental Terms
     Number(&'ast str),
@@ -355,71 +416,114 @@ impl<'ast> Parse<'ast, Segment<'ast>> for Expression<'ast> {
                     else_keyword: ctx.parse_from(SymbolPattern::Exact("else"))?,
                     _else: Box::new(ctx.parse()?),
                 }),
-                _ => ctx.parse_node(parse_compare).map(|v| v.value),
+                _ => ctx.parse_node(parse_binary).map(|v| v.value),
             }
         }
-        fn parse_compare<'ast>(
-            ctx: &NodeContext<'ast, Segment<'ast>>,
-        ) -> ParseResult<Expression<'ast>> {
-            let left = ctx.parse_node(parse_arith)?;
-
-            let next = ctx.peek().map(|v| &v.value);
-
-            match next {
-                Some(SegLisp::Sigil("==" | "!=" | "<=" | ">=" | "<" | ">")) => {
-                    Ok(Expression::Compare {
-                        operator: ctx.parse()?,
-                        left: Box::new(left),
-                        right: Box::new(ctx.parse_node(parse_compare)?),
-                    })
-                }
-                Some(_) | None => Ok(left.value),
+        // Binary operators are read in a single loop with a stack of pending operators, rather
+        // than by recursing once per operator or per precedence level, so that long chains cannot
+        // overflow the stack and each level of nesting costs as few frames as possible. Operators
+        // bind by precedence, comparisons loosest and `*`, `/` and `%` tightest, and associate to
+        // the left: `a - b - c` is `(a - b) - c`.
+        enum BinaryOp {
+            Compare(ParseNode<CompareOp>),
+            Arithmetic(ParseNode<ArithmeticOp>),
+        }
+        fn binary_precedence(sigil: &str) -> Option<u8> {
+            match sigil {
+                "==" | "!=" | "<=" | ">=" | "<" | ">" => Some(0),
+                "+" | "-" => Some(1),
+                "*" | "/" | "%" => Some(2),
+                _ => None,
             }
         }
-        fn parse_arith<'ast>(
+        fn parse_binary<'ast>(
             ctx: &NodeContext<'ast, Segment<'ast>>,
         ) -> ParseResult<Expression<'ast>> {
-            let left = ctx.parse_node(parse_term)?;
-
-            let next = ctx.peek().map(|v| &v.value);
+            fn reduce<'ast>(
+                operands: &mut Vec<ParseNode<Expression<'ast>>>,
+                operators: &mut Vec<(u8, BinaryOp)>,
+            ) {
+                let (_, operator) = operators.pop().unwrap();
+                let right = operands.pop().unwrap();
+                let left = operands.pop().unwrap();
+
+                let range = (left.range.0, right.range.1);
+                let mut has_error = left.has_error || right.has_error;
+                let (left, right) = (Box::new(left), Box::new(right));
+                let value = match operator {
+                    BinaryOp::Compare(operator) => {
+                        has_error |= operator.has_error;
+                        Expression::Compare {
+                            operator,
+                            left,
+                            right,
+                        }
+                    }
+                    BinaryOp::Arithmetic(operator) => {
+                        has_error |= operator.has_error;
+                        Expression::Arithmetic {
+                            operator,
+                            left,
+                            right,
+                        }
+                    }
+                };
 
-            match next {
-                Some(SegLisp::Sigil("+" | "-")) => Ok(Expression::Arithmetic {
-                    operator: ctx.parse()?,
-                    left: Box::new(left),
-                    right: Box::new(ctx.parse_node(parse_arith)?),
-                }),
-                Some(_) | None => Ok(left.value),
+                operands.push(ParseNode {
+                    range,
+                    has_error,
+                    value,
+                });
             }
-        }
-        fn parse_term<'ast>(
-            ctx: &NodeContext<'ast, Segment<'ast>>,
-        ) -> ParseResult<Expression<'ast>> {
-            let left = ctx.parse_node(parse_factor)?;
 
-            let next = ctx.peek().map(|v| &v.value);
+            let mut operands = vec![ctx.parse_node(parse_factor)?];
+            let mut operators: Vec<(u8, BinaryOp)> = Vec::new();
 
-            match next {
-                Some(SegLisp::Sigil("*" | "/" | "%")) => Ok(Expression::Arithmetic {
-                    operator: ctx.parse()?,
-                    left: Box::new(left),
-                    right: Box::new(ctx.parse_node(parse_term)?),
-                }),
-                Some(_) | None => Ok(left.value),
+            while let Some(SegLisp::Sigil(sigil)) = ctx.peek().map(|v| &v.value) {
+                let Some(precedence) = binary_precedence(sigil) else {
+                    break;
+                };
+
+                while operators.last().is_some_and(|(p, _)| *p >= precedence) {
+                    reduce(&mut operands, &mut operators);
+                }
+
+                let operator = if precedence == 0 {
+                    BinaryOp::Compare(ctx.parse()?)
+                } else {
+                    BinaryOp::Arithmetic(ctx.parse()?)
+                };
+                operators.push((precedence, operator));
+                operands.push(ctx.parse_node(parse_factor)?);
             }
+
+            while !operators.is_empty() {
+                reduce(&mut operands, &mut operators);
+            }
+
+            Ok(operands.pop().unwrap().value)
         }
         fn parse_factor<'ast>(
             ctx: &NodeContext<'ast, Segment<'ast>>,
         ) -> ParseResult<Expression<'ast>> {
-            let next = ctx.peek().map(|v| &v.value);
+            let mut operators: Vec<ParseNode<UnaryOp>> = Vec::new();
+            while let Some(SegLisp::Sigil("!" | "-")) = ctx.peek().map(|v| &v.value) {
+                operators.push(ctx.parse()?);
+            }
 
-            match next {
-                Some(SegLisp::Sigil("!" | "-")) => Ok(Expression::Unary {
-                    operator: ctx.parse()?,
-                    expression: Box::new(ctx.parse_node(parse_factor)?),
-                }),
-                Some(_) | None => ctx.parse_node(parse_postfix).map(|v| v.value),
+            let mut operand = ctx.parse_node(parse_postfix)?;
+            while let Some(operator) = operators.pop() {
+                operand = ParseNode {
+                    range: (operator.range.0, operand.range.1),
+                    has_error: operator.has_error || operand.has_error,
+                    value: Expression::Unary {
+                        operator,
+                        expression: Box::new(operand),
+                    },
+                };
             }
+
+            Ok(operand.value)
         }
         fn parse_postfix<'ast>(
             ctx: &NodeContext<'ast, Segment<'ast>>,
@@ -482,7 +586,7 @@ impl<'ast> Parse<'ast, Segment<'ast>> for Expression<'ast> {
         ) -> ParseResult<Expression<'ast>> {
             // List-delimited expressions
             if let Some(
-                node @ SegLispNode {
+                SegLispNode {
                     value:
                         SegLisp::List {
                             delimiters: (d, _), ..
@@ -496,19 +600,7 @@ impl<'ast> Parse<'ast, Segment<'ast>> for Expression<'ast> {
                         let mut elements: ParsedVec<Expression> =
                             ctx.parse_from(ListPattern::each())?;
 
-                        if elements.value.is_empty() {
-                            ctx.add_diagnostic(Diagnostic {
-                                abridged: false,
-                                inner_diagnostics: None,
-                                location: DiagnosticLocation::Range(node.range),
-                                message: "empty tuple (use 'none' instead)".into(),
-                                note: None,
-                                phase: DiagnosticPhase::Parse,
-                                severity: DiagnosticSeverity::Warning,
-                                subject: None, // TODO
-                            });
-                            Ok(Expression::Tuple { elements })
-                        } else if elements.value.len() == 1 {
+                        if elements.value.len() == 1 {
                             Ok(elements.value.remove(0).value)
                         } else {
                             Ok(Expression::Tuple { elements })
@@ -559,6 +651,11 @@ impl<'ast> Parse<'ast, Segment<'ast>> for Expression<'ast> {
 }
 
 #[derive(Debug, Clone, JsInterop)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound(deserialize = "'de: 'ast"))
+)]
 pub struct TypeConstraint<'ast> {
     colon_token: Verbatim<'ast>,
     type_: Box<ParseNode<Type<'ast>>>,
@@ -574,6 +671,11 @@ impl_parse! {
 }
 
 #[derive(Debug, Clone, JsInterop)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound(deserialize = "'de: 'ast"))
+)]
 pub enum Type<'ast> {
     Kind,
     Never,
@@ -664,6 +766,11 @@ impl_parse! {
 }
 
 #[derive(Debug, Clone, JsInterop)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound(deserialize = "'de: 'ast"))
+)]
 pub struct ParameterDeclaration<'ast> {
     name: Verbatim<'ast>,
     type_: Option<ParseNode<TypeConstraint<'ast>>>,
@@ -679,6 +786,11 @@ impl<'ast> Parse<'ast, Segment<'ast>> for ParameterDeclaration<'ast> {
 }
 
 #[derive(Debug, Clone, JsInterop)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound(deserialize = "'de: 'ast"))
+)]
 pub struct Assignment<'ast> {
     symbol: Verbatim<'ast>,
     equal_token: Verbatim<'ast>,
@@ -696,6 +808,11 @@ impl<'ast> Parse<'ast, Segment<'ast>> for Assignment<'ast> {
 }
 
 #[derive(Debug, Clone, JsInterop)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound(deserialize = "'de: 'ast"))
+)]
 pub enum RecordElement<'ast> {
     KeyValuePair {
         key: Verbatim<'ast>,
@@ -741,6 +858,7 @@ impl<'ast> Parse<'ast, Segment<'ast>> for RecordElement<'ast> {
 }
 
 #[derive(Debug, Clone, JsInterop)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CompareOp {
     Equal,
     NotEqual,
@@ -771,6 +889,7 @@ impl<'ast> Parse<'ast, Segment<'ast>> for CompareOp {
 }
 
 #[derive(Debug, Clone, JsInterop)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ArithmeticOp {
     Add,
     Subtract,
@@ -799,6 +918,7 @@ impl<'ast> Parse<'ast, Segment<'ast>> for ArithmeticOp {
 }
 
 #[derive(Debug, Clone, JsInterop)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum UnaryOp {
     Negate,
     Minus,
@@ -823,6 +943,11 @@ impl<'ast> Parse<'ast, Segment<'ast>> for UnaryOp {
 pub type InnerStatement<'ast> = Box<ParseNode<Statement<'ast>>>;
 
 #[derive(Debug, Clone, JsInterop)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound(deserialize = "'de: 'ast"))
+)]
 pub enum Statement<'ast> {
     Let {
         let_keyword: Verbatim<'ast>,
@@ -904,6 +1029,11 @@ impl<'ast> Parse<'ast, Segment<'ast>> for Statement<'ast> {
 }
 
 #[derive(Debug, Clone, JsInterop)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound(deserialize = "'de: 'ast"))
+)]
 pub enum BindingPattern<'ast> {
     Identifier {
         name: Verbatim<'ast>,
@@ -943,6 +1073,11 @@ impl_parse! {
 }
 
 #[derive(Debug, Clone, JsInterop)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound(deserialize = "'de: 'ast"))
+)]
 pub enum RecordBindingElement<'ast> {
     Identifier {
         name: Verbatim<'ast>,
@@ -987,32 +1122,90 @@ impl_parse! {
     }
 }
 
-#[wasm_bindgen]
-pub fn parse_bytes(data: &[u8]) -> JsValue {
+/// Reads and parses `data` as a module, and calls `f` with the resulting document. If `data` is
+/// nested deeper than [`incremental::MAX_NESTING`], none of it is read: as with
+/// [`incremental::parse_module`], the document has no module, and a diagnostic at the bracket that
+/// went too deep.
+pub(crate) fn with_parsed_module<R>(
+    data: &[u8],
+    f: impl FnOnce(seglisp::parse::ParsedDocument<Module>) -> R,
+) -> R {
+    let source = core::str::from_utf8(data).expect("data was not valid UTF-8");
+    let too_deep = incremental::check_nesting(source);
+
     let result = seglisp::read_str(
         &Default::default(),
-        core::str::from_utf8(data).expect("data was not valid UTF-8"),
+        if too_deep.is_some() { "" } else { source },
     );
 
     let host = seglisp::parse::ParseHost::default();
 
-    let result = result.parse::<Module>(&host);
+    let mut document = result.parse::<Module>(&host);
+    if let Some(diagnostic) = too_deep {
+        document.result = Err(ParseError::WrongToken(diagnostic.message.clone()));
+        document.diagnostics = vec![diagnostic];
+    }
+    f(document)
+}
 
-    result.to_js_value()
+/// Reads, parses, and checks `data` as a module, and calls `f` with the resulting document. This
+/// is everything [`parse_bytes`] does short of converting the document to JS, so it can be run
+/// natively, e.g. by the fuzzer. The checks include the lints, at their default levels.
+pub fn with_checked_module<R>(
+    data: &[u8],
+    f: impl FnOnce(seglisp::parse::ParsedDocument<Module>) -> R,
+) -> R {
+    check_module(data, true, f)
 }
 
-#[wasm_bindgen]
-pub fn print_parse(data: &[u8]) -> String {
-    let result = seglisp::read_str(
-        &Default::default(),
-        core::str::from_utf8(data).expect("data was not valid UTF-8"),
-    );
+fn check_module<R>(
+    data: &[u8],
+    lints: bool,
+    f: impl FnOnce(seglisp::parse::ParsedDocument<Module>) -> R,
+) -> R {
+    let registry = if lints {
+        lint::Registry::default()
+    } else {
+        // Empty tuples were reported by the parser before the lints existed, so they are still
+        // reported when the lints are left out.
+        let mut registry = lint::Registry::empty();
+        registry.register(lint::EmptyTuple);
+        registry
+    };
+
+    with_parsed_module(data, |mut result| {
+        if let Ok(module) = &result.result {
+            result.diagnostics.extend(check_exports(&module.value).1);
+            result.diagnostics.extend(lint::lint(
+                core::str::from_utf8(data).unwrap(),
+                module,
+                &registry,
+                &lint::LintConfig::default(),
+            ));
+        }
 
-    let host = seglisp::parse::ParseHost::default();
+        f(result)
+    })
+}
 
-    let result = result.parse::<Module>(&host);
+/// Parses and checks `data` as a module. The diagnostics include the findings of the built-in
+/// lints, at their default levels, if `lints` is set, and of the `empty-tuple` lint either way.
+#[wasm_bindgen]
+pub fn parse_bytes(data: &[u8], lints: bool) -> JsValue {
+    check_module(data, lints, |result| result.to_js_value())
+}
 
-    format!("{}", result.result.unwrap().value)
+#[wasm_bindgen]
+pub fn module_exports(data: &[u8]) -> JsValue {
+    with_parsed_module(data, |result| match &result.result {
+        Ok(module) => module.value.exports().to_js_value(),
+        Err(_) => JsValue::NULL,
+    })
+}
+
+#[wasm_bindgen]
+pub fn print_parse(data: &[u8]) -> String {
+    with_parsed_module(data, |result| format!("{}", result.result.unwrap().value))
 }
 
 impl core::fmt::Display for Module<'_> {
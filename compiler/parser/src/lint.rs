@@ -0,0 +1,702 @@
+//! A linter with named, configurable rules.
+//!
+//! A [`Rule`] inspects a parsed module and reports [`Finding`]s. Rules are collected in a
+//! [`Registry`], and [`lint`] runs every enabled rule and turns its findings into diagnostics in
+//! the lint phase. Each rule has a default [`LintLevel`], which a [`LintConfig`] can override. The
+//! configuration is read from the `[lint]` table of the project file, [`PROJECT_FILE`]:
+//!
+//! ```toml
+//! [lint]
+//! unused-let = "deny"
+//! loop-without-break = "allow"
+//! ```
+//!
+//! Findings can also be suppressed in the source with line comments. `// lint-allow: rule` at the
+//! end of a line suppresses the rule on that line, and on a line of its own suppresses it on the
+//! next line. `// lint-allow-file: rule` suppresses the rule in the whole file. Several rules may be
+//! listed, separated by commas.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    ops::{ControlFlow, Range},
+    path::{Path, PathBuf},
+};
+
+use wasm_bindgen::prelude::*;
+
+use seglisp::{
+    js_interop::{JsInterop, JsValue},
+    parse::ParseNode,
+    Diagnostic, DiagnosticLocation, DiagnosticPhase, DiagnosticSeverity,
+};
+
+use crate::{
+    fix::{Applicability, FixableDiagnostic, Suggestion},
+    printer::print_expression,
+    resolve::{resolve, Resolution, SymbolKind},
+    scan::{scan, Kind, Lexeme},
+    visit::{self, Visit},
+    with_parsed_module, CompareOp, Expression, Module, Statement,
+};
+
+/// The name of the project file that lint configuration is read from.
+pub const PROJECT_FILE: &str = "sdp.toml";
+
+/// How a rule's findings are reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintLevel {
+    /// Not reported at all.
+    Allow,
+    /// Reported as warnings.
+    Warn,
+    /// Reported as errors.
+    Deny,
+}
+
+impl LintLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LintLevel::Allow => "allow",
+            LintLevel::Warn => "warn",
+            LintLevel::Deny => "deny",
+        }
+    }
+}
+
+impl std::str::FromStr for LintLevel {
+    type Err = LintConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "allow" => Ok(LintLevel::Allow),
+            "warn" => Ok(LintLevel::Warn),
+            "deny" => Ok(LintLevel::Deny),
+            _ => Err(LintConfigError(format!(
+                "unknown lint level '{s}', expected 'allow', 'warn', or 'deny'"
+            ))),
+        }
+    }
+}
+
+/// Something a rule found, with the suggestions that fix it, if it knows any.
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub range: seglisp::Range,
+    pub message: String,
+    pub note: Option<String>,
+    pub suggestions: Vec<Suggestion>,
+}
+
+/// What a rule can see of the module it checks.
+pub struct LintContext<'a, 'ast> {
+    pub source: &'a str,
+    pub module: &'ast ParseNode<Module<'ast>>,
+    pub resolution: &'a Resolution<'ast>,
+}
+
+/// A lint rule.
+pub trait Rule {
+    /// The rule's name, in kebab case, as used in configuration and directives.
+    fn name(&self) -> &'static str;
+
+    /// A one-line description of what the rule reports.
+    fn description(&self) -> &'static str;
+
+    fn default_level(&self) -> LintLevel {
+        LintLevel::Warn
+    }
+
+    fn check(&self, cx: &LintContext, findings: &mut Vec<Finding>);
+}
+
+/// A set of rules.
+pub struct Registry {
+    rules: Vec<Box<dyn Rule>>,
+}
+
+impl Default for Registry {
+    /// A registry of the built-in rules.
+    fn default() -> Self {
+        let mut registry = Registry::empty();
+        registry.register(UnusedLet);
+        registry.register(IdenticalBranches);
+        registry.register(NoneComparison);
+        registry.register(EmptyTuple);
+        registry.register(LoopWithoutBreak);
+        registry
+    }
+}
+
+impl Registry {
+    /// A registry without any rules.
+    pub fn empty() -> Self {
+        Registry { rules: Vec::new() }
+    }
+
+    /// Adds a rule. A rule with the same name as one already registered replaces it.
+    pub fn register(&mut self, rule: impl Rule + 'static) {
+        self.rules.retain(|r| r.name() != rule.name());
+        self.rules.push(Box::new(rule));
+    }
+
+    pub fn rules(&self) -> impl Iterator<Item = &dyn Rule> {
+        self.rules.iter().map(|r| &**r)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn Rule> {
+        self.rules().find(|r| r.name() == name)
+    }
+}
+
+/// An invalid lint configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintConfigError(pub String);
+
+impl fmt::Display for LintConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for LintConfigError {}
+
+/// Per-rule levels that override the rules' defaults.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LintConfig {
+    levels: HashMap<String, LintLevel>,
+}
+
+impl LintConfig {
+    pub fn set(&mut self, rule: impl Into<String>, level: LintLevel) {
+        self.levels.insert(rule.into(), level);
+    }
+
+    /// The level `rule` is reported at.
+    pub fn level(&self, rule: &dyn Rule) -> LintLevel {
+        self.levels
+            .get(rule.name())
+            .copied()
+            .unwrap_or_else(|| rule.default_level())
+    }
+
+    /// The names of configured rules that `registry` does not have.
+    pub fn unknown_rules<'c>(&'c self, registry: &Registry) -> Vec<&'c str> {
+        let mut unknown: Vec<&str> = self
+            .levels
+            .keys()
+            .map(String::as_str)
+            .filter(|name| registry.get(name).is_none())
+            .collect();
+        unknown.sort();
+        unknown
+    }
+
+    /// Reads the `[lint]` table of a project file.
+    ///
+    /// Only the subset of TOML that the table needs is understood: table headers, `key = "value"`
+    /// pairs, and `#` comments. Other tables are skipped.
+    pub fn parse(text: &str) -> Result<Self, LintConfigError> {
+        let mut config = LintConfig::default();
+        let mut in_lint = false;
+
+        for (idx, line) in text.lines().enumerate() {
+            let line = strip_comment(line).trim();
+
+            if line.is_empty() {
+                continue;
+            }
+
+            if let Some(header) = line.strip_prefix('[') {
+                in_lint = header.strip_suffix(']').map(str::trim) == Some("lint");
+                continue;
+            }
+
+            if !in_lint {
+                continue;
+            }
+
+            let error = |message: &str| LintConfigError(format!("line {}: {message}", idx + 1));
+
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| error("expected `rule = \"level\"`"))?;
+
+            let key = key.trim().trim_matches('"');
+            let value = value
+                .trim()
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .ok_or_else(|| error("expected the level to be a string"))?;
+
+            config.set(
+                key,
+                value.parse().map_err(|e: LintConfigError| error(&e.0))?,
+            );
+        }
+
+        Ok(config)
+    }
+
+    /// Finds the project file in `directory` or the nearest of its ancestors that has one, and
+    /// reads its configuration. Returns the default configuration if there is no project file.
+    pub fn discover(directory: &Path) -> Result<(Self, Option<PathBuf>), LintConfigError> {
+        for dir in directory.ancestors() {
+            let path = dir.join(PROJECT_FILE);
+
+            if let Ok(text) = std::fs::read_to_string(&path) {
+                let config = LintConfig::parse(&text)
+                    .map_err(|e| LintConfigError(format!("{}: {}", path.display(), e.0)))?;
+                return Ok((config, Some(path)));
+            }
+        }
+
+        Ok((LintConfig::default(), None))
+    }
+}
+
+/// `line` up to the `#` that starts its comment, if it has one. A `#` inside a string does not.
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    let mut chars = line.char_indices();
+
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            '"' => in_string = !in_string,
+            '\\' if in_string => {
+                chars.next();
+            }
+            '#' if !in_string => return &line[..idx],
+            _ => {}
+        }
+    }
+
+    line
+}
+
+/// Runs every rule of `registry` that `config` enables over `module`, which was parsed from
+/// `source`, and returns the findings that are not suppressed as diagnostics.
+pub fn lint<'ast>(
+    source: &str,
+    module: &'ast ParseNode<Module<'ast>>,
+    registry: &Registry,
+    config: &LintConfig,
+) -> Vec<Diagnostic> {
+    check(source, module, registry, config)
+        .into_iter()
+        .map(|d| d.diagnostic)
+        .collect()
+}
+
+/// Like [`lint`], but keeps the suggestions that come with the findings.
+pub fn check<'ast>(
+    source: &str,
+    module: &'ast ParseNode<Module<'ast>>,
+    registry: &Registry,
+    config: &LintConfig,
+) -> Vec<FixableDiagnostic> {
+    let resolution = resolve(module);
+    let cx = LintContext {
+        source,
+        module,
+        resolution: &resolution,
+    };
+    let directives = Directives::read(source);
+
+    let mut diagnostics = Vec::new();
+
+    for rule in registry.rules() {
+        let severity = match config.level(rule) {
+            LintLevel::Allow => continue,
+            LintLevel::Warn => DiagnosticSeverity::Warning,
+            LintLevel::Deny => DiagnosticSeverity::Error,
+        };
+
+        let mut findings = Vec::new();
+        rule.check(&cx, &mut findings);
+
+        for finding in findings {
+            if directives.allows(rule.name(), finding.range.0.line) {
+                continue;
+            }
+
+            diagnostics.push(FixableDiagnostic {
+                diagnostic: Diagnostic {
+                    abridged: false,
+                    inner_diagnostics: None,
+                    location: DiagnosticLocation::Range(finding.range),
+                    message: format!("{} [{}]", finding.message, rule.name()),
+                    note: finding.note,
+                    phase: DiagnosticPhase::Lint,
+                    severity: severity.clone(),
+                    subject: None,
+                },
+                suggestions: finding.suggestions,
+            });
+        }
+    }
+
+    diagnostics
+}
+
+/// The `lint-allow` directives of a file.
+struct Directives {
+    /// Rules allowed on particular (zero-based) lines.
+    lines: Vec<(usize, String)>,
+    /// Rules allowed in the whole file.
+    file: Vec<String>,
+}
+
+impl Directives {
+    fn read(source: &str) -> Self {
+        let mut directives = Directives {
+            lines: Vec::new(),
+            file: Vec::new(),
+        };
+
+        for comment in comments(source).into_iter().filter(|c| c.is_line) {
+            let (line, trailing) = (comment.line, comment.trailing);
+            let comment = comment.text.trim();
+
+            if let Some(rules) = comment.strip_prefix("lint-allow-file:") {
+                directives.file.extend(rule_names(rules));
+            } else if let Some(rules) = comment.strip_prefix("lint-allow:") {
+                let line = if trailing { line } else { line + 1 };
+                directives
+                    .lines
+                    .extend(rule_names(rules).map(|rule| (line, rule)));
+            }
+        }
+
+        directives
+    }
+
+    fn allows(&self, rule: &str, line: usize) -> bool {
+        self.file.iter().any(|r| r == rule)
+            || self.lines.iter().any(|(l, r)| *l == line && r == rule)
+    }
+}
+
+fn rule_names(list: &str) -> impl Iterator<Item = String> + '_ {
+    list.split(',')
+        .map(str::trim)
+        .filter(|r| !r.is_empty())
+        .map(String::from)
+}
+
+/// A `//` or `/* */` comment.
+pub(crate) struct Comment<'a> {
+    /// The bytes of the comment, including its markers.
+    pub range: Range<usize>,
+    /// The zero-based line the comment starts on.
+    pub line: usize,
+    /// Whether code precedes the comment on its line.
+    pub trailing: bool,
+    /// Whether this is a `//` comment.
+    pub is_line: bool,
+    /// The text of the comment after its opening marker.
+    pub text: &'a str,
+}
+
+/// The comments of `source`, skipping comment markers inside strings.
+pub(crate) fn comments(source: &str) -> Vec<Comment<'_>> {
+    let mut comments = Vec::new();
+    let mut line = 0;
+    let mut code_on_line = false;
+
+    for Lexeme { kind, range } in scan(source) {
+        let lines = source[range.clone()].matches('\n').count();
+
+        match kind {
+            Kind::Newline => code_on_line = false,
+            Kind::LineComment | Kind::BlockComment { .. } => comments.push(Comment {
+                range: range.clone(),
+                line,
+                trailing: code_on_line,
+                is_line: kind == Kind::LineComment,
+                text: &source[range.start + 2..range.end],
+            }),
+            Kind::Open | Kind::Close | Kind::String { .. } | Kind::Other => code_on_line = true,
+        }
+
+        line += lines;
+    }
+
+    comments
+}
+
+/// Calls `f` with every expression and statement of a module.
+struct Walker<F>(F);
+
+enum NodeKind<'a, 'ast> {
+    Expression(&'a ParseNode<Expression<'ast>>),
+    Statement(&'a ParseNode<Statement<'ast>>),
+}
+
+impl<'ast, F: FnMut(NodeKind<'ast, 'ast>)> Visit<'ast> for Walker<F> {
+    fn visit_expression(&mut self, node: &'ast ParseNode<Expression<'ast>>) -> ControlFlow<()> {
+        (self.0)(NodeKind::Expression(node));
+        visit::walk_expression(self, node)
+    }
+
+    fn visit_statement(&mut self, node: &'ast ParseNode<Statement<'ast>>) -> ControlFlow<()> {
+        (self.0)(NodeKind::Statement(node));
+        visit::walk_statement(self, node)
+    }
+}
+
+fn walk<'ast>(module: &'ast ParseNode<Module<'ast>>, f: impl FnMut(NodeKind<'ast, 'ast>)) {
+    let _ = Walker(f).visit_module(module);
+}
+
+/// `let` bindings that are never used.
+pub struct UnusedLet;
+
+impl Rule for UnusedLet {
+    fn name(&self) -> &'static str {
+        "unused-let"
+    }
+
+    fn description(&self) -> &'static str {
+        "`let` bindings that are never used"
+    }
+
+    fn check(&self, cx: &LintContext, findings: &mut Vec<Finding>) {
+        for (idx, symbol) in cx.resolution.symbols.iter().enumerate() {
+            if symbol.kind != SymbolKind::Let || symbol.name.starts_with('_') {
+                continue;
+            }
+
+            if cx.resolution.references_to(idx).next().is_none() {
+                // The rename can only change what the program means if the new name is already
+                // declared alongside the binding, or used where the binding would capture it.
+                let renamed = format!("_{}", symbol.name);
+                let namespace = symbol.kind.namespace();
+                let clashes = cx.resolution.symbols.iter().any(|s| {
+                    s.scope == symbol.scope && s.kind.namespace() == namespace && s.name == renamed
+                }) || cx.resolution.references.iter().any(|r| {
+                    let offset = r.range.0.absolute;
+                    r.name == renamed
+                        && r.namespace == namespace
+                        && offset >= symbol.visible_from
+                        && offset <= symbol.scope.1
+                });
+                let applicability = if clashes {
+                    Applicability::MaybeIncorrect
+                } else {
+                    Applicability::MachineApplicable
+                };
+
+                findings.push(Finding {
+                    range: symbol.definition,
+                    message: format!("unused `let` binding '{}'", symbol.name),
+                    note: Some(format!(
+                        "remove it, or rename it to '{renamed}' if it is unused on purpose"
+                    )),
+                    suggestions: vec![Suggestion::replace(
+                        format!("rename to '{renamed}'"),
+                        applicability,
+                        (symbol.definition.0.absolute, symbol.definition.0.absolute),
+                        "_",
+                    )],
+                });
+            }
+        }
+    }
+}
+
+/// `if` expressions whose branches are the same.
+pub struct IdenticalBranches;
+
+impl Rule for IdenticalBranches {
+    fn name(&self) -> &'static str {
+        "identical-branches"
+    }
+
+    fn description(&self) -> &'static str {
+        "`if` expressions whose branches are identical"
+    }
+
+    fn check(&self, cx: &LintContext, findings: &mut Vec<Finding>) {
+        walk(cx.module, |node| {
+            if let NodeKind::Expression(ParseNode {
+                value: Expression::If { then, _else, .. },
+                range,
+                ..
+            }) = node
+            {
+                if print_expression(&then.value) == print_expression(&_else.value) {
+                    findings.push(Finding {
+                        range: *range,
+                        message: "both branches of this `if` are identical".into(),
+                        note: Some("the condition has no effect on the result".into()),
+                        suggestions: vec![Suggestion::replace(
+                            "replace with the branch",
+                            Applicability::MaybeIncorrect,
+                            (range.0.absolute, range.1.absolute),
+                            &cx.source[then.range.0.absolute..then.range.1.absolute],
+                        )],
+                    });
+                }
+            }
+        });
+    }
+}
+
+/// Comparisons against `none` with `==` or `!=`.
+pub struct NoneComparison;
+
+impl Rule for NoneComparison {
+    fn name(&self) -> &'static str {
+        "none-comparison"
+    }
+
+    fn description(&self) -> &'static str {
+        "comparisons against `none` with `==` or `!=`"
+    }
+
+    fn check(&self, cx: &LintContext, findings: &mut Vec<Finding>) {
+        walk(cx.module, |node| {
+            let NodeKind::Expression(ParseNode {
+                value:
+                    Expression::Compare {
+                        operator,
+                        left,
+                        right,
+                    },
+                range,
+                ..
+            }) = node
+            else {
+                return;
+            };
+
+            let equality = matches!(operator.value, CompareOp::Equal | CompareOp::NotEqual);
+            let against_none = [left, right]
+                .iter()
+                .any(|side| matches!(side.value, Expression::None));
+
+            if equality && against_none {
+                findings.push(Finding {
+                    range: *range,
+                    message: "comparison against `none`".into(),
+                    note: Some(
+                        "`none` is falsy, so test the value itself as a condition unless it may \
+                         also be `false`"
+                            .into(),
+                    ),
+                    suggestions: Vec::new(),
+                });
+            }
+        });
+    }
+}
+
+/// Empty tuples, `()`, which are better written `none`.
+pub struct EmptyTuple;
+
+impl Rule for EmptyTuple {
+    fn name(&self) -> &'static str {
+        "empty-tuple"
+    }
+
+    fn description(&self) -> &'static str {
+        "empty tuples, which are better written `none`"
+    }
+
+    fn check(&self, cx: &LintContext, findings: &mut Vec<Finding>) {
+        walk(cx.module, |node| {
+            if let NodeKind::Expression(ParseNode {
+                value: Expression::Tuple { elements },
+                range,
+                ..
+            }) = node
+            {
+                if elements.value.is_empty() {
+                    findings.push(Finding {
+                        range: *range,
+                        message: "empty tuple (use 'none' instead)".into(),
+                        note: None,
+                        suggestions: vec![Suggestion::replace(
+                            "replace with `none`",
+                            Applicability::MachineApplicable,
+                            (range.0.absolute, range.1.absolute),
+                            "none",
+                        )],
+                    });
+                }
+            }
+        });
+    }
+}
+
+/// `loop` statements that contain no `break`.
+pub struct LoopWithoutBreak;
+
+impl Rule for LoopWithoutBreak {
+    fn name(&self) -> &'static str {
+        "loop-without-break"
+    }
+
+    fn description(&self) -> &'static str {
+        "`loop` statements with no `break` to leave them"
+    }
+
+    fn check(&self, cx: &LintContext, findings: &mut Vec<Finding>) {
+        walk(cx.module, |node| {
+            if let NodeKind::Statement(ParseNode {
+                value: Statement::Forever(body),
+                range,
+                ..
+            }) = node
+            {
+                let mut finder = BreakFinder;
+                if finder.visit_statement(body).is_continue() {
+                    findings.push(Finding {
+                        range: *range,
+                        message: "this `loop` has no `break`".into(),
+                        note: Some("it only ends if evaluation fails".into()),
+                        suggestions: Vec::new(),
+                    });
+                }
+            }
+        });
+    }
+}
+
+/// Breaks on the first `break` that leaves the loop being searched, without looking into nested
+/// loops or functions, whose `break`s belong to them.
+struct BreakFinder;
+
+impl<'ast> Visit<'ast> for BreakFinder {
+    fn visit_expression(&mut self, node: &'ast ParseNode<Expression<'ast>>) -> ControlFlow<()> {
+        match node.value {
+            Expression::Function { .. } => ControlFlow::Continue(()),
+            _ => visit::walk_expression(self, node),
+        }
+    }
+
+    fn visit_statement(&mut self, node: &'ast ParseNode<Statement<'ast>>) -> ControlFlow<()> {
+        match node.value {
+            Statement::Break => ControlFlow::Break(()),
+            Statement::Forever(_) | Statement::ForIn { .. } => ControlFlow::Continue(()),
+            _ => visit::walk_statement(self, node),
+        }
+    }
+}
+
+/// Lints a document with the built-in rules, configured by the text of a project file (which may
+/// be empty). Returns an array of `FixableDiagnostic`s, or `null` if the document could not be
+/// parsed or the configuration is invalid.
+#[wasm_bindgen]
+pub fn lint_bytes(data: &[u8], config: &str) -> JsValue {
+    let Ok(config) = LintConfig::parse(config) else {
+        return JsValue::NULL;
+    };
+    let source = core::str::from_utf8(data).expect("data was not valid UTF-8");
+
+    with_parsed_module(data, |doc| match &doc.result {
+        Ok(module) => check(source, module, &Registry::default(), &config).to_js_value(),
+        Err(_) => JsValue::NULL,
+    })
+}
@@ -0,0 +1,822 @@
+//! A language server for Serendipity source files.
+//!
+//! [`Server`] speaks the Language Server Protocol over any reader and writer, using the
+//! `Content-Length` framing of the base protocol. The `sdp-lsp` binary runs it over stdio. Documents
+//! are kept in memory and reparsed on every request; the parser is fast enough that this is not
+//! worth caching.
+//!
+//! Positions are exchanged as zero-based lines and character columns. Clients that offer the
+//! `utf-32` position encoding are told that it is in use. Other clients assume UTF-16 columns, which
+//! only agree with ours on lines without astral characters.
+
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Write},
+    path::Path,
+};
+
+use seglisp::{
+    parse::ParseNode, Diagnostic, DiagnosticLocation, DiagnosticPhase, DiagnosticSeverity,
+};
+use serde_json::{json, Value};
+
+use crate::{
+    check_exports,
+    fix::{Applicability, FixableDiagnostic},
+    graph::ModuleGraph,
+    holes::{complete_hole, CompletionKind},
+    incremental::{parse_module, ParsedModule},
+    lint::{check, comments, LintConfig, LintConfigError, Registry},
+    printer::print_declaration,
+    query::offset_of_char_position,
+    rename::rename,
+    resolve::{resolve, SymbolKind},
+    BindingPattern, Declaration, Module,
+};
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const SERVER_NOT_INITIALIZED: i64 = -32002;
+const REQUEST_FAILED: i64 = -32803;
+
+/// Reads one message from `input`. Returns `None` at the end of the stream, and an error of kind
+/// [`io::ErrorKind::InvalidData`] if the message has no valid `Content-Length` header.
+pub fn read_message(input: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut length = None;
+
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+
+    let Some(length) = length else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "message has no Content-Length header",
+        ));
+    };
+
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+
+    Ok(Some(serde_json::from_slice(&body).unwrap_or(Value::Null)))
+}
+
+/// Writes one message to `output`.
+pub fn write_message(output: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+/// The state of a language server session.
+#[derive(Debug, Default)]
+pub struct Server {
+    documents: HashMap<String, String>,
+    initialized: bool,
+    shutdown: bool,
+    exited: bool,
+}
+
+type Response = Result<Value, (i64, String)>;
+
+impl Server {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serves messages from `input` until the client sends `exit` or closes the stream.
+    pub fn run(&mut self, mut input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+        while !self.exited {
+            let message = match read_message(&mut input) {
+                Ok(Some(message)) => message,
+                Ok(None) => break,
+                // A message with broken framing is skipped, and the next one read as usual.
+                Err(error) if error.kind() == io::ErrorKind::InvalidData => {
+                    let log =
+                        json!({ "type": 1, "message": format!("skipped a message: {error}") });
+                    write_message(&mut output, &notification("window/logMessage", log))?;
+                    continue;
+                }
+                Err(error) => return Err(error),
+            };
+
+            for reply in self.handle(message) {
+                write_message(&mut output, &reply)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The process exit code the protocol requires: zero if the client shut the server down before
+    /// exiting, and one otherwise.
+    pub fn exit_code(&self) -> i32 {
+        if self.shutdown {
+            0
+        } else {
+            1
+        }
+    }
+
+    /// Handles one message, returning the messages to send back to the client.
+    pub fn handle(&mut self, message: Value) -> Vec<Value> {
+        let Some(method) = message.get("method").and_then(Value::as_str) else {
+            // Responses to server-initiated requests are ignored; anything else is malformed.
+            if message.get("id").is_some() && message.get("result").is_some() {
+                return vec![];
+            }
+
+            let error = if message.is_null() {
+                (PARSE_ERROR, "could not parse message".to_string())
+            } else {
+                (INVALID_REQUEST, "message has no method".to_string())
+            };
+            return vec![reply(Value::Null, Err(error))];
+        };
+
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+        match message.get("id").cloned() {
+            Some(id) => {
+                let result = if !self.initialized && method != "initialize" {
+                    Err((
+                        SERVER_NOT_INITIALIZED,
+                        "server is not initialized".to_string(),
+                    ))
+                } else {
+                    self.request(method, &params)
+                };
+
+                vec![reply(id, result)]
+            }
+            None => self.notification(method, &params),
+        }
+    }
+
+    fn request(&mut self, method: &str, params: &Value) -> Response {
+        match method {
+            "initialize" => {
+                self.initialized = true;
+                Ok(initialize_result(params))
+            }
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            }
+            "textDocument/documentSymbol" => self.with_document(params, |doc, _| {
+                Ok(doc.module().map_or(json!([]), document_symbols))
+            }),
+            "textDocument/hover" => self.with_document(params, |doc, offset| {
+                Ok(hover(doc, offset?).unwrap_or(Value::Null))
+            }),
+            "textDocument/definition" => self.with_document(params, |doc, offset| {
+                let uri = &params["textDocument"]["uri"];
+                Ok(definition(doc, offset?, uri).unwrap_or(Value::Null))
+            }),
+            "textDocument/references" => self.with_document(params, |doc, offset| {
+                let uri = &params["textDocument"]["uri"];
+                let include_declaration = params["context"]["includeDeclaration"]
+                    .as_bool()
+                    .unwrap_or(true);
+                Ok(references(doc, offset?, uri, include_declaration).unwrap_or(json!([])))
+            }),
+            "textDocument/completion" => self.with_document(params, |doc, offset| {
+                Ok(completion(doc, offset?).unwrap_or(json!([])))
+            }),
+            "textDocument/rename" => self.rename(params),
+            "textDocument/codeAction" => self.with_document(params, |doc, _| {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                let range = range_offsets(doc.source, &params["range"])
+                    .ok_or((INVALID_PARAMS, "invalid range".to_string()))?;
+                Ok(code_actions(doc, uri, range))
+            }),
+            "textDocument/formatting" => {
+                self.with_document(params, |doc, _| Ok(formatting(doc).unwrap_or(Value::Null)))
+            }
+            _ => Err((METHOD_NOT_FOUND, format!("unknown method '{method}'"))),
+        }
+    }
+
+    fn notification(&mut self, method: &str, params: &Value) -> Vec<Value> {
+        let uri = params["textDocument"]["uri"].as_str().map(str::to_string);
+
+        match (method, uri) {
+            ("exit", _) => {
+                self.exited = true;
+                vec![]
+            }
+            ("textDocument/didOpen", Some(uri)) => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or("");
+                self.documents.insert(uri.clone(), text.to_string());
+                vec![self.publish_diagnostics(&uri)]
+            }
+            ("textDocument/didChange", Some(uri)) => {
+                let Some(text) = self.documents.get_mut(&uri) else {
+                    return vec![];
+                };
+
+                for change in params["contentChanges"].as_array().into_iter().flatten() {
+                    if apply_change(text, change).is_none() {
+                        // The client's copy of the document no longer matches ours, and every
+                        // later change would be applied in the wrong place. The document is
+                        // dropped until the client opens it again.
+                        self.documents.remove(&uri);
+                        return vec![
+                            notification(
+                                "textDocument/publishDiagnostics",
+                                json!({ "uri": uri, "diagnostics": [] }),
+                            ),
+                            notification(
+                                "window/showMessage",
+                                json!({
+                                    "type": 1,
+                                    "message": format!(
+                                        "{uri} is out of sync with the server, close and reopen it"
+                                    ),
+                                }),
+                            ),
+                        ];
+                    }
+                }
+
+                vec![self.publish_diagnostics(&uri)]
+            }
+            ("textDocument/didClose", Some(uri)) => {
+                self.documents.remove(&uri);
+                vec![notification(
+                    "textDocument/publishDiagnostics",
+                    json!({ "uri": uri, "diagnostics": [] }),
+                )]
+            }
+            _ => vec![],
+        }
+    }
+
+    /// Parses the document named by `params` and calls `f` with it and the byte offset of
+    /// `params.position`, if there is one.
+    fn with_document(
+        &self,
+        params: &Value,
+        f: impl FnOnce(&Document, Result<usize, (i64, String)>) -> Response,
+    ) -> Response {
+        let uri = params["textDocument"]["uri"]
+            .as_str()
+            .ok_or((INVALID_PARAMS, "missing textDocument.uri".to_string()))?;
+
+        let source = self
+            .documents
+            .get(uri)
+            .ok_or((INVALID_PARAMS, format!("document '{uri}' is not open")))?;
+
+        let offset = position_offset(source, &params["position"])
+            .ok_or((INVALID_PARAMS, "invalid position".to_string()));
+
+        let doc = Document {
+            source,
+            parsed: parse_module(source),
+        };
+
+        f(&doc, offset)
+    }
+
+    /// Renames a symbol across every open document, treating them as the modules of one project.
+    fn rename(&self, params: &Value) -> Response {
+        let uri = params["textDocument"]["uri"]
+            .as_str()
+            .ok_or((INVALID_PARAMS, "missing textDocument.uri".to_string()))?;
+        let new_name = params["newName"]
+            .as_str()
+            .ok_or((INVALID_PARAMS, "missing newName".to_string()))?;
+
+        let source = self
+            .documents
+            .get(uri)
+            .ok_or((INVALID_PARAMS, format!("document '{uri}' is not open")))?;
+        let offset = position_offset(source, &params["position"])
+            .ok_or((INVALID_PARAMS, "invalid position".to_string()))?;
+
+        let parsed: Vec<(&str, &str, ParsedModule)> = self
+            .documents
+            .iter()
+            .map(|(uri, source)| (uri.as_str(), source.as_str(), parse_module(source)))
+            .collect();
+
+        let mut graph = ModuleGraph::new();
+        let mut uris = Vec::new();
+        for (uri, source, parsed) in &parsed {
+            if let Some(module) = &parsed.module {
+                graph.add(uri.strip_prefix("file://").unwrap_or(uri), source, module);
+                uris.push((*uri, *source));
+            }
+        }
+
+        let module = uris.iter().position(|(u, _)| *u == uri).ok_or((
+            REQUEST_FAILED,
+            "the document could not be parsed".to_string(),
+        ))?;
+
+        let files = rename(&graph, module, offset, new_name).map_err(|e| (REQUEST_FAILED, e.0))?;
+
+        let mut changes = serde_json::Map::new();
+        for file in &files {
+            let (uri, source) = uris[graph
+                .find(&file.path)
+                .expect("renamed a module not in the graph")];
+            let edits = file
+                .edits
+                .iter()
+                .map(|edit| {
+                    json!({
+                        "range": {
+                            "start": lsp_position(source, edit.start),
+                            "end": lsp_position(source, edit.end),
+                        },
+                        "newText": edit.text,
+                    })
+                })
+                .collect();
+
+            changes.insert(uri.to_string(), Value::Array(edits));
+        }
+
+        Ok(json!({ "changes": changes }))
+    }
+
+    fn publish_diagnostics(&self, uri: &str) -> Value {
+        let source = self.documents.get(uri).map_or("", String::as_str);
+
+        let parsed = parse_module(source);
+        let mut diagnostics = parsed.diagnostics.clone();
+        if let Some(module) = &parsed.module {
+            diagnostics.extend(check_exports(&module.value).1);
+
+            match lints(uri, source, module) {
+                Ok(lints) => diagnostics.extend(lints.into_iter().map(|l| l.diagnostic)),
+                Err(error) => diagnostics.push(Diagnostic {
+                    abridged: false,
+                    inner_diagnostics: None,
+                    location: DiagnosticLocation::Range((module.range.0, module.range.0)),
+                    message: error.to_string(),
+                    note: None,
+                    phase: DiagnosticPhase::Lint,
+                    severity: DiagnosticSeverity::Warning,
+                    subject: None,
+                }),
+            }
+        }
+
+        notification(
+            "textDocument/publishDiagnostics",
+            json!({
+                "uri": uri,
+                "diagnostics": diagnostics.iter().map(lsp_diagnostic).collect::<Vec<_>>(),
+            }),
+        )
+    }
+}
+
+struct Document<'a> {
+    source: &'a str,
+    parsed: ParsedModule<'a>,
+}
+
+impl<'a> Document<'a> {
+    fn module(&self) -> Option<&ParseNode<Module<'a>>> {
+        self.parsed.module.as_ref()
+    }
+
+    fn declaration_at(&self, offset: usize) -> Option<&ParseNode<Declaration<'a>>> {
+        self.module()?
+            .value
+            .declarations
+            .iter()
+            .find(|decl| decl.range.0.absolute <= offset && offset <= decl.range.1.absolute)
+    }
+}
+
+fn reply(id: Value, result: Response) -> Value {
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err((code, message)) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": code, "message": message },
+        }),
+    }
+}
+
+fn notification(method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "method": method, "params": params })
+}
+
+fn initialize_result(params: &Value) -> Value {
+    let utf32 = params["capabilities"]["general"]["positionEncodings"]
+        .as_array()
+        .is_some_and(|encodings| encodings.iter().any(|e| e == "utf-32"));
+
+    let mut capabilities = json!({
+        "textDocumentSync": { "openClose": true, "change": 2 },
+        "documentSymbolProvider": true,
+        "hoverProvider": true,
+        "definitionProvider": true,
+        "referencesProvider": true,
+        "documentFormattingProvider": true,
+        "completionProvider": { "triggerCharacters": ["@"] },
+        "renameProvider": true,
+        "codeActionProvider": { "codeActionKinds": ["quickfix"] },
+    });
+
+    if utf32 {
+        capabilities["positionEncoding"] = json!("utf-32");
+    }
+
+    json!({
+        "capabilities": capabilities,
+        "serverInfo": { "name": "sdp-lsp", "version": env!("CARGO_PKG_VERSION") },
+    })
+}
+
+/// Lints a document with the configuration of the project it is in.
+fn lints(
+    uri: &str,
+    source: &str,
+    module: &ParseNode<Module>,
+) -> Result<Vec<FixableDiagnostic>, LintConfigError> {
+    let config = match uri
+        .strip_prefix("file://")
+        .and_then(|path| Path::new(path).parent())
+    {
+        Some(directory) => LintConfig::discover(directory)?.0,
+        None => LintConfig::default(),
+    };
+
+    Ok(check(source, module, &Registry::default(), &config))
+}
+
+/// Quick fixes for the lint findings that overlap the byte range `start..end`.
+fn code_actions(doc: &Document, uri: &str, (start, end): (usize, usize)) -> Value {
+    let Some(module) = doc.module() else {
+        return json!([]);
+    };
+    let Ok(lints) = lints(uri, doc.source, module) else {
+        return json!([]);
+    };
+
+    lints
+        .iter()
+        .filter(|lint| match &lint.diagnostic.location {
+            DiagnosticLocation::Range(range) => {
+                range.0.absolute <= end && start <= range.1.absolute
+            }
+            _ => false,
+        })
+        .flat_map(|lint| {
+            lint.suggestions.iter().map(|suggestion| {
+                let edits: Vec<Value> = suggestion
+                    .edits
+                    .iter()
+                    .map(|edit| {
+                        json!({
+                            "range": {
+                                "start": lsp_position(doc.source, edit.start),
+                                "end": lsp_position(doc.source, edit.end),
+                            },
+                            "newText": edit.text,
+                        })
+                    })
+                    .collect();
+
+                json!({
+                    "title": suggestion.message,
+                    "kind": "quickfix",
+                    "diagnostics": [lsp_diagnostic(&lint.diagnostic)],
+                    "isPreferred": suggestion.applicability == Applicability::MachineApplicable,
+                    "edit": { "changes": { uri: edits } },
+                })
+            })
+        })
+        .collect()
+}
+
+fn position_offset(source: &str, position: &Value) -> Option<usize> {
+    let line = position["line"].as_u64()? as usize;
+    let character = position["character"].as_u64()? as usize;
+
+    offset_of_char_position(source, line, character)
+}
+
+/// The zero-based line and character column of byte `offset`.
+fn lsp_position(source: &str, offset: usize) -> Value {
+    let before = &source[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+
+    json!({
+        "line": before.matches('\n').count(),
+        "character": before[line_start..].chars().count(),
+    })
+}
+
+/// Applies one entry of `contentChanges` to `text`: a replacement of `range` if it has one, and of
+/// the whole document otherwise. Returns `None`, leaving `text` as it was, if the change is
+/// malformed or its range does not lie within `text`.
+fn apply_change(text: &mut String, change: &Value) -> Option<()> {
+    let new_text = change["text"].as_str()?;
+
+    match change.get("range") {
+        Some(range) => {
+            let (start, end) = range_offsets(text, range)?;
+            text.replace_range(start..end, new_text);
+        }
+        None => *text = new_text.to_string(),
+    }
+
+    Some(())
+}
+
+fn range_offsets(source: &str, range: &Value) -> Option<(usize, usize)> {
+    let start = position_offset(source, &range["start"])?;
+    let end = position_offset(source, &range["end"])?;
+
+    (start <= end).then_some((start, end))
+}
+
+fn lsp_range(range: &seglisp::Range) -> Value {
+    json!({
+        "start": { "line": range.0.line, "character": range.0.column },
+        "end": { "line": range.1.line, "character": range.1.column },
+    })
+}
+
+fn lsp_diagnostic(diagnostic: &Diagnostic) -> Value {
+    let range = match &diagnostic.location {
+        DiagnosticLocation::Range(range) => lsp_range(range),
+        _ => json!({
+            "start": { "line": 0, "character": 0 },
+            "end": { "line": 0, "character": 0 },
+        }),
+    };
+
+    let severity = match diagnostic.severity {
+        DiagnosticSeverity::Error => 1,
+        DiagnosticSeverity::Warning => 2,
+        _ => 3,
+    };
+
+    let message = match &diagnostic.note {
+        Some(note) => format!("{}\n{}", diagnostic.message, note),
+        None => diagnostic.message.clone(),
+    };
+
+    json!({
+        "range": range,
+        "severity": severity,
+        "source": "serendipity",
+        "message": message,
+    })
+}
+
+/// The name and LSP symbol kind of a top-level declaration, with the node that names it.
+fn declaration_symbol<'d, 'ast>(
+    decl: &'d Declaration<'ast>,
+) -> (String, u32, Option<&'d ParseNode<&'ast str>>) {
+    match decl {
+        Declaration::Main { main_keyword, .. } => ("main".into(), 12, Some(main_keyword)),
+        Declaration::Const { identifier, .. } => (identifier.value.into(), 14, Some(identifier)),
+        Declaration::Function { identifier, .. } => (identifier.value.into(), 12, Some(identifier)),
+        Declaration::Import {
+            module_specifier, ..
+        } => (format!("use(\"{}\")", module_specifier.value), 2, None),
+        Declaration::Export { export_keyword, .. } => ("export".into(), 3, Some(export_keyword)),
+        Declaration::TypeAlias { name, .. } => (name.value.into(), 26, Some(name)),
+        Declaration::Interface { name, .. } => (name.value.into(), 11, Some(name)),
+    }
+}
+
+fn document_symbols(module: &ParseNode<Module>) -> Value {
+    module
+        .value
+        .declarations
+        .iter()
+        .map(|decl| {
+            let (name, kind, selection) = declaration_symbol(&decl.value);
+
+            let mut symbol = json!({
+                "name": name,
+                "kind": kind,
+                "range": lsp_range(&decl.range),
+                "selectionRange": lsp_range(&selection.map_or(decl.range, |s| s.range)),
+            });
+
+            // Imports list the names they bind as children.
+            if let Declaration::Import { pattern, .. } = &decl.value {
+                let mut children = Vec::new();
+                import_names(pattern, &mut children);
+                symbol["children"] = Value::Array(children);
+            }
+
+            symbol
+        })
+        .collect()
+}
+
+fn import_names(pattern: &ParseNode<BindingPattern>, out: &mut Vec<Value>) {
+    crate::exports::collect_pattern_names(&pattern.value, &mut |name| {
+        out.push(json!({
+            "name": name.value,
+            "kind": 13,
+            "range": lsp_range(&name.range),
+            "selectionRange": lsp_range(&name.range),
+        }))
+    });
+}
+
+/// Returns the documentation comment immediately above the byte offset `start`, without its
+/// comment markers.
+///
+/// Both `/** ... */` blocks and runs of `//` line comments are recognized.
+pub fn doc_comment(source: &str, start: usize) -> Option<String> {
+    let before = source[..start].trim_end();
+
+    if let Some(block) = before.strip_suffix("*/") {
+        let open = block.rfind("/*")?;
+        let body = block[open + 2..]
+            .strip_prefix('*')
+            .unwrap_or(&block[open + 2..]);
+
+        let lines: Vec<&str> = body
+            .lines()
+            .map(|line| {
+                let line = line.trim();
+                line.strip_prefix("* ")
+                    .or_else(|| line.strip_prefix('*'))
+                    .unwrap_or(line)
+            })
+            .collect();
+
+        let text = lines.join("\n").trim().to_string();
+        return (!text.is_empty()).then_some(text);
+    }
+
+    let mut lines: Vec<&str> = before
+        .lines()
+        .rev()
+        .map(str::trim)
+        .take_while(|line| line.starts_with("//"))
+        .map(|line| line.trim_start_matches('/').trim())
+        .collect();
+    lines.reverse();
+
+    let text = lines.join("\n").trim().to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+fn hover(doc: &Document, offset: usize) -> Option<Value> {
+    let module = doc.module()?;
+    let resolution = resolve(module);
+
+    let symbol = &resolution.symbols[resolution.symbol_at(offset)?];
+
+    let contents = if symbol.kind.is_global() {
+        let decl = doc.declaration_at(symbol.definition.0.absolute)?;
+        let text = &doc.source[decl.range.0.absolute..decl.range.1.absolute];
+        let signature = text.lines().next().unwrap_or("").trim_end();
+
+        let mut contents = format!("```serendipity\n{signature}\n```");
+        if let Some(comment) = doc_comment(doc.source, decl.range.0.absolute) {
+            contents.push_str("\n\n");
+            contents.push_str(&comment);
+        }
+        contents
+    } else {
+        let kind = match symbol.kind {
+            SymbolKind::GenericParameter => "type parameter",
+            SymbolKind::Parameter => "parameter",
+            SymbolKind::FunctionName => "function",
+            SymbolKind::With => "with binding",
+            SymbolKind::Let => "let binding",
+            SymbolKind::ForIn => "loop variable",
+            _ => "binding",
+        };
+
+        format!("({kind}) {}", symbol.name)
+    };
+
+    Some(json!({
+        "contents": { "kind": "markdown", "value": contents },
+    }))
+}
+
+fn definition(doc: &Document, offset: usize, uri: &Value) -> Option<Value> {
+    let resolution = resolve(doc.module()?);
+    let symbol = &resolution.symbols[resolution.symbol_at(offset)?];
+
+    Some(json!({ "uri": uri, "range": lsp_range(&symbol.definition) }))
+}
+
+fn references(
+    doc: &Document,
+    offset: usize,
+    uri: &Value,
+    include_declaration: bool,
+) -> Option<Value> {
+    let resolution = resolve(doc.module()?);
+    let idx = resolution.symbol_at(offset)?;
+
+    let declaration = include_declaration.then(|| resolution.symbols[idx].definition);
+
+    let locations = declaration
+        .into_iter()
+        .chain(resolution.references_to(idx).map(|r| r.range))
+        .map(|range| json!({ "uri": uri, "range": lsp_range(&range) }))
+        .collect();
+
+    Some(Value::Array(locations))
+}
+
+/// Suggests ways to fill the hole at `offset`. Each item replaces the whole hole with a snippet.
+fn completion(doc: &Document, offset: usize) -> Option<Value> {
+    let (hole, completions) = complete_hole(doc.module()?, offset)?;
+    let range = lsp_range(&hole.range);
+
+    let items = completions
+        .iter()
+        .enumerate()
+        .map(|(rank, c)| {
+            // Variable, Function, Value, Struct, and Snippet completion item kinds.
+            let kind = match c.kind {
+                CompletionKind::Binding => 6,
+                CompletionKind::Call => 3,
+                CompletionKind::Literal => 12,
+                CompletionKind::Constructor => 22,
+                CompletionKind::Template => 15,
+            };
+
+            json!({
+                "label": c.label,
+                "kind": kind,
+                "detail": c.detail,
+                "sortText": format!("{rank:04}"),
+                // Clients filter by the text being replaced, which starts with the hole's `@`.
+                "filterText": format!("@{}", c.label),
+                "insertTextFormat": 2,
+                "textEdit": { "range": range, "newText": c.snippet },
+            })
+        })
+        .collect::<Vec<_>>();
+
+    Some(Value::Array(items))
+}
+
+/// Reformats every top-level declaration that parsed without errors.
+///
+/// The printer does not preserve comments, so declarations that contain one are left untouched, as
+/// is the text between declarations.
+fn formatting(doc: &Document) -> Option<Value> {
+    let module = doc.module()?;
+    let comments = comments(doc.source);
+
+    let edits = module
+        .value
+        .declarations
+        .iter()
+        .filter(|decl| !decl.has_error)
+        .filter_map(|decl| {
+            let (start, end) = (decl.range.0.absolute, decl.range.1.absolute);
+            if comments
+                .iter()
+                .any(|c| c.range.start < end && start < c.range.end)
+            {
+                return None;
+            }
+
+            let original = &doc.source[start..end];
+
+            let mut printed = print_declaration(&decl.value);
+            // The terminating `;` belongs to the declaration only if the parser included it.
+            if !original.trim_end().ends_with(';') {
+                printed.pop();
+            }
+
+            (printed != original)
+                .then(|| json!({ "range": lsp_range(&decl.range), "newText": printed }))
+        })
+        .collect();
+
+    Some(Value::Array(edits))
+}
@@ -0,0 +1,674 @@
+//! A uniform, borrowed view of any node in the surface AST.
+//!
+//! [`NodeRef`] erases the type of a node so that generic tooling (position queries, tree printers,
+//! diffs) can walk the tree without matching on every variant itself. Each child is reached through
+//! a [`PathSegment`] naming the field it is stored in, and a sequence of segments from the module
+//! root forms a [`NodePath`].
+
+use std::{fmt, ops::ControlFlow, str::FromStr};
+
+use seglisp::parse::ParseNode;
+
+use crate::{
+    Assignment, BindingPattern, Declaration, Expression, GenericParameter, InterfaceField, Module,
+    ParameterDeclaration, ParsedVec, RecordBindingElement, RecordElement, Statement, Type,
+    TypeConstraint, Verbatim,
+};
+
+/// A reference to any node of the surface AST, together with its span.
+#[derive(Debug, Clone, Copy)]
+pub enum NodeRef<'a, 'ast> {
+    Module(&'a ParseNode<Module<'ast>>),
+    Declaration(&'a ParseNode<Declaration<'ast>>),
+    GenericParameter(&'a ParseNode<GenericParameter<'ast>>),
+    InterfaceField(&'a ParseNode<InterfaceField<'ast>>),
+    Expression(&'a ParseNode<Expression<'ast>>),
+    TypeConstraint(&'a ParseNode<TypeConstraint<'ast>>),
+    Type(&'a ParseNode<Type<'ast>>),
+    Parameter(&'a ParseNode<ParameterDeclaration<'ast>>),
+    Assignment(&'a ParseNode<Assignment<'ast>>),
+    RecordElement(&'a ParseNode<RecordElement<'ast>>),
+    Statement(&'a ParseNode<Statement<'ast>>),
+    BindingPattern(&'a ParseNode<BindingPattern<'ast>>),
+    RecordBindingElement(&'a ParseNode<RecordBindingElement<'ast>>),
+    /// An identifier token that is not itself an expression (see
+    /// [`Visit::visit_identifier`](crate::visit::Visit::visit_identifier)).
+    Identifier(&'a Verbatim<'ast>),
+}
+
+/// One step from a node to one of its children: the name of the field, and the index within that
+/// field if it holds a list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PathSegment {
+    pub field: &'static str,
+    pub index: Option<usize>,
+}
+
+impl PathSegment {
+    pub fn field(field: &'static str) -> Self {
+        PathSegment { field, index: None }
+    }
+
+    pub fn indexed(field: &'static str, index: usize) -> Self {
+        PathSegment {
+            field,
+            index: Some(index),
+        }
+    }
+}
+
+impl fmt::Display for PathSegment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.index {
+            Some(index) => write!(f, "{}[{}]", self.field, index),
+            None => write!(f, "{}", self.field),
+        }
+    }
+}
+
+/// A path from the root of a module to one of its nodes, written as `declarations[2].body.left`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct NodePath(pub Vec<PathSegment>);
+
+impl NodePath {
+    pub fn root() -> Self {
+        NodePath(Vec::new())
+    }
+
+    pub fn segments(&self) -> &[PathSegment] {
+        &self.0
+    }
+
+    pub fn child(&self, segment: PathSegment) -> Self {
+        let mut path = self.clone();
+        path.0.push(segment);
+        path
+    }
+
+    pub fn parent(&self) -> Option<Self> {
+        let (_, parent) = self.0.split_last()?;
+        Some(NodePath(parent.to_vec()))
+    }
+
+    pub fn starts_with(&self, other: &NodePath) -> bool {
+        self.0.starts_with(&other.0)
+    }
+}
+
+impl fmt::Display for NodePath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (idx, segment) in self.0.iter().enumerate() {
+            if idx > 0 {
+                write!(f, ".")?;
+            }
+            write!(f, "{segment}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The error returned when a string cannot be parsed as a [`NodePath`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodePathError(pub String);
+
+impl fmt::Display for NodePathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid node path segment '{}'", self.0)
+    }
+}
+
+impl std::error::Error for NodePathError {}
+
+impl FromStr for NodePath {
+    type Err = NodePathError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Ok(NodePath::root());
+        }
+
+        s.split('.')
+            .map(|segment| {
+                let err = || NodePathError(segment.to_string());
+
+                let (name, index) = match segment.split_once('[') {
+                    Some((name, rest)) => {
+                        let index = rest
+                            .strip_suffix(']')
+                            .and_then(|i| i.parse().ok())
+                            .ok_or_else(err)?;
+                        (name, Some(index))
+                    }
+                    None => (segment, None),
+                };
+
+                let field = field_name(name).ok_or_else(err)?;
+
+                Ok(PathSegment { field, index })
+            })
+            .collect::<Result<_, _>>()
+            .map(NodePath)
+    }
+}
+
+/// The field name `name`, if it can appear in a [`PathSegment`].
+pub(crate) fn field_name(name: &str) -> Option<&'static str> {
+    FIELD_NAMES.iter().copied().find(|f| *f == name)
+}
+
+/// Every field name that can appear in a [`PathSegment`].
+const FIELD_NAMES: &[&str] = &[
+    "declarations",
+    "identifier",
+    "name",
+    "type_",
+    "value",
+    "generic_parameters",
+    "parameters",
+    "constraint",
+    "body",
+    "pattern",
+    "patterns",
+    "elements",
+    "expr",
+    "expression",
+    "left",
+    "right",
+    "accessee",
+    "index",
+    "callee",
+    "bindings",
+    "condition",
+    "then",
+    "_else",
+    "field",
+    "members",
+    "return_type",
+    "symbol",
+    "key",
+    "assignment",
+    "binding",
+    "iterator",
+];
+
+impl<'a, 'ast> NodeRef<'a, 'ast> {
+    /// The syntactic category of the node, e.g. `"Expression"` or `"Statement"`.
+    pub fn category(&self) -> &'static str {
+        match self {
+            NodeRef::Module(_) => "Module",
+            NodeRef::Declaration(_) => "Declaration",
+            NodeRef::GenericParameter(_) => "GenericParameter",
+            NodeRef::InterfaceField(_) => "InterfaceField",
+            NodeRef::Expression(_) => "Expression",
+            NodeRef::TypeConstraint(_) => "TypeConstraint",
+            NodeRef::Type(_) => "Type",
+            NodeRef::Parameter(_) => "ParameterDeclaration",
+            NodeRef::Assignment(_) => "Assignment",
+            NodeRef::RecordElement(_) => "RecordElement",
+            NodeRef::Statement(_) => "Statement",
+            NodeRef::BindingPattern(_) => "BindingPattern",
+            NodeRef::RecordBindingElement(_) => "RecordBindingElement",
+            NodeRef::Identifier(_) => "Identifier",
+        }
+    }
+
+    /// The variant of the node within its category, e.g. `"Call"` for an [`Expression::Call`].
+    /// For nodes that are not enums, this is the same as [`NodeRef::category`].
+    pub fn kind(&self) -> &'static str {
+        match self {
+            NodeRef::Declaration(node) => match &node.value {
+                Declaration::Main { .. } => "Main",
+                Declaration::Const { .. } => "Const",
+                Declaration::Function { .. } => "Function",
+                Declaration::Import { .. } => "Import",
+                Declaration::Export { .. } => "Export",
+                Declaration::TypeAlias { .. } => "TypeAlias",
+                Declaration::Interface { .. } => "Interface",
+            },
+            NodeRef::Expression(node) => match &node.value {
+                Expression::Number(_) => "Number",
+                Expression::String(_) => "String",
+                Expression::Boolean(_) => "Boolean",
+                Expression::Name(_) => "Name",
+                Expression::Hole => "Hole",
+                Expression::None => "None",
+                Expression::As { .. } => "As",
+                Expression::Unary { .. } => "Unary",
+                Expression::Compare { .. } => "Compare",
+                Expression::Arithmetic { .. } => "Arithmetic",
+                Expression::Accessor { .. } => "Accessor",
+                Expression::Function { .. } => "Function",
+                Expression::Call { .. } => "Call",
+                Expression::With { .. } => "With",
+                Expression::Tuple { .. } => "Tuple",
+                Expression::List { .. } => "List",
+                Expression::Procedure { .. } => "Procedure",
+                Expression::If { .. } => "If",
+                Expression::Record { .. } => "Record",
+                Expression::FieldAccess { .. } => "FieldAccess",
+            },
+            NodeRef::Type(node) => match &node.value {
+                Type::Kind => "Kind",
+                Type::Never => "Never",
+                Type::Unknown => "Unknown",
+                Type::Reference { .. } => "Reference",
+                Type::Union { .. } => "Union",
+                Type::Tuple { .. } => "Tuple",
+                Type::Function { .. } => "Function",
+            },
+            NodeRef::RecordElement(node) => match &node.value {
+                RecordElement::KeyValuePair { .. } => "KeyValuePair",
+                RecordElement::Identifier { .. } => "Identifier",
+                RecordElement::Spread { .. } => "Spread",
+            },
+            NodeRef::Statement(node) => match &node.value {
+                Statement::Let { .. } => "Let",
+                Statement::Set(_) => "Set",
+                Statement::If { .. } => "If",
+                Statement::ForIn { .. } => "ForIn",
+                Statement::Forever(_) => "Forever",
+                Statement::Do(_) => "Do",
+                Statement::Break => "Break",
+                Statement::Continue => "Continue",
+                Statement::Pass => "Pass",
+                Statement::Expression(_) => "Expression",
+            },
+            NodeRef::BindingPattern(node) => match &node.value {
+                BindingPattern::Identifier { .. } => "Identifier",
+                BindingPattern::Tuple { .. } => "Tuple",
+                BindingPattern::Record { .. } => "Record",
+            },
+            NodeRef::RecordBindingElement(node) => match &node.value {
+                RecordBindingElement::Identifier { .. } => "Identifier",
+                RecordBindingElement::KeyValuePair { .. } => "KeyValuePair",
+                RecordBindingElement::Rest { .. } => "Rest",
+            },
+            other => other.category(),
+        }
+    }
+
+    /// The source range covered by the node.
+    pub fn range(&self) -> seglisp::Range {
+        match self {
+            NodeRef::Module(n) => n.range,
+            NodeRef::Declaration(n) => n.range,
+            NodeRef::GenericParameter(n) => n.range,
+            NodeRef::InterfaceField(n) => n.range,
+            NodeRef::Expression(n) => n.range,
+            NodeRef::TypeConstraint(n) => n.range,
+            NodeRef::Type(n) => n.range,
+            NodeRef::Parameter(n) => n.range,
+            NodeRef::Assignment(n) => n.range,
+            NodeRef::RecordElement(n) => n.range,
+            NodeRef::Statement(n) => n.range,
+            NodeRef::BindingPattern(n) => n.range,
+            NodeRef::RecordBindingElement(n) => n.range,
+            NodeRef::Identifier(n) => n.range,
+        }
+    }
+
+    /// Returns true if this node and `other` are the same node in memory.
+    pub fn ptr_eq(&self, other: &NodeRef<'_, '_>) -> bool {
+        fn addr<T>(r: &T) -> *const () {
+            r as *const T as *const ()
+        }
+
+        let address = |n: &NodeRef<'_, '_>| match n {
+            NodeRef::Module(n) => addr(*n),
+            NodeRef::Declaration(n) => addr(*n),
+            NodeRef::GenericParameter(n) => addr(*n),
+            NodeRef::InterfaceField(n) => addr(*n),
+            NodeRef::Expression(n) => addr(*n),
+            NodeRef::TypeConstraint(n) => addr(*n),
+            NodeRef::Type(n) => addr(*n),
+            NodeRef::Parameter(n) => addr(*n),
+            NodeRef::Assignment(n) => addr(*n),
+            NodeRef::RecordElement(n) => addr(*n),
+            NodeRef::Statement(n) => addr(*n),
+            NodeRef::BindingPattern(n) => addr(*n),
+            NodeRef::RecordBindingElement(n) => addr(*n),
+            NodeRef::Identifier(n) => addr(*n),
+        };
+
+        self.category() == other.category() && address(self) == address(other)
+    }
+
+    /// The direct children of this node, in source order, with the path segment that leads to
+    /// each of them.
+    pub fn children(&self) -> Vec<(PathSegment, NodeRef<'a, 'ast>)> {
+        let mut children = Vec::new();
+
+        let _ = self.for_each_child(|segment, child| {
+            children.push((segment, child));
+            ControlFlow::Continue(())
+        });
+
+        children
+    }
+
+    /// Calls `f` with each direct child of this node, in source order, and the path segment that
+    /// leads to it, until `f` breaks. This is the traversal that [`Visit`](crate::visit::Visit)
+    /// walks.
+    pub fn for_each_child(
+        &self,
+        f: impl FnMut(PathSegment, NodeRef<'a, 'ast>) -> ControlFlow<()>,
+    ) -> ControlFlow<()> {
+        let mut c = Children {
+            f,
+            flow: ControlFlow::Continue(()),
+        };
+
+        match *self {
+            NodeRef::Module(node) => c.list(
+                "declarations",
+                &node.value.declarations,
+                NodeRef::Declaration,
+            ),
+            NodeRef::Declaration(node) => match &node.value {
+                Declaration::Main { body, .. } => c.expr("body", body),
+                Declaration::Const {
+                    identifier,
+                    type_,
+                    value,
+                    ..
+                } => {
+                    c.ident("identifier", identifier);
+                    c.constraint("type_", type_);
+                    c.expr("value", value);
+                }
+                Declaration::Function {
+                    identifier,
+                    generic_parameters,
+                    parameters,
+                    constraint,
+                    body,
+                    ..
+                } => {
+                    c.ident("identifier", identifier);
+                    c.generics(generic_parameters);
+                    c.parsed_list("parameters", parameters, NodeRef::Parameter);
+                    c.constraint("constraint", constraint);
+                    c.expr("body", body);
+                }
+                Declaration::Import { pattern, .. } => {
+                    c.push("pattern", NodeRef::BindingPattern(pattern))
+                }
+                Declaration::Export { elements, .. } => {
+                    c.parsed_list("elements", elements, NodeRef::RecordElement)
+                }
+                Declaration::TypeAlias {
+                    name,
+                    generic_parameters,
+                    value,
+                    ..
+                } => {
+                    c.ident("name", name);
+                    c.generics(generic_parameters);
+                    c.push("value", NodeRef::Type(value));
+                }
+                Declaration::Interface {
+                    name,
+                    generic_parameters,
+                    constraint,
+                    body,
+                    ..
+                } => {
+                    c.ident("name", name);
+                    c.generics(generic_parameters);
+                    c.constraint("constraint", constraint);
+                    c.parsed_list("body", body, NodeRef::InterfaceField);
+                }
+            },
+            NodeRef::GenericParameter(node) => {
+                c.ident("name", &node.value.name);
+                c.constraint("constraint", &node.value.constraint);
+            }
+            NodeRef::InterfaceField(node) => {
+                c.ident("name", &node.value.name);
+                c.push(
+                    "constraint",
+                    NodeRef::TypeConstraint(&node.value.constraint),
+                );
+            }
+            NodeRef::Expression(node) => match &node.value {
+                Expression::Number(_)
+                | Expression::String(_)
+                | Expression::Boolean(_)
+                | Expression::Name(_)
+                | Expression::Hole
+                | Expression::None => {}
+                Expression::As { expr, type_, .. } => {
+                    c.expr("expr", expr);
+                    c.push("type_", NodeRef::Type(type_));
+                }
+                Expression::Unary { expression, .. } => c.expr("expression", expression),
+                Expression::Compare { left, right, .. }
+                | Expression::Arithmetic { left, right, .. } => {
+                    c.expr("left", left);
+                    c.expr("right", right);
+                }
+                Expression::Accessor { accessee, index } => {
+                    c.expr("accessee", accessee);
+                    c.expr("index", index);
+                }
+                Expression::Function {
+                    name,
+                    generic_parameters,
+                    parameters,
+                    constraint,
+                    body,
+                    ..
+                } => {
+                    if let Some(name) = name {
+                        c.ident("name", name);
+                    }
+                    c.generics(generic_parameters);
+                    c.parsed_list("parameters", parameters, NodeRef::Parameter);
+                    c.constraint("constraint", constraint);
+                    c.expr("body", body);
+                }
+                Expression::Call { callee, parameters } => {
+                    c.expr("callee", callee);
+                    c.parsed_list("parameters", parameters, NodeRef::Expression);
+                }
+                Expression::With { bindings, body, .. } => {
+                    c.parsed_list("bindings", bindings, NodeRef::Assignment);
+                    c.expr("body", body);
+                }
+                Expression::Tuple { elements } | Expression::List { elements } => {
+                    c.parsed_list("elements", elements, NodeRef::Expression)
+                }
+                Expression::Procedure { body } => c.parsed_list("body", body, NodeRef::Statement),
+                Expression::If {
+                    condition,
+                    then,
+                    _else,
+                    ..
+                } => {
+                    c.expr("condition", condition);
+                    c.expr("then", then);
+                    c.expr("_else", _else);
+                }
+                Expression::Record { elements } => {
+                    c.parsed_list("elements", elements, NodeRef::RecordElement)
+                }
+                Expression::FieldAccess { accessee, field } => {
+                    c.expr("accessee", accessee);
+                    c.ident("field", field);
+                }
+            },
+            NodeRef::TypeConstraint(node) => c.push("type_", NodeRef::Type(&node.value.type_)),
+            NodeRef::Type(node) => match &node.value {
+                Type::Kind | Type::Never | Type::Unknown => {}
+                Type::Reference {
+                    name,
+                    generic_parameters,
+                } => {
+                    c.ident("name", name);
+                    if let Some(parameters) = generic_parameters {
+                        c.parsed_list("generic_parameters", parameters, NodeRef::Type);
+                    }
+                }
+                Type::Union { left, right } => {
+                    c.push("left", NodeRef::Type(left));
+                    c.push("right", NodeRef::Type(right));
+                }
+                Type::Tuple { members } => c.parsed_list("members", members, NodeRef::Type),
+                Type::Function {
+                    parameters,
+                    return_type,
+                    ..
+                } => {
+                    c.parsed_list("parameters", parameters, NodeRef::Type);
+                    c.push("return_type", NodeRef::Type(return_type));
+                }
+            },
+            NodeRef::Parameter(node) => {
+                c.ident("name", &node.value.name);
+                c.constraint("type_", &node.value.type_);
+            }
+            NodeRef::Assignment(node) => {
+                c.ident("symbol", &node.value.symbol);
+                c.expr("value", &node.value.value);
+            }
+            NodeRef::RecordElement(node) => match &node.value {
+                RecordElement::KeyValuePair { key, value } => {
+                    c.ident("key", key);
+                    c.expr("value", value);
+                }
+                RecordElement::Identifier { name } => c.ident("name", name),
+                RecordElement::Spread { value } => c.expr("value", value),
+            },
+            NodeRef::Statement(node) => match &node.value {
+                Statement::Let { assignment, .. } | Statement::Set(assignment) => {
+                    c.push("assignment", NodeRef::Assignment(assignment))
+                }
+                Statement::If {
+                    condition,
+                    then,
+                    _else,
+                    ..
+                } => {
+                    c.expr("condition", condition);
+                    c.push("then", NodeRef::Statement(then));
+                    if let Some(_else) = _else {
+                        c.push("_else", NodeRef::Statement(_else));
+                    }
+                }
+                Statement::ForIn {
+                    binding,
+                    iterator,
+                    body,
+                    ..
+                } => {
+                    c.ident("binding", binding);
+                    c.expr("iterator", iterator);
+                    c.push("body", NodeRef::Statement(body));
+                }
+                Statement::Forever(body) => c.push("body", NodeRef::Statement(body)),
+                Statement::Do(body) => c.expr("body", body),
+                Statement::Expression(expression) => c.expr("expression", expression),
+                Statement::Break | Statement::Continue | Statement::Pass => {}
+            },
+            NodeRef::BindingPattern(node) => match &node.value {
+                BindingPattern::Identifier { name } => c.ident("name", name),
+                BindingPattern::Tuple { patterns } => {
+                    c.parsed_list("patterns", patterns, NodeRef::BindingPattern)
+                }
+                BindingPattern::Record { elements } => {
+                    c.parsed_list("elements", elements, NodeRef::RecordBindingElement)
+                }
+            },
+            NodeRef::RecordBindingElement(node) => match &node.value {
+                RecordBindingElement::Identifier { name } | RecordBindingElement::Rest { name } => {
+                    c.ident("name", name)
+                }
+                RecordBindingElement::KeyValuePair { name, pattern } => {
+                    c.ident("name", name);
+                    c.push("pattern", NodeRef::BindingPattern(pattern));
+                }
+            },
+            NodeRef::Identifier(_) => {}
+        }
+
+        c.flow
+    }
+
+    /// Follows a single path segment to a child of this node.
+    pub fn child(&self, segment: &PathSegment) -> Option<NodeRef<'a, 'ast>> {
+        self.children()
+            .into_iter()
+            .find(|(s, _)| s == segment)
+            .map(|(_, node)| node)
+    }
+
+    /// Follows a path from this node to one of its descendants.
+    pub fn descendant(&self, path: &NodePath) -> Option<NodeRef<'a, 'ast>> {
+        path.segments()
+            .iter()
+            .try_fold(*self, |node, segment| node.child(segment))
+    }
+}
+
+/// Passes children to a callback until it breaks.
+struct Children<F> {
+    f: F,
+    flow: ControlFlow<()>,
+}
+
+impl<'a, 'ast, F: FnMut(PathSegment, NodeRef<'a, 'ast>) -> ControlFlow<()>> Children<F> {
+    fn emit(&mut self, segment: PathSegment, node: NodeRef<'a, 'ast>) {
+        if self.flow.is_continue() {
+            self.flow = (self.f)(segment, node);
+        }
+    }
+
+    fn push(&mut self, field: &'static str, node: NodeRef<'a, 'ast>) {
+        self.emit(PathSegment::field(field), node);
+    }
+
+    fn expr(&mut self, field: &'static str, node: &'a ParseNode<Expression<'ast>>) {
+        self.push(field, NodeRef::Expression(node));
+    }
+
+    fn ident(&mut self, field: &'static str, node: &'a Verbatim<'ast>) {
+        self.push(field, NodeRef::Identifier(node));
+    }
+
+    fn constraint(
+        &mut self,
+        field: &'static str,
+        node: &'a Option<ParseNode<TypeConstraint<'ast>>>,
+    ) {
+        if let Some(node) = node {
+            self.push(field, NodeRef::TypeConstraint(node));
+        }
+    }
+
+    fn generics(&mut self, generics: &'a Option<ParsedVec<GenericParameter<'ast>>>) {
+        if let Some(generics) = generics {
+            self.parsed_list("generic_parameters", generics, NodeRef::GenericParameter);
+        }
+    }
+
+    fn parsed_list<T>(
+        &mut self,
+        field: &'static str,
+        list: &'a ParsedVec<T>,
+        f: impl Fn(&'a ParseNode<T>) -> NodeRef<'a, 'ast>,
+    ) {
+        self.list(field, &list.value, f)
+    }
+
+    fn list<T>(
+        &mut self,
+        field: &'static str,
+        list: &'a [ParseNode<T>],
+        f: impl Fn(&'a ParseNode<T>) -> NodeRef<'a, 'ast>,
+    ) {
+        for (idx, node) in list.iter().enumerate() {
+            self.emit(PathSegment::indexed(field, idx), f(node));
+        }
+    }
+}
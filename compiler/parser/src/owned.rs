@@ -0,0 +1,151 @@
+//! Parsed modules that own their source.
+//!
+//! The AST borrows every name and token from the text it was parsed from, so a [`ParsedModule`]
+//! cannot outlive that text. An [`OwnedModule`] keeps the tree together with its source in a shared
+//! `Arc<str>`, which makes it `'static`, `Send`, and `Sync`: it can be kept in a long-lived cache,
+//! stored in a struct, or handed to another thread.
+//!
+//! Analyses take a `&ParseNode<Module<'ast>>` and run on either form. [`OwnedModule::module`] lends
+//! out the owned tree with a lifetime bounded by the borrow of the `OwnedModule`.
+
+use std::sync::Arc;
+
+use seglisp::{parse::ParseNode, Diagnostic};
+
+use crate::{
+    incremental::{
+        apply_edits, parse_module, relocate, reparse, ParsedModule, TextEdit, TextEditError,
+    },
+    Module,
+};
+
+/// A parsed module and the source it borrows from.
+#[derive(Debug, Clone)]
+pub struct OwnedModule {
+    // `parsed` borrows from `source`, so it is declared first to be dropped first. The `'static`
+    // lifetime never leaves this struct: every accessor shortens it to the borrow of `self`.
+    // Clones share the same `source`, so their trees stay valid too.
+    parsed: ParsedModule<'static>,
+    source: Arc<str>,
+}
+
+impl OwnedModule {
+    /// Reads and parses all of `source` as a module.
+    pub fn parse(source: impl Into<Arc<str>>) -> Self {
+        let source = source.into();
+
+        // SAFETY: `text` points into the allocation behind `source`, which outlives every use of
+        // the tree:
+        // - `Arc<str>` only gives shared access, so the text is never mutated or moved, even when
+        //   the `OwnedModule` itself is moved;
+        // - the allocation is freed only when the last clone of the `Arc` is dropped, and each
+        //   `OwnedModule` holds a clone for as long as it holds a tree borrowing from it, dropping
+        //   the tree first;
+        // - `parsed` is only replaced together with `source` (see `apply_edits`);
+        // - the `'static` lifetime never leaves the struct, as every accessor shortens it to the
+        //   borrow of `self`, so no reference into the tree can outlive the `OwnedModule`.
+        let text: &'static str = unsafe { &*(source.as_ref() as *const str) };
+
+        OwnedModule {
+            parsed: parse_module(text),
+            source,
+        }
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The source, shared rather than copied.
+    pub fn shared_source(&self) -> Arc<str> {
+        self.source.clone()
+    }
+
+    /// The parsed module, or `None` if the source could not be parsed.
+    pub fn module(&self) -> Option<&ParseNode<Module<'_>>> {
+        self.parsed.module.as_ref()
+    }
+
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.parsed.diagnostics
+    }
+
+    /// The tree and diagnostics in their borrowed form.
+    pub fn parsed(&self) -> &ParsedModule<'_> {
+        &self.parsed
+    }
+
+    /// Applies `edits` to the source and reparses it, reusing the declarations the edits did not
+    /// touch. See [`reparse`].
+    pub fn apply_edits(&mut self, edits: &[TextEdit]) -> Result<(), TextEditError> {
+        let source: Arc<str> = apply_edits(&self.source, edits)?.into();
+
+        // SAFETY: `text` points into the new `source`, which is stored next to the new tree below
+        // and upholds the invariants described in `OwnedModule::parse`. The signature of `reparse`
+        // ensures the new tree borrows only from `text`: the declarations it reuses from
+        // `previous` are moved over to the new text. `previous` borrows from the old source,
+        // which stays in `self.source` until `reparse` has consumed it. If `reparse` panics, the
+        // module is left with an empty tree and its old source.
+        let text: &'static str = unsafe { &*(source.as_ref() as *const str) };
+
+        let previous = std::mem::replace(
+            &mut self.parsed,
+            ParsedModule {
+                module: None,
+                diagnostics: Vec::new(),
+            },
+        );
+
+        self.parsed = reparse(previous, &self.source, text, edits);
+        self.source = source;
+
+        Ok(())
+    }
+}
+
+impl<'ast> ParsedModule<'ast> {
+    /// Copies `source`, which this module was parsed from, and moves the tree over to the copy.
+    ///
+    /// The tree is not parsed again unless some of its strings do not point into `source`.
+    pub fn into_static(self, source: &'ast str) -> OwnedModule {
+        let copy: Arc<str> = source.into();
+
+        // SAFETY: `text` points into `copy`, which is stored next to the tree below and upholds
+        // the invariants described in `OwnedModule::parse`. `relocate` moves every string of the
+        // tree from `source` over to `text`, or fails, in which case `copy` is parsed afresh; so
+        // the result does not borrow from `source`, which may be dropped afterwards.
+        let text: &'static str = unsafe { &*(copy.as_ref() as *const str) };
+
+        let ParsedModule {
+            module,
+            diagnostics,
+        } = self;
+
+        let module = match module {
+            Some(module) => match relocate(module, source, text) {
+                Some(module) => Some(module),
+                None => return OwnedModule::parse(copy),
+            },
+            None => None,
+        };
+
+        OwnedModule {
+            parsed: ParsedModule {
+                module,
+                diagnostics,
+            },
+            source: copy,
+        }
+    }
+
+    /// Like [`ParsedModule::into_static`], but leaves this module as it is.
+    pub fn to_static(&self, source: &'ast str) -> OwnedModule {
+        self.clone().into_static(source)
+    }
+}
+
+/// An `OwnedModule` can be cached and shared between threads.
+const _: fn() = || {
+    fn assert_owned<T: Send + Sync + 'static>() {}
+    assert_owned::<OwnedModule>();
+};
@@ -0,0 +1,979 @@
+//! Structural diffs of modules, and patches to send between collaborators.
+//!
+//! [`diff`] compares two parses of a module and produces a [`Patch`]: a sequence of node-level
+//! [`Operation`]s that insert, delete, move, and update declarations, statements, and expressions.
+//! [`apply`] carries a patch out on a source one operation at a time, as text edits, so that
+//! formatting and comments outside the changed nodes are kept.
+//!
+//! Operations name their targets by [`Anchor`]s rather than by [`NodePath`]s alone. Every list
+//! element along an anchor is identified by its index, its name if it declares one, and the
+//! structural hash it had when the patch was made. When the element at that index no longer
+//! matches, the element with the same hash, or else the same name, is used instead. A patch
+//! therefore still applies after another patch has inserted, deleted, moved, or changed
+//! declarations and statements elsewhere in the module, so two patches over unrelated subtrees can
+//! be applied in either order. A patch that would update, delete, or move a node that has itself
+//! changed since the patch was made is refused.
+//!
+//! Patches are written as text, one operation per line:
+//!
+//! ```text
+//! sdp-patch 1
+//! update $.declarations[1#6c0f3a28e4d1b9a7@double].body 94e1b2c3d4a5f607 "x * 2"
+//! insert $.declarations[0#0b3c2d1e4f5a6978].body body [0#1d2e3f405162738a] "print(x)"
+//! delete $.declarations[2#8a7b6c5d4e3f2a1b@unused]
+//! move $.declarations[3#3e4f5a6b7c8d9e0f@helper] -
+//! ```
+//!
+//! An anchor is `$` followed by the fields leading from the module to the node. A list element is
+//! written as `field[index#hash@name]`. The place an element is inserted or moved to is given by
+//! the sibling it follows, in the same form, or `-` for the front of the list.
+
+use std::{fmt, hash::Hasher, str::FromStr};
+
+use wasm_bindgen::prelude::*;
+
+use seglisp::{js_interop::JsInterop, parse::ParseNode, DiagnosticSeverity};
+
+use crate::{
+    edit::{delete_statement, insert_into_list, line_indent, reindent, span, EditOutcome, List},
+    incremental::{apply_edits, parse_module, TextEdit},
+    node::{field_name, NodePath, NodeRef, PathSegment},
+    structural::{payload, structural_hash, StableHasher},
+    Expression, Module,
+};
+
+/// The version of the text form of a [`Patch`].
+pub const FORMAT_VERSION: u32 = 1;
+
+/// An element of a list, as it was when a patch was made.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Key {
+    pub index: usize,
+    /// The name the element declares, if it is a named declaration.
+    pub name: Option<String>,
+    /// The structural hash of the element.
+    pub hash: u64,
+}
+
+/// One step of an [`Anchor`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Step {
+    /// The node in a field that holds a single node.
+    Field(&'static str),
+    /// An element of a field that holds a list.
+    Element(&'static str, Key),
+}
+
+/// The location of a node, from the root of a module, that survives changes around it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Anchor(pub Vec<Step>);
+
+impl Anchor {
+    pub fn root() -> Self {
+        Anchor(Vec::new())
+    }
+
+    fn child(&self, step: Step) -> Self {
+        let mut anchor = self.clone();
+        anchor.0.push(step);
+        anchor
+    }
+
+    /// The path the anchor leads to if nothing has moved.
+    pub fn path(&self) -> NodePath {
+        NodePath(
+            self.0
+                .iter()
+                .map(|step| match step {
+                    Step::Field(field) => PathSegment::field(field),
+                    Step::Element(field, key) => PathSegment::indexed(field, key.index),
+                })
+                .collect(),
+        )
+    }
+}
+
+/// A change to one node of a module.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operation {
+    /// Replaces the node at `target`, which must still have the structural hash `hash`, with
+    /// `text`.
+    Update {
+        target: Anchor,
+        hash: u64,
+        text: String,
+    },
+    /// Inserts `text` into the list `field` of the node at `parent`, right after the element
+    /// `after`, or at the front if there is none.
+    Insert {
+        parent: Anchor,
+        field: &'static str,
+        after: Option<Key>,
+        text: String,
+    },
+    /// Deletes the declaration or statement at `target`.
+    Delete { target: Anchor },
+    /// Moves the declaration or statement at `target` to right after the element `after` of the
+    /// same list, or to its front if there is none.
+    Move { target: Anchor, after: Option<Key> },
+}
+
+/// A sequence of operations that turns one version of a module into another.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Patch {
+    pub operations: Vec<Operation>,
+}
+
+impl Patch {
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+}
+
+/// The error returned when a patch cannot be read or applied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatchError(pub String);
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for PatchError {}
+
+fn hash(node: NodeRef) -> u64 {
+    let mut hasher = StableHasher::default();
+    structural_hash(node, &mut hasher);
+    hasher.finish()
+}
+
+/// The name a declaration declares, if any.
+fn name(node: &NodeRef) -> Option<String> {
+    let NodeRef::Declaration(_) = node else {
+        return None;
+    };
+
+    ["identifier", "name"].into_iter().find_map(|field| {
+        match node.child(&PathSegment::field(field)) {
+            Some(NodeRef::Identifier(identifier)) => Some(identifier.value.to_string()),
+            _ => None,
+        }
+    })
+}
+
+fn key(index: usize, node: &NodeRef) -> Key {
+    Key {
+        index,
+        name: name(node),
+        hash: hash(*node),
+    }
+}
+
+/// Whether the list `field` of `node` is a sequence of declarations or statements, whose elements
+/// can be inserted, deleted, and moved. The elements of other lists are only ever updated.
+fn is_sequence(node: &NodeRef, field: &str) -> bool {
+    match node {
+        NodeRef::Module(_) => field == "declarations",
+        NodeRef::Expression(ParseNode {
+            value: Expression::Procedure { .. },
+            ..
+        }) => field == "body",
+        _ => false,
+    }
+}
+
+/// The children of a node grouped by field, in source order.
+fn fields<'a, 'ast>(node: &NodeRef<'a, 'ast>) -> Vec<(&'static str, Vec<NodeRef<'a, 'ast>>)> {
+    let mut fields: Vec<(&'static str, Vec<NodeRef>)> = Vec::new();
+
+    for (segment, child) in node.children() {
+        match fields.last_mut() {
+            Some((field, children)) if *field == segment.field => children.push(child),
+            _ => fields.push((segment.field, vec![child])),
+        }
+    }
+
+    fields
+}
+
+/// The elements of the list `field` of `node`.
+fn elements<'a, 'ast>(node: &NodeRef<'a, 'ast>, field: &str) -> Vec<NodeRef<'a, 'ast>> {
+    node.children()
+        .into_iter()
+        .filter(|(segment, _)| segment.field == field && segment.index.is_some())
+        .map(|(_, child)| child)
+        .collect()
+}
+
+/// Works out the operations that turn `old` into `new`, which was parsed from `new_source`.
+pub fn diff(old: &ParseNode<Module>, new: &ParseNode<Module>, new_source: &str) -> Patch {
+    let mut differ = Differ {
+        new_source,
+        operations: Vec::new(),
+    };
+    differ.node(&Anchor::root(), NodeRef::Module(old), NodeRef::Module(new));
+
+    Patch {
+        operations: differ.operations,
+    }
+}
+
+/// Parses both sources and works out the operations that turn `old` into `new`.
+pub fn diff_sources(old: &str, new: &str) -> Result<Patch, PatchError> {
+    let unparsable = |which| PatchError(format!("the {which} module could not be parsed"));
+
+    let old = parse_module(old);
+    let old = old.module.as_ref().ok_or_else(|| unparsable("old"))?;
+    let parsed = parse_module(new);
+    let module = parsed.module.as_ref().ok_or_else(|| unparsable("new"))?;
+
+    Ok(diff(old, module, new))
+}
+
+/// What became of an element of the old list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Fate {
+    /// It is the same, in the same order relative to the other kept elements.
+    Kept(usize),
+    /// It is the same, but elsewhere in the list.
+    Moved(usize),
+    /// It changed in place into the element of the new list at this index.
+    Changed(usize),
+    Deleted,
+}
+
+struct Differ<'s> {
+    new_source: &'s str,
+    operations: Vec<Operation>,
+}
+
+// Operations are emitted in reverse source order, so that the indices in the anchors of those that
+// come later still hold when they are applied: every element an operation is anchored on has not
+// been touched yet, or was only changed inside, which leaves its index alone.
+impl Differ<'_> {
+    /// The text of a node of the new module, with the indentation of its first line taken off the
+    /// lines after it.
+    fn text(&self, node: &NodeRef) -> String {
+        let (start, end) = span(node.range());
+        let indent = line_indent(self.new_source, start);
+        let text = &self.new_source[start..end];
+
+        if indent.is_empty() {
+            text.to_string()
+        } else {
+            text.replace(&format!("\n{indent}"), "\n")
+        }
+    }
+
+    fn node(&mut self, anchor: &Anchor, old: NodeRef, new: NodeRef) {
+        if hash(old) == hash(new) {
+            return;
+        }
+
+        let old_fields = fields(&old);
+        let new_fields = fields(&new);
+
+        // Lists other than sequences must keep their length, since only sequences can have
+        // elements inserted and deleted.
+        let same_shape = old.category() == new.category()
+            && old.kind() == new.kind()
+            && payload(&old) == payload(&new)
+            && old_fields.len() == new_fields.len()
+            && old_fields
+                .iter()
+                .zip(&new_fields)
+                .all(|((a, a_children), (b, b_children))| {
+                    a == b && (is_sequence(&old, a) || a_children.len() == b_children.len())
+                });
+
+        if !same_shape {
+            self.operations.push(Operation::Update {
+                target: anchor.clone(),
+                hash: hash(old),
+                text: self.text(&new),
+            });
+            return;
+        }
+
+        let indexed: Vec<bool> = old
+            .children()
+            .iter()
+            .map(|(segment, _)| segment.index.is_some())
+            .collect();
+        let mut first = indexed.len();
+
+        for ((field, old_children), (_, new_children)) in
+            old_fields.into_iter().zip(new_fields).rev()
+        {
+            first -= old_children.len();
+
+            if is_sequence(&old, field) {
+                self.sequence(anchor, field, &old_children, &new_children);
+            } else if !indexed[first] {
+                self.node(
+                    &anchor.child(Step::Field(field)),
+                    old_children[0],
+                    new_children[0],
+                );
+            } else {
+                for (idx, (a, b)) in old_children.iter().zip(&new_children).enumerate().rev() {
+                    self.node(&anchor.child(Step::Element(field, key(idx, a))), *a, *b);
+                }
+            }
+        }
+    }
+
+    fn sequence(&mut self, parent: &Anchor, field: &'static str, old: &[NodeRef], new: &[NodeRef]) {
+        let old_hashes: Vec<u64> = old.iter().map(|node| hash(*node)).collect();
+        let new_hashes: Vec<u64> = new.iter().map(|node| hash(*node)).collect();
+
+        let mut fates = vec![Fate::Deleted; old.len()];
+        let mut sources: Vec<Option<Fate>> = vec![None; new.len()];
+
+        for (i, j) in common_subsequence(&old_hashes, &new_hashes) {
+            fates[i] = Fate::Kept(j);
+            sources[j] = Some(Fate::Kept(i));
+        }
+
+        // Unchanged elements that are out of order have moved.
+        for j in 0..new.len() {
+            if sources[j].is_some() {
+                continue;
+            }
+            let moved = (0..old.len())
+                .find(|&i| fates[i] == Fate::Deleted && old_hashes[i] == new_hashes[j]);
+            if let Some(i) = moved {
+                fates[i] = Fate::Moved(j);
+                sources[j] = Some(Fate::Moved(i));
+            }
+        }
+
+        // The remaining elements between two kept ones changed in place if they are of the same
+        // kind and declare the same name, in order.
+        let mut next_old = 0;
+        for j in 0..new.len() {
+            match sources[j] {
+                Some(Fate::Kept(i)) => next_old = i + 1,
+                Some(_) => {}
+                None => {
+                    let bound = sources[j..]
+                        .iter()
+                        .find_map(|source| match source {
+                            Some(Fate::Kept(i)) => Some(*i),
+                            _ => None,
+                        })
+                        .unwrap_or(old.len());
+
+                    let changed = (next_old..bound).find(|&i| {
+                        fates[i] == Fate::Deleted
+                            && old[i].category() == new[j].category()
+                            && old[i].kind() == new[j].kind()
+                            && name(&old[i]) == name(&new[j])
+                    });
+                    if let Some(i) = changed {
+                        fates[i] = Fate::Changed(j);
+                        sources[j] = Some(Fate::Changed(i));
+                        next_old = i + 1;
+                    }
+                }
+            }
+        }
+
+        // New elements are inserted right after the last kept or changed element before them.
+        let mut inserted: Vec<Vec<usize>> = vec![Vec::new(); old.len() + 1];
+        let mut last_placed = 0;
+        for (j, source) in sources.iter().enumerate() {
+            match source {
+                Some(Fate::Kept(i) | Fate::Changed(i)) => last_placed = i + 1,
+                Some(_) => {}
+                None => inserted[last_placed].push(j),
+            }
+        }
+
+        let element = |i: usize| parent.child(Step::Element(field, key(i, &old[i])));
+
+        for i in (0..old.len()).rev() {
+            for &j in inserted[i + 1].iter().rev() {
+                self.operations.push(Operation::Insert {
+                    parent: parent.clone(),
+                    field,
+                    after: Some(key(i, &old[i])),
+                    text: self.text(&new[j]),
+                });
+            }
+
+            match fates[i] {
+                Fate::Changed(j) => self.node(&element(i), old[i], new[j]),
+                Fate::Deleted => self
+                    .operations
+                    .push(Operation::Delete { target: element(i) }),
+                Fate::Kept(_) | Fate::Moved(_) => {}
+            }
+        }
+
+        for &j in inserted[0].iter().rev() {
+            self.operations.push(Operation::Insert {
+                parent: parent.clone(),
+                field,
+                after: None,
+                text: self.text(&new[j]),
+            });
+        }
+
+        // Moves come last, in order, after the element that precedes them in the new list, which
+        // is in its final form and place by then.
+        for (j, source) in sources.iter().enumerate() {
+            if let Some(Fate::Moved(i)) = source {
+                self.operations.push(Operation::Move {
+                    target: element(*i),
+                    after: j.checked_sub(1).map(|prev| key(prev, &new[prev])),
+                });
+            }
+        }
+    }
+}
+
+/// The index pairs of a longest common subsequence of `a` and `b`, in order.
+fn common_subsequence(a: &[u64], b: &[u64]) -> Vec<(usize, usize)> {
+    let width = b.len() + 1;
+    let mut lengths = vec![0usize; (a.len() + 1) * width];
+
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lengths[i * width + j] = if a[i] == b[j] {
+                lengths[(i + 1) * width + j + 1] + 1
+            } else {
+                lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut pairs = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            pairs.push((i, j));
+            i += 1;
+            j += 1;
+        } else if lengths[(i + 1) * width + j] >= lengths[i * width + j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+
+    pairs
+}
+
+/// Applies `patch` to `source`, checking that the result still parses.
+pub fn apply(source: &str, patch: &Patch) -> Result<String, PatchError> {
+    let mut source = source.to_string();
+
+    for (idx, operation) in patch.operations.iter().enumerate() {
+        let parsed = parse_module(&source);
+        let module = parsed
+            .module
+            .as_ref()
+            .ok_or_else(|| PatchError("the module could not be parsed".into()))?;
+
+        let edits = text_edits(&source, module, operation)
+            .map_err(|e| PatchError(format!("operation {}: {}", idx + 1, e.0)))?;
+        let edited = apply_edits(&source, &edits)
+            .map_err(|e| PatchError(format!("operation {}: {}", idx + 1, e.0)))?;
+
+        drop(parsed);
+        source = edited;
+    }
+
+    let errors: Vec<String> = parse_module(&source)
+        .diagnostics
+        .iter()
+        .filter(|d| matches!(d.severity, DiagnosticSeverity::Error))
+        .map(|d| d.message.clone())
+        .collect();
+
+    if !errors.is_empty() {
+        return Err(PatchError(format!(
+            "the patched module does not parse: {}",
+            errors.join("; ")
+        )));
+    }
+
+    Ok(source)
+}
+
+/// Finds the element `key` names: the one at its index if it is unchanged, or else the unchanged
+/// one nearest to its index, or else the one with its name, or else whatever is at its index.
+/// Returns its index, and whether it is unchanged.
+fn find_element(elements: &[NodeRef], key: &Key) -> Option<(usize, bool)> {
+    let unchanged = elements
+        .iter()
+        .enumerate()
+        .filter(|(_, element)| hash(**element) == key.hash)
+        .min_by_key(|(idx, _)| idx.abs_diff(key.index));
+    if let Some((idx, _)) = unchanged {
+        return Some((idx, true));
+    }
+
+    if key.name.is_some() {
+        if let Some(idx) = elements.iter().position(|e| name(e) == key.name) {
+            return Some((idx, false));
+        }
+    }
+
+    (key.index < elements.len()).then_some((key.index, false))
+}
+
+fn resolve<'a, 'ast>(
+    module: &'a ParseNode<Module<'ast>>,
+    anchor: &Anchor,
+) -> Result<NodeRef<'a, 'ast>, PatchError> {
+    let missing = || PatchError(format!("there is no node at '{}'", anchor.path()));
+
+    anchor
+        .0
+        .iter()
+        .try_fold(NodeRef::Module(module), |node, step| match step {
+            Step::Field(field) => node.child(&PathSegment::field(field)).ok_or_else(missing),
+            Step::Element(field, key) => {
+                let elements = elements(&node, field);
+                find_element(&elements, key)
+                    .map(|(idx, _)| elements[idx])
+                    .ok_or_else(missing)
+            }
+        })
+}
+
+/// Resolves the list element at `target`, which must be unchanged. Returns its parent, the list it
+/// is in, and its index there.
+fn resolve_element<'a, 'ast>(
+    module: &'a ParseNode<Module<'ast>>,
+    target: &Anchor,
+) -> Result<(NodeRef<'a, 'ast>, &'static str, usize), PatchError> {
+    let Some((Step::Element(field, key), steps)) = target.0.split_last() else {
+        return Err(PatchError(format!(
+            "'{}' is not a declaration or statement",
+            target.path()
+        )));
+    };
+
+    let parent = resolve(module, &Anchor(steps.to_vec()))?;
+    match find_element(&elements(&parent, field), key) {
+        Some((idx, true)) => Ok((parent, field, idx)),
+        Some((_, false)) => Err(changed(target)),
+        None => Err(PatchError(format!(
+            "there is no node at '{}'",
+            target.path()
+        ))),
+    }
+}
+
+fn changed(target: &Anchor) -> PatchError {
+    PatchError(format!(
+        "'{}' has changed since the patch was made",
+        target.path()
+    ))
+}
+
+/// The index an element inserted after `after` takes in `elements`.
+fn insertion_index(elements: &[NodeRef], after: &Option<Key>) -> Result<usize, PatchError> {
+    match after {
+        None => Ok(0),
+        Some(key) => find_element(elements, key)
+            .map(|(idx, _)| idx + 1)
+            .ok_or_else(|| {
+                PatchError(format!("there is no element {} to insert after", key.index))
+            }),
+    }
+}
+
+/// Inserts `text` as the element at `index` of the list `field` of `parent`.
+fn insert(
+    source: &str,
+    parent: NodeRef,
+    field: &'static str,
+    index: usize,
+    text: &str,
+) -> Result<TextEdit, PatchError> {
+    if !is_sequence(&parent, field) {
+        return Err(PatchError(format!(
+            "elements cannot be inserted into the {field} of {}",
+            parent.kind()
+        )));
+    }
+
+    let spans: Vec<_> = elements(&parent, field)
+        .iter()
+        .map(|e| span(e.range()))
+        .collect();
+    let text = text.trim_end().trim_end_matches(';');
+
+    // A module has no delimiters to insert its first declaration between.
+    if let (NodeRef::Module(_), true) = (parent, spans.is_empty()) {
+        let newline = if source.is_empty() || source.ends_with('\n') {
+            ""
+        } else {
+            "\n"
+        };
+        return Ok(TextEdit {
+            start: source.len(),
+            end: source.len(),
+            text: format!("{newline}{text};\n"),
+        });
+    }
+
+    let list = List {
+        span: span(parent.range()),
+        open: "[",
+        separator: ";",
+    };
+    let path = NodePath::root();
+    insert_into_list(source, &path, &list, &spans, index, text).map_err(|e| PatchError(e.0))
+}
+
+fn text_edits(
+    source: &str,
+    module: &ParseNode<Module>,
+    operation: &Operation,
+) -> Result<Vec<TextEdit>, PatchError> {
+    match operation {
+        Operation::Update {
+            target,
+            hash: expected,
+            text,
+        } => {
+            let node = resolve(module, target)?;
+            if hash(node) != *expected {
+                return Err(changed(target));
+            }
+
+            let (start, end) = span(node.range());
+            Ok(vec![TextEdit {
+                start,
+                end,
+                text: reindent(text, line_indent(source, start)),
+            }])
+        }
+        Operation::Insert {
+            parent,
+            field,
+            after,
+            text,
+        } => {
+            let parent = resolve(module, parent)?;
+            let index = insertion_index(&elements(&parent, field), after)?;
+            insert(source, parent, field, index, text).map(|e| vec![e])
+        }
+        Operation::Delete { target } => {
+            let (parent, field, idx) = resolve_element(module, target)?;
+            let element = elements(&parent, field)[idx];
+            Ok(vec![delete_statement(source, span(element.range()))])
+        }
+        Operation::Move { target, after } => {
+            let (parent, field, idx) = resolve_element(module, target)?;
+            let siblings = elements(&parent, field);
+            let index = insertion_index(&siblings, after)?;
+
+            if index == idx + 1 {
+                return Err(PatchError(format!(
+                    "'{}' cannot be moved after itself",
+                    target.path()
+                )));
+            }
+            // Already in place.
+            if index == idx {
+                return Ok(Vec::new());
+            }
+
+            let (start, end) = span(siblings[idx].range());
+            let indent = line_indent(source, start);
+            let text = source[start..end].replace(&format!("\n{indent}"), "\n");
+
+            Ok(vec![
+                delete_statement(source, (start, end)),
+                insert(source, parent, field, index, &text)?,
+            ])
+        }
+    }
+}
+
+/// Writes `text` as a double-quoted string, escaping quotes, backslashes, and line breaks.
+fn quote(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn unquote(text: &str) -> Option<String> {
+    let inner = text.strip_prefix('"')?.strip_suffix('"')?;
+    let mut unquoted = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unquoted.push(match chars.next()? {
+                '"' => '"',
+                '\\' => '\\',
+                'n' => '\n',
+                'r' => '\r',
+                't' => '\t',
+                _ => return None,
+            }),
+            '"' => return None,
+            c => unquoted.push(c),
+        }
+    }
+
+    Some(unquoted)
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}#{:016x}", self.index, self.hash)?;
+        if let Some(name) = &self.name {
+            write!(f, "@{name}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Key {
+    type Err = PatchError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || PatchError(format!("invalid element '{s}'"));
+
+        let (index, rest) = s.split_once('#').ok_or_else(err)?;
+        let (hash, name) = match rest.split_once('@') {
+            Some((hash, name)) => (hash, Some(name.to_string())),
+            None => (rest, None),
+        };
+
+        Ok(Key {
+            index: index.parse().map_err(|_| err())?,
+            name,
+            hash: u64::from_str_radix(hash, 16).map_err(|_| err())?,
+        })
+    }
+}
+
+impl fmt::Display for Anchor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "$")?;
+        for step in &self.0 {
+            match step {
+                Step::Field(field) => write!(f, ".{field}")?,
+                Step::Element(field, key) => write!(f, ".{field}[{key}]")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn parse_field(name: &str) -> Result<&'static str, PatchError> {
+    field_name(name).ok_or_else(|| PatchError(format!("unknown field '{name}'")))
+}
+
+impl FromStr for Anchor {
+    type Err = PatchError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rest = s
+            .strip_prefix('$')
+            .ok_or_else(|| PatchError(format!("invalid anchor '{s}'")))?;
+        if rest.is_empty() {
+            return Ok(Anchor::root());
+        }
+
+        rest.strip_prefix('.')
+            .ok_or_else(|| PatchError(format!("invalid anchor '{s}'")))?
+            .split('.')
+            .map(|step| match step.split_once('[') {
+                Some((field, key)) => {
+                    let key = key
+                        .strip_suffix(']')
+                        .ok_or_else(|| PatchError(format!("invalid element '{step}'")))?;
+                    Ok(Step::Element(parse_field(field)?, key.parse()?))
+                }
+                None => Ok(Step::Field(parse_field(step)?)),
+            })
+            .collect::<Result<_, _>>()
+            .map(Anchor)
+    }
+}
+
+/// An insertion point: the element to follow, or `-` for the front of the list.
+struct After<'a>(&'a Option<Key>);
+
+impl fmt::Display for After<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(key) => write!(f, "[{key}]"),
+            None => write!(f, "-"),
+        }
+    }
+}
+
+fn parse_after(s: &str) -> Result<Option<Key>, PatchError> {
+    if s == "-" {
+        return Ok(None);
+    }
+
+    s.strip_prefix('[')
+        .and_then(|s| s.strip_suffix(']'))
+        .ok_or_else(|| PatchError(format!("invalid insertion point '{s}'")))?
+        .parse()
+        .map(Some)
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operation::Update { target, hash, text } => {
+                write!(f, "update {target} {hash:016x} {}", quote(text))
+            }
+            Operation::Insert {
+                parent,
+                field,
+                after,
+                text,
+            } => write!(
+                f,
+                "insert {parent} {field} {} {}",
+                After(after),
+                quote(text)
+            ),
+            Operation::Delete { target } => write!(f, "delete {target}"),
+            Operation::Move { target, after } => write!(f, "move {target} {}", After(after)),
+        }
+    }
+}
+
+impl FromStr for Operation {
+    type Err = PatchError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || PatchError(format!("invalid operation '{s}'"));
+        let text = |text: &str| unquote(text).ok_or_else(err);
+
+        let (operation, rest) = s.split_once(' ').ok_or_else(err)?;
+        match operation {
+            "update" => {
+                let [target, hash, quoted] = rest.splitn(3, ' ').collect::<Vec<_>>()[..] else {
+                    return Err(err());
+                };
+                Ok(Operation::Update {
+                    target: target.parse()?,
+                    hash: u64::from_str_radix(hash, 16).map_err(|_| err())?,
+                    text: text(quoted)?,
+                })
+            }
+            "insert" => {
+                let [parent, field, after, quoted] = rest.splitn(4, ' ').collect::<Vec<_>>()[..]
+                else {
+                    return Err(err());
+                };
+                Ok(Operation::Insert {
+                    parent: parent.parse()?,
+                    field: parse_field(field)?,
+                    after: parse_after(after)?,
+                    text: text(quoted)?,
+                })
+            }
+            "delete" => Ok(Operation::Delete {
+                target: rest.parse()?,
+            }),
+            "move" => {
+                let (target, after) = rest.split_once(' ').ok_or_else(err)?;
+                Ok(Operation::Move {
+                    target: target.parse()?,
+                    after: parse_after(after)?,
+                })
+            }
+            _ => Err(err()),
+        }
+    }
+}
+
+impl fmt::Display for Patch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "sdp-patch {FORMAT_VERSION}")?;
+        for operation in &self.operations {
+            writeln!(f, "{operation}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Patch {
+    type Err = PatchError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines().filter(|line| !line.trim().is_empty());
+
+        match lines
+            .next()
+            .and_then(|line| line.trim().strip_prefix("sdp-patch "))
+        {
+            Some(version) if version.trim() == FORMAT_VERSION.to_string() => {}
+            Some(version) => {
+                return Err(PatchError(format!(
+                    "patch has format version {}, expected {FORMAT_VERSION}",
+                    version.trim()
+                )))
+            }
+            None => return Err(PatchError("not a patch".into())),
+        }
+
+        lines
+            .map(|line| line.trim_end_matches('\r').parse())
+            .collect::<Result<_, _>>()
+            .map(|operations| Patch { operations })
+    }
+}
+
+/// Works out the patch that turns `old` into `new`, in its text form. Returns `null` if either
+/// does not parse.
+#[wasm_bindgen]
+pub fn diff_bytes(old: &[u8], new: &[u8]) -> Option<String> {
+    let old = core::str::from_utf8(old).expect("data was not valid UTF-8");
+    let new = core::str::from_utf8(new).expect("data was not valid UTF-8");
+
+    diff_sources(old, new).ok().map(|patch| patch.to_string())
+}
+
+/// Applies a patch in its text form, returning an `EditOutcome`.
+#[wasm_bindgen]
+pub fn patch_bytes(data: &[u8], patch: &str) -> JsValue {
+    let source = core::str::from_utf8(data).expect("data was not valid UTF-8");
+
+    match patch
+        .parse::<Patch>()
+        .and_then(|patch| apply(source, &patch))
+    {
+        Ok(patched) => EditOutcome {
+            error: None,
+            source: Some(&patched),
+            parsed: Some(parse_module(&patched)),
+        }
+        .to_js_value(),
+        Err(e) => EditOutcome {
+            error: Some(e.0),
+            source: None,
+            parsed: None,
+        }
+        .to_js_value(),
+    }
+}
@@ -0,0 +1,588 @@
+//! Pretty printing of the surface AST back to Serendipity source.
+//!
+//! Unlike the `Display` implementations, which produce a fully parenthesized debugging form, the
+//! printer emits canonical source that reads back into the same tree. Parentheses are only added
+//! where precedence or the grammar requires them. Procedures and multi-line exports are broken over
+//! several lines and indented by two spaces; everything else is printed on one line.
+
+use itertools::Itertools;
+
+use crate::{
+    ArithmeticOp, Assignment, BindingPattern, Declaration, Expression, GenericParameter,
+    InterfaceField, Module, ParameterDeclaration, ParsedVec, RecordBindingElement, RecordElement,
+    Statement, Type, TypeConstraint,
+};
+
+const INDENT: &str = "  ";
+
+/// Prints a whole module, separating declarations with blank lines.
+pub fn print_module(module: &Module) -> String {
+    module
+        .declarations
+        .iter()
+        .map(|decl| print_declaration(&decl.value))
+        .join("\n\n")
+        + "\n"
+}
+
+/// Prints a single top-level declaration, including its terminating `;`.
+pub fn print_declaration(decl: &Declaration) -> String {
+    let mut p = Printer::default();
+    p.declaration(decl);
+    p.out
+}
+
+/// Prints an expression as it would appear at the top of a declaration or statement.
+pub fn print_expression(expr: &Expression) -> String {
+    let mut p = Printer::default();
+    p.expression(expr, Precedence::Top);
+    p.out
+}
+
+/// Prints a statement, without a terminating `;`.
+pub fn print_statement(stmt: &Statement) -> String {
+    let mut p = Printer::default();
+    p.statement(stmt);
+    p.out
+}
+
+/// Prints a type.
+pub fn print_type(ty: &Type) -> String {
+    let mut p = Printer::default();
+    p.type_(ty, false);
+    p.out
+}
+
+/// Binding strength of the expression grammar, from loosest to tightest. An expression printed in
+/// a position that requires a tighter precedence than its own is wrapped in parentheses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Precedence {
+    /// `fn`, `with`, and `if` expressions, which extend as far right as possible.
+    Top,
+    Compare,
+    Additive,
+    Multiplicative,
+    Unary,
+    Postfix,
+    FieldAccess,
+    Element,
+}
+
+fn precedence(expr: &Expression) -> Precedence {
+    match expr {
+        Expression::Function { .. } | Expression::With { .. } | Expression::If { .. } => {
+            Precedence::Top
+        }
+        Expression::As { .. } => Precedence::Top,
+        Expression::Compare { .. } => Precedence::Compare,
+        Expression::Arithmetic { operator, .. } => match operator.value {
+            ArithmeticOp::Add | ArithmeticOp::Subtract => Precedence::Additive,
+            ArithmeticOp::Multiply | ArithmeticOp::Divide | ArithmeticOp::Modulus => {
+                Precedence::Multiplicative
+            }
+        },
+        Expression::Unary { .. } => Precedence::Unary,
+        Expression::Call { .. } | Expression::Accessor { .. } => Precedence::Postfix,
+        Expression::FieldAccess { .. } => Precedence::FieldAccess,
+        // A negative number literal reads back as a unary minus.
+        Expression::Number(n) if n.starts_with('-') => Precedence::Unary,
+        _ => Precedence::Element,
+    }
+}
+
+/// Escapes the contents of a string literal.
+pub fn escape_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[derive(Default)]
+struct Printer {
+    out: String,
+    indent: usize,
+}
+
+impl Printer {
+    fn write(&mut self, s: &str) {
+        self.out.push_str(s);
+    }
+
+    fn newline(&mut self) {
+        self.out.push('\n');
+        for _ in 0..self.indent {
+            self.out.push_str(INDENT);
+        }
+    }
+
+    fn separated<T>(&mut self, items: &[T], separator: &str, mut f: impl FnMut(&mut Self, &T)) {
+        for (idx, item) in items.iter().enumerate() {
+            if idx > 0 {
+                self.write(separator);
+            }
+            f(self, item);
+        }
+    }
+
+    fn generics(&mut self, generics: &Option<ParsedVec<GenericParameter>>) {
+        if let Some(generics) = generics {
+            self.write("[");
+            self.separated(&generics.value, ", ", |p, g| p.generic_parameter(&g.value));
+            self.write("]");
+        }
+    }
+
+    fn generic_parameter(&mut self, param: &GenericParameter) {
+        self.write(param.name.value);
+        if let Some(constraint) = &param.constraint {
+            self.type_constraint(&constraint.value);
+        }
+    }
+
+    fn type_constraint(&mut self, constraint: &TypeConstraint) {
+        self.write(": ");
+        self.type_(&constraint.type_.value, false);
+    }
+
+    fn parameters(&mut self, parameters: &ParsedVec<ParameterDeclaration>) {
+        self.write("(");
+        self.separated(&parameters.value, ", ", |p, param| {
+            p.write(param.value.name.value);
+            if let Some(type_) = &param.value.type_ {
+                p.type_constraint(&type_.value);
+            }
+        });
+        self.write(")");
+    }
+
+    fn declaration(&mut self, decl: &Declaration) {
+        match decl {
+            Declaration::Main { body, .. } => {
+                self.write("main ");
+                self.expression(&body.value, Precedence::Top);
+            }
+            Declaration::Const {
+                identifier,
+                type_,
+                value,
+                ..
+            } => {
+                self.write("const ");
+                self.write(identifier.value);
+                if let Some(type_) = type_ {
+                    self.type_constraint(&type_.value);
+                }
+                self.write(" = ");
+                self.expression(&value.value, Precedence::Top);
+            }
+            Declaration::Function {
+                identifier,
+                generic_parameters,
+                parameters,
+                constraint,
+                body,
+                ..
+            } => {
+                self.write("fn ");
+                self.write(identifier.value);
+                self.generics(generic_parameters);
+                self.parameters(parameters);
+                if let Some(constraint) = constraint {
+                    self.type_constraint(&constraint.value);
+                }
+                self.write(" -> ");
+                self.expression(&body.value, Precedence::Top);
+            }
+            Declaration::Import {
+                pattern,
+                module_specifier,
+                ..
+            } => {
+                self.write("import ");
+                self.binding_pattern(&pattern.value);
+                self.write(" = use(");
+                self.write(&escape_string(module_specifier.value));
+                self.write(")");
+            }
+            Declaration::Export { elements, .. } => {
+                self.write("export {");
+                self.indent += 1;
+                for (idx, element) in elements.value.iter().enumerate() {
+                    if idx > 0 {
+                        self.write(",");
+                    }
+                    self.newline();
+                    self.record_element(&element.value);
+                }
+                self.indent -= 1;
+                self.newline();
+                self.write("}");
+            }
+            Declaration::TypeAlias {
+                name,
+                generic_parameters,
+                value,
+                ..
+            } => {
+                self.write("type ");
+                self.write(name.value);
+                self.generics(generic_parameters);
+                self.write(" = ");
+                self.type_(&value.value, false);
+            }
+            Declaration::Interface {
+                name,
+                generic_parameters,
+                constraint,
+                body,
+                ..
+            } => {
+                self.write("interface ");
+                self.write(name.value);
+                self.generics(generic_parameters);
+                if let Some(constraint) = constraint {
+                    self.type_constraint(&constraint.value);
+                }
+                self.write(" {");
+                self.indent += 1;
+                for (idx, field) in body.value.iter().enumerate() {
+                    if idx > 0 {
+                        self.write(",");
+                    }
+                    self.newline();
+                    self.interface_field(&field.value);
+                }
+                self.indent -= 1;
+                self.newline();
+                self.write("}");
+            }
+        }
+
+        self.write(";");
+    }
+
+    fn interface_field(&mut self, field: &InterfaceField) {
+        self.write(field.name.value);
+        self.type_constraint(&field.constraint.value);
+    }
+
+    fn expression(&mut self, expr: &Expression, context: Precedence) {
+        if precedence(expr) < context {
+            self.write("(");
+            self.expression(expr, Precedence::Top);
+            self.write(")");
+            return;
+        }
+
+        match expr {
+            Expression::Number(n) => self.write(n),
+            Expression::String(s) => self.write(&escape_string(s)),
+            Expression::Boolean(b) => self.write(if *b { "true" } else { "false" }),
+            Expression::Name(n) => self.write(n),
+            Expression::Hole => self.write("@"),
+            Expression::None => self.write("none"),
+            Expression::As { expr, type_, .. } => {
+                self.expression(&expr.value, Precedence::Compare);
+                self.write(" as ");
+                self.type_(&type_.value, false);
+            }
+            Expression::Unary {
+                operator,
+                expression,
+            } => {
+                self.write(&operator.value.to_string());
+                self.expression(&expression.value, Precedence::Unary);
+            }
+            Expression::Compare {
+                operator,
+                left,
+                right,
+            } => {
+                // Comparisons associate to the left.
+                self.expression(&left.value, Precedence::Compare);
+                self.write(&format!(" {} ", operator.value));
+                self.expression(&right.value, Precedence::Additive);
+            }
+            Expression::Arithmetic {
+                operator,
+                left,
+                right,
+            } => {
+                // Arithmetic operators associate to the left within each precedence level.
+                let own = precedence(expr);
+                let tighter = if own == Precedence::Additive {
+                    Precedence::Multiplicative
+                } else {
+                    Precedence::Unary
+                };
+                self.expression(&left.value, own);
+                self.write(&format!(" {} ", operator.value));
+                self.expression(&right.value, tighter);
+            }
+            Expression::Accessor { accessee, index } => {
+                self.expression(&accessee.value, Precedence::Postfix);
+                self.write("[");
+                self.expression(&index.value, Precedence::Top);
+                self.write("]");
+            }
+            Expression::Function {
+                name,
+                generic_parameters,
+                parameters,
+                constraint,
+                body,
+                ..
+            } => {
+                self.write("fn");
+                if let Some(name) = name {
+                    self.write(" ");
+                    self.write(name.value);
+                } else {
+                    self.write(" ");
+                }
+                self.generics(generic_parameters);
+                self.parameters(parameters);
+                if let Some(constraint) = constraint {
+                    self.type_constraint(&constraint.value);
+                }
+                self.write(" -> ");
+                self.expression(&body.value, Precedence::Top);
+            }
+            Expression::Call { callee, parameters } => {
+                self.expression(&callee.value, Precedence::Postfix);
+                self.write("(");
+                self.separated(&parameters.value, ", ", |p, e| {
+                    p.expression(&e.value, Precedence::Top)
+                });
+                self.write(")");
+            }
+            Expression::With { bindings, body, .. } => {
+                self.write("with (");
+                self.separated(&bindings.value, ", ", |p, b| p.assignment(&b.value));
+                self.write(") ");
+                self.expression(&body.value, Precedence::Top);
+            }
+            Expression::Tuple { elements } => {
+                self.write("(");
+                self.separated(&elements.value, ", ", |p, e| {
+                    p.expression(&e.value, Precedence::Top)
+                });
+                self.write(")");
+            }
+            Expression::List { elements } => {
+                self.write("[");
+                self.separated(&elements.value, ", ", |p, e| {
+                    p.expression(&e.value, Precedence::Top)
+                });
+                self.write("]");
+            }
+            Expression::Procedure { body } => {
+                self.write("#[");
+                self.indent += 1;
+                for stmt in &body.value {
+                    self.newline();
+                    self.statement(&stmt.value);
+                    self.write(";");
+                }
+                self.indent -= 1;
+                self.newline();
+                self.write("]");
+            }
+            Expression::If {
+                condition,
+                then,
+                _else,
+                ..
+            } => {
+                self.write("if ");
+                self.expression(&condition.value, Precedence::Top);
+                self.write(" then ");
+                self.expression(&then.value, Precedence::Top);
+                self.write(" else ");
+                self.expression(&_else.value, Precedence::Top);
+            }
+            Expression::Record { elements } => {
+                if elements.value.is_empty() {
+                    self.write("{}");
+                } else {
+                    self.write("{ ");
+                    self.separated(&elements.value, ", ", |p, e| p.record_element(&e.value));
+                    self.write(" }");
+                }
+            }
+            Expression::FieldAccess { accessee, field } => {
+                self.expression(&accessee.value, Precedence::Element);
+                self.write(".");
+                self.write(field.value);
+            }
+        }
+    }
+
+    fn assignment(&mut self, assignment: &Assignment) {
+        self.write(assignment.symbol.value);
+        self.write(" = ");
+        self.expression(&assignment.value.value, Precedence::Top);
+    }
+
+    fn record_element(&mut self, element: &RecordElement) {
+        match element {
+            RecordElement::KeyValuePair { key, value } => {
+                self.write(key.value);
+                self.write(": ");
+                self.expression(&value.value, Precedence::Top);
+            }
+            RecordElement::Identifier { name } => self.write(name.value),
+            RecordElement::Spread { value } => {
+                self.write("...");
+                self.expression(&value.value, Precedence::Element);
+            }
+        }
+    }
+
+    fn statement(&mut self, stmt: &Statement) {
+        match stmt {
+            Statement::Let { assignment, .. } => {
+                self.write("let ");
+                self.assignment(&assignment.value);
+            }
+            Statement::Set(assignment) => self.assignment(&assignment.value),
+            Statement::If {
+                condition,
+                then,
+                _else,
+                ..
+            } => {
+                self.write("if ");
+                self.expression(&condition.value, Precedence::Top);
+                self.write(" ");
+                self.statement(&then.value);
+                if let Some(_else) = _else {
+                    self.write(" else ");
+                    self.statement(&_else.value);
+                }
+            }
+            Statement::ForIn {
+                binding,
+                iterator,
+                body,
+                ..
+            } => {
+                self.write("for ");
+                self.write(binding.value);
+                self.write(" in ");
+                self.expression(&iterator.value, Precedence::Top);
+                self.write(" ");
+                self.statement(&body.value);
+            }
+            Statement::Forever(body) => {
+                self.write("loop ");
+                self.statement(&body.value);
+            }
+            Statement::Do(body) => {
+                self.write("do ");
+                self.expression(&body.value, Precedence::Top);
+            }
+            Statement::Break => self.write("break"),
+            Statement::Continue => self.write("continue"),
+            Statement::Pass => self.write("pass"),
+            Statement::Expression(e) => self.expression(&e.value, Precedence::Top),
+        }
+    }
+
+    /// Prints a type. Operands of a union must be simple types, so `operand` requests parentheses
+    /// around anything else.
+    fn type_(&mut self, ty: &Type, operand: bool) {
+        match ty {
+            Type::Kind => self.write("*"),
+            Type::Never => self.write("!"),
+            Type::Unknown => self.write("_"),
+            Type::Reference {
+                name,
+                generic_parameters,
+            } => {
+                self.write(name.value);
+                if let Some(parameters) = generic_parameters {
+                    self.write("[");
+                    self.separated(&parameters.value, ", ", |p, t| p.type_(&t.value, false));
+                    self.write("]");
+                }
+            }
+            Type::Union { left, right } => {
+                if operand {
+                    self.write("(");
+                }
+                self.type_(&left.value, true);
+                self.write(" | ");
+                self.type_(&right.value, true);
+                if operand {
+                    self.write(")");
+                }
+            }
+            Type::Tuple { members } => {
+                self.write("(");
+                self.separated(&members.value, ", ", |p, t| p.type_(&t.value, false));
+                self.write(")");
+            }
+            Type::Function {
+                parameters,
+                return_type,
+                ..
+            } => {
+                if operand {
+                    self.write("(");
+                }
+                self.write("fn(");
+                self.separated(&parameters.value, ", ", |p, t| p.type_(&t.value, false));
+                self.write(") -> ");
+                self.type_(&return_type.value, false);
+                if operand {
+                    self.write(")");
+                }
+            }
+        }
+    }
+
+    fn binding_pattern(&mut self, pattern: &BindingPattern) {
+        match pattern {
+            BindingPattern::Identifier { name } => self.write(name.value),
+            BindingPattern::Tuple { patterns } => {
+                self.write("(");
+                self.separated(&patterns.value, ", ", |p, b| p.binding_pattern(&b.value));
+                self.write(")");
+            }
+            BindingPattern::Record { elements } => {
+                self.write("{ ");
+                self.separated(&elements.value, ", ", |p, e| {
+                    p.record_binding_element(&e.value)
+                });
+                self.write(" }");
+            }
+        }
+    }
+
+    fn record_binding_element(&mut self, element: &RecordBindingElement) {
+        match element {
+            RecordBindingElement::Identifier { name } => self.write(name.value),
+            RecordBindingElement::KeyValuePair { name, pattern } => {
+                self.write(name.value);
+                self.write(": ");
+                self.binding_pattern(&pattern.value);
+            }
+            RecordBindingElement::Rest { name } => {
+                self.write("...");
+                self.write(name.value);
+            }
+        }
+    }
+}
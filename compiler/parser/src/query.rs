@@ -0,0 +1,190 @@
+//! Position queries over a parsed module, for editor tooling.
+//!
+//! Given a cursor position, [`nodes_at_offset`] returns the chain of nodes that enclose it, from
+//! the module down to the deepest node. [`range_of_path`] goes the other way, from a [`NodePath`]
+//! to the source range of the node it names.
+
+use wasm_bindgen::prelude::*;
+
+use seglisp::{
+    js_interop::{JsInterop, JsValue},
+    parse::ParseNode,
+};
+
+use crate::{
+    ids::NodeId,
+    node::{NodePath, NodeRef},
+    with_parsed_module, Module,
+};
+
+/// A description of a node that encloses a queried position.
+#[derive(Debug, Clone, JsInterop)]
+pub struct NodeInfo {
+    pub category: &'static str,
+    pub kind: &'static str,
+    pub path: String,
+    pub range: seglisp::Range,
+    /// The stable identifier of the node, if the query was made through a
+    /// [`NodeIdTracker`](crate::ids::NodeIdTracker).
+    pub id: Option<NodeId>,
+}
+
+impl NodeInfo {
+    pub(crate) fn new(path: &NodePath, node: NodeRef, id: Option<NodeId>) -> Self {
+        NodeInfo {
+            category: node.category(),
+            kind: node.kind(),
+            path: path.to_string(),
+            range: node.range(),
+            id,
+        }
+    }
+}
+
+/// Returns the chain of nodes enclosing the byte `offset`, outermost first.
+///
+/// The chain always starts with the module itself. A node encloses `offset` if its range starts at
+/// or before it and ends after it. When no child of the innermost node so far encloses `offset`, a
+/// child that ends exactly at it is taken instead, so that a cursor just after an identifier still
+/// finds the identifier.
+pub fn nodes_at_offset<'a, 'ast>(
+    module: &'a ParseNode<Module<'ast>>,
+    offset: usize,
+) -> Vec<(NodePath, NodeRef<'a, 'ast>)> {
+    let mut chain = vec![(NodePath::root(), NodeRef::Module(module))];
+
+    loop {
+        let (path, node) = chain.last().unwrap();
+        let children = node.children();
+
+        let next = children
+            .iter()
+            .find(|(_, child)| {
+                let range = child.range();
+                range.0.absolute <= offset && offset < range.1.absolute
+            })
+            .or_else(|| {
+                children
+                    .iter()
+                    .find(|(_, child)| child.range().1.absolute == offset)
+            });
+
+        match next {
+            Some((segment, child)) => {
+                let path = path.child(*segment);
+                chain.push((path, *child));
+            }
+            None => break,
+        }
+    }
+
+    chain
+}
+
+/// Returns the deepest node enclosing the byte `offset`.
+pub fn node_at_offset<'a, 'ast>(
+    module: &'a ParseNode<Module<'ast>>,
+    offset: usize,
+) -> (NodePath, NodeRef<'a, 'ast>) {
+    nodes_at_offset(module, offset).pop().unwrap()
+}
+
+/// Converts a zero-based line and column to a byte offset in `source`.
+///
+/// Columns count UTF-16 code units, as Monaco and most language clients send them. Returns `None`
+/// if the position is past the end of its line, falls inside a character, or the line does not
+/// exist.
+pub fn offset_of_position(source: &str, line: usize, column: usize) -> Option<usize> {
+    offset_in_line(source, line, column, char::len_utf16)
+}
+
+/// Like [`offset_of_position`], but for columns that count characters, like the columns of a
+/// [`seglisp::Location`] and the `utf-32` position encoding of the language server protocol.
+pub fn offset_of_char_position(source: &str, line: usize, column: usize) -> Option<usize> {
+    offset_in_line(source, line, column, |_| 1)
+}
+
+fn offset_in_line(
+    source: &str,
+    line: usize,
+    column: usize,
+    width: impl Fn(char) -> usize,
+) -> Option<usize> {
+    let line_start = if line == 0 {
+        0
+    } else {
+        source
+            .match_indices('\n')
+            .nth(line - 1)
+            .map(|(idx, _)| idx + 1)?
+    };
+
+    let rest = &source[line_start..];
+    let line_text = rest.split('\n').next().unwrap_or("");
+
+    let mut units = 0;
+    for (idx, ch) in line_text.char_indices() {
+        if units >= column {
+            return (units == column).then_some(line_start + idx);
+        }
+        units += width(ch);
+    }
+
+    (units == column).then_some(line_start + line_text.len())
+}
+
+/// Returns the range of the node at `path`, if the path names a node in `module`.
+pub fn range_of_path(module: &ParseNode<Module>, path: &NodePath) -> Option<seglisp::Range> {
+    NodeRef::Module(module)
+        .descendant(path)
+        .map(|node| node.range())
+}
+
+fn describe(chain: Vec<(NodePath, NodeRef)>) -> Vec<NodeInfo> {
+    chain
+        .into_iter()
+        .map(|(path, node)| NodeInfo::new(&path, node, None))
+        .collect()
+}
+
+/// Returns the chain of nodes enclosing the byte `offset` as an array of `NodeInfo`, outermost
+/// first, or `null` if the document could not be parsed.
+#[wasm_bindgen]
+pub fn nodes_at(data: &[u8], offset: usize) -> JsValue {
+    with_parsed_module(data, |doc| match &doc.result {
+        Ok(module) => describe(nodes_at_offset(module, offset)).to_js_value(),
+        Err(_) => JsValue::NULL,
+    })
+}
+
+/// Like `nodes_at`, but for a zero-based line and UTF-16 column.
+#[wasm_bindgen]
+pub fn nodes_at_position(data: &[u8], line: usize, column: usize) -> JsValue {
+    let source = core::str::from_utf8(data).expect("data was not valid UTF-8");
+
+    match offset_of_position(source, line, column) {
+        Some(offset) => nodes_at(data, offset),
+        None => JsValue::NULL,
+    }
+}
+
+/// Returns the range of the node at `path` (e.g. `declarations[0].body`), or `null` if there is no
+/// such node.
+#[wasm_bindgen]
+pub fn node_range(data: &[u8], path: &str) -> JsValue {
+    let Ok(path) = path.parse::<NodePath>() else {
+        return JsValue::NULL;
+    };
+
+    with_parsed_module(data, |doc| {
+        match doc
+            .result
+            .as_ref()
+            .ok()
+            .and_then(|module| range_of_path(module, &path))
+        {
+            Some(range) => range.to_js_value(),
+            None => JsValue::NULL,
+        }
+    })
+}
@@ -0,0 +1,726 @@
+//! Renaming a symbol across the modules of a project.
+//!
+//! [`rename`] starts from the symbol at a position and renames its declaration and every reference
+//! to it that [`resolve`] finds. A top-level binding that a module exports under its own name
+//! (`export { name }`) is renamed in the modules that import it too: in `import { name } = ...`
+//! patterns, in the keys of `import { name: pattern } = ...` patterns, and in field accesses on a
+//! module imported as a whole (`lib.name`). Renaming an imported name renames the binding it was
+//! imported from, if that module is part of the graph.
+//!
+//! Shorthand record fields keep their keys, so `{ name }` becomes `{ name: renamed }` when `name` is
+//! renamed as a variable. A rename is refused if the new name is already declared in the same
+//! scope, if a reference would be captured by another binding of the new name, or if the renamed
+//! binding would shadow a binding that some reference of the new name currently resolves to.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt,
+    ops::ControlFlow,
+};
+
+use seglisp::parse::ParseNode;
+
+use crate::{
+    graph::ModuleGraph,
+    incremental::TextEdit,
+    resolve::{resolve, Resolution, SymbolKind},
+    visit::{self, Visit},
+    BindingPattern, Declaration, Expression, Module, RecordBindingElement, RecordElement, Verbatim,
+};
+
+/// Words that cannot be used as names: every word the parser gives a meaning to, and `as`, which
+/// is reserved for the type assertions of [`Expression::As`].
+const KEYWORDS: &[&str] = &[
+    "as",
+    "break",
+    "const",
+    "continue",
+    "do",
+    "else",
+    "export",
+    "false",
+    "fn",
+    "for",
+    "if",
+    "import",
+    "in",
+    "interface",
+    "let",
+    "loop",
+    "main",
+    "none",
+    "pass",
+    "then",
+    "true",
+    "type",
+    "use",
+    "with",
+];
+
+/// The changes a rename makes to one module.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileEdit {
+    pub path: String,
+    /// Non-overlapping edits, in order.
+    pub edits: Vec<TextEdit>,
+}
+
+/// A rename that could not be made.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenameError(pub String);
+
+impl fmt::Display for RenameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for RenameError {}
+
+/// Returns true if `name` can be used as an identifier: a letter or `_`, then letters, digits, or
+/// `_`, and not a keyword.
+///
+/// The reader reads any run of characters other than whitespace, brackets, quotes, and sigil
+/// characters as a symbol, as long as it does not start with an ASCII digit. Valid names are a
+/// subset of those, so a valid name always reads back as the one symbol.
+pub fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+        && !KEYWORDS.contains(&name)
+}
+
+/// Renames the symbol at byte `offset` of module `module` to `new_name`, returning the edits to
+/// make to each module it appears in.
+pub fn rename(
+    graph: &ModuleGraph,
+    module: usize,
+    offset: usize,
+    new_name: &str,
+) -> Result<Vec<FileEdit>, RenameError> {
+    if !is_valid_name(new_name) {
+        return Err(RenameError(format!("'{new_name}' is not a valid name")));
+    }
+
+    let analyses: Vec<Analysis> = (0..graph.len())
+        .map(|idx| Analysis::new(graph.get(idx).module))
+        .collect();
+
+    let (module, symbol) = start(graph, &analyses, module, offset)
+        .ok_or_else(|| RenameError("there is no symbol to rename here".into()))?;
+
+    let old_name = analyses[module].resolution.symbols[symbol].name;
+    if old_name == new_name {
+        return Ok(vec![]);
+    }
+
+    let mut renamer = Renamer {
+        graph,
+        analyses: &analyses,
+        old_name,
+        new_name,
+        symbols: HashSet::new(),
+        exports: HashSet::new(),
+        edits: BTreeMap::new(),
+    };
+    renamer.rename_symbol(module, symbol)?;
+
+    Ok(renamer
+        .edits
+        .into_iter()
+        .map(|(idx, mut edits)| {
+            edits.sort_by_key(|e| e.start);
+            edits.dedup();
+
+            FileEdit {
+                path: graph.get(idx).path.to_string(),
+                edits,
+            }
+        })
+        .collect())
+}
+
+fn span(range: &seglisp::Range) -> (usize, usize) {
+    (range.0.absolute, range.1.absolute)
+}
+
+/// The symbol at `offset`, or for a field access on an imported module, the binding it accesses.
+fn start(
+    graph: &ModuleGraph,
+    analyses: &[Analysis],
+    module: usize,
+    offset: usize,
+) -> Option<(usize, usize)> {
+    let analysis = &analyses[module];
+
+    if let Some(symbol) = analysis.resolution.symbol_at(offset) {
+        return Some((module, symbol));
+    }
+
+    let (accessee, field) = analysis
+        .field_accesses
+        .iter()
+        .find(|(_, field)| field.range.0.absolute <= offset && offset <= field.range.1.absolute)?;
+
+    let import = analysis.symbol_referenced_at(*accessee)?;
+    let target = graph.imports(module).into_iter().find_map(|import_decl| {
+        match &import_decl.pattern.value {
+            BindingPattern::Identifier { name }
+                if span(&name.range) == span(&analysis.resolution.symbols[import].definition) =>
+            {
+                import_decl.target
+            }
+            _ => None,
+        }
+    })?;
+
+    let symbol = analyses[target].exported_binding(field.value)?;
+    Some((target, symbol))
+}
+
+/// What renaming needs to know about a module beyond its name resolution.
+struct Analysis<'ast> {
+    resolution: Resolution<'ast>,
+    /// Shorthand fields of record expressions, `{ name }`.
+    record_shorthands: Vec<(usize, usize)>,
+    /// Shorthand elements of `export` declarations.
+    export_shorthands: Vec<(usize, usize)>,
+    /// Shorthand elements of record binding patterns.
+    pattern_shorthands: Vec<(usize, usize)>,
+    /// Field accesses whose accessee is a name: the span of the name and the field.
+    field_accesses: Vec<((usize, usize), &'ast Verbatim<'ast>)>,
+    /// Wholesale re-exports, `export { ...name }`.
+    reexports: Vec<&'ast str>,
+}
+
+impl<'ast> Analysis<'ast> {
+    fn new(module: &'ast ParseNode<Module<'ast>>) -> Self {
+        let mut analysis = Analysis {
+            resolution: resolve(module),
+            record_shorthands: Vec::new(),
+            export_shorthands: Vec::new(),
+            pattern_shorthands: Vec::new(),
+            field_accesses: Vec::new(),
+            reexports: module
+                .value
+                .exports()
+                .reexports
+                .iter()
+                .map(|name| name.value)
+                .collect(),
+        };
+
+        let _ = analysis.visit_module(module);
+        analysis
+    }
+
+    /// The symbol referred to by the name spanning `name`.
+    fn symbol_referenced_at(&self, name: (usize, usize)) -> Option<usize> {
+        self.resolution
+            .references
+            .iter()
+            .find(|r| span(&r.range) == name)
+            .and_then(|r| r.symbol)
+    }
+
+    /// The top-level symbol exported under its own name as `name`.
+    fn exported_binding(&self, name: &str) -> Option<usize> {
+        self.resolution
+            .references
+            .iter()
+            .filter(|r| self.export_shorthands.contains(&span(&r.range)))
+            .find(|r| r.name == name)
+            .and_then(|r| r.symbol)
+    }
+}
+
+impl<'ast> Visit<'ast> for Analysis<'ast> {
+    fn visit_declaration(&mut self, node: &'ast ParseNode<Declaration<'ast>>) -> ControlFlow<()> {
+        match &node.value {
+            Declaration::Export { elements, .. } => {
+                for element in &elements.value {
+                    match &element.value {
+                        RecordElement::Identifier { name } => {
+                            self.export_shorthands.push(span(&name.range))
+                        }
+                        RecordElement::KeyValuePair { value, .. }
+                        | RecordElement::Spread { value } => self.visit_expression(value)?,
+                    }
+                }
+                ControlFlow::Continue(())
+            }
+            _ => visit::walk_declaration(self, node),
+        }
+    }
+
+    fn visit_expression(&mut self, node: &'ast ParseNode<Expression<'ast>>) -> ControlFlow<()> {
+        if let Expression::FieldAccess { accessee, field } = &node.value {
+            if let Expression::Name(_) = accessee.value {
+                self.field_accesses.push((span(&accessee.range), field));
+            }
+        }
+
+        visit::walk_expression(self, node)
+    }
+
+    fn visit_record_element(
+        &mut self,
+        node: &'ast ParseNode<RecordElement<'ast>>,
+    ) -> ControlFlow<()> {
+        if let RecordElement::Identifier { name } = &node.value {
+            self.record_shorthands.push(span(&name.range));
+        }
+
+        visit::walk_record_element(self, node)
+    }
+
+    fn visit_record_binding_element(
+        &mut self,
+        node: &'ast ParseNode<RecordBindingElement<'ast>>,
+    ) -> ControlFlow<()> {
+        if let RecordBindingElement::Identifier { name } = &node.value {
+            self.pattern_shorthands.push(span(&name.range));
+        }
+
+        visit::walk_record_binding_element(self, node)
+    }
+}
+
+struct Renamer<'r, 'ast> {
+    graph: &'r ModuleGraph<'ast>,
+    analyses: &'r [Analysis<'ast>],
+    old_name: &'ast str,
+    new_name: &'r str,
+    /// The symbols renamed so far, by module.
+    symbols: HashSet<(usize, usize)>,
+    /// The modules whose export of the old name has been renamed.
+    exports: HashSet<usize>,
+    edits: BTreeMap<usize, Vec<TextEdit>>,
+}
+
+impl<'r, 'ast> Renamer<'r, 'ast> {
+    fn edit(&mut self, module: usize, (start, end): (usize, usize), text: String) {
+        self.edits
+            .entry(module)
+            .or_default()
+            .push(TextEdit { start, end, text });
+    }
+
+    fn location(&self, module: usize, offset: usize) -> String {
+        let module = self.graph.get(module);
+        let line = module.source[..offset].matches('\n').count() + 1;
+        format!("{}:{line}", module.path)
+    }
+
+    fn rename_symbol(&mut self, module: usize, idx: usize) -> Result<(), RenameError> {
+        if !self.symbols.insert((module, idx)) {
+            return Ok(());
+        }
+
+        self.check(module, idx)?;
+
+        let analyses = self.analyses;
+        let analysis = &analyses[module];
+        let symbol = &analysis.resolution.symbols[idx];
+        let definition = span(&symbol.definition);
+        let (old, new) = (self.old_name, self.new_name);
+
+        // An imported name is renamed where it was declared, if that is part of the graph.
+        // Otherwise, a shorthand import keeps importing the old name, unless the module it imports
+        // from has had that export renamed, as when it re-exports the declaring module.
+        let target = match symbol.kind {
+            SymbolKind::Import => self.shorthand_import_target(module, definition),
+            _ => None,
+        };
+        let origin = target.and_then(|target| {
+            let symbol = self.analyses[target].exported_binding(old)?;
+            Some((target, symbol))
+        });
+        let keeps_key = origin.is_none() && !target.is_some_and(|t| self.exports.contains(&t));
+
+        if keeps_key && analysis.pattern_shorthands.contains(&definition) {
+            self.edit(module, definition, format!("{old}: {new}"));
+        } else {
+            self.edit(module, definition, new.to_string());
+        }
+
+        let mut exported = false;
+        for reference in analysis.resolution.references_to(idx) {
+            let range = span(&reference.range);
+
+            if analysis.record_shorthands.contains(&range) {
+                self.edit(module, range, format!("{old}: {new}"));
+            } else {
+                if analysis.export_shorthands.contains(&range) {
+                    exported = true;
+                }
+                self.edit(module, range, new.to_string());
+            }
+        }
+
+        if let Some((target, symbol)) = origin {
+            self.rename_symbol(target, symbol)?;
+        }
+        if exported {
+            self.rename_export(module)?;
+        }
+
+        Ok(())
+    }
+
+    /// The module that the import bound at `definition` in `module` imports from, if the import is
+    /// a shorthand element of a record pattern and that module is part of the graph.
+    fn shorthand_import_target(&self, module: usize, definition: (usize, usize)) -> Option<usize> {
+        self.graph.imports(module).into_iter().find_map(|import| {
+            let BindingPattern::Record { elements } = &import.pattern.value else {
+                return None;
+            };
+
+            elements
+                .value
+                .iter()
+                .any(|element| {
+                    matches!(
+                        &element.value,
+                        RecordBindingElement::Identifier { name } if span(&name.range) == definition
+                    )
+                })
+                .then_some(import.target)
+                .flatten()
+        })
+    }
+
+    /// Follows the renamed export of `module` into the modules that import it.
+    fn rename_export(&mut self, module: usize) -> Result<(), RenameError> {
+        if !self.exports.insert(module) {
+            return Ok(());
+        }
+
+        let exports = self.graph.get(module).module.value.exports();
+        if let Some(existing) = exports.get(self.new_name) {
+            return Err(RenameError(format!(
+                "{} already exports '{}'",
+                self.location(module, existing.name().range.0.absolute),
+                self.new_name
+            )));
+        }
+
+        for importer in 0..self.graph.len() {
+            for import in self.graph.imports(importer) {
+                if import.target == Some(module) {
+                    self.rename_import(importer, &import.pattern.value)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Renames the old name where `pattern` imports it from a module whose export was renamed.
+    fn rename_import(
+        &mut self,
+        module: usize,
+        pattern: &'ast BindingPattern<'ast>,
+    ) -> Result<(), RenameError> {
+        let analyses = self.analyses;
+        let analysis = &analyses[module];
+
+        // Names bound to the whole module, or to the rest of it.
+        let mut wholes = Vec::new();
+
+        match pattern {
+            BindingPattern::Identifier { name } => wholes.push(name),
+            BindingPattern::Record { elements } => {
+                for element in &elements.value {
+                    match &element.value {
+                        RecordBindingElement::Identifier { name }
+                            if name.value == self.old_name =>
+                        {
+                            let symbol = analysis
+                                .resolution
+                                .symbols
+                                .iter()
+                                .position(|s| span(&s.definition) == span(&name.range))
+                                .expect("import binding was not declared");
+                            self.rename_symbol(module, symbol)?;
+                        }
+                        RecordBindingElement::KeyValuePair { name, .. }
+                            if name.value == self.old_name =>
+                        {
+                            self.edit(module, span(&name.range), self.new_name.to_string());
+                        }
+                        RecordBindingElement::Rest { name } => wholes.push(name),
+                        _ => {}
+                    }
+                }
+            }
+            // Tuple patterns do not bind by name.
+            BindingPattern::Tuple { .. } => {}
+        }
+
+        for whole in wholes {
+            let Some(symbol) = analysis
+                .resolution
+                .symbols
+                .iter()
+                .position(|s| span(&s.definition) == span(&whole.range))
+            else {
+                continue;
+            };
+
+            let fields: Vec<(usize, usize)> = analysis
+                .field_accesses
+                .iter()
+                .filter(|(accessee, field)| {
+                    field.value == self.old_name
+                        && analysis.symbol_referenced_at(*accessee) == Some(symbol)
+                })
+                .map(|(_, field)| span(&field.range))
+                .collect();
+
+            for field in fields {
+                self.edit(module, field, self.new_name.to_string());
+            }
+
+            if analysis.reexports.contains(&whole.value) {
+                self.rename_export(module)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks that renaming symbol `idx` of `module` does not change what any name refers to.
+    fn check(&self, module: usize, idx: usize) -> Result<(), RenameError> {
+        let resolution = &self.analyses[module].resolution;
+        let symbol = &resolution.symbols[idx];
+        let namespace = symbol.kind.namespace();
+        let width = |scope: (usize, usize)| scope.1 - scope.0;
+
+        if let Some(existing) = resolution.symbols.iter().find(|s| {
+            s.scope == symbol.scope && s.kind.namespace() == namespace && s.name == self.new_name
+        }) {
+            return Err(RenameError(format!(
+                "'{}' is already declared at {}",
+                self.new_name,
+                self.location(module, existing.definition.0.absolute)
+            )));
+        }
+
+        // A reference to the symbol would instead refer to a nearer binding of the new name.
+        for reference in resolution.references_to(idx) {
+            let offset = reference.range.0.absolute;
+
+            if let Some(other) = resolution.lookup(self.new_name, namespace, offset) {
+                if width(resolution.symbols[other].scope) < width(symbol.scope) {
+                    return Err(RenameError(format!(
+                        "the reference at {} would refer to the '{}' declared at {}",
+                        self.location(module, offset),
+                        self.new_name,
+                        self.location(module, resolution.symbols[other].definition.0.absolute)
+                    )));
+                }
+            }
+        }
+
+        // A reference to another binding of the new name would instead refer to the symbol.
+        for reference in &resolution.references {
+            let offset = reference.range.0.absolute;
+
+            if reference.name != self.new_name
+                || reference.namespace != namespace
+                || offset < symbol.scope.0
+                || offset > symbol.scope.1
+                || offset < symbol.visible_from
+            {
+                continue;
+            }
+
+            let captured = match reference.symbol {
+                Some(other) => width(resolution.symbols[other].scope) > width(symbol.scope),
+                None => true,
+            };
+
+            if captured {
+                return Err(RenameError(format!(
+                    "the renamed binding would shadow '{}' where it is used at {}",
+                    self.new_name,
+                    self.location(module, offset)
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::incremental::{apply_edits, parse_module};
+
+    /// Renames the symbol at the first occurrence of `at` in the `module`th of `files` to
+    /// `new_name`, returning the source of every file afterwards.
+    fn rename_in(
+        files: &[(&str, &str)],
+        (module, at): (usize, &str),
+        new_name: &str,
+    ) -> Result<Vec<String>, String> {
+        let parsed: Vec<_> = files
+            .iter()
+            .map(|(_, source)| parse_module(source))
+            .collect();
+
+        let mut graph = ModuleGraph::new();
+        for ((path, source), parsed) in files.iter().zip(&parsed) {
+            graph.add(path, source, parsed.module.as_ref().unwrap());
+        }
+
+        let offset = files[module].1.find(at).unwrap();
+        let edits = rename(&graph, module, offset, new_name).map_err(|e| e.0)?;
+
+        Ok(files
+            .iter()
+            .map(
+                |(path, source)| match edits.iter().find(|file| file.path == *path) {
+                    Some(file) => apply_edits(source, &file.edits).unwrap(),
+                    None => source.to_string(),
+                },
+            )
+            .collect())
+    }
+
+    const LIB: (&str, &str) = ("lib.sdp", "fn twice(x) -> x * 2;\nexport { twice };\n");
+    const RENAMED_LIB: &str = "fn double(x) -> x * 2;\nexport { double };\n";
+
+    #[test]
+    fn shorthand_fields_keep_their_keys() {
+        assert_eq!(
+            rename_in(
+                &[("main.sdp", "fn f(name) -> { name };\n")],
+                (0, "name"),
+                "title"
+            ),
+            Ok(vec!["fn f(title) -> { name: title };\n".to_string()])
+        );
+    }
+
+    #[test]
+    fn shorthand_imports_follow_the_export() {
+        let main = (
+            "main.sdp",
+            "import { twice } = use(\"./lib.sdp\");\nmain twice(2);\n",
+        );
+
+        assert_eq!(
+            rename_in(&[LIB, main], (1, "twice(2)"), "double"),
+            Ok(vec![
+                RENAMED_LIB.to_string(),
+                "import { double } = use(\"./lib.sdp\");\nmain double(2);\n".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn import_keys_are_renamed() {
+        let main = (
+            "main.sdp",
+            "import { twice: t } = use(\"./lib.sdp\");\nmain t(2);\n",
+        );
+
+        assert_eq!(
+            rename_in(&[LIB, main], (0, "twice"), "double"),
+            Ok(vec![
+                RENAMED_LIB.to_string(),
+                "import { double: t } = use(\"./lib.sdp\");\nmain t(2);\n".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn fields_of_imported_modules_are_renamed() {
+        let main = (
+            "main.sdp",
+            "import lib = use(\"./lib.sdp\");\nmain lib.twice(2);\n",
+        );
+
+        assert_eq!(
+            rename_in(&[LIB, main], (1, "twice"), "double"),
+            Ok(vec![
+                RENAMED_LIB.to_string(),
+                "import lib = use(\"./lib.sdp\");\nmain lib.double(2);\n".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn rest_imports_and_reexports_are_followed() {
+        let mid = (
+            "mid.sdp",
+            "import lib = use(\"./lib.sdp\");\nexport { ...lib };\n",
+        );
+        let main = (
+            "main.sdp",
+            "import { twice } = use(\"./mid.sdp\");\nmain twice(2);\n",
+        );
+        let rest = (
+            "rest.sdp",
+            "import { ...rest } = use(\"./lib.sdp\");\nmain rest.twice(2);\n",
+        );
+
+        assert_eq!(
+            rename_in(&[LIB, mid, main, rest], (0, "twice"), "double"),
+            Ok(vec![
+                RENAMED_LIB.to_string(),
+                mid.1.to_string(),
+                "import { double } = use(\"./mid.sdp\");\nmain double(2);\n".to_string(),
+                "import { ...rest } = use(\"./lib.sdp\");\nmain rest.double(2);\n".to_string(),
+            ])
+        );
+    }
+
+    #[test]
+    fn renames_that_change_meaning_are_refused() {
+        let declared = ("main.sdp", "const a = 1;\nconst b = 2;\nmain a;\n");
+        assert_eq!(
+            rename_in(&[declared], (0, "a"), "b"),
+            Err("'b' is already declared at main.sdp:2".to_string())
+        );
+
+        let captured = ("main.sdp", "const a = 1;\nfn f(b) -> a + b;\n");
+        assert_eq!(
+            rename_in(&[captured], (0, "a"), "b"),
+            Err("the reference at main.sdp:2 would refer to the 'b' declared at main.sdp:2".into())
+        );
+
+        let shadowing = ("main.sdp", "const a = 1;\nfn f(b) -> b + a;\n");
+        assert_eq!(
+            rename_in(&[shadowing], (0, "b)"), "a"),
+            Err("the renamed binding would shadow 'a' where it is used at main.sdp:2".into())
+        );
+
+        let unresolved = ("main.sdp", "const a = 1;\nmain print(a);\n");
+        assert_eq!(
+            rename_in(&[unresolved], (0, "a"), "print"),
+            Err("the renamed binding would shadow 'print' where it is used at main.sdp:2".into())
+        );
+
+        assert_eq!(
+            rename_in(&[declared], (0, "a"), "fn"),
+            Err("'fn' is not a valid name".to_string())
+        );
+    }
+
+    #[test]
+    fn valid_names() {
+        for name in ["x", "_", "_tmp", "snake_case2", "café", "π"] {
+            assert!(is_valid_name(name), "{name}");
+        }
+        for name in [
+            "", "2x", "a-b", "a b", "a.b", "x?", "fn", "none", "as", "with",
+        ] {
+            assert!(!is_valid_name(name), "{name}");
+        }
+    }
+}
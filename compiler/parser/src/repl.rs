@@ -0,0 +1,345 @@
+//! An interactive read-eval-print loop.
+//!
+//! [`Repl`] accepts one line at a time. Lines are buffered until the input has no unclosed
+//! delimiters, and the complete input is then treated as top-level declarations, an expression, or
+//! statements, in that order of preference. Declarations and `let` statements add to an environment
+//! that persists across inputs. Lines starting with `:` are meta-commands; see [`HELP`].
+
+use seglisp::{parse::ParseNode, DiagnosticSeverity};
+
+use crate::{
+    eval::{Host, Interpreter, Value},
+    incremental::ParsedModule,
+    owned::OwnedModule,
+    scan::{scan, Kind, Lexeme},
+    types::TypeEnv,
+    Declaration, Expression, Module, Statement,
+};
+
+/// The text shown by `:help`.
+pub const HELP: &str = "\
+Enter declarations (const, fn, type, interface), expressions, or statements.
+Input continues over several lines until every (, [, and { is closed.
+
+  :type <expr>   show the inferred type of an expression
+  :ast <input>   show how an input parses
+  :help          show this message
+  :quit          leave the REPL";
+
+/// The declarations available in every session: the core library, `lib/core/lib.sdp`.
+pub const PRELUDE: &str = include_str!("../../../lib/core/lib.sdp");
+
+/// The result of feeding a line to the REPL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reply {
+    /// The input so far is incomplete, and more lines are needed.
+    Incomplete,
+    /// The input was handled. The string is the text to show, which may be empty.
+    Output(String),
+    /// The user asked to leave.
+    Quit,
+}
+
+/// An input, parsed.
+enum Input {
+    Declarations(&'static ParseNode<Module<'static>>),
+    Expression(&'static ParseNode<Expression<'static>>),
+    Statements(&'static [ParseNode<Statement<'static>>]),
+}
+
+/// The source and tree of every input of a session.
+///
+/// Values created by an input, such as closures, refer to its tree and can outlive the input, so
+/// every input is kept until the session ends. A REPL session only reads as much input as a person
+/// types into it.
+#[derive(Default)]
+struct Inputs {
+    modules: Vec<Box<OwnedModule>>,
+}
+
+impl Inputs {
+    /// Parses `source` and keeps it for the rest of the session.
+    fn parse(&mut self, source: &str) -> &'static ParsedModule<'static> {
+        let module = Box::new(OwnedModule::parse(source));
+
+        // SAFETY: the module is boxed, so its tree stays put as `modules` grows, and it is never
+        // removed or mutated. `Inputs` is only held by `Repl`, which declares it after the
+        // interpreter and type environment so that it is dropped after every value that may refer
+        // to the tree, and which never hands a reference to the tree out.
+        let parsed = unsafe {
+            &*(module.parsed() as *const ParsedModule<'_> as *const ParsedModule<'static>)
+        };
+
+        self.modules.push(module);
+        parsed
+    }
+}
+
+/// A REPL session.
+pub struct Repl<H: Host> {
+    // `interpreter` refers to the trees in `inputs`, so it is declared first to be dropped first.
+    interpreter: Interpreter<'static, H>,
+    types: TypeEnv,
+    buffer: String,
+    inputs: Inputs,
+}
+
+impl<H: Host> Repl<H> {
+    /// Starts a session with the core library declared.
+    pub fn new(host: H) -> Self {
+        let mut repl = Repl {
+            interpreter: Interpreter::new(host),
+            types: TypeEnv::new(),
+            buffer: String::new(),
+            inputs: Inputs::default(),
+        };
+
+        let parsed = repl.inputs.parse(PRELUDE);
+        if let Some(module) = &parsed.module {
+            for decl in &module.value.declarations {
+                if let Declaration::Const { .. } | Declaration::Function { .. } = &decl.value {
+                    let _ = repl.interpreter.declare(decl);
+                    repl.types.declare(&decl.value);
+                }
+            }
+        }
+
+        repl
+    }
+
+    /// The prompt to show before the next line.
+    pub fn prompt(&self) -> &'static str {
+        if self.buffer.is_empty() {
+            "sdp> "
+        } else {
+            "...> "
+        }
+    }
+
+    /// Feeds one line of input.
+    pub fn feed(&mut self, line: &str) -> Reply {
+        if !self.buffer.is_empty() {
+            self.buffer.push('\n');
+        }
+        self.buffer.push_str(line.trim_end_matches(['\r', '\n']));
+
+        if is_incomplete(&self.buffer) {
+            return Reply::Incomplete;
+        }
+
+        let input = std::mem::take(&mut self.buffer);
+        self.handle(input.trim())
+    }
+
+    /// Handles a complete input.
+    pub fn handle(&mut self, input: &str) -> Reply {
+        let input = input.trim().trim_end_matches(';').trim_end();
+
+        if let Some(command) = input.strip_prefix(':') {
+            let (name, argument) = command
+                .split_once(char::is_whitespace)
+                .map_or((command, ""), |(name, argument)| (name, argument.trim()));
+
+            return match name {
+                "q" | "quit" => Reply::Quit,
+                "h" | "help" => Reply::Output(HELP.into()),
+                "t" | "type" => Reply::Output(self.type_of(argument)),
+                "ast" => Reply::Output(match parse_input(&mut self.inputs, argument) {
+                    Ok(input) => show_ast(&input),
+                    Err(message) => message,
+                }),
+                _ => Reply::Output(format!("unknown command ':{name}', try :help")),
+            };
+        }
+
+        if input.is_empty() {
+            return Reply::Output(String::new());
+        }
+
+        Reply::Output(match parse_input(&mut self.inputs, input) {
+            Ok(input) => self.run(input),
+            Err(message) => message,
+        })
+    }
+
+    fn type_of(&mut self, input: &str) -> String {
+        match parse_expression(&mut self.inputs, input) {
+            Ok(expr) => self.types.infer(&expr.value).to_string(),
+            Err(message) => message,
+        }
+    }
+
+    fn run(&mut self, input: Input) -> String {
+        let result = match input {
+            Input::Declarations(module) => {
+                let mut result = Ok(String::new());
+
+                for decl in &module.value.declarations {
+                    if let Err(e) = self.interpreter.declare(decl) {
+                        result = Err(e);
+                        break;
+                    }
+                    self.types.declare(&decl.value);
+
+                    if let Declaration::Main { body, .. } = &decl.value {
+                        let globals = self.interpreter.globals().clone();
+                        if let Err(e) = self
+                            .interpreter
+                            .eval(body, &globals)
+                            .and_then(|value| self.interpreter.run(value))
+                        {
+                            result = Err(e);
+                            break;
+                        }
+                    }
+                }
+
+                result
+            }
+            Input::Expression(expr) => {
+                let globals = self.interpreter.globals().clone();
+
+                self.interpreter.eval(expr, &globals).and_then(|value| {
+                    if let Value::Action(_) = value {
+                        self.interpreter.run(value).map(|_| String::new())
+                    } else {
+                        self.interpreter.show_value(&value)
+                    }
+                })
+            }
+            Input::Statements(statements) => {
+                let globals = self.interpreter.globals().clone();
+
+                let result = self
+                    .interpreter
+                    .run_statements(statements, &globals)
+                    .map(|_| String::new());
+
+                for statement in statements {
+                    self.types.infer_statement(&statement.value);
+                }
+
+                result
+            }
+        };
+
+        result.unwrap_or_else(|e| format!("error: {}", e.message))
+    }
+}
+
+/// Returns true if `source` has an unclosed `(`, `[`, or `{`, or an unterminated string or block
+/// comment, so that more input is needed before it can be read.
+///
+/// This follows the reader's rules for delimiters, through the same scan that the parser uses to
+/// limit nesting: brackets inside string literals and comments do not count, and a stray closing
+/// delimiter is left for the reader to report.
+pub fn is_incomplete(source: &str) -> bool {
+    let mut depth = 0usize;
+
+    for Lexeme { kind, .. } in scan(source) {
+        match kind {
+            Kind::Open => depth += 1,
+            Kind::Close => depth = depth.saturating_sub(1),
+            Kind::String { terminated: false } | Kind::BlockComment { terminated: false } => {
+                return true
+            }
+            _ => {}
+        }
+    }
+
+    depth > 0
+}
+
+/// Returns the parsed module if it parsed without errors, and the error messages otherwise.
+fn without_errors(
+    parsed: &'static ParsedModule<'static>,
+) -> Result<&'static ParseNode<Module<'static>>, String> {
+    let errors: Vec<&str> = parsed
+        .diagnostics
+        .iter()
+        .filter(|d| matches!(d.severity, DiagnosticSeverity::Error))
+        .map(|d| d.message.as_str())
+        .collect();
+
+    match &parsed.module {
+        Some(module) if errors.is_empty() => Ok(module),
+        _ if errors.is_empty() => Err("error: could not parse input".into()),
+        _ => Err(errors
+            .iter()
+            .map(|message| format!("error: {message}"))
+            .collect::<Vec<_>>()
+            .join("\n")),
+    }
+}
+
+/// Returns true if `input` starts with a keyword that only begins a top-level declaration.
+fn is_declaration(input: &str) -> bool {
+    let mut words = input.split(|c: char| !(c.is_alphanumeric() || c == '_'));
+
+    match words.next() {
+        Some("const" | "type" | "interface" | "import" | "export" | "main") => true,
+        // `fn name(...)` declares a function, but `fn (...)` is a function expression.
+        Some("fn") => input[2..]
+            .trim_start()
+            .starts_with(|c: char| c.is_alphabetic() || c == '_'),
+        _ => false,
+    }
+}
+
+fn main_body(
+    module: &'static ParseNode<Module<'static>>,
+) -> Option<&'static ParseNode<Expression<'static>>> {
+    match module.value.declarations.as_slice() {
+        [decl] => match &decl.value {
+            Declaration::Main { body, .. } => Some(&**body),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn parse_expression(
+    inputs: &mut Inputs,
+    input: &str,
+) -> Result<&'static ParseNode<Expression<'static>>, String> {
+    let module = without_errors(inputs.parse(&format!("main {input};")))?;
+    main_body(module).ok_or_else(|| "error: expected a single expression".into())
+}
+
+fn parse_input(inputs: &mut Inputs, input: &str) -> Result<Input, String> {
+    if is_declaration(input) {
+        return without_errors(inputs.parse(&format!("{input};"))).map(Input::Declarations);
+    }
+
+    let expression_error = match parse_expression(inputs, input) {
+        Ok(expr) => return Ok(Input::Expression(expr)),
+        Err(message) => message,
+    };
+
+    let procedure = without_errors(inputs.parse(&format!("main #[\n{input};\n];")))
+        .ok()
+        .and_then(main_body);
+
+    match procedure.map(|body| &body.value) {
+        Some(Expression::Procedure { body }) => Ok(Input::Statements(&body.value)),
+        _ => Err(expression_error),
+    }
+}
+
+fn show_ast(input: &Input) -> String {
+    match input {
+        Input::Declarations(module) => module
+            .value
+            .declarations
+            .iter()
+            .map(|decl| decl.value.to_string())
+            .collect::<Vec<_>>()
+            .join(";\n"),
+        Input::Expression(expr) => expr.value.to_string(),
+        Input::Statements(statements) => statements
+            .iter()
+            .map(|statement| statement.value.to_string())
+            .collect::<Vec<_>>()
+            .join(";\n"),
+    }
+}
@@ -0,0 +1,479 @@
+//! Name resolution.
+//!
+//! [`resolve`] walks a module and binds every name reference to the declaration that introduces
+//! it. Values and types live in separate namespaces. Top-level declarations are visible throughout
+//! the module, the bindings of a `with` expression are visible in their own values (so they can be
+//! recursive), and `let` statements are visible to the statements that follow them in the same
+//! procedure.
+
+use std::ops::ControlFlow;
+
+use seglisp::parse::ParseNode;
+
+use crate::{
+    exports::collect_pattern_names,
+    visit::{self, Visit},
+    Declaration, Expression, GenericParameter, Module, ParsedVec, RecordElement, Statement, Type,
+    Verbatim,
+};
+
+/// The namespace a name is declared in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Namespace {
+    Value,
+    Type,
+}
+
+/// The construct that declares a symbol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SymbolKind {
+    Const,
+    Function,
+    TypeAlias,
+    Interface,
+    Import,
+    GenericParameter,
+    Parameter,
+    /// The name of a named function expression, visible in its own body.
+    FunctionName,
+    With,
+    Let,
+    ForIn,
+}
+
+impl SymbolKind {
+    pub fn namespace(&self) -> Namespace {
+        match self {
+            SymbolKind::TypeAlias | SymbolKind::Interface | SymbolKind::GenericParameter => {
+                Namespace::Type
+            }
+            _ => Namespace::Value,
+        }
+    }
+
+    /// Returns true for symbols declared at the top level of a module.
+    pub fn is_global(&self) -> bool {
+        matches!(
+            self,
+            SymbolKind::Const
+                | SymbolKind::Function
+                | SymbolKind::TypeAlias
+                | SymbolKind::Interface
+                | SymbolKind::Import
+        )
+    }
+}
+
+/// A declared name.
+#[derive(Debug, Clone)]
+pub struct Symbol<'ast> {
+    pub name: &'ast str,
+    pub kind: SymbolKind,
+    /// The range of the identifier that declares the symbol.
+    pub definition: seglisp::Range,
+    /// The byte range of the scope the symbol is declared in.
+    pub scope: (usize, usize),
+    /// The byte offset from which the symbol is visible within its scope.
+    pub visible_from: usize,
+}
+
+/// A use of a name.
+#[derive(Debug, Clone)]
+pub struct Reference<'ast> {
+    pub name: &'ast str,
+    pub namespace: Namespace,
+    pub range: seglisp::Range,
+    /// The index of the symbol the reference resolves to, or `None` if the name is unbound.
+    pub symbol: Option<usize>,
+}
+
+/// The symbols declared by a module and the references to them.
+#[derive(Debug, Clone, Default)]
+pub struct Resolution<'ast> {
+    pub symbols: Vec<Symbol<'ast>>,
+    pub references: Vec<Reference<'ast>>,
+}
+
+fn contains(range: &seglisp::Range, offset: usize) -> bool {
+    range.0.absolute <= offset && offset <= range.1.absolute
+}
+
+impl<'ast> Resolution<'ast> {
+    /// The symbol declared or referenced by the identifier at `offset`.
+    pub fn symbol_at(&self, offset: usize) -> Option<usize> {
+        self.symbols
+            .iter()
+            .position(|s| contains(&s.definition, offset))
+            .or_else(|| {
+                self.references
+                    .iter()
+                    .find(|r| contains(&r.range, offset))
+                    .and_then(|r| r.symbol)
+            })
+    }
+
+    /// Every reference that resolves to `symbol`.
+    pub fn references_to(&self, symbol: usize) -> impl Iterator<Item = &Reference<'ast>> {
+        self.references
+            .iter()
+            .filter(move |r| r.symbol == Some(symbol))
+    }
+
+    /// References to names that are not declared anywhere in scope.
+    pub fn unresolved(&self) -> impl Iterator<Item = &Reference<'ast>> {
+        self.references.iter().filter(|r| r.symbol.is_none())
+    }
+
+    /// The symbols visible at `offset`, innermost first, without the symbols they shadow.
+    pub fn visible_at(&self, offset: usize) -> Vec<usize> {
+        let mut visible: Vec<usize> = (0..self.symbols.len())
+            .filter(|idx| {
+                let s = &self.symbols[*idx];
+                s.scope.0 <= offset && offset <= s.scope.1 && s.visible_from <= offset
+            })
+            .collect();
+
+        // Narrower scopes are nested inside wider ones, and later declarations in the same scope
+        // shadow earlier ones.
+        visible.sort_by_key(|idx| {
+            let s = &self.symbols[*idx];
+            (s.scope.1 - s.scope.0, usize::MAX - s.visible_from)
+        });
+
+        let mut seen = Vec::new();
+        visible.retain(|idx| {
+            let s = &self.symbols[*idx];
+            let key = (s.name, s.kind.namespace());
+            if seen.contains(&key) {
+                false
+            } else {
+                seen.push(key);
+                true
+            }
+        });
+
+        visible
+    }
+
+    /// Looks up `name` as it would be resolved at `offset`.
+    pub fn lookup(&self, name: &str, namespace: Namespace, offset: usize) -> Option<usize> {
+        self.visible_at(offset).into_iter().find(|idx| {
+            let s = &self.symbols[*idx];
+            s.name == name && s.kind.namespace() == namespace
+        })
+    }
+}
+
+/// Resolves every name in `module`.
+pub fn resolve<'ast>(module: &'ast ParseNode<Module<'ast>>) -> Resolution<'ast> {
+    let mut resolver = Resolver {
+        resolution: Resolution::default(),
+        scopes: Vec::new(),
+    };
+
+    let _ = resolver.visit_module(module);
+
+    resolver.resolution
+}
+
+/// The names used in `expression` that are not bound within it. Used for expressions that can
+/// only see the top-level bindings of their module, such as the values of an `export`.
+pub(crate) fn free_names<'ast>(
+    expression: &'ast ParseNode<Expression<'ast>>,
+) -> Vec<Reference<'ast>> {
+    let mut resolver = Resolver {
+        resolution: Resolution::default(),
+        scopes: Vec::new(),
+    };
+
+    resolver.push_scope(&expression.range);
+    let _ = resolver.visit_expression(expression);
+
+    resolver
+        .resolution
+        .references
+        .into_iter()
+        .filter(|r| r.symbol.is_none() && r.namespace == Namespace::Value)
+        .collect()
+}
+
+struct Scope<'ast> {
+    range: (usize, usize),
+    symbols: Vec<(&'ast str, Namespace, usize)>,
+}
+
+struct Resolver<'ast> {
+    resolution: Resolution<'ast>,
+    scopes: Vec<Scope<'ast>>,
+}
+
+impl<'ast> Resolver<'ast> {
+    fn push_scope(&mut self, range: &seglisp::Range) {
+        self.scopes.push(Scope {
+            range: (range.0.absolute, range.1.absolute),
+            symbols: Vec::new(),
+        });
+    }
+
+    fn pop_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn declare(&mut self, name: &Verbatim<'ast>, kind: SymbolKind, visible_from: Option<usize>) {
+        let scope = self.scopes.last_mut().expect("no scope to declare in");
+
+        let idx = self.resolution.symbols.len();
+        self.resolution.symbols.push(Symbol {
+            name: name.value,
+            kind,
+            definition: name.range,
+            scope: scope.range,
+            visible_from: visible_from.unwrap_or(scope.range.0),
+        });
+
+        scope.symbols.push((name.value, kind.namespace(), idx));
+    }
+
+    fn reference(&mut self, name: &'ast str, namespace: Namespace, range: seglisp::Range) {
+        let symbol = self.scopes.iter().rev().find_map(|scope| {
+            scope
+                .symbols
+                .iter()
+                .rev()
+                .find(|(n, ns, _)| *n == name && *ns == namespace)
+                .map(|(_, _, idx)| *idx)
+        });
+
+        self.resolution.references.push(Reference {
+            name,
+            namespace,
+            range,
+            symbol,
+        });
+    }
+
+    fn declare_generics(&mut self, generics: &'ast Option<ParsedVec<GenericParameter<'ast>>>) {
+        for p in generics.iter().flat_map(|g| &g.value) {
+            self.declare(&p.value.name, SymbolKind::GenericParameter, None);
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for Resolver<'ast> {
+    fn visit_module(&mut self, node: &'ast ParseNode<Module<'ast>>) -> ControlFlow<()> {
+        self.push_scope(&node.range);
+
+        // Top-level declarations are visible throughout the module.
+        for decl in &node.value.declarations {
+            match &decl.value {
+                Declaration::Const { identifier, .. } => {
+                    self.declare(identifier, SymbolKind::Const, None)
+                }
+                Declaration::Function { identifier, .. } => {
+                    self.declare(identifier, SymbolKind::Function, None)
+                }
+                Declaration::TypeAlias { name, .. } => {
+                    self.declare(name, SymbolKind::TypeAlias, None)
+                }
+                Declaration::Interface { name, .. } => {
+                    self.declare(name, SymbolKind::Interface, None)
+                }
+                Declaration::Import { pattern, .. } => {
+                    let mut names = Vec::new();
+                    collect_pattern_names(&pattern.value, &mut |name| names.push(name));
+                    for name in names {
+                        self.declare(name, SymbolKind::Import, None);
+                    }
+                }
+                Declaration::Main { .. } | Declaration::Export { .. } => {}
+            }
+        }
+
+        visit::walk_module(self, node)?;
+
+        self.pop_scope();
+        ControlFlow::Continue(())
+    }
+
+    fn visit_declaration(&mut self, node: &'ast ParseNode<Declaration<'ast>>) -> ControlFlow<()> {
+        match &node.value {
+            Declaration::Function {
+                generic_parameters,
+                parameters,
+                constraint,
+                body,
+                ..
+            } => {
+                self.push_scope(&node.range);
+                self.declare_generics(generic_parameters);
+                for p in &parameters.value {
+                    if let Some(type_) = &p.value.type_ {
+                        self.visit_type_constraint(type_)?;
+                    }
+                }
+                for p in &parameters.value {
+                    self.declare(&p.value.name, SymbolKind::Parameter, None);
+                }
+                if let Some(constraint) = constraint {
+                    self.visit_type_constraint(constraint)?;
+                }
+                self.visit_expression(body)?;
+                self.pop_scope();
+            }
+            Declaration::Const { type_, value, .. } => {
+                if let Some(type_) = type_ {
+                    self.visit_type_constraint(type_)?;
+                }
+                self.visit_expression(value)?;
+            }
+            Declaration::Export { elements, .. } => {
+                for element in &elements.value {
+                    self.visit_record_element(element)?;
+                }
+            }
+            Declaration::TypeAlias {
+                generic_parameters,
+                value,
+                ..
+            } => {
+                self.push_scope(&node.range);
+                self.declare_generics(generic_parameters);
+                self.visit_type(value)?;
+                self.pop_scope();
+            }
+            Declaration::Interface {
+                generic_parameters,
+                constraint,
+                body,
+                ..
+            } => {
+                self.push_scope(&node.range);
+                self.declare_generics(generic_parameters);
+                if let Some(constraint) = constraint {
+                    self.visit_type_constraint(constraint)?;
+                }
+                for field in &body.value {
+                    self.visit_type_constraint(&field.value.constraint)?;
+                }
+                self.pop_scope();
+            }
+            // Import patterns were declared with the module.
+            Declaration::Import { .. } => {}
+            Declaration::Main { body, .. } => self.visit_expression(body)?,
+        }
+
+        ControlFlow::Continue(())
+    }
+
+    fn visit_expression(&mut self, node: &'ast ParseNode<Expression<'ast>>) -> ControlFlow<()> {
+        match &node.value {
+            Expression::Name(name) => self.reference(name, Namespace::Value, node.range),
+            Expression::Function {
+                name,
+                generic_parameters,
+                parameters,
+                constraint,
+                body,
+                ..
+            } => {
+                self.push_scope(&node.range);
+                if let Some(name) = name {
+                    self.declare(name, SymbolKind::FunctionName, None);
+                }
+                self.declare_generics(generic_parameters);
+                for p in &parameters.value {
+                    if let Some(type_) = &p.value.type_ {
+                        self.visit_type_constraint(type_)?;
+                    }
+                }
+                for p in &parameters.value {
+                    self.declare(&p.value.name, SymbolKind::Parameter, None);
+                }
+                if let Some(constraint) = constraint {
+                    self.visit_type_constraint(constraint)?;
+                }
+                self.visit_expression(body)?;
+                self.pop_scope();
+            }
+            Expression::With { bindings, body, .. } => {
+                self.push_scope(&node.range);
+                for binding in &bindings.value {
+                    self.declare(&binding.value.symbol, SymbolKind::With, None);
+                }
+                for binding in &bindings.value {
+                    self.visit_expression(&binding.value.value)?;
+                }
+                self.visit_expression(body)?;
+                self.pop_scope();
+            }
+            Expression::Procedure { body } => {
+                self.push_scope(&node.range);
+                for stmt in &body.value {
+                    self.visit_statement(stmt)?;
+                }
+                self.pop_scope();
+            }
+            Expression::FieldAccess { accessee, .. } => self.visit_expression(accessee)?,
+            _ => visit::walk_expression(self, node)?,
+        }
+
+        ControlFlow::Continue(())
+    }
+
+    fn visit_record_element(
+        &mut self,
+        node: &'ast ParseNode<RecordElement<'ast>>,
+    ) -> ControlFlow<()> {
+        match &node.value {
+            RecordElement::Identifier { name } => {
+                self.reference(name.value, Namespace::Value, name.range)
+            }
+            RecordElement::KeyValuePair { value, .. } | RecordElement::Spread { value } => {
+                self.visit_expression(value)?
+            }
+        }
+
+        ControlFlow::Continue(())
+    }
+
+    fn visit_statement(&mut self, node: &'ast ParseNode<Statement<'ast>>) -> ControlFlow<()> {
+        match &node.value {
+            Statement::Let { assignment, .. } => {
+                self.visit_expression(&assignment.value.value)?;
+                self.declare(
+                    &assignment.value.symbol,
+                    SymbolKind::Let,
+                    Some(node.range.1.absolute),
+                );
+            }
+            Statement::Set(assignment) => {
+                let symbol = &assignment.value.symbol;
+                self.reference(symbol.value, Namespace::Value, symbol.range);
+                self.visit_expression(&assignment.value.value)?;
+            }
+            Statement::ForIn {
+                binding,
+                iterator,
+                body,
+                ..
+            } => {
+                self.visit_expression(iterator)?;
+                self.push_scope(&node.range);
+                self.declare(binding, SymbolKind::ForIn, Some(body.range.0.absolute));
+                self.visit_statement(body)?;
+                self.pop_scope();
+            }
+            _ => visit::walk_statement(self, node)?,
+        }
+
+        ControlFlow::Continue(())
+    }
+
+    fn visit_type(&mut self, node: &'ast ParseNode<Type<'ast>>) -> ControlFlow<()> {
+        if let Type::Reference { name, .. } = &node.value {
+            self.reference(name.value, Namespace::Type, name.range);
+        }
+
+        visit::walk_type(self, node)
+    }
+}
@@ -0,0 +1,160 @@
+//! A flat scan of source text for brackets, string literals and comments.
+//!
+//! The reader nests brackets recursively; this scan follows the same rules without recursing or
+//! building anything, for the places that only need to know where the brackets, strings and
+//! comments are: finding comments for the linter, deciding whether REPL input is complete, and
+//! measuring how deeply a source nests before reading it. Brackets inside strings and comments do
+//! not count, a `\` in a string escapes the character after it, and `//` comments run to the end
+//! of the line.
+
+use std::{iter::Peekable, ops::Range, str::CharIndices};
+
+/// What a [`Lexeme`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Kind {
+    /// `(`, `[` or `{`.
+    Open,
+    /// `)`, `]` or `}`.
+    Close,
+    /// A string literal, including its quotes.
+    String { terminated: bool },
+    /// A `//` comment, up to but not including the end of its line.
+    LineComment,
+    /// A `/* */` comment, including its markers.
+    BlockComment { terminated: bool },
+    /// A line break outside strings and comments.
+    Newline,
+    /// Any other character that is not whitespace.
+    Other,
+}
+
+/// A piece of source text found by [`scan`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Lexeme {
+    pub kind: Kind,
+    /// The bytes of the lexeme.
+    pub range: Range<usize>,
+}
+
+/// Scans `source` from the start. Whitespace other than line breaks is skipped.
+pub(crate) fn scan(source: &str) -> Scanner<'_> {
+    Scanner {
+        source,
+        chars: source.char_indices().peekable(),
+    }
+}
+
+pub(crate) struct Scanner<'a> {
+    source: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+}
+
+impl Scanner<'_> {
+    /// The byte offset of the next character, or the end of the source.
+    fn offset(&mut self) -> usize {
+        self.chars.peek().map_or(self.source.len(), |(i, _)| *i)
+    }
+
+    fn next_is(&mut self, c: char) -> bool {
+        self.chars.peek().is_some_and(|(_, next)| *next == c)
+    }
+}
+
+impl Iterator for Scanner<'_> {
+    type Item = Lexeme;
+
+    fn next(&mut self) -> Option<Lexeme> {
+        loop {
+            let (start, c) = self.chars.next()?;
+
+            let kind = match c {
+                '(' | '[' | '{' => Kind::Open,
+                ')' | ']' | '}' => Kind::Close,
+                '\n' => Kind::Newline,
+                '"' => {
+                    let mut terminated = false;
+                    while let Some((_, c)) = self.chars.next() {
+                        match c {
+                            '\\' => {
+                                self.chars.next();
+                            }
+                            '"' => {
+                                terminated = true;
+                                break;
+                            }
+                            _ => {}
+                        }
+                    }
+                    Kind::String { terminated }
+                }
+                '/' if self.next_is('/') => {
+                    while self.chars.peek().is_some_and(|(_, c)| *c != '\n') {
+                        self.chars.next();
+                    }
+                    Kind::LineComment
+                }
+                '/' if self.next_is('*') => {
+                    self.chars.next();
+                    let mut terminated = false;
+                    while let Some((_, c)) = self.chars.next() {
+                        if c == '*' && self.next_is('/') {
+                            self.chars.next();
+                            terminated = true;
+                            break;
+                        }
+                    }
+                    Kind::BlockComment { terminated }
+                }
+                c if c.is_whitespace() => continue,
+                _ => Kind::Other,
+            };
+
+            return Some(Lexeme {
+                kind,
+                range: start..self.offset(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(source: &str) -> Vec<(Kind, &str)> {
+        scan(source)
+            .map(|lexeme| (lexeme.kind, &source[lexeme.range]))
+            .collect()
+    }
+
+    #[test]
+    fn brackets_inside_strings_and_comments_are_skipped() {
+        assert_eq!(
+            kinds("f(\"(\\\"\", /* ) */ x) // ]\n"),
+            [
+                (Kind::Other, "f"),
+                (Kind::Open, "("),
+                (Kind::String { terminated: true }, "\"(\\\"\""),
+                (Kind::Other, ","),
+                (Kind::BlockComment { terminated: true }, "/* ) */"),
+                (Kind::Other, "x"),
+                (Kind::Close, ")"),
+                (Kind::LineComment, "// ]"),
+                (Kind::Newline, "\n"),
+            ]
+        );
+    }
+
+    #[test]
+    fn unterminated_strings_and_comments_run_to_the_end() {
+        assert_eq!(
+            kinds("\"a\n("),
+            [(Kind::String { terminated: false }, "\"a\n(")]
+        );
+        assert_eq!(
+            kinds("/*/ ("),
+            [(Kind::BlockComment { terminated: false }, "/*/ (")]
+        );
+        assert_eq!(kinds("// ("), [(Kind::LineComment, "// (")]);
+    }
+}
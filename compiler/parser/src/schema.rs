@@ -0,0 +1,536 @@
+//! A description of the shape of every AST type, for generating code in other languages.
+//!
+//! `gen_ts` reads [`TYPES`] to write TypeScript type guards, visitors, a walker, and builders that
+//! match the Rust definitions, and `gen_schema` reads it to write the JSON schema of the parse
+//! format in `json`. Each description is checked against its type when the crate is
+//! compiled: adding, removing, or renaming a variant or field, or changing what kind of value a
+//! field holds, fails to compile until the description here is updated to match.
+
+use std::marker::PhantomData;
+
+use seglisp::parse::ParseNode;
+
+use crate::{
+    ArithmeticOp, Assignment, BindingPattern, CompareOp, Declaration, Expression, GenericParameter,
+    InterfaceField, Module, ParameterDeclaration, RecordBindingElement, RecordElement, Statement,
+    Type, TypeConstraint, UnaryOp, Verbatim,
+};
+
+/// What a field holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldKind {
+    /// A token or name, as a `ParseNode` of its text.
+    Token,
+    /// A bare string, such as a literal's text.
+    Text,
+    Boolean,
+    /// A `ParseNode` of the named type.
+    Node(&'static str),
+    /// A `ParseNode` of a list of `ParseNode`s of the named type.
+    List(&'static str),
+    /// A plain list of `ParseNode`s of the named type.
+    Items(&'static str),
+    Optional(&'static FieldKind),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Field {
+    /// The name of the field in Rust. Fields of tuple variants are named `_0`, `_1`, and so on.
+    pub name: &'static str,
+    pub kind: FieldKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shape {
+    Unit,
+    Tuple(&'static [Field]),
+    Struct(&'static [Field]),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Variant {
+    pub name: &'static str,
+    pub shape: Shape,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TypeKind {
+    Enum(&'static [Variant]),
+    Struct(&'static [Field]),
+}
+
+/// The shape of an AST type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TypeSchema {
+    pub name: &'static str,
+    pub kind: TypeKind,
+}
+
+// The checks below ask the compiler whether each field's type is of its described kind.
+#[allow(dead_code)]
+struct Token;
+#[allow(dead_code)]
+struct Text;
+#[allow(dead_code)]
+struct Boolean;
+#[allow(dead_code)]
+struct Node<T>(PhantomData<T>);
+#[allow(dead_code)]
+struct List<T>(PhantomData<T>);
+#[allow(dead_code)]
+struct Items<T>(PhantomData<T>);
+#[allow(dead_code)]
+struct Optional<K>(PhantomData<K>);
+
+trait Is<K> {}
+
+impl Is<Token> for Verbatim<'_> {}
+impl Is<Text> for &str {}
+impl Is<Text> for String {}
+impl Is<Boolean> for bool {}
+impl<T> Is<Node<T>> for ParseNode<T> {}
+impl<T> Is<Node<T>> for Box<ParseNode<T>> {}
+impl<T> Is<List<T>> for ParseNode<Vec<ParseNode<T>>> {}
+impl<T> Is<Items<T>> for Vec<ParseNode<T>> {}
+impl<K, T: Is<K>> Is<Optional<K>> for Option<T> {}
+
+#[allow(dead_code)]
+fn is<K, T: Is<K>>(_: &T) {}
+
+macro_rules! marker {
+    (token) => { Token };
+    (text) => { Text };
+    (boolean) => { Boolean };
+    (node($t:ty)) => { Node<$t> };
+    (list($t:ty)) => { List<$t> };
+    (items($t:ty)) => { Items<$t> };
+    (optional($($k:tt)*)) => { Optional<marker!($($k)*)> };
+}
+
+macro_rules! field_kind {
+    (token) => { FieldKind::Token };
+    (text) => { FieldKind::Text };
+    (boolean) => { FieldKind::Boolean };
+    (node($t:ident $(<$lt:lifetime>)?)) => { FieldKind::Node(stringify!($t)) };
+    (list($t:ident $(<$lt:lifetime>)?)) => { FieldKind::List(stringify!($t)) };
+    (items($t:ident $(<$lt:lifetime>)?)) => { FieldKind::Items(stringify!($t)) };
+    (optional($($k:tt)*)) => { FieldKind::Optional(&field_kind!($($k)*)) };
+}
+
+/// Describes a struct, and checks the description by destructuring the struct.
+macro_rules! struct_schema {
+    ($const:ident: $ty:ident<$lt:lifetime> {
+        $($field:ident: $kind:ident $(($($arg:tt)*))?),* $(,)?
+    }) => {
+        pub const $const: TypeSchema = TypeSchema {
+            name: stringify!($ty),
+            kind: TypeKind::Struct(&[$(Field {
+                name: stringify!($field),
+                kind: field_kind!($kind $(($($arg)*))?),
+            }),*]),
+        };
+
+        const _: () = {
+            #[allow(dead_code)]
+            fn check<$lt>(node: &$ty<$lt>) {
+                let $ty { $($field),* } = node;
+                $(is::<marker!($kind $(($($arg)*))?), _>($field);)*
+            }
+        };
+    };
+}
+
+/// Describes an enum, and checks the description by matching on every variant.
+macro_rules! enum_schema {
+    (@munch $const:ident $ty:ident [$($lt:lifetime)?] [$($variants:tt)*] [$($arms:tt)*]) => {
+        pub const $const: TypeSchema = TypeSchema {
+            name: stringify!($ty),
+            kind: TypeKind::Enum(&[$($variants)*]),
+        };
+
+        const _: () = {
+            #[allow(dead_code)]
+            fn check<$($lt)?>(node: &$ty<$($lt)?>) {
+                match node {
+                    $($arms)*
+                }
+            }
+        };
+    };
+    (@munch $const:ident $ty:ident [$($lt:lifetime)?] [$($variants:tt)*] [$($arms:tt)*]
+        $variant:ident $(, $($rest:tt)*)?
+    ) => {
+        enum_schema!(@munch $const $ty [$($lt)?]
+            [$($variants)* Variant { name: stringify!($variant), shape: Shape::Unit },]
+            [$($arms)* $ty::$variant => {}]
+            $($($rest)*)?
+        );
+    };
+    (@munch $const:ident $ty:ident [$($lt:lifetime)?] [$($variants:tt)*] [$($arms:tt)*]
+        $variant:ident($($field:ident: $kind:ident $(($($arg:tt)*))?),*) $(, $($rest:tt)*)?
+    ) => {
+        enum_schema!(@munch $const $ty [$($lt)?]
+            [$($variants)* Variant {
+                name: stringify!($variant),
+                shape: Shape::Tuple(&[$(Field {
+                    name: stringify!($field),
+                    kind: field_kind!($kind $(($($arg)*))?),
+                }),*]),
+            },]
+            [$($arms)* $ty::$variant($($field),*) => {
+                $(is::<marker!($kind $(($($arg)*))?), _>($field);)*
+            }]
+            $($($rest)*)?
+        );
+    };
+    (@munch $const:ident $ty:ident [$($lt:lifetime)?] [$($variants:tt)*] [$($arms:tt)*]
+        $variant:ident { $($field:ident: $kind:ident $(($($arg:tt)*))?),* $(,)? } $(, $($rest:tt)*)?
+    ) => {
+        enum_schema!(@munch $const $ty [$($lt)?]
+            [$($variants)* Variant {
+                name: stringify!($variant),
+                shape: Shape::Struct(&[$(Field {
+                    name: stringify!($field),
+                    kind: field_kind!($kind $(($($arg)*))?),
+                }),*]),
+            },]
+            [$($arms)* $ty::$variant { $($field),* } => {
+                $(is::<marker!($kind $(($($arg)*))?), _>($field);)*
+            }]
+            $($($rest)*)?
+        );
+    };
+    ($const:ident: $ty:ident $(<$lt:lifetime>)? { $($body:tt)* }) => {
+        enum_schema!(@munch $const $ty [$($lt)?] [] [] $($body)*);
+    };
+}
+
+struct_schema! {
+    MODULE: Module<'ast> {
+        declarations: items(Declaration<'ast>),
+    }
+}
+
+struct_schema! {
+    GENERIC_PARAMETER: GenericParameter<'ast> {
+        name: token,
+        constraint: optional(node(TypeConstraint<'ast>)),
+    }
+}
+
+enum_schema! {
+    DECLARATION: Declaration<'ast> {
+        Main {
+            main_keyword: token,
+            body: node(Expression<'ast>),
+        },
+        Const {
+            const_keyword: token,
+            identifier: token,
+            type_: optional(node(TypeConstraint<'ast>)),
+            equals_token: token,
+            value: node(Expression<'ast>),
+        },
+        Function {
+            function_keyword: token,
+            identifier: token,
+            generic_parameters: optional(list(GenericParameter<'ast>)),
+            parameters: list(ParameterDeclaration<'ast>),
+            constraint: optional(node(TypeConstraint<'ast>)),
+            arrow_token: token,
+            body: node(Expression<'ast>),
+        },
+        Import {
+            import_keyword: token,
+            pattern: node(BindingPattern<'ast>),
+            equal_token: token,
+            use_keyword: token,
+            module_specifier: token,
+        },
+        Export {
+            export_keyword: token,
+            elements: list(RecordElement<'ast>),
+        },
+        TypeAlias {
+            type_keyword: token,
+            name: token,
+            generic_parameters: optional(list(GenericParameter<'ast>)),
+            equals_token: token,
+            value: node(Type<'ast>),
+        },
+        Interface {
+            interface_keyword: token,
+            name: token,
+            generic_parameters: optional(list(GenericParameter<'ast>)),
+            constraint: optional(node(TypeConstraint<'ast>)),
+            body: list(InterfaceField<'ast>),
+        },
+    }
+}
+
+struct_schema! {
+    INTERFACE_FIELD: InterfaceField<'ast> {
+        name: token,
+        constraint: node(TypeConstraint<'ast>),
+    }
+}
+
+enum_schema! {
+    EXPRESSION: Expression<'ast> {
+        Number(_0: text),
+        String(_0: text),
+        Boolean(_0: boolean),
+        Name(_0: text),
+        Hole,
+        None,
+        As {
+            expr: node(Expression<'ast>),
+            as_token: token,
+            type_: node(Type<'ast>),
+        },
+        Unary {
+            operator: node(UnaryOp),
+            expression: node(Expression<'ast>),
+        },
+        Compare {
+            operator: node(CompareOp),
+            left: node(Expression<'ast>),
+            right: node(Expression<'ast>),
+        },
+        Arithmetic {
+            operator: node(ArithmeticOp),
+            left: node(Expression<'ast>),
+            right: node(Expression<'ast>),
+        },
+        Accessor {
+            accessee: node(Expression<'ast>),
+            index: node(Expression<'ast>),
+        },
+        Function {
+            fn_keyword: token,
+            name: optional(token),
+            generic_parameters: optional(list(GenericParameter<'ast>)),
+            parameters: list(ParameterDeclaration<'ast>),
+            constraint: optional(node(TypeConstraint<'ast>)),
+            arrow_token: token,
+            body: node(Expression<'ast>),
+        },
+        Call {
+            callee: node(Expression<'ast>),
+            parameters: list(Expression<'ast>),
+        },
+        With {
+            with_keyword: token,
+            bindings: list(Assignment<'ast>),
+            body: node(Expression<'ast>),
+        },
+        Tuple {
+            elements: list(Expression<'ast>),
+        },
+        List {
+            elements: list(Expression<'ast>),
+        },
+        Procedure {
+            body: list(Statement<'ast>),
+        },
+        If {
+            if_keyword: token,
+            condition: node(Expression<'ast>),
+            then_keyword: token,
+            then: node(Expression<'ast>),
+            else_keyword: token,
+            _else: node(Expression<'ast>),
+        },
+        Record {
+            elements: list(RecordElement<'ast>),
+        },
+        FieldAccess {
+            accessee: node(Expression<'ast>),
+            field: token,
+        },
+    }
+}
+
+struct_schema! {
+    TYPE_CONSTRAINT: TypeConstraint<'ast> {
+        colon_token: token,
+        type_: node(Type<'ast>),
+    }
+}
+
+enum_schema! {
+    TYPE: Type<'ast> {
+        Kind,
+        Never,
+        Unknown,
+        Reference {
+            name: token,
+            generic_parameters: optional(list(Type<'ast>)),
+        },
+        Union {
+            left: node(Type<'ast>),
+            right: node(Type<'ast>),
+        },
+        Tuple {
+            members: list(Type<'ast>),
+        },
+        Function {
+            fn_keyword: token,
+            parameters: list(Type<'ast>),
+            arrow_token: token,
+            return_type: node(Type<'ast>),
+        },
+    }
+}
+
+struct_schema! {
+    PARAMETER_DECLARATION: ParameterDeclaration<'ast> {
+        name: token,
+        type_: optional(node(TypeConstraint<'ast>)),
+    }
+}
+
+struct_schema! {
+    ASSIGNMENT: Assignment<'ast> {
+        symbol: token,
+        equal_token: token,
+        value: node(Expression<'ast>),
+    }
+}
+
+enum_schema! {
+    RECORD_ELEMENT: RecordElement<'ast> {
+        KeyValuePair {
+            key: token,
+            value: node(Expression<'ast>),
+        },
+        Identifier {
+            name: token,
+        },
+        Spread {
+            value: node(Expression<'ast>),
+        },
+    }
+}
+
+enum_schema! {
+    STATEMENT: Statement<'ast> {
+        Let {
+            let_keyword: token,
+            assignment: node(Assignment<'ast>),
+        },
+        Set(_0: node(Assignment<'ast>)),
+        If {
+            if_keyword: token,
+            condition: node(Expression<'ast>),
+            then: node(Statement<'ast>),
+            _else: optional(node(Statement<'ast>)),
+        },
+        ForIn {
+            for_keyword: token,
+            binding: token,
+            in_keyword: token,
+            iterator: node(Expression<'ast>),
+            body: node(Statement<'ast>),
+        },
+        Forever(_0: node(Statement<'ast>)),
+        Do(_0: node(Expression<'ast>)),
+        Break,
+        Continue,
+        Pass,
+        Expression(_0: node(Expression<'ast>)),
+    }
+}
+
+enum_schema! {
+    BINDING_PATTERN: BindingPattern<'ast> {
+        Identifier {
+            name: token,
+        },
+        Tuple {
+            patterns: list(BindingPattern<'ast>),
+        },
+        Record {
+            elements: list(RecordBindingElement<'ast>),
+        },
+    }
+}
+
+enum_schema! {
+    RECORD_BINDING_ELEMENT: RecordBindingElement<'ast> {
+        Identifier {
+            name: token,
+        },
+        KeyValuePair {
+            name: token,
+            pattern: node(BindingPattern<'ast>),
+        },
+        Rest {
+            name: token,
+        },
+    }
+}
+
+enum_schema! {
+    COMPARE_OP: CompareOp {
+        Equal,
+        NotEqual,
+        LessThanOrEqual,
+        GreaterThanOrEqual,
+        LessThan,
+        GreaterThan,
+    }
+}
+
+enum_schema! {
+    ARITHMETIC_OP: ArithmeticOp {
+        Add,
+        Subtract,
+        Multiply,
+        Divide,
+        Modulus,
+    }
+}
+
+enum_schema! {
+    UNARY_OP: UnaryOp {
+        Negate,
+        Minus,
+    }
+}
+
+/// Every AST type, from the module down.
+pub const TYPES: &[TypeSchema] = &[
+    MODULE,
+    DECLARATION,
+    GENERIC_PARAMETER,
+    INTERFACE_FIELD,
+    EXPRESSION,
+    TYPE_CONSTRAINT,
+    TYPE,
+    PARAMETER_DECLARATION,
+    ASSIGNMENT,
+    RECORD_ELEMENT,
+    STATEMENT,
+    BINDING_PATTERN,
+    RECORD_BINDING_ELEMENT,
+];
+
+/// The operators, whose variants hold nothing.
+pub const OPERATORS: &[TypeSchema] = &[COMPARE_OP, ARITHMETIC_OP, UNARY_OP];
+
+/// The name of a field in JavaScript, e.g. `arrowToken` for `arrow_token` and `Else` for `_else`.
+pub fn js_field_name(name: &str) -> String {
+    let mut parts = name.split('_');
+    let mut js = parts.next().unwrap_or_default().to_string();
+
+    for part in parts {
+        let mut chars = part.chars();
+        if let Some(first) = chars.next() {
+            js.extend(first.to_uppercase());
+            js.push_str(chars.as_str());
+        }
+    }
+
+    js
+}
@@ -0,0 +1,1251 @@
+//! A canonical S-expression form of the surface AST.
+//!
+//! [`print_module`] writes a module as nested lists, and [`read_module`] reads one back. The
+//! encoding follows the surface syntax where it can:
+//!
+//! ```text
+//! (module
+//!   (fn add ((param x) (param y (: number))) (+ x y))
+//!   (main (proc (let z (call add 1 2)) (call print z))))
+//! ```
+//!
+//! Literals are written as themselves, with `@` for a hole and `none` for `none`. Operators head
+//! their own forms, e.g. `(+ x y)` and `(! x)`. Everything else is headed by a keyword:
+//!
+//! | Form | Meaning |
+//! | ---- | ------- |
+//! | `(main e)`, `(const x [(: t)] e)`, `(import p "spec")`, `(export elements...)` | declarations |
+//! | `(fn name [(generics ...)] (params...) [(: t)] e)` | a function; the name is optional in expressions |
+//! | `(type Name [(generics ...)] t)`, `(interface Name [(generics ...)] [(: t)] (field x (: t))...)` | type declarations |
+//! | `(param x [(: t)])`, `(generic T [(: t)])` | parameters |
+//! | `(as e t)`, `(index e i)`, `(. e field)`, `(call f args...)`, `(with ((= x e)...) e)`, `(if c t e)` | expressions |
+//! | `(tuple ...)`, `(list ...)`, `(record ...)`, `(proc statements...)` | compound expressions |
+//! | `(: key e)`, `x`, `(... e)` | record elements, and likewise in record patterns |
+//! | `(let x e)`, `(set x e)`, `(if c s [s])`, `(for x e s)`, `(loop s)`, `(do e)`, `break`, `continue`, `pass` | statements |
+//! | `*`, `!`, `_`, `Name`, `(Name args...)`, `(\| t t)`, `(tuple t...)`, `(fn (t...) t)` | types |
+//!
+//! Any other expression is also a statement. An `if` expression used as a statement is wrapped in
+//! `(expr ...)` to tell it apart from an `if` statement. Comments run from `;` to the end of the
+//! line.
+//!
+//! Tokens that the encoding leaves out, such as keywords and `=`, are filled in when reading. Every
+//! node read back spans the form it was read from, so errors found in a tree read from a
+//! hand-written fixture point into the fixture.
+
+use std::{borrow::Cow, fmt};
+
+use seglisp::{parse::ParseNode, Location, Range};
+
+use crate::{
+    printer::escape_string, ArithmeticOp, Assignment, BindingPattern, CompareOp, Declaration,
+    Expression, GenericParameter, InterfaceField, Module, ParameterDeclaration, ParsedVec,
+    RecordBindingElement, RecordElement, Statement, Type, TypeConstraint, UnaryOp, Verbatim,
+};
+
+/// Lists longer than this are broken over several lines.
+const WIDTH: usize = 80;
+
+/// Writes `module` in its S-expression form, with each declaration on its own line.
+pub fn print_module(module: &Module) -> String {
+    let mut out = String::from("(module");
+    for decl in &module.declarations {
+        out.push_str("\n  ");
+        declaration(&decl.value).write(2, &mut out);
+    }
+    out.push_str(")\n");
+    out
+}
+
+/// Writes `expr` in its S-expression form.
+pub fn print_expression(expr: &Expression) -> String {
+    let mut out = String::new();
+    expression(expr).write(0, &mut out);
+    out
+}
+
+/// Reads a module written by [`print_module`].
+pub fn read_module(text: &str) -> Result<ParseNode<Module<'_>>, SexprError> {
+    let form = read_one(text)?;
+    let mut args = Args::new(&form, "module")?;
+
+    let declarations = args
+        .rest()
+        .iter()
+        .map(declaration_from)
+        .collect::<Result<_, _>>()?;
+
+    Ok(node(Module { declarations }, &form))
+}
+
+/// Reads an expression written by [`print_expression`].
+pub fn read_expression(text: &str) -> Result<ParseNode<Expression<'_>>, SexprError> {
+    expression_from(&read_one(text)?)
+}
+
+/// An error reading an S-expression, with the one-based line and column it was found at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SexprError(pub String);
+
+impl fmt::Display for SexprError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for SexprError {}
+
+fn error_at(location: Location, message: impl fmt::Display) -> SexprError {
+    SexprError(format!(
+        "{}:{}: {message}",
+        location.line + 1,
+        location.column + 1
+    ))
+}
+
+// Printing
+
+/// An S-expression to print. Atoms are written as they are.
+enum Out {
+    Atom(String),
+    List(Vec<Out>),
+}
+
+fn atom(text: impl Into<String>) -> Out {
+    Out::Atom(text.into())
+}
+
+fn form(head: &str, items: impl IntoIterator<Item = Out>) -> Out {
+    Out::List(std::iter::once(atom(head)).chain(items).collect())
+}
+
+impl Out {
+    /// The length of the expression written on one line.
+    fn width(&self) -> usize {
+        match self {
+            Out::Atom(text) => text.chars().count(),
+            Out::List(items) => {
+                items.iter().map(Out::width).sum::<usize>() + items.len().saturating_sub(1) + 2
+            }
+        }
+    }
+
+    fn write_flat(&self, out: &mut String) {
+        match self {
+            Out::Atom(text) => out.push_str(text),
+            Out::List(items) => {
+                out.push('(');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        out.push(' ');
+                    }
+                    item.write_flat(out);
+                }
+                out.push(')');
+            }
+        }
+    }
+
+    /// Writes the expression starting at column `indent`. A list that does not fit keeps its
+    /// head, and any atoms right after it, on the first line, and puts each other item on a line
+    /// of its own.
+    fn write(&self, indent: usize, out: &mut String) {
+        match self {
+            Out::List(items) if !items.is_empty() && indent + self.width() > WIDTH => {
+                let inline = items
+                    .iter()
+                    .take_while(|item| matches!(item, Out::Atom(_)))
+                    .count()
+                    .max(1);
+
+                out.push('(');
+                for (i, item) in items[..inline].iter().enumerate() {
+                    if i > 0 {
+                        out.push(' ');
+                    }
+                    item.write_flat(out);
+                }
+                for item in &items[inline..] {
+                    out.push('\n');
+                    out.push_str(&" ".repeat(indent + 2));
+                    item.write(indent + 2, out);
+                }
+                out.push(')');
+            }
+            _ => self.write_flat(out),
+        }
+    }
+}
+
+fn declaration(decl: &Declaration) -> Out {
+    match decl {
+        Declaration::Main { body, .. } => form("main", [expression(&body.value)]),
+        Declaration::Const {
+            identifier,
+            type_,
+            value,
+            ..
+        } => {
+            let mut items = vec![atom(identifier.value)];
+            items.extend(type_.as_ref().map(|c| constraint(&c.value)));
+            items.push(expression(&value.value));
+            form("const", items)
+        }
+        Declaration::Function {
+            identifier,
+            generic_parameters,
+            parameters,
+            constraint,
+            body,
+            ..
+        } => form(
+            "fn",
+            signature(
+                Some(identifier.value),
+                generic_parameters,
+                parameters,
+                constraint,
+                &body.value,
+            ),
+        ),
+        Declaration::Import {
+            pattern,
+            module_specifier,
+            ..
+        } => form(
+            "import",
+            [
+                binding_pattern(&pattern.value),
+                atom(escape_string(module_specifier.value)),
+            ],
+        ),
+        Declaration::Export { elements, .. } => form(
+            "export",
+            elements.value.iter().map(|e| record_element(&e.value)),
+        ),
+        Declaration::TypeAlias {
+            name,
+            generic_parameters,
+            value,
+            ..
+        } => {
+            let mut items = vec![atom(name.value)];
+            items.extend(generic_parameters.as_ref().map(generics));
+            items.push(type_(&value.value));
+            form("type", items)
+        }
+        Declaration::Interface {
+            name,
+            generic_parameters,
+            constraint: interface_constraint,
+            body,
+            ..
+        } => {
+            let mut items = vec![atom(name.value)];
+            items.extend(generic_parameters.as_ref().map(generics));
+            items.extend(interface_constraint.as_ref().map(|c| constraint(&c.value)));
+            items.extend(body.value.iter().map(|field| {
+                form(
+                    "field",
+                    [
+                        atom(field.value.name.value),
+                        constraint(&field.value.constraint.value),
+                    ],
+                )
+            }));
+            form("interface", items)
+        }
+    }
+}
+
+/// The items of a `fn` form after its head.
+fn signature(
+    name: Option<&str>,
+    generic_parameters: &Option<ParsedVec<GenericParameter>>,
+    parameters: &ParsedVec<ParameterDeclaration>,
+    return_constraint: &Option<ParseNode<TypeConstraint>>,
+    body: &Expression,
+) -> Vec<Out> {
+    let mut items: Vec<_> = name.map(atom).into_iter().collect();
+    items.extend(generic_parameters.as_ref().map(generics));
+    items.push(Out::List(
+        parameters
+            .value
+            .iter()
+            .map(|p| {
+                let mut param = vec![atom(p.value.name.value)];
+                param.extend(p.value.type_.as_ref().map(|c| constraint(&c.value)));
+                form("param", param)
+            })
+            .collect(),
+    ));
+    items.extend(return_constraint.as_ref().map(|c| constraint(&c.value)));
+    items.push(expression(body));
+    items
+}
+
+fn generics(parameters: &ParsedVec<GenericParameter>) -> Out {
+    form(
+        "generics",
+        parameters.value.iter().map(|g| {
+            let mut items = vec![atom(g.value.name.value)];
+            items.extend(g.value.constraint.as_ref().map(|c| constraint(&c.value)));
+            form("generic", items)
+        }),
+    )
+}
+
+fn constraint(constraint: &TypeConstraint) -> Out {
+    form(":", [type_(&constraint.type_.value)])
+}
+
+fn expressions<'a>(
+    exprs: impl IntoIterator<Item = &'a ParseNode<Expression<'a>>>,
+) -> impl Iterator<Item = Out> {
+    exprs.into_iter().map(|e| expression(&e.value))
+}
+
+fn expression(expr: &Expression) -> Out {
+    match expr {
+        Expression::Number(n) | Expression::Name(n) => atom(*n),
+        Expression::String(s) => atom(escape_string(s)),
+        Expression::Boolean(b) => atom(b.to_string()),
+        Expression::Hole => atom("@"),
+        Expression::None => atom("none"),
+        Expression::As { expr, type_: t, .. } => {
+            form("as", [expression(&expr.value), type_(&t.value)])
+        }
+        Expression::Unary {
+            operator,
+            expression: operand,
+        } => form(&operator.value.to_string(), [expression(&operand.value)]),
+        Expression::Compare {
+            operator,
+            left,
+            right,
+        } => form(
+            &operator.value.to_string(),
+            [expression(&left.value), expression(&right.value)],
+        ),
+        Expression::Arithmetic {
+            operator,
+            left,
+            right,
+        } => form(
+            &operator.value.to_string(),
+            [expression(&left.value), expression(&right.value)],
+        ),
+        Expression::Accessor { accessee, index } => form(
+            "index",
+            [expression(&accessee.value), expression(&index.value)],
+        ),
+        Expression::Function {
+            name,
+            generic_parameters,
+            parameters,
+            constraint,
+            body,
+            ..
+        } => form(
+            "fn",
+            signature(
+                name.as_ref().map(|n| n.value),
+                generic_parameters,
+                parameters,
+                constraint,
+                &body.value,
+            ),
+        ),
+        Expression::Call { callee, parameters } => form(
+            "call",
+            std::iter::once(expression(&callee.value)).chain(expressions(&parameters.value)),
+        ),
+        Expression::With { bindings, body, .. } => form(
+            "with",
+            [
+                Out::List(
+                    bindings
+                        .value
+                        .iter()
+                        .map(|b| {
+                            form(
+                                "=",
+                                [atom(b.value.symbol.value), expression(&b.value.value.value)],
+                            )
+                        })
+                        .collect(),
+                ),
+                expression(&body.value),
+            ],
+        ),
+        Expression::Tuple { elements } => form("tuple", expressions(&elements.value)),
+        Expression::List { elements } => form("list", expressions(&elements.value)),
+        Expression::Procedure { body } => {
+            form("proc", body.value.iter().map(|s| statement(&s.value)))
+        }
+        Expression::If {
+            condition,
+            then,
+            _else,
+            ..
+        } => form(
+            "if",
+            [
+                expression(&condition.value),
+                expression(&then.value),
+                expression(&_else.value),
+            ],
+        ),
+        Expression::Record { elements } => form(
+            "record",
+            elements.value.iter().map(|e| record_element(&e.value)),
+        ),
+        Expression::FieldAccess { accessee, field } => {
+            form(".", [expression(&accessee.value), atom(field.value)])
+        }
+    }
+}
+
+fn record_element(element: &RecordElement) -> Out {
+    match element {
+        RecordElement::KeyValuePair { key, value } => {
+            form(":", [atom(key.value), expression(&value.value)])
+        }
+        RecordElement::Identifier { name } => atom(name.value),
+        RecordElement::Spread { value } => form("...", [expression(&value.value)]),
+    }
+}
+
+fn statement(stmt: &Statement) -> Out {
+    match stmt {
+        Statement::Let { assignment, .. } => form(
+            "let",
+            [
+                atom(assignment.value.symbol.value),
+                expression(&assignment.value.value.value),
+            ],
+        ),
+        Statement::Set(assignment) => form(
+            "set",
+            [
+                atom(assignment.value.symbol.value),
+                expression(&assignment.value.value.value),
+            ],
+        ),
+        Statement::If {
+            condition,
+            then,
+            _else,
+            ..
+        } => {
+            let mut items = vec![expression(&condition.value), statement(&then.value)];
+            items.extend(_else.as_ref().map(|e| statement(&e.value)));
+            form("if", items)
+        }
+        Statement::ForIn {
+            binding,
+            iterator,
+            body,
+            ..
+        } => form(
+            "for",
+            [
+                atom(binding.value),
+                expression(&iterator.value),
+                statement(&body.value),
+            ],
+        ),
+        Statement::Forever(body) => form("loop", [statement(&body.value)]),
+        Statement::Do(expr) => form("do", [expression(&expr.value)]),
+        Statement::Break => atom("break"),
+        Statement::Continue => atom("continue"),
+        Statement::Pass => atom("pass"),
+        Statement::Expression(expr) => match &expr.value {
+            Expression::If { .. } => form("expr", [expression(&expr.value)]),
+            other => expression(other),
+        },
+    }
+}
+
+fn type_(ty: &Type) -> Out {
+    let types = |types: &ParsedVec<Type>| -> Vec<Out> {
+        types.value.iter().map(|t| type_(&t.value)).collect()
+    };
+
+    match ty {
+        Type::Kind => atom("*"),
+        Type::Never => atom("!"),
+        Type::Unknown => atom("_"),
+        Type::Reference {
+            name,
+            generic_parameters: None,
+        } => atom(name.value),
+        Type::Reference {
+            name,
+            generic_parameters: Some(parameters),
+        } => form(name.value, types(parameters)),
+        Type::Union { left, right } => form("|", [type_(&left.value), type_(&right.value)]),
+        Type::Tuple { members } => form("tuple", types(members)),
+        Type::Function {
+            parameters,
+            return_type,
+            ..
+        } => form(
+            "fn",
+            [Out::List(types(parameters)), type_(&return_type.value)],
+        ),
+    }
+}
+
+fn binding_pattern(pattern: &BindingPattern) -> Out {
+    match pattern {
+        BindingPattern::Identifier { name } => atom(name.value),
+        BindingPattern::Tuple { patterns } => form(
+            "tuple",
+            patterns.value.iter().map(|p| binding_pattern(&p.value)),
+        ),
+        BindingPattern::Record { elements } => form(
+            "record",
+            elements.value.iter().map(|e| match &e.value {
+                RecordBindingElement::Identifier { name } => atom(name.value),
+                RecordBindingElement::KeyValuePair { name, pattern } => {
+                    form(":", [atom(name.value), binding_pattern(&pattern.value)])
+                }
+                RecordBindingElement::Rest { name } => form("...", [atom(name.value)]),
+            }),
+        ),
+    }
+}
+
+// Reading
+
+/// An S-expression read from text, with the range it was read from.
+struct Form<'a> {
+    value: FormValue<'a>,
+    range: Range,
+}
+
+enum FormValue<'a> {
+    Atom(&'a str),
+    /// A string literal, borrowed from the text unless it contains escapes.
+    String(Cow<'a, str>),
+    List(Vec<Form<'a>>),
+}
+
+impl<'a> Form<'a> {
+    fn atom(&self) -> Option<&'a str> {
+        match self.value {
+            FormValue::Atom(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    /// The atom at the head of the list, if this is a list that starts with one.
+    fn head(&self) -> Option<&'a str> {
+        match &self.value {
+            FormValue::List(items) => items.first().and_then(Form::atom),
+            _ => None,
+        }
+    }
+
+    fn error(&self, message: impl fmt::Display) -> SexprError {
+        error_at(self.range.0, message)
+    }
+}
+
+struct Reader<'a> {
+    text: &'a str,
+    location: Location,
+}
+
+impl<'a> Reader<'a> {
+    fn peek(&self) -> Option<char> {
+        self.text[self.location.absolute..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.location.absolute += c.len_utf8();
+        if c == '\n' {
+            self.location.line += 1;
+            self.location.column = 0;
+        } else {
+            self.location.column += 1;
+        }
+        Some(c)
+    }
+
+    fn skip_trivia(&mut self) {
+        while let Some(c) = self.peek() {
+            if c == ';' {
+                while self.peek().is_some_and(|c| c != '\n') {
+                    self.bump();
+                }
+            } else if c.is_whitespace() {
+                self.bump();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn form(&mut self) -> Result<Form<'a>, SexprError> {
+        self.skip_trivia();
+        let start = self.location;
+
+        let value = match self.peek() {
+            None => return Err(error_at(start, "unexpected end of input")),
+            Some(')') => return Err(error_at(start, "unexpected `)`")),
+            Some('(') => {
+                self.bump();
+                let mut items = Vec::new();
+                loop {
+                    self.skip_trivia();
+                    match self.peek() {
+                        Some(')') => {
+                            self.bump();
+                            break;
+                        }
+                        None => return Err(error_at(start, "unclosed `(`")),
+                        Some(_) => items.push(self.form()?),
+                    }
+                }
+                FormValue::List(items)
+            }
+            Some('"') => FormValue::String(self.string()?),
+            Some(_) => {
+                while self
+                    .peek()
+                    .is_some_and(|c| !c.is_whitespace() && !"()\";".contains(c))
+                {
+                    self.bump();
+                }
+                FormValue::Atom(&self.text[start.absolute..self.location.absolute])
+            }
+        };
+
+        Ok(Form {
+            value,
+            range: (start, self.location),
+        })
+    }
+
+    fn string(&mut self) -> Result<Cow<'a, str>, SexprError> {
+        let start = self.location;
+        self.bump();
+
+        let mut escaped: Option<String> = None;
+        loop {
+            let before = self.location;
+            match self.bump() {
+                None => return Err(error_at(start, "unclosed string")),
+                Some('"') => {
+                    let raw = &self.text[start.absolute + 1..before.absolute];
+                    return Ok(escaped.map_or(Cow::Borrowed(raw), Cow::Owned));
+                }
+                Some('\\') => {
+                    let c = match self.bump() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('r') => '\r',
+                        _ => return Err(error_at(before, "invalid escape sequence")),
+                    };
+                    escaped
+                        .get_or_insert_with(|| {
+                            self.text[start.absolute + 1..before.absolute].to_string()
+                        })
+                        .push(c);
+                }
+                Some(c) => {
+                    if let Some(escaped) = &mut escaped {
+                        escaped.push(c);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Reads the single form that makes up `text`.
+fn read_one(text: &str) -> Result<Form<'_>, SexprError> {
+    let mut reader = Reader {
+        text,
+        location: Location {
+            absolute: 0,
+            line: 0,
+            column: 0,
+        },
+    };
+
+    let form = reader.form()?;
+    reader.skip_trivia();
+    if reader.peek().is_some() {
+        return Err(error_at(reader.location, "expected the end of input"));
+    }
+    Ok(form)
+}
+
+/// The arguments of a list form, taken from the front.
+struct Args<'f, 'a> {
+    form: &'f Form<'a>,
+    head: &'a str,
+    rest: &'f [Form<'a>],
+}
+
+impl<'f, 'a> Args<'f, 'a> {
+    /// The arguments of `form`, which must be a list headed by `head`.
+    fn new(form: &'f Form<'a>, head: &str) -> Result<Self, SexprError> {
+        match &form.value {
+            FormValue::List(items) if form.head() == Some(head) => Ok(Args {
+                form,
+                head: items[0].atom().unwrap(),
+                rest: &items[1..],
+            }),
+            _ => Err(form.error(format!("expected `({head} ...)`"))),
+        }
+    }
+
+    /// The arguments of `form`, which must be a list headed by any atom.
+    fn any(form: &'f Form<'a>) -> Option<Self> {
+        let head = form.head()?;
+        Args::new(form, head).ok()
+    }
+
+    fn next(&mut self, what: &str) -> Result<&'f Form<'a>, SexprError> {
+        let (first, rest) = self.rest.split_first().ok_or_else(|| {
+            self.form
+                .error(format!("`({} ...)` is missing {what}", self.head))
+        })?;
+        self.rest = rest;
+        Ok(first)
+    }
+
+    fn next_optional(&mut self) -> Option<&'f Form<'a>> {
+        let (first, rest) = self.rest.split_first()?;
+        self.rest = rest;
+        Some(first)
+    }
+
+    /// Takes the next argument if it is a list headed by `head`.
+    fn next_headed(&mut self, head: &str) -> Option<&'f Form<'a>> {
+        match self.rest.split_first() {
+            Some((first, rest)) if first.head() == Some(head) => {
+                self.rest = rest;
+                Some(first)
+            }
+            _ => None,
+        }
+    }
+
+    fn rest(&mut self) -> &'f [Form<'a>] {
+        std::mem::take(&mut self.rest)
+    }
+
+    fn finish(self) -> Result<(), SexprError> {
+        match self.rest.first() {
+            Some(extra) => Err(extra.error(format!("too many arguments to `({} ...)`", self.head))),
+            None => Ok(()),
+        }
+    }
+
+    fn constraint(&mut self) -> Result<Option<ParseNode<TypeConstraint<'a>>>, SexprError> {
+        self.next_headed(":").map(constraint_from).transpose()
+    }
+
+    fn generics(&mut self) -> Result<Option<ParsedVec<GenericParameter<'a>>>, SexprError> {
+        self.next_headed("generics")
+            .map(|form| {
+                let mut args = Args::new(form, "generics")?;
+                list(form, args.rest(), |generic| {
+                    let mut args = Args::new(generic, "generic")?;
+                    let parameter = GenericParameter {
+                        name: name(args.next("a name")?)?,
+                        constraint: args.constraint()?,
+                    };
+                    args.finish()?;
+                    Ok(node(parameter, generic))
+                })
+            })
+            .transpose()
+    }
+}
+
+fn node<T>(value: T, form: &Form) -> ParseNode<T> {
+    ParseNode {
+        value,
+        range: form.range,
+        has_error: false,
+    }
+}
+
+/// A token that the encoding leaves out, spanning the form it belongs to.
+fn token<'a>(text: &'a str, form: &Form) -> Verbatim<'a> {
+    node(text, form)
+}
+
+fn name<'a>(form: &Form<'a>) -> Result<Verbatim<'a>, SexprError> {
+    form.atom()
+        .map(|text| node(text, form))
+        .ok_or_else(|| form.error("expected a name"))
+}
+
+fn list<'a, T>(
+    form: &Form<'a>,
+    items: &[Form<'a>],
+    read: impl Fn(&Form<'a>) -> Result<ParseNode<T>, SexprError>,
+) -> Result<ParsedVec<T>, SexprError> {
+    Ok(node(
+        items.iter().map(read).collect::<Result<_, _>>()?,
+        form,
+    ))
+}
+
+/// The items of `form`, which must be a list.
+fn items<'f, 'a>(form: &'f Form<'a>) -> Result<&'f [Form<'a>], SexprError> {
+    match &form.value {
+        FormValue::List(items) => Ok(items),
+        _ => Err(form.error("expected a list")),
+    }
+}
+
+fn constraint_from<'a>(form: &Form<'a>) -> Result<ParseNode<TypeConstraint<'a>>, SexprError> {
+    let mut args = Args::new(form, ":")?;
+    let constraint = TypeConstraint {
+        colon_token: token(":", form),
+        type_: Box::new(type_from(args.next("a type")?)?),
+    };
+    args.finish()?;
+    Ok(node(constraint, form))
+}
+
+fn declaration_from<'a>(form: &Form<'a>) -> Result<ParseNode<Declaration<'a>>, SexprError> {
+    let mut args = Args::any(form).ok_or_else(|| form.error("expected a declaration"))?;
+
+    let decl = match args.head {
+        "main" => Declaration::Main {
+            main_keyword: token("main", form),
+            body: Box::new(expression_from(args.next("a body")?)?),
+        },
+        "const" => Declaration::Const {
+            const_keyword: token("const", form),
+            identifier: name(args.next("a name")?)?,
+            type_: args.constraint()?,
+            equals_token: token("=", form),
+            value: Box::new(expression_from(args.next("a value")?)?),
+        },
+        "fn" => {
+            let signature = signature_from(form, &mut args, true)?;
+            Declaration::Function {
+                function_keyword: token("fn", form),
+                identifier: signature.name.unwrap(),
+                generic_parameters: signature.generic_parameters,
+                parameters: signature.parameters,
+                constraint: signature.constraint,
+                arrow_token: token("->", form),
+                body: Box::new(signature.body),
+            }
+        }
+        "import" => {
+            let pattern = binding_pattern_from(args.next("a pattern")?)?;
+            let specifier = args.next("a module specifier")?;
+            let module_specifier = match &specifier.value {
+                FormValue::String(Cow::Borrowed(text)) => node(*text, specifier),
+                FormValue::String(Cow::Owned(_)) => {
+                    return Err(specifier.error("module specifiers cannot contain escapes"))
+                }
+                _ => return Err(specifier.error("expected a module specifier string")),
+            };
+
+            Declaration::Import {
+                import_keyword: token("import", form),
+                pattern,
+                equal_token: token("=", form),
+                use_keyword: token("use", form),
+                module_specifier,
+            }
+        }
+        "export" => Declaration::Export {
+            export_keyword: token("export", form),
+            elements: list(form, args.rest(), record_element_from)?,
+        },
+        "type" => Declaration::TypeAlias {
+            type_keyword: token("type", form),
+            name: name(args.next("a name")?)?,
+            generic_parameters: args.generics()?,
+            equals_token: token("=", form),
+            value: type_from(args.next("a type")?)?,
+        },
+        "interface" => Declaration::Interface {
+            interface_keyword: token("interface", form),
+            name: name(args.next("a name")?)?,
+            generic_parameters: args.generics()?,
+            constraint: args.constraint()?,
+            body: list(form, args.rest(), |field| {
+                let mut args = Args::new(field, "field")?;
+                let interface_field = InterfaceField {
+                    name: name(args.next("a name")?)?,
+                    constraint: constraint_from(args.next("a type constraint")?)?,
+                };
+                args.finish()?;
+                Ok(node(interface_field, field))
+            })?,
+        },
+        other => return Err(form.error(format!("unknown declaration `({other} ...)`"))),
+    };
+
+    args.finish()?;
+    Ok(node(decl, form))
+}
+
+/// The parts of a `fn` form shared by function declarations and expressions.
+struct Signature<'a> {
+    name: Option<Verbatim<'a>>,
+    generic_parameters: Option<ParsedVec<GenericParameter<'a>>>,
+    parameters: ParsedVec<ParameterDeclaration<'a>>,
+    constraint: Option<ParseNode<TypeConstraint<'a>>>,
+    body: ParseNode<Expression<'a>>,
+}
+
+fn signature_from<'a>(
+    form: &Form<'a>,
+    args: &mut Args<'_, 'a>,
+    named: bool,
+) -> Result<Signature<'a>, SexprError> {
+    let name = match args.rest.first() {
+        Some(first) if named || first.atom().is_some() => Some(name(args.next("a name")?)?),
+        _ if named => return Err(form.error("`(fn ...)` is missing a name")),
+        _ => None,
+    };
+    let generic_parameters = args.generics()?;
+
+    let parameters = args.next("a parameter list")?;
+    let parameters = list(parameters, items(parameters)?, |parameter| {
+        let mut args = Args::new(parameter, "param")?;
+        let declaration = ParameterDeclaration {
+            name: name(args.next("a name")?)?,
+            type_: args.constraint()?,
+        };
+        args.finish()?;
+        Ok(node(declaration, parameter))
+    })?;
+
+    Ok(Signature {
+        name,
+        generic_parameters,
+        parameters,
+        constraint: args.constraint()?,
+        body: expression_from(args.next("a body")?)?,
+    })
+}
+
+fn compare_op(text: &str) -> Option<CompareOp> {
+    Some(match text {
+        "==" => CompareOp::Equal,
+        "!=" => CompareOp::NotEqual,
+        "<=" => CompareOp::LessThanOrEqual,
+        ">=" => CompareOp::GreaterThanOrEqual,
+        "<" => CompareOp::LessThan,
+        ">" => CompareOp::GreaterThan,
+        _ => return None,
+    })
+}
+
+fn arithmetic_op(text: &str) -> Option<ArithmeticOp> {
+    Some(match text {
+        "+" => ArithmeticOp::Add,
+        "-" => ArithmeticOp::Subtract,
+        "*" => ArithmeticOp::Multiply,
+        "/" => ArithmeticOp::Divide,
+        "%" => ArithmeticOp::Modulus,
+        _ => return None,
+    })
+}
+
+fn boxed<'a>(form: &Form<'a>) -> Result<Box<ParseNode<Expression<'a>>>, SexprError> {
+    expression_from(form).map(Box::new)
+}
+
+fn expression_from<'a>(form: &Form<'a>) -> Result<ParseNode<Expression<'a>>, SexprError> {
+    let items = match &form.value {
+        FormValue::Atom(text) => {
+            let expr = match *text {
+                "@" => Expression::Hole,
+                "none" => Expression::None,
+                "true" => Expression::Boolean(true),
+                "false" => Expression::Boolean(false),
+                _ if is_number(text) => Expression::Number(text),
+                _ => Expression::Name(text),
+            };
+            return Ok(node(expr, form));
+        }
+        FormValue::String(text) => return Ok(node(Expression::String(text.to_string()), form)),
+        FormValue::List(items) => items,
+    };
+
+    let head_form = items
+        .first()
+        .ok_or_else(|| form.error("expected an expression"))?;
+    let mut args = Args::any(form).ok_or_else(|| form.error("expected an expression"))?;
+    let arity = args.rest.len();
+
+    let expr = match args.head {
+        "as" => Expression::As {
+            expr: boxed(args.next("an expression")?)?,
+            as_token: token("as", form),
+            type_: Box::new(type_from(args.next("a type")?)?),
+        },
+        "!" => Expression::Unary {
+            operator: node(UnaryOp::Negate, head_form),
+            expression: boxed(args.next("an operand")?)?,
+        },
+        "-" if arity == 1 => Expression::Unary {
+            operator: node(UnaryOp::Minus, head_form),
+            expression: boxed(args.next("an operand")?)?,
+        },
+        op if compare_op(op).is_some() => Expression::Compare {
+            operator: node(compare_op(op).unwrap(), head_form),
+            left: boxed(args.next("a left operand")?)?,
+            right: boxed(args.next("a right operand")?)?,
+        },
+        op if arithmetic_op(op).is_some() => Expression::Arithmetic {
+            operator: node(arithmetic_op(op).unwrap(), head_form),
+            left: boxed(args.next("a left operand")?)?,
+            right: boxed(args.next("a right operand")?)?,
+        },
+        "index" => Expression::Accessor {
+            accessee: boxed(args.next("an expression")?)?,
+            index: boxed(args.next("an index")?)?,
+        },
+        "fn" => {
+            let signature = signature_from(form, &mut args, false)?;
+            Expression::Function {
+                fn_keyword: token("fn", form),
+                name: signature.name,
+                generic_parameters: signature.generic_parameters,
+                parameters: signature.parameters,
+                constraint: signature.constraint,
+                arrow_token: token("->", form),
+                body: Box::new(signature.body),
+            }
+        }
+        "call" => Expression::Call {
+            callee: boxed(args.next("a callee")?)?,
+            parameters: list(form, args.rest(), expression_from)?,
+        },
+        "with" => {
+            let bindings = args.next("a list of bindings")?;
+            Expression::With {
+                with_keyword: token("with", form),
+                bindings: list(bindings, items(bindings)?, |binding| {
+                    let mut args = Args::new(binding, "=")?;
+                    let assignment = assignment_from(binding, &mut args)?;
+                    args.finish()?;
+                    Ok(assignment)
+                })?,
+                body: boxed(args.next("a body")?)?,
+            }
+        }
+        "tuple" => Expression::Tuple {
+            elements: list(form, args.rest(), expression_from)?,
+        },
+        "list" => Expression::List {
+            elements: list(form, args.rest(), expression_from)?,
+        },
+        "proc" => Expression::Procedure {
+            body: list(form, args.rest(), statement_from)?,
+        },
+        "if" => Expression::If {
+            if_keyword: token("if", form),
+            condition: boxed(args.next("a condition")?)?,
+            then_keyword: token("then", form),
+            then: boxed(args.next("a `then` branch")?)?,
+            else_keyword: token("else", form),
+            _else: boxed(args.next("an `else` branch")?)?,
+        },
+        "record" => Expression::Record {
+            elements: list(form, args.rest(), record_element_from)?,
+        },
+        "." => Expression::FieldAccess {
+            accessee: boxed(args.next("an expression")?)?,
+            field: name(args.next("a field name")?)?,
+        },
+        other => return Err(form.error(format!("unknown expression `({other} ...)`"))),
+    };
+
+    args.finish()?;
+    Ok(node(expr, form))
+}
+
+/// Numbers start with a digit, or with `-` and a digit.
+fn is_number(text: &str) -> bool {
+    let digits = text.strip_prefix('-').unwrap_or(text);
+    digits.starts_with(|c: char| c.is_ascii_digit())
+}
+
+/// Reads the name and value of `(let x e)`, `(set x e)`, or `(= x e)`.
+fn assignment_from<'a>(
+    form: &Form<'a>,
+    args: &mut Args<'_, 'a>,
+) -> Result<ParseNode<Assignment<'a>>, SexprError> {
+    let assignment = Assignment {
+        symbol: name(args.next("a name")?)?,
+        equal_token: token("=", form),
+        value: expression_from(args.next("a value")?)?,
+    };
+    Ok(node(assignment, form))
+}
+
+fn record_element_from<'a>(form: &Form<'a>) -> Result<ParseNode<RecordElement<'a>>, SexprError> {
+    if form.atom().is_some() {
+        return Ok(node(RecordElement::Identifier { name: name(form)? }, form));
+    }
+
+    let mut args = Args::any(form).ok_or_else(|| form.error("expected a record element"))?;
+    let element = match args.head {
+        ":" => RecordElement::KeyValuePair {
+            key: name(args.next("a key")?)?,
+            value: expression_from(args.next("a value")?)?,
+        },
+        "..." => RecordElement::Spread {
+            value: expression_from(args.next("a value")?)?,
+        },
+        other => return Err(form.error(format!("unknown record element `({other} ...)`"))),
+    };
+
+    args.finish()?;
+    Ok(node(element, form))
+}
+
+fn statement_from<'a>(form: &Form<'a>) -> Result<ParseNode<Statement<'a>>, SexprError> {
+    let boxed_statement =
+        |form: &Form<'a>| -> Result<_, SexprError> { statement_from(form).map(Box::new) };
+
+    match form.atom() {
+        Some("break") => return Ok(node(Statement::Break, form)),
+        Some("continue") => return Ok(node(Statement::Continue, form)),
+        Some("pass") => return Ok(node(Statement::Pass, form)),
+        _ => {}
+    }
+
+    let Some(mut args) = Args::any(form) else {
+        return Ok(node(Statement::Expression(boxed(form)?), form));
+    };
+
+    let stmt = match args.head {
+        "let" => Statement::Let {
+            let_keyword: token("let", form),
+            assignment: assignment_from(form, &mut args)?,
+        },
+        "set" => Statement::Set(assignment_from(form, &mut args)?),
+        "if" => Statement::If {
+            if_keyword: token("if", form),
+            condition: boxed(args.next("a condition")?)?,
+            then: boxed_statement(args.next("a body")?)?,
+            _else: args.next_optional().map(boxed_statement).transpose()?,
+        },
+        "for" => Statement::ForIn {
+            for_keyword: token("for", form),
+            binding: name(args.next("a binding")?)?,
+            in_keyword: token("in", form),
+            iterator: boxed(args.next("an iterator")?)?,
+            body: boxed_statement(args.next("a body")?)?,
+        },
+        "loop" => Statement::Forever(boxed_statement(args.next("a body")?)?),
+        "do" => Statement::Do(boxed(args.next("an expression")?)?),
+        "expr" => Statement::Expression(boxed(args.next("an expression")?)?),
+        _ => return Ok(node(Statement::Expression(boxed(form)?), form)),
+    };
+
+    args.finish()?;
+    Ok(node(stmt, form))
+}
+
+fn type_from<'a>(form: &Form<'a>) -> Result<ParseNode<Type<'a>>, SexprError> {
+    if let Some(text) = form.atom() {
+        let ty = match text {
+            "*" => Type::Kind,
+            "!" => Type::Never,
+            "_" => Type::Unknown,
+            _ => Type::Reference {
+                name: node(text, form),
+                generic_parameters: None,
+            },
+        };
+        return Ok(node(ty, form));
+    }
+
+    let mut args = Args::any(form).ok_or_else(|| form.error("expected a type"))?;
+    let boxed_type = |form: &Form<'a>| -> Result<_, SexprError> { type_from(form).map(Box::new) };
+
+    let ty = match args.head {
+        "|" => Type::Union {
+            left: boxed_type(args.next("a left member")?)?,
+            right: boxed_type(args.next("a right member")?)?,
+        },
+        "tuple" => Type::Tuple {
+            members: list(form, args.rest(), type_from)?,
+        },
+        "fn" => {
+            let parameters = args.next("a parameter list")?;
+            Type::Function {
+                fn_keyword: token("fn", form),
+                parameters: list(parameters, items(parameters)?, type_from)?,
+                arrow_token: token("->", form),
+                return_type: boxed_type(args.next("a return type")?)?,
+            }
+        }
+        name => {
+            let head = &items(form)?[0];
+            Type::Reference {
+                name: node(name, head),
+                generic_parameters: Some(list(form, args.rest(), type_from)?),
+            }
+        }
+    };
+
+    args.finish()?;
+    Ok(node(ty, form))
+}
+
+fn binding_pattern_from<'a>(form: &Form<'a>) -> Result<ParseNode<BindingPattern<'a>>, SexprError> {
+    if form.atom().is_some() {
+        return Ok(node(BindingPattern::Identifier { name: name(form)? }, form));
+    }
+
+    let mut args = Args::any(form).ok_or_else(|| form.error("expected a binding pattern"))?;
+    let pattern = match args.head {
+        "tuple" => BindingPattern::Tuple {
+            patterns: list(form, args.rest(), binding_pattern_from)?,
+        },
+        "record" => BindingPattern::Record {
+            elements: list(form, args.rest(), |element| {
+                if element.atom().is_some() {
+                    let name = name(element)?;
+                    return Ok(node(RecordBindingElement::Identifier { name }, element));
+                }
+
+                let mut args =
+                    Args::any(element).ok_or_else(|| element.error("expected a record pattern"))?;
+                let value = match args.head {
+                    ":" => RecordBindingElement::KeyValuePair {
+                        name: name(args.next("a key")?)?,
+                        pattern: binding_pattern_from(args.next("a pattern")?)?,
+                    },
+                    "..." => RecordBindingElement::Rest {
+                        name: name(args.next("a name")?)?,
+                    },
+                    other => {
+                        return Err(element.error(format!("unknown record pattern `({other} ...)`")))
+                    }
+                };
+                args.finish()?;
+                Ok(node(value, element))
+            })?,
+        },
+        other => return Err(form.error(format!("unknown binding pattern `({other} ...)`"))),
+    };
+
+    args.finish()?;
+    Ok(node(pattern, form))
+}
@@ -0,0 +1,222 @@
+//! Comparing and hashing trees by their structure alone.
+//!
+//! Two nodes are structurally equal if they have the same kind, the same names and literals, and
+//! structurally equal children in the same fields, wherever they are in the source. This is what
+//! tests of AST transforms want to compare, and what a build cache wants to key on.
+//!
+//! [`Structural`] wraps a [`NodeRef`] to give it span-insensitive `PartialEq`, `Eq`, and `Hash`.
+//! [`diff`] finds the first place two trees differ, and [`content_hash`] gives each declaration a
+//! hash that is stable across runs, platforms, and compiler versions.
+
+use std::{
+    borrow::Cow,
+    fmt,
+    hash::{Hash, Hasher},
+};
+
+use seglisp::parse::ParseNode;
+
+use crate::{
+    node::{NodePath, NodeRef, PathSegment},
+    Declaration, Expression,
+};
+
+/// A node that compares and hashes by structure, ignoring spans.
+#[derive(Debug, Clone, Copy)]
+pub struct Structural<'a, 'ast>(pub NodeRef<'a, 'ast>);
+
+impl PartialEq for Structural<'_, '_> {
+    fn eq(&self, other: &Self) -> bool {
+        structural_eq(self.0, other.0)
+    }
+}
+
+impl Eq for Structural<'_, '_> {}
+
+impl Hash for Structural<'_, '_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        structural_hash(self.0, state)
+    }
+}
+
+/// The text a node holds itself, rather than in its children: a name, a literal, or an operator.
+pub(crate) fn payload<'a>(node: &NodeRef<'a, '_>) -> Option<Cow<'a, str>> {
+    match node {
+        NodeRef::Expression(node) => match &node.value {
+            Expression::Number(n) | Expression::Name(n) => Some(Cow::Borrowed(*n)),
+            Expression::String(s) => Some(Cow::Borrowed(s.as_str())),
+            Expression::Boolean(b) => Some(Cow::Borrowed(if *b { "true" } else { "false" })),
+            Expression::Unary { operator, .. } => Some(format!("{:?}", operator.value).into()),
+            Expression::Compare { operator, .. } => Some(format!("{:?}", operator.value).into()),
+            Expression::Arithmetic { operator, .. } => Some(format!("{:?}", operator.value).into()),
+            _ => None,
+        },
+        NodeRef::Declaration(node) => match &node.value {
+            Declaration::Import {
+                module_specifier, ..
+            } => Some(Cow::Borrowed(module_specifier.value)),
+            _ => None,
+        },
+        NodeRef::Identifier(node) => Some(Cow::Borrowed(node.value)),
+        _ => None,
+    }
+}
+
+/// Whether `a` and `b` are structurally equal.
+pub fn structural_eq(a: NodeRef, b: NodeRef) -> bool {
+    diff(a, b).is_none()
+}
+
+/// Feeds the structure of `node` to `state`. Structurally equal nodes hash the same.
+pub fn structural_hash<H: Hasher>(node: NodeRef, state: &mut H) {
+    state.write(node.category().as_bytes());
+    state.write(node.kind().as_bytes());
+
+    match payload(&node) {
+        Some(text) => {
+            state.write_u8(1);
+            state.write_u64(text.len() as u64);
+            state.write(text.as_bytes());
+        }
+        None => state.write_u8(0),
+    }
+
+    let children = node.children();
+    state.write_u64(children.len() as u64);
+
+    for (segment, child) in children {
+        state.write(segment.field.as_bytes());
+        state.write_u64(segment.index.map_or(u64::MAX, |idx| idx as u64));
+        structural_hash(child, state);
+    }
+}
+
+/// A 64-bit FNV-1a hasher. Unlike the standard library's hashers, its output is fixed, so hashes
+/// can be stored and compared across runs.
+#[derive(Debug, Clone, Copy)]
+pub struct StableHasher(u64);
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        StableHasher(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for StableHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    // The defaults write integers in native byte order and width.
+    fn write_u64(&mut self, n: u64) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, n: usize) {
+        self.write_u64(n as u64);
+    }
+}
+
+/// A hash of the structure of `decl`, which stays the same as long as the declaration does,
+/// wherever it moves in the source.
+pub fn content_hash(decl: &ParseNode<Declaration>) -> u64 {
+    let mut hasher = StableHasher::default();
+    structural_hash(NodeRef::Declaration(decl), &mut hasher);
+    hasher.finish()
+}
+
+/// The first structural difference between two trees.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Difference {
+    /// The path to the differing node, from the roots of the trees that were compared.
+    pub path: NodePath,
+    /// A description of the node on the left, or `None` if the left tree has no node there.
+    pub left: Option<String>,
+    /// A description of the node on the right, or `None` if the right tree has no node there.
+    pub right: Option<String>,
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let side = |node: &Option<String>| node.clone().unwrap_or_else(|| "nothing".into());
+
+        write!(f, "at ")?;
+        if self.path.segments().is_empty() {
+            write!(f, "the root")?;
+        } else {
+            write!(f, "{}", self.path)?;
+        }
+        write!(f, ": {} != {}", side(&self.left), side(&self.right))
+    }
+}
+
+/// A short description of a node, e.g. ``Expression::Name `x` ``.
+fn describe(node: &NodeRef) -> String {
+    let mut description = match (node.category(), node.kind()) {
+        (category, kind) if category == kind => category.to_string(),
+        (category, kind) => format!("{category}::{kind}"),
+    };
+
+    if let Some(text) = payload(node) {
+        description.push_str(&format!(" `{text}`"));
+    }
+
+    description
+}
+
+/// Finds the first structural difference between `a` and `b`, in source order. Returns `None` if
+/// they are structurally equal.
+pub fn diff(a: NodeRef, b: NodeRef) -> Option<Difference> {
+    diff_at(a, b, NodePath::root())
+}
+
+fn diff_at(a: NodeRef, b: NodeRef, path: NodePath) -> Option<Difference> {
+    if a.category() != b.category() || a.kind() != b.kind() || payload(&a) != payload(&b) {
+        return Some(Difference {
+            path,
+            left: Some(describe(&a)),
+            right: Some(describe(&b)),
+        });
+    }
+
+    let left = a.children();
+    let right = b.children();
+
+    let missing = |segment: PathSegment, left: Option<&NodeRef>, right: Option<&NodeRef>| {
+        Some(Difference {
+            path: path.child(segment),
+            left: left.map(describe),
+            right: right.map(describe),
+        })
+    };
+
+    for idx in 0..left.len().max(right.len()) {
+        match (left.get(idx), right.get(idx)) {
+            (Some((ls, l)), Some((rs, r))) if ls == rs => {
+                if let Some(difference) = diff_at(*l, *r, path.child(*ls)) {
+                    return Some(difference);
+                }
+            }
+            // A child in a field the other node leaves empty, such as an optional type constraint.
+            (Some((ls, l)), Some((rs, r))) => {
+                return if right.iter().any(|(s, _)| s == ls) {
+                    missing(*rs, None, Some(r))
+                } else {
+                    missing(*ls, Some(l), None)
+                };
+            }
+            (Some((ls, l)), None) => return missing(*ls, Some(l), None),
+            (None, Some((rs, r))) => return missing(*rs, None, Some(r)),
+            (None, None) => unreachable!(),
+        }
+    }
+
+    None
+}
@@ -0,0 +1,785 @@
+//! Best-effort type inference over the surface AST.
+//!
+//! Serendipity has no type checker yet, so this module only recovers what can be read off the
+//! source: the types of literals and operators, declared constraints on constants, functions, and
+//! parameters, and the shapes of tuples, lists, records, and function expressions. Anything it
+//! cannot determine is [`Ty::Unknown`], which is compatible with every other type. It is meant for
+//! tooling (REPL `:type`, completion ranking), not for rejecting programs.
+
+use std::{collections::HashMap, fmt};
+
+use itertools::Itertools;
+
+use crate::{
+    ArithmeticOp, Declaration, Expression, GenericParameter, ParsedVec, RecordElement, Statement,
+    Type, UnaryOp,
+};
+
+/// An inferred or declared type.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Ty {
+    /// A type that could not be determined. Compatible with every type.
+    Unknown,
+    /// The type of expressions that never produce a value.
+    Never,
+    /// The type of types.
+    Kind,
+    Number,
+    String,
+    Boolean,
+    None,
+    /// A procedure, which performs its statements when run.
+    Procedure,
+    /// A named type, such as a type alias, an interface, or a generic parameter.
+    Named {
+        name: String,
+        arguments: Vec<Ty>,
+    },
+    Tuple(Vec<Ty>),
+    List(Box<Ty>),
+    Record(Vec<(String, Ty)>),
+    Function {
+        parameters: Vec<Ty>,
+        result: Box<Ty>,
+    },
+    Union(Vec<Ty>),
+}
+
+impl Ty {
+    /// Converts a type written in the source.
+    pub fn from_type(ty: &Type) -> Ty {
+        match ty {
+            Type::Kind => Ty::Kind,
+            Type::Never => Ty::Never,
+            Type::Unknown => Ty::Unknown,
+            Type::Reference {
+                name,
+                generic_parameters,
+            } => {
+                let arguments: Vec<Ty> = generic_parameters
+                    .iter()
+                    .flat_map(|p| &p.value)
+                    .map(|t| Ty::from_type(&t.value))
+                    .collect();
+
+                match (name.value, arguments.is_empty()) {
+                    ("unknown", true) => Ty::Unknown,
+                    ("number", true) => Ty::Number,
+                    ("string", true) => Ty::String,
+                    ("boolean", true) => Ty::Boolean,
+                    ("none", true) => Ty::None,
+                    _ => Ty::Named {
+                        name: name.value.to_string(),
+                        arguments,
+                    },
+                }
+            }
+            Type::Union { left, right } => {
+                Ty::union([Ty::from_type(&left.value), Ty::from_type(&right.value)])
+            }
+            Type::Tuple { members } => Ty::Tuple(
+                members
+                    .value
+                    .iter()
+                    .map(|t| Ty::from_type(&t.value))
+                    .collect(),
+            ),
+            Type::Function {
+                parameters,
+                return_type,
+                ..
+            } => Ty::Function {
+                parameters: parameters
+                    .value
+                    .iter()
+                    .map(|t| Ty::from_type(&t.value))
+                    .collect(),
+                result: Box::new(Ty::from_type(&return_type.value)),
+            },
+        }
+    }
+
+    /// The union of `types`, flattening nested unions and dropping duplicates. A union with a
+    /// single member is that member, and an empty union is [`Ty::Never`].
+    pub fn union(types: impl IntoIterator<Item = Ty>) -> Ty {
+        let mut members: Vec<Ty> = Vec::new();
+
+        for ty in types {
+            let flattened = match ty {
+                Ty::Union(inner) => inner,
+                Ty::Never => vec![],
+                ty => vec![ty],
+            };
+
+            for ty in flattened {
+                if !members.contains(&ty) {
+                    members.push(ty);
+                }
+            }
+        }
+
+        if members.contains(&Ty::Unknown) {
+            return Ty::Unknown;
+        }
+
+        match members.len() {
+            0 => Ty::Never,
+            1 => members.pop().unwrap(),
+            _ => Ty::Union(members),
+        }
+    }
+
+    /// Returns true if nothing is known about this type.
+    pub fn is_unknown(&self) -> bool {
+        matches!(self, Ty::Unknown)
+    }
+}
+
+impl fmt::Display for Ty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Ty::Unknown => write!(f, "_"),
+            Ty::Never => write!(f, "!"),
+            Ty::Kind => write!(f, "*"),
+            Ty::Number => write!(f, "number"),
+            Ty::String => write!(f, "string"),
+            Ty::Boolean => write!(f, "boolean"),
+            Ty::None => write!(f, "none"),
+            Ty::Procedure => write!(f, "procedure"),
+            Ty::Named { name, arguments } if arguments.is_empty() => write!(f, "{name}"),
+            Ty::Named { name, arguments } => write!(f, "{name}[{}]", arguments.iter().join(", ")),
+            Ty::Tuple(members) => write!(f, "({})", members.iter().join(", ")),
+            Ty::List(element) => write!(f, "List[{element}]"),
+            Ty::Record(fields) => {
+                if fields.is_empty() {
+                    write!(f, "{{}}")
+                } else {
+                    write!(
+                        f,
+                        "{{ {} }}",
+                        fields
+                            .iter()
+                            .map(|(name, ty)| format!("{name}: {ty}"))
+                            .join(", ")
+                    )
+                }
+            }
+            Ty::Function { parameters, result } => {
+                write!(f, "fn({}) -> {result}", parameters.iter().join(", "))
+            }
+            Ty::Union(members) => write!(
+                f,
+                "{}",
+                members
+                    .iter()
+                    .map(|ty| match ty {
+                        Ty::Function { .. } | Ty::Union(_) => format!("({ty})"),
+                        ty => ty.to_string(),
+                    })
+                    .join(" | ")
+            ),
+        }
+    }
+}
+
+/// A type alias, kept so that references to it can be expanded.
+#[derive(Debug, Clone)]
+struct Alias {
+    parameters: Vec<String>,
+    value: Ty,
+}
+
+/// The types of the names in scope, and the type aliases they may refer to.
+#[derive(Debug, Clone)]
+pub struct TypeEnv {
+    scopes: Vec<HashMap<String, Ty>>,
+    aliases: HashMap<String, Alias>,
+}
+
+/// How many aliases deep [`TypeEnv::fits`] expands before giving up.
+const MAX_ALIAS_DEPTH: usize = 16;
+
+fn generic_names(generics: &Option<ParsedVec<GenericParameter>>) -> Vec<String> {
+    generics
+        .iter()
+        .flat_map(|g| &g.value)
+        .map(|p| p.value.name.value.to_string())
+        .collect()
+}
+
+impl Default for TypeEnv {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TypeEnv {
+    pub fn new() -> Self {
+        TypeEnv {
+            scopes: vec![HashMap::new()],
+            aliases: HashMap::new(),
+        }
+    }
+
+    /// Creates an environment holding the top-level declarations of a module.
+    pub fn for_declarations<'a, 'ast: 'a>(
+        declarations: impl IntoIterator<Item = &'a Declaration<'ast>> + Clone,
+    ) -> Self {
+        let mut env = TypeEnv::new();
+
+        // Declared types first, so that inference of the others can see them regardless of order.
+        for decl in declarations.clone() {
+            env.declare_signature(decl);
+        }
+
+        for decl in declarations {
+            env.declare(decl);
+        }
+
+        env
+    }
+
+    /// Binds `name` to `ty` in the innermost scope.
+    pub fn bind(&mut self, name: impl Into<String>, ty: Ty) {
+        self.scopes
+            .last_mut()
+            .expect("type environment has no scope")
+            .insert(name.into(), ty);
+    }
+
+    /// The type of `name`, if it is in scope.
+    pub fn lookup(&self, name: &str) -> Option<&Ty> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    /// Every name in scope with its type, innermost first, without the names they shadow.
+    pub fn bindings(&self) -> Vec<(&str, &Ty)> {
+        let mut seen = Vec::new();
+        let mut bindings = Vec::new();
+
+        for scope in self.scopes.iter().rev() {
+            for (name, ty) in scope.iter().sorted_by_key(|(name, _)| name.as_str()) {
+                if !seen.contains(&name.as_str()) {
+                    seen.push(name.as_str());
+                    bindings.push((name.as_str(), ty));
+                }
+            }
+        }
+
+        bindings
+    }
+
+    pub fn push_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    pub fn pop_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    /// Binds the name a declaration introduces using only its written types.
+    fn declare_signature(&mut self, decl: &Declaration) {
+        match decl {
+            Declaration::Const {
+                identifier,
+                type_: Some(type_),
+                ..
+            } => self.bind(identifier.value, Ty::from_type(&type_.value.type_.value)),
+            Declaration::Function {
+                identifier,
+                parameters,
+                constraint,
+                ..
+            } => {
+                let parameters = parameters
+                    .value
+                    .iter()
+                    .map(|p| {
+                        p.value
+                            .type_
+                            .as_ref()
+                            .map_or(Ty::Unknown, |t| Ty::from_type(&t.value.type_.value))
+                    })
+                    .collect();
+                let result = constraint
+                    .as_ref()
+                    .map_or(Ty::Unknown, |c| Ty::from_type(&c.value.type_.value));
+
+                self.bind(
+                    identifier.value,
+                    Ty::Function {
+                        parameters,
+                        result: Box::new(result),
+                    },
+                );
+            }
+            Declaration::TypeAlias {
+                name,
+                generic_parameters,
+                value,
+                ..
+            } => {
+                self.aliases.insert(
+                    name.value.to_string(),
+                    Alias {
+                        parameters: generic_names(generic_parameters),
+                        value: Ty::from_type(&value.value),
+                    },
+                );
+            }
+            Declaration::Interface {
+                name,
+                generic_parameters,
+                body,
+                ..
+            } => {
+                self.aliases.insert(
+                    name.value.to_string(),
+                    Alias {
+                        parameters: generic_names(generic_parameters),
+                        value: Ty::Record(
+                            body.value
+                                .iter()
+                                .map(|field| {
+                                    (
+                                        field.value.name.value.to_string(),
+                                        Ty::from_type(&field.value.constraint.value.type_.value),
+                                    )
+                                })
+                                .collect(),
+                        ),
+                    },
+                );
+            }
+            _ => {}
+        }
+    }
+
+    /// Binds the name a declaration introduces, inferring whatever its written types leave out.
+    pub fn declare(&mut self, decl: &Declaration) {
+        self.declare_signature(decl);
+
+        match decl {
+            Declaration::Const {
+                identifier,
+                type_: None,
+                value,
+                ..
+            } => {
+                let ty = self.infer(&value.value);
+                self.bind(identifier.value, ty);
+            }
+            Declaration::Function {
+                identifier,
+                constraint: None,
+                parameters,
+                body,
+                ..
+            } => {
+                let Some(Ty::Function {
+                    parameters: types, ..
+                }) = self.lookup(identifier.value).cloned()
+                else {
+                    return;
+                };
+
+                self.push_scope();
+                for (p, ty) in parameters.value.iter().zip(&types) {
+                    self.bind(p.value.name.value, ty.clone());
+                }
+                let result = self.infer(&body.value);
+                self.pop_scope();
+
+                self.bind(
+                    identifier.value,
+                    Ty::Function {
+                        parameters: types,
+                        result: Box::new(result),
+                    },
+                );
+            }
+            Declaration::Import { pattern, .. } => {
+                crate::exports::collect_pattern_names(&pattern.value, &mut |name| {
+                    self.bind(name.value, Ty::Unknown)
+                });
+            }
+            _ => {}
+        }
+    }
+
+    /// Infers the type of `expr` in this environment.
+    pub fn infer(&mut self, expr: &Expression) -> Ty {
+        match expr {
+            Expression::Number(_) => Ty::Number,
+            Expression::String(_) => Ty::String,
+            Expression::Boolean(_) => Ty::Boolean,
+            Expression::None => Ty::None,
+            Expression::Hole => Ty::Unknown,
+            Expression::Name(name) => self.lookup(name).cloned().unwrap_or(Ty::Unknown),
+            Expression::As { type_, .. } => Ty::from_type(&type_.value),
+            Expression::Unary { operator, .. } => match operator.value {
+                UnaryOp::Negate => Ty::Boolean,
+                UnaryOp::Minus => Ty::Number,
+            },
+            Expression::Compare { .. } => Ty::Boolean,
+            Expression::Arithmetic {
+                operator,
+                left,
+                right,
+            } => {
+                let left = self.infer(&left.value);
+                let right = self.infer(&right.value);
+
+                match (operator.value, left, right) {
+                    (ArithmeticOp::Add, Ty::String, _) | (ArithmeticOp::Add, _, Ty::String) => {
+                        Ty::String
+                    }
+                    _ => Ty::Number,
+                }
+            }
+            Expression::Accessor { accessee, .. } => match self.infer(&accessee.value) {
+                Ty::List(element) => *element,
+                _ => Ty::Unknown,
+            },
+            Expression::Function {
+                name,
+                parameters,
+                constraint,
+                body,
+                ..
+            } => {
+                let types: Vec<Ty> = parameters
+                    .value
+                    .iter()
+                    .map(|p| {
+                        p.value
+                            .type_
+                            .as_ref()
+                            .map_or(Ty::Unknown, |t| Ty::from_type(&t.value.type_.value))
+                    })
+                    .collect();
+
+                let declared = constraint
+                    .as_ref()
+                    .map(|c| Ty::from_type(&c.value.type_.value));
+
+                self.push_scope();
+                if let Some(name) = name {
+                    self.bind(
+                        name.value,
+                        Ty::Function {
+                            parameters: types.clone(),
+                            result: Box::new(declared.clone().unwrap_or(Ty::Unknown)),
+                        },
+                    );
+                }
+                for (p, ty) in parameters.value.iter().zip(&types) {
+                    self.bind(p.value.name.value, ty.clone());
+                }
+                let result = declared.unwrap_or_else(|| self.infer(&body.value));
+                self.pop_scope();
+
+                Ty::Function {
+                    parameters: types,
+                    result: Box::new(result),
+                }
+            }
+            Expression::Call { callee, parameters } => match self.infer(&callee.value) {
+                Ty::Function {
+                    parameters: expected,
+                    result,
+                } => {
+                    let supplied = parameters.value.len();
+                    if supplied < expected.len() {
+                        // Functions are curried, so a partial application is a function of the
+                        // remaining parameters.
+                        Ty::Function {
+                            parameters: expected[supplied..].to_vec(),
+                            result,
+                        }
+                    } else {
+                        *result
+                    }
+                }
+                _ => Ty::Unknown,
+            },
+            Expression::With { bindings, body, .. } => {
+                self.push_scope();
+
+                // Bindings may refer to themselves and each other.
+                for b in &bindings.value {
+                    self.bind(b.value.symbol.value, Ty::Unknown);
+                }
+                for b in &bindings.value {
+                    let ty = self.infer(&b.value.value.value);
+                    self.bind(b.value.symbol.value, ty);
+                }
+
+                let ty = self.infer(&body.value);
+                self.pop_scope();
+                ty
+            }
+            Expression::Tuple { elements } => Ty::Tuple(
+                elements
+                    .value
+                    .iter()
+                    .map(|e| self.infer(&e.value))
+                    .collect(),
+            ),
+            Expression::List { elements } => {
+                let element = if elements.value.is_empty() {
+                    Ty::Unknown
+                } else {
+                    let types: Vec<Ty> = elements
+                        .value
+                        .iter()
+                        .map(|e| self.infer(&e.value))
+                        .collect();
+                    Ty::union(types)
+                };
+
+                Ty::List(Box::new(element))
+            }
+            Expression::Procedure { body } => {
+                // Walk the body so that `let` bindings are typed for later statements, but the
+                // procedure itself is opaque.
+                self.push_scope();
+                for stmt in &body.value {
+                    self.infer_statement(&stmt.value);
+                }
+                self.pop_scope();
+                Ty::Procedure
+            }
+            Expression::If { then, _else, .. } => {
+                let then = self.infer(&then.value);
+                let _else = self.infer(&_else.value);
+                Ty::union([then, _else])
+            }
+            Expression::Record { elements } => {
+                let mut fields: Vec<(String, Ty)> = Vec::new();
+
+                for element in &elements.value {
+                    let new_fields = match &element.value {
+                        RecordElement::KeyValuePair { key, value } => {
+                            vec![(key.value.to_string(), self.infer(&value.value))]
+                        }
+                        RecordElement::Identifier { name } => vec![(
+                            name.value.to_string(),
+                            self.lookup(name.value).cloned().unwrap_or(Ty::Unknown),
+                        )],
+                        RecordElement::Spread { value } => match self.infer(&value.value) {
+                            Ty::Record(spread) => spread,
+                            _ => vec![],
+                        },
+                    };
+
+                    for (name, ty) in new_fields {
+                        fields.retain(|(n, _)| *n != name);
+                        fields.push((name, ty));
+                    }
+                }
+
+                Ty::Record(fields)
+            }
+            Expression::FieldAccess { accessee, field } => {
+                let accessee = self.infer(&accessee.value);
+
+                match self.expand(accessee) {
+                    Ty::Record(fields) => fields
+                        .into_iter()
+                        .find(|(name, _)| name == field.value)
+                        .map_or(Ty::Unknown, |(_, ty)| ty),
+                    _ => Ty::Unknown,
+                }
+            }
+        }
+    }
+
+    /// Records the bindings a statement introduces into the innermost scope.
+    pub fn infer_statement(&mut self, stmt: &Statement) {
+        match stmt {
+            Statement::Let { assignment, .. } => {
+                let ty = self.infer(&assignment.value.value.value);
+                self.bind(assignment.value.symbol.value, ty);
+            }
+            Statement::ForIn {
+                binding,
+                iterator,
+                body,
+                ..
+            } => {
+                let element = match self.infer(&iterator.value) {
+                    Ty::List(element) => *element,
+                    _ => Ty::Unknown,
+                };
+
+                self.push_scope();
+                self.bind(binding.value, element);
+                self.infer_statement(&body.value);
+                self.pop_scope();
+            }
+            Statement::If { then, _else, .. } => {
+                self.push_scope();
+                self.infer_statement(&then.value);
+                self.pop_scope();
+
+                if let Some(_else) = _else {
+                    self.push_scope();
+                    self.infer_statement(&_else.value);
+                    self.pop_scope();
+                }
+            }
+            Statement::Forever(body) => {
+                self.push_scope();
+                self.infer_statement(&body.value);
+                self.pop_scope();
+            }
+            Statement::Expression(e) | Statement::Do(e) => {
+                self.infer(&e.value);
+            }
+            Statement::Set(_) | Statement::Break | Statement::Continue | Statement::Pass => {}
+        }
+    }
+
+    /// Expands `ty` if it names a type alias or interface.
+    pub fn expand(&self, ty: Ty) -> Ty {
+        let mut ty = ty;
+
+        for _ in 0..MAX_ALIAS_DEPTH {
+            let Ty::Named { name, arguments } = &ty else {
+                break;
+            };
+            let Some(alias) = self.aliases.get(name) else {
+                break;
+            };
+
+            let substitution: HashMap<&str, &Ty> = alias
+                .parameters
+                .iter()
+                .map(String::as_str)
+                .zip(arguments)
+                .collect();
+
+            ty = substitute(&alias.value, &substitution);
+        }
+
+        ty
+    }
+
+    /// Returns true if a value of type `actual` may be used where `expected` is required.
+    ///
+    /// Unknown types fit everywhere, so this only returns false when the types are known to
+    /// disagree.
+    pub fn fits(&self, actual: &Ty, expected: &Ty) -> bool {
+        self.fits_at(actual, expected, 0)
+    }
+
+    fn fits_at(&self, actual: &Ty, expected: &Ty, depth: usize) -> bool {
+        if depth > MAX_ALIAS_DEPTH {
+            return true;
+        }
+
+        match (actual, expected) {
+            (Ty::Unknown, _) | (_, Ty::Unknown) | (Ty::Never, _) => true,
+            (actual, expected) if actual == expected => true,
+            (Ty::Union(members), expected) => members
+                .iter()
+                .all(|member| self.fits_at(member, expected, depth + 1)),
+            (actual, Ty::Union(members)) => members
+                .iter()
+                .any(|member| self.fits_at(actual, member, depth + 1)),
+            (Ty::Named { .. }, _) | (_, Ty::Named { .. }) => {
+                let actual_expanded = self.expand(actual.clone());
+                let expected_expanded = self.expand(expected.clone());
+
+                match (&actual_expanded, &expected_expanded) {
+                    // Generic parameters and opaque names are only known to fit themselves.
+                    (Ty::Named { name: a, .. }, Ty::Named { name: e, .. }) => {
+                        a == e || self.is_opaque(a) || self.is_opaque(e)
+                    }
+                    (Ty::Named { name, .. }, _) | (_, Ty::Named { name, .. })
+                        if self.is_opaque(name) =>
+                    {
+                        true
+                    }
+                    (a, e) => self.fits_at(a, e, depth + 1),
+                }
+            }
+            (Ty::Tuple(actual), Ty::Tuple(expected)) => {
+                actual.len() == expected.len()
+                    && actual
+                        .iter()
+                        .zip(expected)
+                        .all(|(a, e)| self.fits_at(a, e, depth + 1))
+            }
+            (Ty::List(actual), Ty::List(expected)) => self.fits_at(actual, expected, depth + 1),
+            (Ty::Record(actual), Ty::Record(expected)) => expected.iter().all(|(name, e)| {
+                actual
+                    .iter()
+                    .find(|(n, _)| n == name)
+                    .is_some_and(|(_, a)| self.fits_at(a, e, depth + 1))
+            }),
+            (
+                Ty::Function {
+                    parameters: ap,
+                    result: ar,
+                },
+                Ty::Function {
+                    parameters: ep,
+                    result: er,
+                },
+            ) => {
+                ap.len() == ep.len()
+                    && ap
+                        .iter()
+                        .zip(ep)
+                        .all(|(a, e)| self.fits_at(e, a, depth + 1))
+                    && self.fits_at(ar, er, depth + 1)
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns true if `name` is not an alias this environment can see through, such as a generic
+    /// parameter or a type defined in another module.
+    fn is_opaque(&self, name: &str) -> bool {
+        !self.aliases.contains_key(name)
+    }
+}
+
+fn substitute(ty: &Ty, substitution: &HashMap<&str, &Ty>) -> Ty {
+    match ty {
+        Ty::Named { name, arguments } => match substitution.get(name.as_str()) {
+            Some(replacement) if arguments.is_empty() => (*replacement).clone(),
+            _ => Ty::Named {
+                name: name.clone(),
+                arguments: arguments
+                    .iter()
+                    .map(|a| substitute(a, substitution))
+                    .collect(),
+            },
+        },
+        Ty::Tuple(members) => Ty::Tuple(
+            members
+                .iter()
+                .map(|m| substitute(m, substitution))
+                .collect(),
+        ),
+        Ty::List(element) => Ty::List(Box::new(substitute(element, substitution))),
+        Ty::Record(fields) => Ty::Record(
+            fields
+                .iter()
+                .map(|(name, ty)| (name.clone(), substitute(ty, substitution)))
+                .collect(),
+        ),
+        Ty::Function { parameters, result } => Ty::Function {
+            parameters: parameters
+                .iter()
+                .map(|p| substitute(p, substitution))
+                .collect(),
+            result: Box::new(substitute(result, substitution)),
+        },
+        Ty::Union(members) => Ty::union(members.iter().map(|m| substitute(m, substitution))),
+        ty => ty.clone(),
+    }
+}
@@ -0,0 +1,216 @@
+//! Read-only traversal of the surface AST.
+//!
+//! Every method of [`Visit`] receives the [`ParseNode`] wrapping the node it visits, so the span of
+//! every node is available through `node.range`. The default implementation of each method calls
+//! the matching `walk_*` function, which visits the node's children in source order. Implementors
+//! override only the methods for the nodes they care about, and call the `walk_*` function to keep
+//! descending. The children of a node are the ones [`NodeRef::children`] lists, so a visitor and a
+//! node path always agree on the shape of the tree.
+//!
+//! Returning [`ControlFlow::Break`] from any method stops the traversal immediately.
+
+use std::ops::ControlFlow;
+
+use seglisp::parse::ParseNode;
+
+use crate::{
+    node::NodeRef, Assignment, BindingPattern, Declaration, Expression, GenericParameter,
+    InterfaceField, Module, ParameterDeclaration, RecordBindingElement, RecordElement, Statement,
+    Type, TypeConstraint, Verbatim,
+};
+
+pub trait Visit<'ast> {
+    fn visit_module(&mut self, node: &'ast ParseNode<Module<'ast>>) -> ControlFlow<()> {
+        walk_module(self, node)
+    }
+
+    fn visit_declaration(&mut self, node: &'ast ParseNode<Declaration<'ast>>) -> ControlFlow<()> {
+        walk_declaration(self, node)
+    }
+
+    fn visit_generic_parameter(
+        &mut self,
+        node: &'ast ParseNode<GenericParameter<'ast>>,
+    ) -> ControlFlow<()> {
+        walk_generic_parameter(self, node)
+    }
+
+    fn visit_interface_field(
+        &mut self,
+        node: &'ast ParseNode<InterfaceField<'ast>>,
+    ) -> ControlFlow<()> {
+        walk_interface_field(self, node)
+    }
+
+    fn visit_expression(&mut self, node: &'ast ParseNode<Expression<'ast>>) -> ControlFlow<()> {
+        walk_expression(self, node)
+    }
+
+    fn visit_type_constraint(
+        &mut self,
+        node: &'ast ParseNode<TypeConstraint<'ast>>,
+    ) -> ControlFlow<()> {
+        walk_type_constraint(self, node)
+    }
+
+    fn visit_type(&mut self, node: &'ast ParseNode<Type<'ast>>) -> ControlFlow<()> {
+        walk_type(self, node)
+    }
+
+    fn visit_parameter(
+        &mut self,
+        node: &'ast ParseNode<ParameterDeclaration<'ast>>,
+    ) -> ControlFlow<()> {
+        walk_parameter(self, node)
+    }
+
+    fn visit_assignment(&mut self, node: &'ast ParseNode<Assignment<'ast>>) -> ControlFlow<()> {
+        walk_assignment(self, node)
+    }
+
+    fn visit_record_element(
+        &mut self,
+        node: &'ast ParseNode<RecordElement<'ast>>,
+    ) -> ControlFlow<()> {
+        walk_record_element(self, node)
+    }
+
+    fn visit_statement(&mut self, node: &'ast ParseNode<Statement<'ast>>) -> ControlFlow<()> {
+        walk_statement(self, node)
+    }
+
+    fn visit_binding_pattern(
+        &mut self,
+        node: &'ast ParseNode<BindingPattern<'ast>>,
+    ) -> ControlFlow<()> {
+        walk_binding_pattern(self, node)
+    }
+
+    fn visit_record_binding_element(
+        &mut self,
+        node: &'ast ParseNode<RecordBindingElement<'ast>>,
+    ) -> ControlFlow<()> {
+        walk_record_binding_element(self, node)
+    }
+
+    /// Called for every identifier token that is not itself an expression: declared names,
+    /// parameter and binding names, record keys, field names, and type names.
+    fn visit_identifier(&mut self, _node: &'ast Verbatim<'ast>) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+}
+
+/// Visits each direct child of `node` with the matching method of `v`. The children, and their
+/// order, are those of [`NodeRef::for_each_child`].
+fn walk_children<'ast, V: Visit<'ast> + ?Sized>(
+    v: &mut V,
+    node: NodeRef<'ast, 'ast>,
+) -> ControlFlow<()> {
+    node.for_each_child(|_, child| match child {
+        NodeRef::Module(node) => v.visit_module(node),
+        NodeRef::Declaration(node) => v.visit_declaration(node),
+        NodeRef::GenericParameter(node) => v.visit_generic_parameter(node),
+        NodeRef::InterfaceField(node) => v.visit_interface_field(node),
+        NodeRef::Expression(node) => v.visit_expression(node),
+        NodeRef::TypeConstraint(node) => v.visit_type_constraint(node),
+        NodeRef::Type(node) => v.visit_type(node),
+        NodeRef::Parameter(node) => v.visit_parameter(node),
+        NodeRef::Assignment(node) => v.visit_assignment(node),
+        NodeRef::RecordElement(node) => v.visit_record_element(node),
+        NodeRef::Statement(node) => v.visit_statement(node),
+        NodeRef::BindingPattern(node) => v.visit_binding_pattern(node),
+        NodeRef::RecordBindingElement(node) => v.visit_record_binding_element(node),
+        NodeRef::Identifier(node) => v.visit_identifier(node),
+    })
+}
+
+pub fn walk_module<'ast, V: Visit<'ast> + ?Sized>(
+    v: &mut V,
+    node: &'ast ParseNode<Module<'ast>>,
+) -> ControlFlow<()> {
+    walk_children(v, NodeRef::Module(node))
+}
+
+pub fn walk_declaration<'ast, V: Visit<'ast> + ?Sized>(
+    v: &mut V,
+    node: &'ast ParseNode<Declaration<'ast>>,
+) -> ControlFlow<()> {
+    walk_children(v, NodeRef::Declaration(node))
+}
+
+pub fn walk_generic_parameter<'ast, V: Visit<'ast> + ?Sized>(
+    v: &mut V,
+    node: &'ast ParseNode<GenericParameter<'ast>>,
+) -> ControlFlow<()> {
+    walk_children(v, NodeRef::GenericParameter(node))
+}
+
+pub fn walk_interface_field<'ast, V: Visit<'ast> + ?Sized>(
+    v: &mut V,
+    node: &'ast ParseNode<InterfaceField<'ast>>,
+) -> ControlFlow<()> {
+    walk_children(v, NodeRef::InterfaceField(node))
+}
+
+pub fn walk_expression<'ast, V: Visit<'ast> + ?Sized>(
+    v: &mut V,
+    node: &'ast ParseNode<Expression<'ast>>,
+) -> ControlFlow<()> {
+    walk_children(v, NodeRef::Expression(node))
+}
+
+pub fn walk_type_constraint<'ast, V: Visit<'ast> + ?Sized>(
+    v: &mut V,
+    node: &'ast ParseNode<TypeConstraint<'ast>>,
+) -> ControlFlow<()> {
+    walk_children(v, NodeRef::TypeConstraint(node))
+}
+
+pub fn walk_type<'ast, V: Visit<'ast> + ?Sized>(
+    v: &mut V,
+    node: &'ast ParseNode<Type<'ast>>,
+) -> ControlFlow<()> {
+    walk_children(v, NodeRef::Type(node))
+}
+
+pub fn walk_parameter<'ast, V: Visit<'ast> + ?Sized>(
+    v: &mut V,
+    node: &'ast ParseNode<ParameterDeclaration<'ast>>,
+) -> ControlFlow<()> {
+    walk_children(v, NodeRef::Parameter(node))
+}
+
+pub fn walk_assignment<'ast, V: Visit<'ast> + ?Sized>(
+    v: &mut V,
+    node: &'ast ParseNode<Assignment<'ast>>,
+) -> ControlFlow<()> {
+    walk_children(v, NodeRef::Assignment(node))
+}
+
+pub fn walk_record_element<'ast, V: Visit<'ast> + ?Sized>(
+    v: &mut V,
+    node: &'ast ParseNode<RecordElement<'ast>>,
+) -> ControlFlow<()> {
+    walk_children(v, NodeRef::RecordElement(node))
+}
+
+pub fn walk_statement<'ast, V: Visit<'ast> + ?Sized>(
+    v: &mut V,
+    node: &'ast ParseNode<Statement<'ast>>,
+) -> ControlFlow<()> {
+    walk_children(v, NodeRef::Statement(node))
+}
+
+pub fn walk_binding_pattern<'ast, V: Visit<'ast> + ?Sized>(
+    v: &mut V,
+    node: &'ast ParseNode<BindingPattern<'ast>>,
+) -> ControlFlow<()> {
+    walk_children(v, NodeRef::BindingPattern(node))
+}
+
+pub fn walk_record_binding_element<'ast, V: Visit<'ast> + ?Sized>(
+    v: &mut V,
+    node: &'ast ParseNode<RecordBindingElement<'ast>>,
+) -> ControlFlow<()> {
+    walk_children(v, NodeRef::RecordBindingElement(node))
+}
@@ -0,0 +1,423 @@
+//! In-place mutable traversal of the surface AST.
+//!
+//! This is the mutable counterpart of [`Visit`](crate::visit::Visit). The methods of [`VisitMut`]
+//! receive a mutable reference to the [`ParseNode`] wrapping each node, so a visitor can rewrite a
+//! node (or its span) in place before or after walking its children. Returning
+//! [`ControlFlow::Break`] from any method stops the traversal immediately.
+
+use std::ops::ControlFlow;
+
+use seglisp::parse::ParseNode;
+
+use crate::{
+    Assignment, BindingPattern, Declaration, Expression, GenericParameter, InterfaceField, Module,
+    ParameterDeclaration, ParsedVec, RecordBindingElement, RecordElement, Statement, Type,
+    TypeConstraint, Verbatim,
+};
+
+pub trait VisitMut<'ast> {
+    fn visit_module_mut(&mut self, node: &mut ParseNode<Module<'ast>>) -> ControlFlow<()> {
+        walk_module_mut(self, node)
+    }
+
+    fn visit_declaration_mut(
+        &mut self,
+        node: &mut ParseNode<Declaration<'ast>>,
+    ) -> ControlFlow<()> {
+        walk_declaration_mut(self, node)
+    }
+
+    fn visit_generic_parameter_mut(
+        &mut self,
+        node: &mut ParseNode<GenericParameter<'ast>>,
+    ) -> ControlFlow<()> {
+        walk_generic_parameter_mut(self, node)
+    }
+
+    fn visit_interface_field_mut(
+        &mut self,
+        node: &mut ParseNode<InterfaceField<'ast>>,
+    ) -> ControlFlow<()> {
+        walk_interface_field_mut(self, node)
+    }
+
+    fn visit_expression_mut(&mut self, node: &mut ParseNode<Expression<'ast>>) -> ControlFlow<()> {
+        walk_expression_mut(self, node)
+    }
+
+    fn visit_type_constraint_mut(
+        &mut self,
+        node: &mut ParseNode<TypeConstraint<'ast>>,
+    ) -> ControlFlow<()> {
+        walk_type_constraint_mut(self, node)
+    }
+
+    fn visit_type_mut(&mut self, node: &mut ParseNode<Type<'ast>>) -> ControlFlow<()> {
+        walk_type_mut(self, node)
+    }
+
+    fn visit_parameter_mut(
+        &mut self,
+        node: &mut ParseNode<ParameterDeclaration<'ast>>,
+    ) -> ControlFlow<()> {
+        walk_parameter_mut(self, node)
+    }
+
+    fn visit_assignment_mut(&mut self, node: &mut ParseNode<Assignment<'ast>>) -> ControlFlow<()> {
+        walk_assignment_mut(self, node)
+    }
+
+    fn visit_record_element_mut(
+        &mut self,
+        node: &mut ParseNode<RecordElement<'ast>>,
+    ) -> ControlFlow<()> {
+        walk_record_element_mut(self, node)
+    }
+
+    fn visit_statement_mut(&mut self, node: &mut ParseNode<Statement<'ast>>) -> ControlFlow<()> {
+        walk_statement_mut(self, node)
+    }
+
+    fn visit_binding_pattern_mut(
+        &mut self,
+        node: &mut ParseNode<BindingPattern<'ast>>,
+    ) -> ControlFlow<()> {
+        walk_binding_pattern_mut(self, node)
+    }
+
+    fn visit_record_binding_element_mut(
+        &mut self,
+        node: &mut ParseNode<RecordBindingElement<'ast>>,
+    ) -> ControlFlow<()> {
+        walk_record_binding_element_mut(self, node)
+    }
+
+    /// Called for every identifier token that is not itself an expression: declared names,
+    /// parameter and binding names, record keys, field names, and type names.
+    fn visit_identifier_mut(&mut self, _node: &mut Verbatim<'ast>) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+}
+
+fn walk_list<T>(
+    list: &mut ParsedVec<T>,
+    mut f: impl FnMut(&mut ParseNode<T>) -> ControlFlow<()>,
+) -> ControlFlow<()> {
+    for node in &mut list.value {
+        f(node)?;
+    }
+
+    ControlFlow::Continue(())
+}
+
+fn walk_generics_mut<'ast, V: VisitMut<'ast> + ?Sized>(
+    v: &mut V,
+    generics: &mut Option<ParsedVec<GenericParameter<'ast>>>,
+) -> ControlFlow<()> {
+    match generics {
+        Some(generics) => walk_list(generics, |p| v.visit_generic_parameter_mut(p)),
+        None => ControlFlow::Continue(()),
+    }
+}
+
+fn walk_constraint_mut<'ast, V: VisitMut<'ast> + ?Sized>(
+    v: &mut V,
+    constraint: &mut Option<ParseNode<TypeConstraint<'ast>>>,
+) -> ControlFlow<()> {
+    match constraint {
+        Some(constraint) => v.visit_type_constraint_mut(constraint),
+        None => ControlFlow::Continue(()),
+    }
+}
+
+pub fn walk_module_mut<'ast, V: VisitMut<'ast> + ?Sized>(
+    v: &mut V,
+    node: &mut ParseNode<Module<'ast>>,
+) -> ControlFlow<()> {
+    for decl in &mut node.value.declarations {
+        v.visit_declaration_mut(decl)?;
+    }
+
+    ControlFlow::Continue(())
+}
+
+pub fn walk_declaration_mut<'ast, V: VisitMut<'ast> + ?Sized>(
+    v: &mut V,
+    node: &mut ParseNode<Declaration<'ast>>,
+) -> ControlFlow<()> {
+    match &mut node.value {
+        Declaration::Main { body, .. } => v.visit_expression_mut(body),
+        Declaration::Const {
+            identifier,
+            type_,
+            value,
+            ..
+        } => {
+            v.visit_identifier_mut(identifier)?;
+            walk_constraint_mut(v, type_)?;
+            v.visit_expression_mut(value)
+        }
+        Declaration::Function {
+            identifier,
+            generic_parameters,
+            parameters,
+            constraint,
+            body,
+            ..
+        } => {
+            v.visit_identifier_mut(identifier)?;
+            walk_generics_mut(v, generic_parameters)?;
+            walk_list(parameters, |p| v.visit_parameter_mut(p))?;
+            walk_constraint_mut(v, constraint)?;
+            v.visit_expression_mut(body)
+        }
+        Declaration::Import { pattern, .. } => v.visit_binding_pattern_mut(pattern),
+        Declaration::Export { elements, .. } => {
+            walk_list(elements, |e| v.visit_record_element_mut(e))
+        }
+        Declaration::TypeAlias {
+            name,
+            generic_parameters,
+            value,
+            ..
+        } => {
+            v.visit_identifier_mut(name)?;
+            walk_generics_mut(v, generic_parameters)?;
+            v.visit_type_mut(value)
+        }
+        Declaration::Interface {
+            name,
+            generic_parameters,
+            constraint,
+            body,
+            ..
+        } => {
+            v.visit_identifier_mut(name)?;
+            walk_generics_mut(v, generic_parameters)?;
+            walk_constraint_mut(v, constraint)?;
+            walk_list(body, |f| v.visit_interface_field_mut(f))
+        }
+    }
+}
+
+pub fn walk_generic_parameter_mut<'ast, V: VisitMut<'ast> + ?Sized>(
+    v: &mut V,
+    node: &mut ParseNode<GenericParameter<'ast>>,
+) -> ControlFlow<()> {
+    v.visit_identifier_mut(&mut node.value.name)?;
+    walk_constraint_mut(v, &node.value.constraint)
+}
+
+pub fn walk_interface_field_mut<'ast, V: VisitMut<'ast> + ?Sized>(
+    v: &mut V,
+    node: &mut ParseNode<InterfaceField<'ast>>,
+) -> ControlFlow<()> {
+    v.visit_identifier_mut(&mut node.value.name)?;
+    v.visit_type_constraint_mut(&mut node.value.constraint)
+}
+
+pub fn walk_expression_mut<'ast, V: VisitMut<'ast> + ?Sized>(
+    v: &mut V,
+    node: &mut ParseNode<Expression<'ast>>,
+) -> ControlFlow<()> {
+    match &mut node.value {
+        Expression::Number(_)
+        | Expression::String(_)
+        | Expression::Boolean(_)
+        | Expression::Name(_)
+        | Expression::Hole
+        | Expression::None => ControlFlow::Continue(()),
+        Expression::As { expr, type_, .. } => {
+            v.visit_expression_mut(expr)?;
+            v.visit_type_mut(type_)
+        }
+        Expression::Unary { expression, .. } => v.visit_expression_mut(expression),
+        Expression::Compare { left, right, .. } | Expression::Arithmetic { left, right, .. } => {
+            v.visit_expression_mut(left)?;
+            v.visit_expression_mut(right)
+        }
+        Expression::Accessor { accessee, index } => {
+            v.visit_expression_mut(accessee)?;
+            v.visit_expression_mut(index)
+        }
+        Expression::Function {
+            name,
+            generic_parameters,
+            parameters,
+            constraint,
+            body,
+            ..
+        } => {
+            if let Some(name) = name {
+                v.visit_identifier_mut(name)?;
+            }
+            walk_generics_mut(v, generic_parameters)?;
+            walk_list(parameters, |p| v.visit_parameter_mut(p))?;
+            walk_constraint_mut(v, constraint)?;
+            v.visit_expression_mut(body)
+        }
+        Expression::Call { callee, parameters } => {
+            v.visit_expression_mut(callee)?;
+            walk_list(parameters, |p| v.visit_expression_mut(p))
+        }
+        Expression::With { bindings, body, .. } => {
+            walk_list(bindings, |b| v.visit_assignment_mut(b))?;
+            v.visit_expression_mut(body)
+        }
+        Expression::Tuple { elements } | Expression::List { elements } => {
+            walk_list(elements, |e| v.visit_expression_mut(e))
+        }
+        Expression::Procedure { body } => walk_list(body, |s| v.visit_statement_mut(s)),
+        Expression::If {
+            condition,
+            then,
+            _else,
+            ..
+        } => {
+            v.visit_expression_mut(condition)?;
+            v.visit_expression_mut(then)?;
+            v.visit_expression_mut(_else)
+        }
+        Expression::Record { elements } => walk_list(elements, |e| v.visit_record_element_mut(e)),
+        Expression::FieldAccess { accessee, field } => {
+            v.visit_expression_mut(accessee)?;
+            v.visit_identifier_mut(field)
+        }
+    }
+}
+
+pub fn walk_type_constraint_mut<'ast, V: VisitMut<'ast> + ?Sized>(
+    v: &mut V,
+    node: &mut ParseNode<TypeConstraint<'ast>>,
+) -> ControlFlow<()> {
+    v.visit_type_mut(&mut node.value.type_)
+}
+
+pub fn walk_type_mut<'ast, V: VisitMut<'ast> + ?Sized>(
+    v: &mut V,
+    node: &mut ParseNode<Type<'ast>>,
+) -> ControlFlow<()> {
+    match &mut node.value {
+        Type::Kind | Type::Never | Type::Unknown => ControlFlow::Continue(()),
+        Type::Reference {
+            name,
+            generic_parameters,
+        } => {
+            v.visit_identifier_mut(name)?;
+            match generic_parameters {
+                Some(parameters) => walk_list(parameters, |t| v.visit_type_mut(t)),
+                None => ControlFlow::Continue(()),
+            }
+        }
+        Type::Union { left, right } => {
+            v.visit_type_mut(left)?;
+            v.visit_type_mut(right)
+        }
+        Type::Tuple { members } => walk_list(members, |t| v.visit_type_mut(t)),
+        Type::Function {
+            parameters,
+            return_type,
+            ..
+        } => {
+            walk_list(parameters, |t| v.visit_type_mut(t))?;
+            v.visit_type_mut(return_type)
+        }
+    }
+}
+
+pub fn walk_parameter_mut<'ast, V: VisitMut<'ast> + ?Sized>(
+    v: &mut V,
+    node: &mut ParseNode<ParameterDeclaration<'ast>>,
+) -> ControlFlow<()> {
+    v.visit_identifier_mut(&mut node.value.name)?;
+    walk_constraint_mut(v, &node.value.type_)
+}
+
+pub fn walk_assignment_mut<'ast, V: VisitMut<'ast> + ?Sized>(
+    v: &mut V,
+    node: &mut ParseNode<Assignment<'ast>>,
+) -> ControlFlow<()> {
+    v.visit_identifier_mut(&mut node.value.symbol)?;
+    v.visit_expression_mut(&mut node.value.value)
+}
+
+pub fn walk_record_element_mut<'ast, V: VisitMut<'ast> + ?Sized>(
+    v: &mut V,
+    node: &mut ParseNode<RecordElement<'ast>>,
+) -> ControlFlow<()> {
+    match &mut node.value {
+        RecordElement::KeyValuePair { key, value } => {
+            v.visit_identifier_mut(key)?;
+            v.visit_expression_mut(value)
+        }
+        RecordElement::Identifier { name } => v.visit_identifier_mut(name),
+        RecordElement::Spread { value } => v.visit_expression_mut(value),
+    }
+}
+
+pub fn walk_statement_mut<'ast, V: VisitMut<'ast> + ?Sized>(
+    v: &mut V,
+    node: &mut ParseNode<Statement<'ast>>,
+) -> ControlFlow<()> {
+    match &mut node.value {
+        Statement::Let { assignment, .. } | Statement::Set(assignment) => {
+            v.visit_assignment_mut(assignment)
+        }
+        Statement::If {
+            condition,
+            then,
+            _else,
+            ..
+        } => {
+            v.visit_expression_mut(condition)?;
+            v.visit_statement_mut(then)?;
+            match _else {
+                Some(_else) => v.visit_statement_mut(_else),
+                None => ControlFlow::Continue(()),
+            }
+        }
+        Statement::ForIn {
+            binding,
+            iterator,
+            body,
+            ..
+        } => {
+            v.visit_identifier_mut(binding)?;
+            v.visit_expression_mut(iterator)?;
+            v.visit_statement_mut(body)
+        }
+        Statement::Forever(body) => v.visit_statement_mut(body),
+        Statement::Do(body) | Statement::Expression(body) => v.visit_expression_mut(body),
+        Statement::Break | Statement::Continue | Statement::Pass => ControlFlow::Continue(()),
+    }
+}
+
+pub fn walk_binding_pattern_mut<'ast, V: VisitMut<'ast> + ?Sized>(
+    v: &mut V,
+    node: &mut ParseNode<BindingPattern<'ast>>,
+) -> ControlFlow<()> {
+    match &mut node.value {
+        BindingPattern::Identifier { name } => v.visit_identifier_mut(name),
+        BindingPattern::Tuple { patterns } => {
+            walk_list(patterns, |p| v.visit_binding_pattern_mut(p))
+        }
+        BindingPattern::Record { elements } => {
+            walk_list(elements, |e| v.visit_record_binding_element_mut(e))
+        }
+    }
+}
+
+pub fn walk_record_binding_element_mut<'ast, V: VisitMut<'ast> + ?Sized>(
+    v: &mut V,
+    node: &mut ParseNode<RecordBindingElement<'ast>>,
+) -> ControlFlow<()> {
+    match &mut node.value {
+        RecordBindingElement::Identifier { name } | RecordBindingElement::Rest { name } => {
+            v.visit_identifier_mut(name)
+        }
+        RecordBindingElement::KeyValuePair { name, pattern } => {
+            v.visit_identifier_mut(name)?;
+            v.visit_binding_pattern_mut(pattern)
+        }
+    }
+}
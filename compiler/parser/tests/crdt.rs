@@ -0,0 +1,268 @@
+use seglisp::DiagnosticSeverity;
+use serendipity_parser::{
+    crdt::{Document, Id, Object, Op},
+    incremental::parse_module,
+    node::NodePath,
+};
+
+const SOURCE: &str = "fn double(x) -> x * @;
+
+main #[
+  print(double(@));
+  let y = @;
+  print(y);
+];
+";
+
+const EXPRESSIONS: &[&str] = &[
+    "1",
+    "y",
+    "y + 1",
+    "double(@)",
+    "if @ then 1 else 2",
+    "#[ print(@); ]",
+];
+
+const STATEMENTS: &[&str] = &["print(@)", "let z = @", "pass", "print(#[ print(@); ])"];
+
+const DECLARATIONS: &[&str] = &["const c = @", "fn inc(a) -> a + @"];
+
+fn path(path: &str) -> NodePath {
+    path.parse().unwrap()
+}
+
+fn hole(document: &Document, at: &str) -> Id {
+    match document.find(&path(at)) {
+        Some(Object::Hole(id)) => id,
+        other => panic!("expected a hole at {at}, found {other:?}"),
+    }
+}
+
+fn sequence(document: &Document, at: &str) -> Id {
+    match document.find(&path(at)) {
+        Some(Object::Sequence(id)) => id,
+        other => panic!("expected a sequence at {at}, found {other:?}"),
+    }
+}
+
+fn assert_parses(source: &str) {
+    let errors: Vec<_> = parse_module(source)
+        .diagnostics
+        .into_iter()
+        .filter(|d| matches!(d.severity, DiagnosticSeverity::Error))
+        .map(|d| d.message)
+        .collect();
+    assert!(errors.is_empty(), "{source}\n{errors:?}");
+}
+
+#[test]
+fn renders_the_starting_source() {
+    let document = Document::new(1, SOURCE).unwrap();
+
+    assert_eq!(document.source(), SOURCE);
+    assert_eq!(document.holes().len(), 3);
+    assert!(document.module().module().is_some());
+}
+
+#[test]
+fn fills_different_holes_concurrently() {
+    let mut a = Document::new(1, SOURCE).unwrap();
+    let mut b = a.fork(2);
+
+    let from_a = a
+        .fill(hole(&a, "declarations[0].body.right"), Some("2"))
+        .unwrap();
+    let from_b = b
+        .fill(
+            hole(
+                &b,
+                "declarations[1].body.body[0].expression.parameters[0].parameters[0]",
+            ),
+            Some("21"),
+        )
+        .unwrap();
+
+    a.apply(from_b);
+    b.apply(from_a);
+
+    assert_eq!(a.source(), b.source());
+    assert!(a.source().contains("x * 2;"));
+    assert!(a.source().contains("print(double(21));"));
+}
+
+#[test]
+fn concurrent_fills_of_one_hole_agree() {
+    let mut a = Document::new(1, SOURCE).unwrap();
+    let mut b = a.fork(2);
+    let target = hole(&a, "declarations[0].body.right");
+
+    let from_a = a.fill(target, Some("2")).unwrap();
+    let from_b = b.fill(target, Some("y + 1")).unwrap();
+
+    a.apply(from_b);
+    b.apply(from_a);
+
+    assert_eq!(a.source(), b.source());
+    assert!(a.source().contains("x * (y + 1);"));
+}
+
+#[test]
+fn reorders_statements_concurrently() {
+    let mut a = Document::new(1, SOURCE).unwrap();
+    let mut b = a.fork(2);
+    let body = sequence(&a, "declarations[1].body");
+    let statements = a.elements(body);
+
+    // One person moves the last statement to the front, while another moves the first to the end.
+    let from_a = a.move_after(statements[2], None).unwrap();
+    let from_b = b.move_after(statements[0], Some(statements[2])).unwrap();
+
+    a.apply(from_b);
+    b.apply(from_a);
+
+    assert_eq!(a.source(), b.source());
+    let mut merged = a.elements(body);
+    assert_eq!(merged.len(), 3);
+    merged.sort();
+    let mut original = statements;
+    original.sort();
+    assert_eq!(merged, original);
+}
+
+#[test]
+fn edits_inside_inserted_statements() {
+    let mut a = Document::new(1, SOURCE).unwrap();
+    let mut b = a.fork(2);
+    let body = sequence(&a, "declarations[1].body");
+
+    let inserted = a.insert(body, None, "print(@)").unwrap();
+    b.apply(inserted.clone());
+
+    let fill = b
+        .fill(
+            hole(&b, "declarations[1].body.body[0].expression.parameters[0]"),
+            Some("1"),
+        )
+        .unwrap();
+
+    // The fill arrives at a third replica before the statement it fills.
+    let mut c = Document::new(3, SOURCE).unwrap();
+    c.apply(fill.clone());
+    assert_eq!(c.pending(), 1);
+    c.apply(inserted);
+    assert_eq!(c.pending(), 0);
+    a.apply(fill);
+
+    assert_eq!(a.source(), b.source());
+    assert_eq!(a.source(), c.source());
+    assert!(a.source().contains("main #[\n  print(1);\n"));
+}
+
+/// A small xorshift generator, so that failures can be reproduced from their seed.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn pick<T: Copy>(&mut self, items: &[T]) -> Option<T> {
+        (!items.is_empty()).then(|| items[self.below(items.len())])
+    }
+}
+
+/// Makes a random local edit, if there is one to make.
+fn random_edit(document: &mut Document, rng: &mut Rng) -> Option<Op> {
+    let sequences: Vec<_> = document
+        .sequences()
+        .into_iter()
+        .filter(|s| *s != document.root())
+        .collect();
+
+    match rng.below(100) {
+        0..=34 => {
+            let hole = rng.pick(&document.holes())?;
+            let text = rng.pick(EXPRESSIONS)?;
+            Some(document.fill(hole, Some(text)).unwrap())
+        }
+        35..=59 => {
+            let sequence = rng.pick(&sequences)?;
+            let after = rng.pick(&document.elements(sequence));
+            let after = after.filter(|_| rng.below(4) > 0);
+            let text = rng.pick(STATEMENTS)?;
+            Some(document.insert(sequence, after, text).unwrap())
+        }
+        60..=79 => {
+            let sequence = rng.pick(&sequences)?;
+            let elements = document.elements(sequence);
+            let element = rng.pick(&elements)?;
+            let after = rng.pick(&elements).filter(|after| *after != element);
+            Some(document.move_after(element, after).unwrap())
+        }
+        80..=91 => {
+            let sequence = rng.pick(&sequences)?;
+            let element = rng.pick(&document.elements(sequence))?;
+            Some(document.delete(element).unwrap())
+        }
+        _ => {
+            let root = document.root();
+            let after = rng.pick(&document.elements(root));
+            let text = rng.pick(DECLARATIONS)?;
+            Some(document.insert(root, after, text).unwrap())
+        }
+    }
+}
+
+#[test]
+fn random_peers_converge() {
+    const PEERS: usize = 4;
+    const ROUNDS: usize = 40;
+
+    for seed in 1..=25u64 {
+        let mut rng = Rng(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+        let start = Document::new(0, SOURCE).unwrap();
+        let mut peers: Vec<Document> = (1..=PEERS as u32).map(|id| start.fork(id)).collect();
+        let mut inboxes: Vec<Vec<Op>> = vec![Vec::new(); PEERS];
+
+        for _ in 0..ROUNDS {
+            for peer in 0..PEERS {
+                if let Some(op) = random_edit(&mut peers[peer], &mut rng) {
+                    for (other, inbox) in inboxes.iter_mut().enumerate() {
+                        if other != peer {
+                            inbox.push(op.clone());
+                        }
+                    }
+                }
+            }
+
+            // Deliver some of each peer's messages, in any order.
+            for (peer, inbox) in peers.iter_mut().zip(&mut inboxes) {
+                for _ in 0..rng.below(inbox.len() + 1) {
+                    let idx = rng.below(inbox.len());
+                    peer.apply(inbox.swap_remove(idx));
+                }
+            }
+        }
+
+        for (peer, inbox) in peers.iter_mut().zip(&mut inboxes) {
+            while !inbox.is_empty() {
+                let idx = rng.below(inbox.len());
+                peer.apply(inbox.swap_remove(idx));
+            }
+        }
+
+        let source = peers[0].source();
+        for peer in &peers {
+            assert_eq!(peer.pending(), 0, "seed {seed}");
+            assert_eq!(peer.source(), source, "seed {seed}");
+        }
+        assert_parses(&source);
+    }
+}
@@ -0,0 +1,71 @@
+//! Runs `gen_ts` and checks what it writes. `rushx test` type-checks the declarations with
+//! `check:declarations`; the type check here needs the package's TypeScript installed, so it is
+//! ignored unless asked for with `cargo test --test declarations -- --ignored`.
+
+use std::{path::Path, process::Command};
+
+use serendipity_parser::schema::{TypeKind, TYPES};
+
+fn generate() -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_gen_ts"))
+        .output()
+        .expect("failed to run gen_ts");
+
+    assert!(output.status.success(), "gen_ts failed");
+    String::from_utf8(output.stdout).expect("gen_ts wrote invalid UTF-8")
+}
+
+#[test]
+fn every_enum_gets_guards_a_visitor_and_builders() {
+    let declarations = generate();
+
+    for ty in TYPES {
+        let TypeKind::Enum(variants) = ty.kind else {
+            continue;
+        };
+        let name = ty.name;
+
+        assert!(declarations.contains(&format!("export interface {name}Visitor<R> {{")));
+        assert!(declarations.contains(&format!("export function visit{name}<R>(")));
+        for variant in variants {
+            assert!(
+                declarations.contains(&format!("export function is{}{name}(", variant.name)),
+                "there is no guard for {}{name}",
+                variant.name
+            );
+        }
+    }
+
+    assert!(declarations.contains("export interface Walker {"));
+}
+
+#[test]
+#[ignore = "needs TypeScript installed; `rushx check:declarations` runs the real check"]
+fn declarations_type_check() {
+    let tsc = Path::new(env!("CARGO_MANIFEST_DIR")).join("node_modules/.bin/tsc");
+    assert!(
+        tsc.exists(),
+        "TypeScript is not installed; install the package's dependencies first"
+    );
+
+    let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("index.gen.ts");
+    std::fs::write(&path, generate()).unwrap();
+
+    let output = Command::new(tsc)
+        .args([
+            "--noEmit",
+            "--target",
+            "ES2020",
+            "--moduleResolution",
+            "node",
+        ])
+        .arg(&path)
+        .output()
+        .expect("failed to run tsc");
+
+    assert!(
+        output.status.success(),
+        "the declarations do not type-check:\n{}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+}
@@ -0,0 +1,130 @@
+use serendipity_parser::diagram::{render_source, Format, Options};
+
+const CALL: &str = "main f(a + 1, fn (x) -> x);\n";
+
+fn draw(source: &str, path: &str, format: Format, spans: bool) -> String {
+    render_source(source, &path.parse().unwrap(), Options { format, spans }).unwrap()
+}
+
+#[test]
+fn dot_graphs_label_edges_with_fields() {
+    assert_eq!(
+        draw(CALL, "", Format::Dot, false),
+        r#"digraph ast {
+  node [shape=box, fontname="monospace"];
+  n0 [label="Module"];
+  n1 [label="Main"];
+  n2 [label="Call"];
+  n3 [label="Name f"];
+  n2 -> n3 [label="callee"];
+  n4 [label="Arithmetic +"];
+  n5 [label="Name a"];
+  n4 -> n5 [label="left"];
+  n6 [label="Number 1"];
+  n4 -> n6 [label="right"];
+  n2 -> n4 [label="parameters[0]"];
+  n7 [label="Function"];
+  n8 [label="ParameterDeclaration x"];
+  n9 [label="Identifier x"];
+  n8 -> n9 [label="name"];
+  n7 -> n8 [label="parameters[0]"];
+  n10 [label="Name x"];
+  n7 -> n10 [label="body"];
+  n2 -> n7 [label="parameters[1]"];
+  n1 -> n2 [label="body"];
+  n0 -> n1 [label="declarations[0]"];
+}
+"#
+    );
+}
+
+#[test]
+fn mermaid_graphs_label_edges_with_fields() {
+    assert_eq!(
+        draw(CALL, "declarations[0].body", Format::Mermaid, false),
+        r#"graph TD
+  n0["Call"]
+  n1["Name f"]
+  n0 -->|"callee"| n1
+  n2["Arithmetic +"]
+  n3["Name a"]
+  n2 -->|"left"| n3
+  n4["Number 1"]
+  n2 -->|"right"| n4
+  n0 -->|"parameters[0]"| n2
+  n5["Function"]
+  n6["ParameterDeclaration x"]
+  n7["Identifier x"]
+  n6 -->|"name"| n7
+  n5 -->|"parameters[0]"| n6
+  n8["Name x"]
+  n5 -->|"body"| n8
+  n0 -->|"parameters[1]"| n5
+"#
+    );
+}
+
+#[test]
+fn spans_are_one_based_line_column_pairs() {
+    let source = "const a =\n  f(1);\n";
+
+    assert_eq!(
+        draw(source, "declarations[0].value", Format::Dot, true),
+        r#"digraph ast {
+  node [shape=box, fontname="monospace"];
+  n0 [label="Call\n2:3-2:7"];
+  n1 [label="Name f\n2:3-2:4"];
+  n0 -> n1 [label="callee"];
+  n2 [label="Number 1\n2:5-2:6"];
+  n0 -> n2 [label="parameters[0]"];
+}
+"#
+    );
+    assert_eq!(
+        draw(source, "declarations[0].value", Format::Mermaid, true),
+        r#"graph TD
+  n0["Call<br/>2:3-2:7"]
+  n1["Name f<br/>2:3-2:4"]
+  n0 -->|"callee"| n1
+  n2["Number 1<br/>2:5-2:6"]
+  n0 -->|"parameters[0]"| n2
+"#
+    );
+}
+
+#[test]
+fn mermaid_labels_escape_html() {
+    assert_eq!(
+        draw(
+            "const a = b < c;",
+            "declarations[0].value",
+            Format::Mermaid,
+            false
+        ),
+        r#"graph TD
+  n0["Compare #lt;"]
+  n1["Name b"]
+  n0 -->|"left"| n1
+  n2["Name c"]
+  n0 -->|"right"| n2
+"#
+    );
+}
+
+#[test]
+fn missing_nodes_and_unknown_formats_are_errors() {
+    let error = render_source(
+        CALL,
+        &"declarations[3]".parse().unwrap(),
+        Options {
+            format: Format::Dot,
+            spans: false,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(error.to_string(), "there is no node at `declarations[3]`");
+
+    assert_eq!("dot".parse(), Ok(Format::Dot));
+    assert_eq!("mermaid".parse(), Ok(Format::Mermaid));
+    assert!("svg".parse::<Format>().is_err());
+}
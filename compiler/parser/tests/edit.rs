@@ -0,0 +1,268 @@
+use serendipity_parser::{
+    edit::{edit, Edit, Wrapper},
+    incremental::parse_module,
+    node::NodePath,
+    query::node_at_offset,
+};
+
+const SOURCE: &str = "// Greets the world.
+fn greet(name, punctuation) -> \"Hello, \" + name + punctuation;
+
+main #[
+  // Say hello.
+  let who = \"world\";
+  print(greet(who, \"!\"));
+];
+";
+
+/// The path of the deepest node at the start of `needle`, offset by `skip` bytes.
+fn path_at(needle: &str, skip: usize) -> NodePath {
+    let module = parse_module(SOURCE).module.unwrap();
+    node_at_offset(&module, SOURCE.find(needle).unwrap() + skip).0
+}
+
+fn path(path: &str) -> NodePath {
+    path.parse().unwrap()
+}
+
+/// Applies `edit` to `SOURCE` and checks that the result parses without diagnostics.
+fn apply(edit_: Edit) -> String {
+    let edited = edit(SOURCE, &edit_).unwrap();
+    assert!(
+        edited.parse().diagnostics.is_empty(),
+        "{:?} has diagnostics",
+        edited.source
+    );
+    edited.source
+}
+
+#[test]
+fn replacing_a_node_leaves_the_rest_of_the_source_alone() {
+    let source = apply(Edit::Replace {
+        path: path_at("\"world\"", 1),
+        text: "\"there\"".into(),
+    });
+
+    assert_eq!(source, SOURCE.replace("\"world\"", "\"there\""));
+}
+
+#[test]
+fn statements_are_inserted_on_their_own_line() {
+    let source = apply(Edit::InsertStatement {
+        path: path("declarations[1].body"),
+        index: 1,
+        text: "print(who)".into(),
+    });
+
+    assert_eq!(
+        source,
+        SOURCE.replace(
+            "  let who = \"world\";\n",
+            "  let who = \"world\";\n  print(who);\n"
+        )
+    );
+}
+
+#[test]
+fn statements_are_appended_after_the_last() {
+    let source = apply(Edit::InsertStatement {
+        path: path("declarations[1].body"),
+        index: 2,
+        text: "print(who);".into(),
+    });
+
+    assert_eq!(
+        source,
+        SOURCE.replace(
+            "  print(greet(who, \"!\"));\n",
+            "  print(greet(who, \"!\"));\n  print(who);\n"
+        )
+    );
+}
+
+#[test]
+fn statements_follow_their_neighbour_onto_the_same_line() {
+    let source = "main #[ let x = 1; print(x); ];\n";
+
+    let edited = edit(
+        source,
+        &Edit::InsertStatement {
+            path: path("declarations[0].body"),
+            index: 1,
+            text: "print(0)".into(),
+        },
+    )
+    .unwrap();
+
+    assert_eq!(edited.source, "main #[ let x = 1; print(0); print(x); ];\n");
+}
+
+#[test]
+fn statements_inserted_into_an_empty_body_are_indented() {
+    let edited = edit(
+        "main #[];\n",
+        &Edit::InsertStatement {
+            path: path("declarations[0].body"),
+            index: 0,
+            text: "print(1)".into(),
+        },
+    )
+    .unwrap();
+
+    assert_eq!(edited.source, "main #[\n  print(1);\n];\n");
+    assert!(edited.parse().diagnostics.is_empty());
+}
+
+#[test]
+fn a_statement_alone_on_its_line_is_deleted_with_it() {
+    let source = apply(Edit::DeleteStatement {
+        path: path("declarations[1].body"),
+        index: 0,
+    });
+
+    assert_eq!(source, SOURCE.replace("  let who = \"world\";\n", ""));
+    assert!(source.contains("  // Say hello.\n  print(greet"));
+}
+
+#[test]
+fn a_statement_sharing_its_line_is_deleted_alone() {
+    let edited = edit(
+        "main #[ let x = 1; print(x); ];\n",
+        &Edit::DeleteStatement {
+            path: path("declarations[0].body"),
+            index: 0,
+        },
+    )
+    .unwrap();
+
+    assert_eq!(edited.source, "main #[ print(x); ];\n");
+}
+
+#[test]
+fn expressions_are_wrapped_in_place() {
+    let target = path_at("\"world\"", 1);
+    let wrap = |wrapper| {
+        apply(Edit::Wrap {
+            path: target.clone(),
+            wrapper,
+        })
+    };
+
+    assert_eq!(
+        wrap(Wrapper::If {
+            condition: "loud".into()
+        }),
+        SOURCE.replace("\"world\";", "if loud then \"world\" else @;")
+    );
+    assert_eq!(
+        wrap(Wrapper::With {
+            name: "w".into(),
+            value: "1".into()
+        }),
+        SOURCE.replace("\"world\";", "with (w = 1) \"world\";")
+    );
+    assert_eq!(
+        wrap(Wrapper::Function {
+            parameters: vec!["a".into(), "b".into()]
+        }),
+        SOURCE.replace("\"world\";", "fn (a, b) -> \"world\";")
+    );
+}
+
+#[test]
+fn wrapped_operands_are_parenthesized() {
+    let source = apply(Edit::Wrap {
+        path: path_at("name + punctuation", 0),
+        wrapper: Wrapper::With {
+            name: "n".into(),
+            value: "1".into(),
+        },
+    });
+
+    assert_eq!(
+        source,
+        SOURCE.replace("+ name +", "+ (with (n = 1) name) +")
+    );
+}
+
+#[test]
+fn parameters_are_added_at_either_end() {
+    let add = |index, text: &str| {
+        apply(Edit::AddParameter {
+            path: path("declarations[0]"),
+            index,
+            text: text.into(),
+        })
+    };
+
+    assert_eq!(
+        add(0, "greeting"),
+        SOURCE.replace("greet(name,", "greet(greeting, name,")
+    );
+    assert_eq!(
+        add(2, " suffix "),
+        SOURCE.replace("punctuation) ->", "punctuation, suffix) ->")
+    );
+}
+
+#[test]
+fn parameters_are_removed_with_a_separator() {
+    let remove = |index| {
+        edit(
+            SOURCE,
+            &Edit::RemoveParameter {
+                path: path("declarations[0]"),
+                index,
+            },
+        )
+        .unwrap()
+        .source
+    };
+
+    assert_eq!(
+        remove(0),
+        SOURCE.replace("(name, punctuation)", "(punctuation)")
+    );
+    assert_eq!(remove(1), SOURCE.replace("(name, punctuation)", "(name)"));
+}
+
+#[test]
+fn edits_out_of_range_are_refused() {
+    let error = |edit_| edit(SOURCE, &edit_).unwrap_err().to_string();
+
+    assert!(error(Edit::DeleteStatement {
+        path: path("declarations[1].body"),
+        index: 2,
+    })
+    .contains("has no statement at index 2"));
+    assert!(error(Edit::RemoveParameter {
+        path: path("declarations[0]"),
+        index: 2,
+    })
+    .contains("has no parameter at index 2"));
+    assert!(error(Edit::Replace {
+        path: path("declarations[5]"),
+        text: "main 1".into(),
+    })
+    .contains("there is no node at 'declarations[5]'"));
+    assert!(error(Edit::AddParameter {
+        path: path("declarations[1]"),
+        index: 0,
+        text: "x".into(),
+    })
+    .contains("is not a function"));
+}
+
+#[test]
+fn edits_that_do_not_parse_are_refused() {
+    let error = edit(
+        SOURCE,
+        &Edit::Replace {
+            path: path_at("\"world\"", 1),
+            text: "(".into(),
+        },
+    )
+    .unwrap_err();
+
+    assert!(error.to_string().contains("does not parse"));
+}
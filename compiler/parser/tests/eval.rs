@@ -0,0 +1,120 @@
+use serendipity_parser::{
+    eval::{Host, Interpreter},
+    incremental::parse_module,
+    repl::PRELUDE,
+};
+
+/// Records what a program prints, and answers every prompt with `input`.
+#[derive(Default)]
+struct Recorder {
+    printed: Vec<String>,
+    prompts: Vec<Option<String>>,
+    input: &'static str,
+}
+
+impl Host for Recorder {
+    fn print(&mut self, text: &str) {
+        self.printed.push(text.to_string());
+    }
+
+    fn read_line(&mut self, prompt: Option<&str>) -> String {
+        self.prompts.push(prompt.map(String::from));
+        self.input.to_string()
+    }
+}
+
+/// Runs `source` after the prelude, returning what it printed or the message of the error it
+/// stopped at.
+fn run(source: &str, host: &mut Recorder) -> Result<Vec<String>, String> {
+    let prelude = parse_module(PRELUDE).module.unwrap();
+    let module = parse_module(source)
+        .module
+        .expect("the program does not parse");
+
+    let mut interpreter = Interpreter::new(std::mem::take(host));
+    interpreter
+        .run_module(&prelude)
+        .expect("the prelude does not run");
+    let result = interpreter.run_module(&module);
+
+    *host = std::mem::take(interpreter.host());
+    result.map(|()| host.printed.clone()).map_err(|e| e.message)
+}
+
+fn example(source: &str) -> Result<Vec<String>, String> {
+    run(source, &mut Recorder::default())
+}
+
+#[test]
+fn hello_world() {
+    assert_eq!(
+        example(include_str!("../../../cli/slipr/examples/hello_world.sdp")),
+        Ok(vec!["Hello, world!".to_string()])
+    );
+}
+
+#[test]
+fn functions_take_several_parameters() {
+    assert_eq!(
+        example(include_str!("../../../cli/slipr/examples/2param.sdp")),
+        Ok(vec!["27".to_string()])
+    );
+}
+
+#[test]
+fn if_statements_run_when_true() {
+    assert_eq!(
+        example(include_str!("../../../cli/slipr/examples/if.sdp")),
+        Ok(vec!["It's true.".to_string()])
+    );
+}
+
+#[test]
+fn loops_over_finite_sequences_end() {
+    assert_eq!(
+        example(include_str!("../../../cli/slipr/examples/iter.sdp")),
+        Ok(vec![])
+    );
+}
+
+#[test]
+fn break_leaves_loops_over_infinite_sequences() {
+    let expected: Vec<String> = (0..=200).map(|i| i.to_string()).collect();
+
+    assert_eq!(
+        example(include_str!("../../../cli/slipr/examples/break.sdp")),
+        Ok(expected)
+    );
+}
+
+#[test]
+fn prompts_read_from_the_host() {
+    let mut host = Recorder {
+        input: "typed",
+        ..Default::default()
+    };
+
+    let printed = run(
+        include_str!("../../../cli/slipr/examples/echo.sdp"),
+        &mut host,
+    );
+
+    assert_eq!(printed, Ok(vec!["typed".to_string()]));
+    assert_eq!(host.prompts, [Some("> ".to_string())]);
+}
+
+#[test]
+fn imports_are_not_supported() {
+    let error = example(include_str!("../../../cli/slipr/examples/recur.sdp")).unwrap_err();
+
+    assert!(error.contains("imports are not supported"), "{error}");
+}
+
+#[test]
+fn arguments_are_evaluated_lazily() {
+    // `panic` would stop the program if its argument to `first` were evaluated.
+    assert_eq!(
+        example("fn first(a, b) -> a;\nmain print(first(1, panic(\"evaluated\")));\n"),
+        Ok(vec!["1".to_string()])
+    );
+}
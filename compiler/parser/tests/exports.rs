@@ -0,0 +1,191 @@
+use seglisp::DiagnosticSeverity;
+use serendipity_parser::{check_exports, incremental::parse_module, BindingKind, Export};
+
+const BINDINGS: &str = "
+const a = 1;
+fn f(x) -> x;
+type Pair[T] = (T, T);
+interface Named { name: string };
+import { m, n: (p, q) } = use(\"./m\");
+";
+
+/// Checks the exports of `BINDINGS` followed by `exports`. Returns the names in the export table,
+/// the re-exported modules, and the diagnostic messages, with errors prefixed by `error: `.
+fn check(exports: &str) -> (Vec<String>, Vec<String>, Vec<String>) {
+    let source = format!("{BINDINGS}{exports}");
+    let parsed = parse_module(&source);
+    assert!(
+        parsed.diagnostics.is_empty(),
+        "{source} has parse errors: {:?}",
+        parsed.diagnostics
+    );
+    let module = parsed.module.expect("the module does not parse");
+
+    let (table, diagnostics) = check_exports(&module.value);
+
+    let names = table
+        .exports
+        .iter()
+        .map(|e| e.name().value.to_string())
+        .collect();
+    let reexports = table
+        .reexports
+        .iter()
+        .map(|r| r.value.to_string())
+        .collect();
+    let messages = diagnostics
+        .iter()
+        .map(|d| match d.severity {
+            DiagnosticSeverity::Error => format!("error: {}", d.message),
+            _ => d.message.to_string(),
+        })
+        .collect();
+
+    (names, reexports, messages)
+}
+
+#[test]
+fn every_kind_of_binding_can_be_exported() {
+    let source = format!("{BINDINGS}export {{ a, f, Pair, Named, m, q }};");
+    let parsed = parse_module(&source);
+    let module = parsed.module.unwrap();
+    let (table, diagnostics) = check_exports(&module.value);
+
+    assert!(diagnostics.is_empty(), "{diagnostics:?}");
+
+    let kinds: Vec<_> = table
+        .exports
+        .iter()
+        .map(|e| match e {
+            Export::Binding { name, kind } => (name.value, *kind),
+            Export::Value { name, .. } => panic!("'{}' exported as a value", name.value),
+        })
+        .collect();
+    assert_eq!(
+        kinds,
+        [
+            ("a", BindingKind::Const),
+            ("f", BindingKind::Function),
+            ("Pair", BindingKind::TypeAlias),
+            ("Named", BindingKind::Interface),
+            ("m", BindingKind::Import),
+            ("q", BindingKind::Import),
+        ]
+    );
+}
+
+#[test]
+fn a_module_without_exports_has_an_empty_table() {
+    let (names, reexports, messages) = check("");
+
+    assert!(names.is_empty());
+    assert!(reexports.is_empty());
+    assert!(messages.is_empty());
+}
+
+#[test]
+fn unknown_exports_are_errors() {
+    let (names, _, messages) = check("export { a, b };");
+
+    assert_eq!(names, ["a"]);
+    assert_eq!(
+        messages,
+        ["error: unknown export 'b', no such binding in this module"]
+    );
+}
+
+#[test]
+fn duplicate_exports_are_errors() {
+    let (names, _, messages) = check("export { a, f, a: 2 };");
+
+    assert_eq!(names, ["a", "f"]);
+    assert_eq!(messages, ["error: duplicate export 'a'"]);
+}
+
+#[test]
+fn imported_modules_can_be_reexported() {
+    let (names, reexports, messages) = check("export { ...m, ...p };");
+
+    assert!(names.is_empty());
+    assert_eq!(reexports, ["m", "p"]);
+    assert!(messages.is_empty(), "{messages:?}");
+}
+
+#[test]
+fn only_imported_modules_can_be_spread() {
+    let (_, reexports, messages) = check("export { ...a, ...z, ...f(1) };");
+
+    assert!(reexports.is_empty());
+    assert_eq!(
+        messages,
+        [
+            "error: cannot spread 'a' in an export, only imported modules can be re-exported",
+            "error: unknown export 'z', no such binding in this module",
+            "error: cannot spread an arbitrary expression in an export",
+        ]
+    );
+}
+
+#[test]
+fn more_than_one_export_declaration_is_a_warning() {
+    let (names, _, messages) = check("export { a };\nexport { f };\nexport { m };");
+
+    assert_eq!(names, ["a", "f", "m"]);
+    assert_eq!(
+        messages,
+        [
+            "module has more than one 'export' declaration (merge them into one)",
+            "module has more than one 'export' declaration (merge them into one)",
+        ]
+    );
+}
+
+#[test]
+fn exported_values_can_use_top_level_bindings() {
+    let (names, _, messages) = check("export { one: a, two: f(a) + 1, pair: (p, q), m };");
+
+    assert_eq!(names, ["one", "two", "pair", "m"]);
+    assert!(messages.is_empty(), "{messages:?}");
+}
+
+#[test]
+fn exported_values_can_use_their_own_bindings() {
+    let (names, _, messages) =
+        check("export { g: fn (x) -> x + a, h: with (y = 1) y * 2, r: { k: a } };");
+
+    assert_eq!(names, ["g", "h", "r"]);
+    assert!(messages.is_empty(), "{messages:?}");
+}
+
+#[test]
+fn exported_values_report_unknown_names() {
+    let (names, _, messages) =
+        check("export { one: b, two: f(a, c) + d, three: fn (x) -> x + y };");
+
+    assert!(names.is_empty(), "{names:?}");
+    assert_eq!(
+        messages,
+        [
+            "error: unknown name 'b' in export of 'one'",
+            "error: unknown name 'c' in export of 'two'",
+            "error: unknown name 'd' in export of 'two'",
+            "error: unknown name 'y' in export of 'three'",
+        ]
+    );
+}
+
+#[test]
+fn unknown_names_are_reported_at_their_use() {
+    let source = format!("{BINDINGS}export {{ one: f(zz) }};");
+    let parsed = parse_module(&source);
+    let module = parsed.module.unwrap();
+    let (_, diagnostics) = check_exports(&module.value);
+
+    let [diagnostic] = diagnostics.as_slice() else {
+        panic!("expected one diagnostic, got {diagnostics:?}");
+    };
+    let seglisp::DiagnosticLocation::Range(range) = &diagnostic.location else {
+        panic!("the diagnostic has no range");
+    };
+    assert_eq!(&source[range.0.absolute..range.1.absolute], "zz");
+}
@@ -0,0 +1,206 @@
+use std::{fs, path::Path, process::Command};
+
+use seglisp::Diagnostic;
+use serendipity_parser::{
+    fix::{apply_suggestions, fix, fix_document, Applicability, FixableDiagnostic, Suggestion},
+    incremental::parse_module,
+    lint::{check, EmptyTuple, Finding, LintConfig, LintContext, Registry, Rule},
+};
+
+const MACHINE: Applicability = Applicability::MachineApplicable;
+const MAYBE: Applicability = Applicability::MaybeIncorrect;
+
+/// Empty tuples in both branches of an `if`: the branches are identical, which has a fix that may
+/// be incorrect, and each empty tuple has a machine-applicable fix of its own.
+const BRANCHES: &str = "const a = if b then () else ();\n";
+
+fn fixed(source: &str, allowed: Applicability) -> (String, usize) {
+    let fixed = fix(
+        source,
+        &Registry::default(),
+        &LintConfig::default(),
+        allowed,
+    );
+    (fixed.source, fixed.applied)
+}
+
+/// The lint findings of `source` with the built-in rules.
+fn findings(source: &str) -> Vec<FixableDiagnostic> {
+    let parsed = parse_module(source);
+    check(
+        source,
+        parsed.module.as_ref().unwrap(),
+        &Registry::default(),
+        &LintConfig::default(),
+    )
+}
+
+/// A diagnostic to hang suggestions on.
+fn diagnostic() -> Diagnostic {
+    findings("const a = ();\n").remove(0).diagnostic
+}
+
+fn suggesting(suggestions: &[(Applicability, (usize, usize), &str)]) -> FixableDiagnostic {
+    FixableDiagnostic {
+        diagnostic: diagnostic(),
+        suggestions: suggestions
+            .iter()
+            .map(|(applicability, range, text)| {
+                Suggestion::replace("edit", *applicability, *range, *text)
+            })
+            .collect(),
+    }
+}
+
+#[test]
+fn suggestions_apply_until_none_are_left() {
+    // Replacing the `if` with a branch leaves an empty tuple, which the next pass replaces.
+    assert_eq!(fixed(BRANCHES, MAYBE), ("const a = none;\n".into(), 2));
+    assert_eq!(
+        fixed("const a = none;\n", MAYBE),
+        ("const a = none;\n".into(), 0)
+    );
+}
+
+#[test]
+fn suggestions_that_may_be_incorrect_are_applied_only_when_allowed() {
+    assert_eq!(
+        fixed(BRANCHES, MACHINE),
+        ("const a = if b then none else none;\n".into(), 2)
+    );
+}
+
+#[test]
+fn overlapping_and_misplaced_suggestions_are_skipped() {
+    let diagnostics = [
+        suggesting(&[(MACHINE, (0, 2), "X")]),
+        suggesting(&[(MACHINE, (1, 3), "Y")]),
+        suggesting(&[(MAYBE, (4, 5), "Z"), (MACHINE, (5, 6), "W")]),
+        suggesting(&[(MACHINE, (2, 2), "I")]),
+        suggesting(&[(MACHINE, (2, 2), "J")]),
+        suggesting(&[(MACHINE, (10, 12), "O")]),
+    ];
+
+    assert_eq!(
+        apply_suggestions("abcdef", &diagnostics, MACHINE),
+        ("XIcdeW".into(), 3)
+    );
+    assert_eq!(
+        apply_suggestions("abcdef", &diagnostics, MAYBE),
+        ("XIcdZf".into(), 3)
+    );
+}
+
+/// Suggests replacing every `none` with an unclosed parenthesis.
+struct Unbalance;
+
+impl Rule for Unbalance {
+    fn name(&self) -> &'static str {
+        "unbalance"
+    }
+
+    fn description(&self) -> &'static str {
+        "breaks the parse"
+    }
+
+    fn check(&self, cx: &LintContext, findings: &mut Vec<Finding>) {
+        for (start, none) in cx.source.match_indices("none") {
+            findings.push(Finding {
+                range: cx.module.range,
+                message: "none".into(),
+                note: None,
+                suggestions: vec![Suggestion::replace(
+                    "unbalance",
+                    MACHINE,
+                    (start, start + none.len()),
+                    "(",
+                )],
+            });
+        }
+    }
+}
+
+#[test]
+fn a_pass_that_breaks_the_parse_is_dropped() {
+    let mut registry = Registry::empty();
+    registry.register(EmptyTuple);
+    registry.register(Unbalance);
+
+    let fixed = fix(
+        "const a = ();\n",
+        &registry,
+        &LintConfig::default(),
+        MACHINE,
+    );
+
+    assert_eq!(fixed.source, "const a = none;\n");
+    assert_eq!(fixed.applied, 1);
+}
+
+#[test]
+fn unused_lets_are_renamed_unless_that_captures_a_name() {
+    let unused = "main #[\n  let x = 1;\n];\n";
+    assert_eq!(
+        fixed(unused, MACHINE),
+        ("main #[\n  let _x = 1;\n];\n".into(), 1)
+    );
+
+    let captures = "const _x = 1;\nmain #[\n  let x = 2;\n  print(_x);\n];\n";
+    let diagnostics = findings(captures);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].suggestions[0].applicability, MAYBE);
+    assert_eq!(fixed(captures, MACHINE), (captures.into(), 0));
+}
+
+#[test]
+fn documents_are_fixed_natively() {
+    assert_eq!(
+        fix_document(BRANCHES.as_bytes(), "", true).as_deref(),
+        Some("const a = none;\n")
+    );
+    assert_eq!(
+        fix_document(
+            BRANCHES.as_bytes(),
+            "[lint]\nempty-tuple = \"allow\"\n",
+            false
+        )
+        .as_deref(),
+        Some(BRANCHES)
+    );
+    assert_eq!(
+        fix_document(
+            BRANCHES.as_bytes(),
+            "[lint]\nempty-tuple = \"loud\"\n",
+            false
+        ),
+        None
+    );
+    assert_eq!(fix_document(b"const a = \xff;", "", false), None);
+}
+
+#[test]
+fn sdp_fix_takes_its_flag_anywhere() {
+    let directory = Path::new(env!("CARGO_TARGET_TMPDIR")).join("sdp-fix");
+    fs::create_dir_all(&directory).unwrap();
+    fs::write(directory.join("sdp.toml"), "").unwrap();
+    let file = directory.join("branches.sdp");
+    fs::write(&file, BRANCHES).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_sdp"))
+        .arg("fix")
+        .arg(&file)
+        .arg("--maybe-incorrect")
+        .output()
+        .expect("failed to run sdp");
+
+    assert!(output.status.success(), "sdp fix failed");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("applied 2 fixes"));
+    assert_eq!(fs::read_to_string(&file).unwrap(), "const a = none;\n");
+
+    let status = Command::new(env!("CARGO_BIN_EXE_sdp"))
+        .args(["fix", "--unknown"])
+        .arg(&file)
+        .status()
+        .expect("failed to run sdp");
+    assert_eq!(status.code(), Some(2));
+}
@@ -0,0 +1,142 @@
+//! Golden-file tests for the parser.
+//!
+//! The fixtures are the programs themselves: every `.sdp` file under each of [`FIXTURES`] is
+//! parsed, and its S-expression dump followed by its diagnostics is compared against an
+//! `.expected` file at the same relative path under `tests/golden`. The examples are never copied
+//! there, so they cannot drift from the originals; `tests/golden/errors` holds fixtures of its own,
+//! for sources that do not parse, next to their `.expected` files.
+//! Diagnostics are written as `;` comments, so an `.expected` file can be read back with
+//! `sexpr::read_module`.
+//!
+//! Run with `BLESS=1` to write the current output to the `.expected` files instead of comparing.
+//! A fixture without an `.expected` file fails the test until it is blessed.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use seglisp::{DiagnosticLocation, DiagnosticSeverity};
+use serendipity_parser::{incremental::parse_module, sexpr::print_module};
+
+/// Each fixture directory, relative to the crate, and the directory under `tests/golden` that holds
+/// its `.expected` files.
+const FIXTURES: &[(&str, &str)] = &[
+    ("../../cli/slipr/examples", "examples"),
+    ("../../lib/core", "core"),
+    ("tests/golden/errors", "errors"),
+];
+
+fn fixtures(dir: &Path, out: &mut Vec<PathBuf>) {
+    let mut entries: Vec<_> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    entries.sort();
+
+    for path in entries {
+        if path.is_dir() {
+            fixtures(&path, out);
+        } else if path.extension().is_some_and(|ext| ext == "sdp") {
+            out.push(path);
+        }
+    }
+}
+
+fn dump(source: &str) -> String {
+    let parsed = parse_module(source);
+    let mut out = match &parsed.module {
+        Some(module) => print_module(&module.value),
+        None => "; no module\n".to_string(),
+    };
+
+    for diagnostic in &parsed.diagnostics {
+        let (line, column) = match &diagnostic.location {
+            DiagnosticLocation::Range((start, _)) => (start.line + 1, start.column + 1),
+            _ => (1, 1),
+        };
+        let severity = match diagnostic.severity {
+            DiagnosticSeverity::Error => "error",
+            DiagnosticSeverity::Warning => "warning",
+            _ => "info",
+        };
+        out.push_str(&format!(
+            "; {line}:{column}: {severity}: {}\n",
+            diagnostic.message
+        ));
+    }
+
+    out
+}
+
+/// The first line at which `expected` and `actual` differ, for failure messages.
+fn first_difference(expected: &str, actual: &str) -> String {
+    let mut expected_lines = expected.lines();
+    let mut actual_lines = actual.lines();
+
+    for number in 1.. {
+        match (expected_lines.next(), actual_lines.next()) {
+            (Some(e), Some(a)) if e == a => continue,
+            (e, a) => {
+                return format!(
+                    "line {number}:\n  expected: {}\n  actual:   {}",
+                    e.unwrap_or("<end of file>"),
+                    a.unwrap_or("<end of file>")
+                )
+            }
+        }
+    }
+    unreachable!()
+}
+
+#[test]
+fn golden_files() {
+    let crate_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let golden = crate_dir.join("tests/golden");
+    let bless = std::env::var_os("BLESS").is_some();
+
+    let mut count = 0;
+    let mut failures = Vec::new();
+    for (fixture_dir, expected_dir) in FIXTURES {
+        let root = crate_dir.join(fixture_dir);
+        let mut paths = Vec::new();
+        fixtures(&root, &mut paths);
+        assert!(!paths.is_empty(), "no fixtures under {}", root.display());
+        count += paths.len();
+
+        for path in &paths {
+            let relative = path.strip_prefix(&root).unwrap();
+            let name = Path::new(expected_dir).join(relative);
+            let actual = dump(&fs::read_to_string(path).unwrap());
+            let expected_path = golden.join(&name).with_extension("expected");
+
+            if bless {
+                fs::create_dir_all(expected_path.parent().unwrap()).unwrap();
+                fs::write(&expected_path, &actual).unwrap();
+                continue;
+            }
+
+            match fs::read_to_string(&expected_path) {
+                Ok(expected) if expected != actual => failures.push(format!(
+                    "{}: {}",
+                    name.display(),
+                    first_difference(&expected, &actual)
+                )),
+                Ok(_) => {}
+                Err(_) => failures.push(format!(
+                    "{}: there is no {}",
+                    name.display(),
+                    expected_path.display()
+                )),
+            }
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{} of {} golden files differ; rerun with BLESS=1 to update them\n\n{}",
+        failures.len(),
+        count,
+        failures.join("\n\n")
+    );
+}
@@ -0,0 +1,132 @@
+use serendipity_parser::{
+    holes::{complete_hole, find_hole, CompletionKind, HoleCompletion},
+    incremental::parse_module,
+    Expression,
+};
+
+/// The type expected of the only hole in `source`.
+fn expected(source: &str) -> String {
+    let module = parse_module(source).module.unwrap();
+    let offset = source.find('@').expect("the source has no hole");
+
+    find_hole(&module, offset)
+        .expect("no hole was found")
+        .expected
+        .to_string()
+}
+
+/// The candidates for the only hole in `source`, best first.
+fn candidates(source: &str) -> Vec<HoleCompletion> {
+    let module = parse_module(source).module.unwrap();
+    let offset = source.find('@').expect("the source has no hole");
+
+    complete_hole(&module, offset).unwrap().1
+}
+
+fn labels(completions: &[HoleCompletion]) -> Vec<&str> {
+    completions.iter().map(|c| c.label.as_str()).collect()
+}
+
+#[test]
+fn the_context_decides_the_expected_type() {
+    assert_eq!(expected("const n: number = @;"), "number");
+    assert_eq!(expected("fn f(x: number): string -> @;"), "string");
+    assert_eq!(
+        expected("fn add(x: number, y: number): number -> x + y;\nmain add(1, @);"),
+        "number"
+    );
+    assert_eq!(expected("main 1 + @;"), "number");
+    assert_eq!(expected("main \"a\" + @;"), "string");
+    assert_eq!(expected("main 1 < @;"), "number");
+    assert_eq!(expected("main if @ then 1 else 2;"), "boolean");
+    assert_eq!(expected("main if true then @ else \"a\";"), "string");
+    assert_eq!(expected("main #[ do @; ];"), "procedure");
+    assert_eq!(expected("main unknown(@);"), "_");
+}
+
+#[test]
+fn the_cursor_may_follow_the_hole() {
+    let source = "main 1 + @;";
+    let module = parse_module(source).module.unwrap();
+    let after = source.find('@').unwrap() + 1;
+
+    assert!(find_hole(&module, after).is_some());
+    assert!(find_hole(&module, 0).is_none());
+}
+
+#[test]
+fn fitting_bindings_rank_above_literals_calls_and_templates() {
+    let completions = candidates(
+        "const greeting = \"hi\";\nconst n: number = 1;\n\
+         fn inc(x: number): number -> x + 1;\nmain inc(@);",
+    );
+
+    assert_eq!(
+        labels(&completions),
+        ["n", "0", "inc(…)", "if … then … else …", "with (…) …"]
+    );
+    assert_eq!(completions[0].kind, CompletionKind::Binding);
+    assert_eq!(completions[2].snippet, "inc(${1:@})");
+    assert!(completions.windows(2).all(|w| w[0].score >= w[1].score));
+}
+
+#[test]
+fn constructors_take_the_shape_of_the_expected_type() {
+    let completions = candidates("interface Point { x: number, y: number };\nconst p: Point = @;");
+    let first = &completions[0];
+
+    assert_eq!(first.kind, CompletionKind::Constructor);
+    assert_eq!(first.snippet, "{ x: ${1:@}, y: ${2:@} \\}");
+    assert_eq!(first.text, "{ x: @, y: @ }");
+}
+
+#[test]
+fn generic_shapes_are_offered_when_nothing_is_expected() {
+    let completions = candidates("main @;");
+    let constructors: Vec<_> = completions
+        .iter()
+        .filter(|c| c.kind == CompletionKind::Constructor)
+        .map(|c| c.snippet.as_str())
+        .collect();
+
+    assert_eq!(
+        constructors,
+        [
+            "(${1:@}, ${2:@})",
+            "[${1:@}]",
+            "{ ${1:key}: ${2:@} \\}",
+            "#[\n  ${1:pass};\n]"
+        ]
+    );
+}
+
+#[test]
+fn fragments_count_from_the_start_of_the_text() {
+    let completions = candidates("fn inc(x: number): number -> x + 1;\nmain inc(@);");
+    let call = completions.iter().find(|c| c.label == "inc(…)").unwrap();
+    let fragment = call.fragment().unwrap();
+
+    assert_eq!(
+        (fragment.range.0.absolute, fragment.range.1.absolute),
+        (0, call.text.len())
+    );
+    assert_eq!(fragment.range.0.column, 0);
+    let Expression::Call { callee, .. } = &fragment.value else {
+        panic!("the fragment is not a call");
+    };
+    assert!(matches!(callee.value, Expression::Name("inc")));
+    assert_eq!((callee.range.0.absolute, callee.range.1.absolute), (0, 3));
+
+    let completions = candidates("main #[ do @; ];");
+    let procedure = completions.iter().find(|c| c.label == "#[ … ]").unwrap();
+    let fragment = procedure.fragment().unwrap();
+    let Expression::Procedure { body } = &fragment.value else {
+        panic!("the fragment is not a procedure");
+    };
+    let statement = body.value[0].range.0;
+
+    assert_eq!(
+        (statement.absolute, statement.line, statement.column),
+        (5, 1, 2)
+    );
+}
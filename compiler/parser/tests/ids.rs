@@ -0,0 +1,164 @@
+use serendipity_parser::{
+    ids::{NodeId, NodeIdTree},
+    incremental::parse_module,
+    node::NodePath,
+};
+
+fn track(source: &str) -> NodeIdTree {
+    NodeIdTree::new(&parse_module(source).module.unwrap())
+}
+
+fn update(tree: &mut NodeIdTree, source: &str) {
+    tree.update(&parse_module(source).module.unwrap());
+}
+
+fn id(tree: &NodeIdTree, path: &str) -> NodeId {
+    let path: NodePath = path.parse().unwrap();
+    tree.id_at(&path)
+        .unwrap_or_else(|| panic!("no node at {path}"))
+}
+
+fn all_ids(tree: &NodeIdTree) -> Vec<NodeId> {
+    tree.entries().into_iter().map(|entry| entry.id).collect()
+}
+
+#[test]
+fn every_node_gets_a_distinct_id() {
+    let tree = track("const a = 1 + 2;\nmain a;\n");
+
+    let mut ids = all_ids(&tree);
+    let count = ids.len();
+    ids.sort();
+    ids.dedup();
+
+    assert_eq!(ids.len(), count);
+}
+
+#[test]
+fn entries_describe_their_nodes() {
+    let source = "main f(xy);\n";
+    let tree = track(source);
+
+    let entries = tree.entries();
+    assert_eq!(entries[0].path, "");
+    assert_eq!(entries[0].category, "Module");
+
+    let entry = entries
+        .iter()
+        .find(|entry| entry.path == "declarations[0].body.parameters[0]")
+        .unwrap();
+    assert_eq!(entry.category, "Expression");
+    assert_eq!(
+        &source[entry.range.0.absolute..entry.range.1.absolute],
+        "xy"
+    );
+}
+
+#[test]
+fn ids_survive_edits_in_other_declarations() {
+    let before = "const a = 1 + 2;\nfn f(x) -> x;\nmain f(a);\n";
+    let after = "const a = 1 + 2;\nfn f(x) -> x * 10;\nmain f(a);\n";
+
+    let mut tree = track(before);
+    let unchanged = [
+        "",
+        "declarations[0]",
+        "declarations[0].value",
+        "declarations[0].value.left",
+        "declarations[0].value.right",
+        "declarations[1]",
+        "declarations[1].parameters[0]",
+        "declarations[2].body",
+        "declarations[2].body.parameters[0]",
+    ];
+    let ids: Vec<_> = unchanged.iter().map(|path| id(&tree, path)).collect();
+    let old_body = id(&tree, "declarations[1].body");
+    let old_ids = all_ids(&tree);
+
+    update(&mut tree, after);
+
+    for (path, old) in unchanged.iter().zip(ids) {
+        assert_eq!(id(&tree, path), old, "the id of {path} changed");
+    }
+
+    // The body changed from a name to an arithmetic expression, so it is a new node.
+    let new_body = id(&tree, "declarations[1].body");
+    assert_ne!(new_body, old_body);
+    assert!(!old_ids.contains(&new_body), "an id was reused");
+}
+
+#[test]
+fn ids_follow_declarations_that_move() {
+    let before = "const a = 1;\nfn f(x) -> x;\n";
+    let mut tree = track(before);
+    let a = id(&tree, "declarations[0]");
+    let f = id(&tree, "declarations[1].body");
+
+    update(&mut tree, &format!("const z = 0;\n{before}"));
+
+    assert_eq!(id(&tree, "declarations[1]"), a);
+    assert_eq!(id(&tree, "declarations[2].body"), f);
+    assert_eq!(
+        tree.path_of(a).map(|path| path.to_string()).as_deref(),
+        Some("declarations[1]")
+    );
+}
+
+#[test]
+fn ids_survive_insertions_and_deletions_in_sibling_lists() {
+    let mut tree = track("main #[ let a = 1; let b = 2; do b; ];\n");
+    let statements: Vec<_> = (0..3)
+        .map(|idx| id(&tree, &format!("declarations[0].body.body[{idx}]")))
+        .collect();
+
+    update(
+        &mut tree,
+        "main #[ let z = 0; let a = 1; let b = 2; do b; ];\n",
+    );
+
+    for (idx, old) in statements.iter().enumerate() {
+        assert_eq!(
+            id(&tree, &format!("declarations[0].body.body[{}]", idx + 1)),
+            *old
+        );
+    }
+    assert!(!statements.contains(&id(&tree, "declarations[0].body.body[0]")));
+
+    update(&mut tree, "main #[ let b = 2; do b; ];\n");
+
+    assert_eq!(id(&tree, "declarations[0].body.body[0]"), statements[1]);
+    assert_eq!(id(&tree, "declarations[0].body.body[1]"), statements[2]);
+    assert!(tree.path_of(statements[0]).is_none());
+}
+
+#[test]
+fn ids_survive_insertions_in_argument_lists() {
+    let mut tree = track("main f(1, 2, 3);\n");
+    let arguments: Vec<_> = (0..3)
+        .map(|idx| id(&tree, &format!("declarations[0].body.parameters[{idx}]")))
+        .collect();
+
+    update(&mut tree, "main f(0, 1, 2, 3);\n");
+
+    for (idx, old) in arguments.iter().enumerate() {
+        assert_eq!(
+            id(
+                &tree,
+                &format!("declarations[0].body.parameters[{}]", idx + 1)
+            ),
+            *old
+        );
+    }
+}
+
+#[test]
+fn renamed_declarations_keep_their_ids() {
+    let mut tree = track("fn f(x) -> x;\n");
+    let f = id(&tree, "declarations[0]");
+    let body = id(&tree, "declarations[0].body");
+
+    update(&mut tree, "fn g(x) -> x;\n");
+
+    assert_eq!(id(&tree, "declarations[0]"), f);
+    assert_eq!(id(&tree, "declarations[0].body"), body);
+}
@@ -0,0 +1,298 @@
+use serendipity_parser::{
+    incremental::{apply_edits, parse_module, reparse, ParsedModule, TextEdit},
+    node::NodeRef,
+    owned::OwnedModule,
+    structural::structural_eq,
+};
+
+const SOURCE: &str = "const a = 1 + 2;
+fn f(x: number) -> x * a;
+type Pair[T] = (T, T);
+main #[ let y = f(a); do y; ];
+const s = \"text\"; const t = s;
+";
+
+/// Text inserted by the random edits: brackets, strings and comments that change how the rest of
+/// the source is read, as well as ordinary tokens.
+const SNIPPETS: &[&str] = &[
+    "(",
+    ")",
+    "[",
+    "]",
+    "#[",
+    "{",
+    "}",
+    ";",
+    "\"",
+    "//",
+    "/*",
+    "*/",
+    "\n",
+    " ",
+    "x",
+    " + 1",
+    "a,",
+    "const b = 3;\n",
+    "fn",
+];
+
+fn edit(start: usize, end: usize, text: &str) -> TextEdit {
+    TextEdit {
+        start,
+        end,
+        text: text.into(),
+    }
+}
+
+/// The span of every node in `node`, in traversal order.
+fn spans(node: NodeRef, out: &mut Vec<[usize; 6]>) {
+    let (start, end) = node.range();
+    out.push([
+        start.absolute,
+        start.line,
+        start.column,
+        end.absolute,
+        end.line,
+        end.column,
+    ]);
+
+    for (_, child) in node.children() {
+        spans(child, out);
+    }
+}
+
+/// The start, end and message of every diagnostic, in order.
+fn diagnostics(parsed: &ParsedModule) -> Vec<(Option<(usize, usize)>, String)> {
+    let mut diagnostics: Vec<_> = parsed
+        .diagnostics
+        .iter()
+        .map(|d| {
+            let range = match &d.location {
+                seglisp::DiagnosticLocation::Range(range) => {
+                    Some((range.0.absolute, range.1.absolute))
+                }
+                _ => None,
+            };
+            (range, d.message.clone())
+        })
+        .collect();
+    diagnostics.sort();
+    diagnostics
+}
+
+/// Asserts that `parsed`, reparsed incrementally, is what parsing all of `source` produces.
+fn assert_same_as_full_parse(parsed: &ParsedModule, source: &str) {
+    let full = parse_module(source);
+
+    match (&parsed.module, &full.module) {
+        (Some(module), Some(expected)) => {
+            let (module, expected) = (NodeRef::Module(module), NodeRef::Module(expected));
+            assert!(
+                structural_eq(module, expected),
+                "the trees of {source:?} differ"
+            );
+
+            let (mut actual_spans, mut expected_spans) = (Vec::new(), Vec::new());
+            for (_, child) in module.children() {
+                spans(child, &mut actual_spans);
+            }
+            for (_, child) in expected.children() {
+                spans(child, &mut expected_spans);
+            }
+            assert_eq!(
+                actual_spans, expected_spans,
+                "the spans in {source:?} differ"
+            );
+        }
+        (None, None) => {}
+        _ => panic!("only one parse of {source:?} produced a module"),
+    }
+
+    assert_eq!(
+        diagnostics(parsed),
+        diagnostics(&full),
+        "the diagnostics of {source:?} differ"
+    );
+}
+
+/// Applies `edits` to `SOURCE`, reparses it incrementally, and checks the result.
+fn check_reparse(edits: &[TextEdit]) -> String {
+    let source = apply_edits(SOURCE, edits).unwrap();
+    let parsed = reparse(parse_module(SOURCE), SOURCE, &source, edits);
+
+    assert_same_as_full_parse(&parsed, &source);
+    source
+}
+
+#[test]
+fn edits_apply_in_order_of_position() {
+    let source = apply_edits(
+        "abcdef",
+        &[edit(4, 5, "E"), edit(0, 1, "AA"), edit(2, 2, "-")],
+    );
+
+    assert_eq!(source.as_deref(), Ok("AAb-cdEf"));
+}
+
+#[test]
+fn edits_out_of_bounds_are_rejected() {
+    assert!(apply_edits("abc", &[edit(2, 4, "")]).is_err());
+    assert!(apply_edits("abc", &[edit(2, 1, "")]).is_err());
+    assert!(apply_edits("abc", &[edit(3, 3, "d")]).is_ok());
+}
+
+#[test]
+fn edits_inside_characters_are_rejected() {
+    // `é` takes up bytes 1 and 2.
+    assert!(apply_edits("aéb", &[edit(2, 3, "")]).is_err());
+    assert!(apply_edits("aéb", &[edit(1, 3, "e")]).is_ok());
+}
+
+#[test]
+fn overlapping_edits_are_rejected() {
+    assert!(apply_edits("abcdef", &[edit(1, 3, ""), edit(2, 4, "")]).is_err());
+    assert!(apply_edits("abcdef", &[edit(1, 3, ""), edit(3, 4, "")]).is_ok());
+}
+
+#[test]
+fn an_edit_inside_a_declaration_is_reparsed() {
+    let start = SOURCE.find("x * a").unwrap();
+
+    let source = check_reparse(&[edit(start, start + 5, "x * a + 1")]);
+    assert!(source.contains("x * a + 1;"));
+}
+
+#[test]
+fn edits_in_several_declarations_are_reparsed() {
+    let one = SOURCE.find("1 + 2").unwrap();
+    let pair = SOURCE.find("(T, T)").unwrap();
+
+    check_reparse(&[edit(pair, pair + 6, "(T, T, T)"), edit(one, one + 1, "10")]);
+}
+
+#[test]
+fn an_inserted_declaration_is_reparsed() {
+    let main = SOURCE.find("main").unwrap();
+
+    check_reparse(&[edit(main, main, "const b = a * 2;\n")]);
+}
+
+#[test]
+fn an_unclosed_bracket_runs_into_later_declarations() {
+    let one = SOURCE.find("1 + 2").unwrap();
+
+    check_reparse(&[edit(one, one, "(")]);
+    check_reparse(&[edit(one, one, "[")]);
+}
+
+#[test]
+fn an_extra_closing_bracket_is_reparsed() {
+    let one = SOURCE.find("1 + 2").unwrap();
+
+    check_reparse(&[edit(one, one, ")")]);
+}
+
+#[test]
+fn a_removed_semicolon_joins_declarations() {
+    let semicolon = SOURCE.find(';').unwrap();
+
+    check_reparse(&[edit(semicolon, semicolon + 1, "")]);
+}
+
+#[test]
+fn an_unclosed_string_or_comment_runs_into_later_declarations() {
+    let one = SOURCE.find("1 + 2").unwrap();
+
+    check_reparse(&[edit(one, one, "\"")]);
+    check_reparse(&[edit(one, one, "/*")]);
+}
+
+#[test]
+fn a_line_comment_hides_the_rest_of_its_line() {
+    // `const s` and `const t` share a line, so a comment after the first hides the second.
+    let after_s = SOURCE.find("\"text\";").unwrap() + "\"text\";".len();
+
+    check_reparse(&[edit(after_s, after_s, " //")]);
+}
+
+#[test]
+fn owned_modules_are_reparsed_in_place() {
+    let mut module = OwnedModule::parse(SOURCE);
+    let start = SOURCE.find("x * a").unwrap();
+
+    module
+        .apply_edits(&[edit(start, start + 1, "(x + 1)")])
+        .unwrap();
+
+    assert!(module.source().contains("(x + 1) * a"));
+    assert_same_as_full_parse(module.parsed(), module.source());
+
+    assert!(module.apply_edits(&[edit(0, 10_000, "")]).is_err());
+    assert!(module.source().contains("(x + 1) * a"));
+}
+
+/// Makes random edits. A small xorshift generator keeps every case reproducible from its seed.
+struct Gen {
+    state: u64,
+}
+
+impl Gen {
+    fn new(seed: u64) -> Self {
+        Gen {
+            state: seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1,
+        }
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        (self.state % n as u64) as usize
+    }
+
+    /// An edit of up to six bytes of `source` within `bounds`, which may also insert a snippet.
+    fn edit(&mut self, source: &str, bounds: (usize, usize)) -> TextEdit {
+        let start = bounds.0 + self.below(bounds.1 - bounds.0 + 1);
+        let end = (start + self.below(7)).min(bounds.1);
+        let text = match self.below(3) {
+            0 => "",
+            _ => SNIPPETS[self.below(SNIPPETS.len())],
+        };
+
+        // The sources are ASCII, so every offset is a character boundary.
+        assert!(source.is_ascii());
+        edit(start, end, text)
+    }
+}
+
+#[test]
+fn random_edit_sequences_match_full_parses() {
+    for seed in 0..64 {
+        let mut gen = Gen::new(seed);
+        let mut module = OwnedModule::parse(SOURCE);
+
+        for _ in 0..32 {
+            let source = module.source().to_string();
+            let len = source.len();
+
+            let edits = if gen.below(4) == 0 && len > 1 {
+                // Two edits, one in each half of the source.
+                let middle = len / 2;
+                vec![
+                    gen.edit(&source, (0, middle)),
+                    gen.edit(&source, (middle, len)),
+                ]
+            } else {
+                vec![gen.edit(&source, (0, len))]
+            };
+
+            module.apply_edits(&edits).unwrap();
+            assert_eq!(
+                module.source(),
+                apply_edits(&source, &edits).unwrap(),
+                "seed {seed}"
+            );
+            assert_same_as_full_parse(module.parsed(), module.source());
+        }
+    }
+}
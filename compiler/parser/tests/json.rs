@@ -0,0 +1,60 @@
+#![cfg(feature = "serde")]
+
+use std::ops::ControlFlow;
+
+use seglisp::parse::ParseNode;
+use serendipity_parser::{
+    json::{from_json, to_json, ReadError, SCHEMA_VERSION},
+    visit::{self, Visit},
+    Expression,
+};
+
+/// A module with a non-ASCII name.
+const CAFE: &str = "const café = 1;\nmain café;\n";
+
+#[derive(Default)]
+struct Names(Vec<String>);
+
+impl<'ast> Visit<'ast> for Names {
+    fn visit_expression(&mut self, node: &'ast ParseNode<Expression<'ast>>) -> ControlFlow<()> {
+        if let Expression::Name(name) = node.value {
+            self.0.push(name.to_string());
+        }
+        visit::walk_expression(self, node)
+    }
+}
+
+/// The names referred to in a parse read back from `json`, and the parse written out again.
+fn read(json: &str) -> (Vec<String>, String) {
+    let read = from_json(json).unwrap();
+    let mut names = Names::default();
+    let _ = names.visit_module(read.module().unwrap());
+
+    (names.0, serde_json::to_string(read.output()).unwrap())
+}
+
+#[test]
+fn parses_read_back_unchanged() {
+    let json = to_json(CAFE, false);
+
+    assert_eq!(read(&json), (vec!["café".to_string()], json));
+}
+
+#[test]
+fn names_may_be_escaped() {
+    let json = to_json(CAFE, false);
+
+    // How Python's `json.dumps` writes it by default, with an ASCII escape thrown in.
+    let escaped = json.replace("café", "\\u0063af\\u00e9");
+    assert!(escaped.is_ascii());
+
+    assert_eq!(read(&escaped), (vec!["café".to_string()], json));
+}
+
+#[test]
+fn other_versions_are_refused() {
+    let json =
+        to_json(CAFE, false).replacen(&format!("\"version\":{SCHEMA_VERSION}"), "\"version\":0", 1);
+
+    assert!(matches!(from_json(&json), Err(ReadError::Version(0))));
+}
@@ -0,0 +1,232 @@
+use seglisp::{DiagnosticLocation, DiagnosticSeverity};
+use serendipity_parser::{
+    incremental::parse_module,
+    lint::{lint, LintConfig, LintLevel, Registry},
+};
+
+/// The zero-based line, severity and message of every lint finding in `source`.
+fn findings_with(source: &str, config: &LintConfig) -> Vec<(usize, &'static str, String)> {
+    let parsed = parse_module(source);
+    assert!(parsed.diagnostics.is_empty(), "{source:?} does not parse");
+
+    lint(
+        source,
+        parsed.module.as_ref().unwrap(),
+        &Registry::default(),
+        config,
+    )
+    .into_iter()
+    .map(|d| {
+        let DiagnosticLocation::Range(range) = d.location else {
+            panic!("{:?} has no range", d.message);
+        };
+        let severity = match d.severity {
+            DiagnosticSeverity::Error => "error",
+            DiagnosticSeverity::Warning => "warning",
+            _ => "other",
+        };
+        (range.0.line, severity, d.message)
+    })
+    .collect()
+}
+
+fn findings(source: &str) -> Vec<(usize, &'static str, String)> {
+    findings_with(source, &LintConfig::default())
+}
+
+fn messages(source: &str) -> Vec<String> {
+    findings(source).into_iter().map(|(_, _, m)| m).collect()
+}
+
+#[test]
+fn unused_lets_are_reported_unless_they_start_with_an_underscore() {
+    let source = "main #[\n  let x = 1;\n  let _y = 2;\n  let z = 3;\n  print(z);\n];\n";
+
+    assert_eq!(
+        findings(source),
+        [(
+            1,
+            "warning",
+            "unused `let` binding 'x' [unused-let]".to_string()
+        )]
+    );
+}
+
+#[test]
+fn identical_branches_are_reported() {
+    assert_eq!(
+        messages("const a = if b then 1 else 1;\nconst c = if b then 1 else 2;\n"),
+        ["both branches of this `if` are identical [identical-branches]"]
+    );
+}
+
+#[test]
+fn equality_with_none_is_reported() {
+    assert_eq!(
+        messages("const a = b == none;\nconst c = none != b;\nconst d = b < none;\n"),
+        [
+            "comparison against `none` [none-comparison]",
+            "comparison against `none` [none-comparison]",
+        ]
+    );
+}
+
+#[test]
+fn empty_tuples_are_reported() {
+    assert_eq!(
+        messages("const a = ();\nconst b = (1, 2);\n"),
+        ["empty tuple (use 'none' instead) [empty-tuple]"]
+    );
+}
+
+#[test]
+fn loops_without_their_own_break_are_reported() {
+    let source = "main #[
+  loop print(1);
+  loop #[ break; ];
+  loop #[ for x in xs break; ];
+];
+";
+
+    assert_eq!(
+        findings(source),
+        [
+            (
+                1,
+                "warning",
+                "this `loop` has no `break` [loop-without-break]".to_string()
+            ),
+            (
+                3,
+                "warning",
+                "this `loop` has no `break` [loop-without-break]".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn trailing_directives_allow_rules_on_their_line() {
+    let source = "const a = (); // lint-allow: empty-tuple\nconst b = ();\n";
+
+    assert_eq!(
+        findings(source),
+        [(
+            1,
+            "warning",
+            "empty tuple (use 'none' instead) [empty-tuple]".to_string()
+        )]
+    );
+}
+
+#[test]
+fn directives_on_their_own_line_allow_rules_on_the_next() {
+    let source = "// lint-allow: none-comparison, empty-tuple
+const a = ();
+const b = ();
+";
+
+    assert_eq!(findings(source).len(), 1);
+    assert_eq!(findings(source)[0].0, 2);
+}
+
+#[test]
+fn file_directives_allow_rules_everywhere() {
+    let source = "const a = ();\n// lint-allow-file: empty-tuple\nconst b = ();\n";
+
+    assert!(findings(source).is_empty());
+}
+
+#[test]
+fn directives_only_allow_the_rules_they_name() {
+    let source = "const a = (); // lint-allow: unused-let\n";
+
+    assert_eq!(messages(source).len(), 1);
+}
+
+#[test]
+fn directives_inside_strings_are_ignored() {
+    let source = "const s = \"// lint-allow-file: empty-tuple\";\nconst a = ();\n";
+
+    assert_eq!(
+        messages(source),
+        ["empty tuple (use 'none' instead) [empty-tuple]"]
+    );
+}
+
+#[test]
+fn configured_levels_override_the_defaults() {
+    let config = LintConfig::parse(
+        "[lint]
+empty-tuple = \"deny\"
+none-comparison = \"allow\"
+",
+    )
+    .unwrap();
+
+    assert_eq!(
+        findings_with("const a = ();\nconst b = c == none;\n", &config),
+        [(
+            0,
+            "error",
+            "empty tuple (use 'none' instead) [empty-tuple]".to_string()
+        )]
+    );
+}
+
+#[test]
+fn config_comments_and_other_tables_are_skipped() {
+    let config = LintConfig::parse(
+        "# project settings
+[package]
+name = \"example\"
+empty-tuple = \"nonsense\"
+
+[lint] # rule levels
+\"empty-tuple\" = \"deny\" # no empty tuples
+unused-let = \"allow\"
+",
+    )
+    .unwrap();
+
+    let mut expected = LintConfig::default();
+    expected.set("empty-tuple", LintLevel::Deny);
+    expected.set("unused-let", LintLevel::Allow);
+    assert_eq!(config, expected);
+}
+
+#[test]
+fn config_strings_may_contain_hashes() {
+    let config = LintConfig::parse("[lint]\n\"#empty-tuple\" = \"deny\"\n").unwrap();
+
+    let mut expected = LintConfig::default();
+    expected.set("#empty-tuple", LintLevel::Deny);
+    assert_eq!(config, expected);
+    assert_eq!(config.unknown_rules(&Registry::default()), ["#empty-tuple"]);
+}
+
+#[test]
+fn invalid_configs_say_which_line_is_wrong() {
+    let error = |text| LintConfig::parse(text).unwrap_err().to_string();
+
+    assert_eq!(
+        error("[lint]\nempty-tuple = \"loud\"\n"),
+        "line 2: unknown lint level 'loud', expected 'allow', 'warn', or 'deny'"
+    );
+    assert_eq!(
+        error("[lint]\n\nempty-tuple\n"),
+        "line 3: expected `rule = \"level\"`"
+    );
+    assert_eq!(
+        error("[lint]\nempty-tuple = deny\n"),
+        "line 2: expected the level to be a string"
+    );
+}
+
+#[test]
+fn unknown_rules_are_listed() {
+    let config =
+        LintConfig::parse("[lint]\nunused-lets = \"deny\"\nempty-tuple = \"deny\"\n").unwrap();
+
+    assert_eq!(config.unknown_rules(&Registry::default()), ["unused-lets"]);
+}
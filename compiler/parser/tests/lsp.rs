@@ -0,0 +1,512 @@
+#![cfg(feature = "lsp")]
+
+use serde_json::{json, Value};
+use serendipity_parser::lsp::{read_message, write_message, Server};
+
+const URI: &str = "file:///test.sdp";
+
+const SOURCE: &str = "/**
+ * Adds one to `x`.
+ */
+fn inc(x) -> x + 1;
+
+main inc(inc(2));
+";
+
+fn request(id: u64, method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+}
+
+fn notification(method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "method": method, "params": params })
+}
+
+fn at(line: u64, character: u64) -> Value {
+    json!({
+        "textDocument": { "uri": URI },
+        "position": { "line": line, "character": character },
+    })
+}
+
+fn change(changes: Value) -> Value {
+    notification(
+        "textDocument/didChange",
+        json!({
+            "textDocument": { "uri": URI, "version": 2 },
+            "contentChanges": changes,
+        }),
+    )
+}
+
+fn range(start: (u64, u64), end: (u64, u64)) -> Value {
+    json!({
+        "start": { "line": start.0, "character": start.1 },
+        "end": { "line": end.0, "character": end.1 },
+    })
+}
+
+fn symbol_names(replies: &[Value], id: u64) -> Vec<&str> {
+    response(replies, id)
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|s| s["name"].as_str().unwrap())
+        .collect()
+}
+
+/// Runs a server over `script` and returns every message it sent back, with its exit code.
+fn run(script: &[Value]) -> (Vec<Value>, i32) {
+    let mut input = Vec::new();
+    for message in script {
+        write_message(&mut input, message).unwrap();
+    }
+
+    serve(&input)
+}
+
+/// Runs a server over the raw bytes of `input`.
+fn serve(input: &[u8]) -> (Vec<Value>, i32) {
+    let mut output = Vec::new();
+    let mut server = Server::new();
+    server.run(input, &mut output).unwrap();
+
+    let mut replies = Vec::new();
+    let mut output = output.as_slice();
+    while let Some(message) = read_message(&mut output).unwrap() {
+        replies.push(message);
+    }
+    (replies, server.exit_code())
+}
+
+fn response(replies: &[Value], id: u64) -> &Value {
+    let reply = replies
+        .iter()
+        .find(|r| r["id"] == json!(id))
+        .unwrap_or_else(|| panic!("no response to request {id}"));
+
+    assert!(reply.get("error").is_none(), "request {id} failed: {reply}");
+    &reply["result"]
+}
+
+fn session(requests: Vec<Value>) -> Vec<Value> {
+    let mut script = vec![
+        request(0, "initialize", json!({ "capabilities": {} })),
+        notification("initialized", json!({})),
+        notification(
+            "textDocument/didOpen",
+            json!({
+                "textDocument": {
+                    "uri": URI,
+                    "languageId": "serendipity",
+                    "version": 1,
+                    "text": SOURCE,
+                },
+            }),
+        ),
+    ];
+    script.extend(requests);
+    script.push(request(99, "shutdown", Value::Null));
+    script.push(notification("exit", Value::Null));
+
+    let (replies, exit_code) = run(&script);
+    assert_eq!(exit_code, 0);
+    replies
+}
+
+#[test]
+fn initialize_advertises_capabilities() {
+    let replies = session(vec![]);
+    let capabilities = &response(&replies, 0)["capabilities"];
+
+    for provider in [
+        "documentSymbolProvider",
+        "hoverProvider",
+        "definitionProvider",
+        "referencesProvider",
+        "documentFormattingProvider",
+    ] {
+        assert_eq!(capabilities[provider], json!(true), "{provider}");
+    }
+    assert_eq!(
+        capabilities["completionProvider"]["triggerCharacters"],
+        json!(["@"])
+    );
+    assert_eq!(
+        capabilities["codeActionProvider"]["codeActionKinds"],
+        json!(["quickfix"])
+    );
+}
+
+#[test]
+fn publishes_diagnostics_on_open_and_change() {
+    let replies = session(vec![notification(
+        "textDocument/didChange",
+        json!({
+            "textDocument": { "uri": URI, "version": 2 },
+            "contentChanges": [{ "text": "frobnicate x;" }],
+        }),
+    )]);
+
+    let published: Vec<&Value> = replies
+        .iter()
+        .filter(|r| r["method"] == json!("textDocument/publishDiagnostics"))
+        .collect();
+
+    assert_eq!(published.len(), 2);
+    assert_eq!(published[0]["params"]["diagnostics"], json!([]));
+
+    let diagnostics = published[1]["params"]["diagnostics"].as_array().unwrap();
+    assert!(!diagnostics.is_empty());
+    assert_eq!(diagnostics[0]["severity"], json!(1));
+}
+
+#[test]
+fn publishes_lint_warnings_unless_allowed() {
+    let replies = session(vec![notification(
+        "textDocument/didChange",
+        json!({
+            "textDocument": { "uri": URI, "version": 2 },
+            "contentChanges": [{
+                "text": "main #[\n  let unused = 1;\n  // lint-allow: empty-tuple\n  print(());\n];\n",
+            }],
+        }),
+    )]);
+
+    let published = replies
+        .iter()
+        .filter(|r| r["method"] == json!("textDocument/publishDiagnostics"))
+        .last()
+        .unwrap();
+
+    let diagnostics = published["params"]["diagnostics"].as_array().unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0]["severity"], json!(2));
+    assert_eq!(diagnostics[0]["range"]["start"]["line"], json!(1));
+    assert!(diagnostics[0]["message"]
+        .as_str()
+        .unwrap()
+        .contains("[unused-let]"));
+}
+
+#[test]
+fn offers_quick_fixes_for_lint_findings() {
+    let replies = session(vec![
+        notification(
+            "textDocument/didChange",
+            json!({
+                "textDocument": { "uri": URI, "version": 2 },
+                "contentChanges": [{ "text": "main print(());\n" }],
+            }),
+        ),
+        request(
+            1,
+            "textDocument/codeAction",
+            json!({
+                "textDocument": { "uri": URI },
+                "range": {
+                    "start": { "line": 0, "character": 11 },
+                    "end": { "line": 0, "character": 11 },
+                },
+                "context": { "diagnostics": [] },
+            }),
+        ),
+    ]);
+
+    let actions = response(&replies, 1).as_array().unwrap();
+    assert_eq!(actions.len(), 1);
+    assert_eq!(actions[0]["kind"], json!("quickfix"));
+    assert_eq!(actions[0]["isPreferred"], json!(true));
+    assert_eq!(
+        actions[0]["edit"]["changes"][URI],
+        json!([{
+            "range": {
+                "start": { "line": 0, "character": 11 },
+                "end": { "line": 0, "character": 13 },
+            },
+            "newText": "none",
+        }])
+    );
+}
+
+#[test]
+fn document_symbols_list_declarations() {
+    let replies = session(vec![request(
+        1,
+        "textDocument/documentSymbol",
+        json!({ "textDocument": { "uri": URI } }),
+    )]);
+
+    assert_eq!(symbol_names(&replies, 1), ["inc", "main"]);
+}
+
+#[test]
+fn ranged_changes_replace_part_of_the_document() {
+    let replies = session(vec![
+        change(json!([
+            { "range": range((4, 0), (4, 0)), "text": "const two = 2;\n" },
+            // `fn inc` is still on line 3 after the first change.
+            { "range": range((3, 3), (3, 6)), "text": "add" },
+        ])),
+        request(
+            1,
+            "textDocument/documentSymbol",
+            json!({ "textDocument": { "uri": URI } }),
+        ),
+    ]);
+
+    assert_eq!(symbol_names(&replies, 1), ["add", "two", "main"]);
+}
+
+#[test]
+fn changes_outside_the_document_drop_it_until_it_is_reopened() {
+    let symbols = |id| {
+        request(
+            id,
+            "textDocument/documentSymbol",
+            json!({ "textDocument": { "uri": URI } }),
+        )
+    };
+
+    let replies = session(vec![
+        change(json!([{ "range": range((40, 0), (40, 1)), "text": "x" }])),
+        symbols(1),
+        change(json!([{ "text": "const ignored = 1;" }])),
+        symbols(2),
+        notification(
+            "textDocument/didOpen",
+            json!({
+                "textDocument": {
+                    "uri": URI,
+                    "languageId": "serendipity",
+                    "version": 3,
+                    "text": "const reopened = 1;",
+                },
+            }),
+        ),
+        symbols(3),
+    ]);
+
+    let message = replies
+        .iter()
+        .find(|r| r["method"] == json!("window/showMessage"))
+        .expect("the client was not told about the lost document");
+    assert_eq!(message["params"]["type"], json!(1));
+
+    for id in [1, 2] {
+        let reply = replies.iter().find(|r| r["id"] == json!(id)).unwrap();
+        assert!(reply.get("error").is_some(), "{reply}");
+    }
+    assert_eq!(symbol_names(&replies, 3), ["reopened"]);
+}
+
+#[test]
+fn messages_without_a_length_are_skipped() {
+    let mut input = b"Content-Type: application/vscode-jsonrpc\r\n\r\n".to_vec();
+    for message in [
+        request(1, "initialize", json!({ "capabilities": {} })),
+        request(2, "shutdown", Value::Null),
+        notification("exit", Value::Null),
+    ] {
+        write_message(&mut input, &message).unwrap();
+    }
+
+    let (replies, exit_code) = serve(&input);
+
+    assert_eq!(replies[0]["method"], json!("window/logMessage"));
+    response(&replies, 1);
+    response(&replies, 2);
+    assert_eq!(exit_code, 0);
+}
+
+#[test]
+fn hover_shows_doc_comment() {
+    let replies = session(vec![request(1, "textDocument/hover", at(5, 5))]);
+
+    let contents = response(&replies, 1)["contents"]["value"].as_str().unwrap();
+    assert!(contents.contains("fn inc(x) -> x + 1;"), "{contents}");
+    assert!(contents.contains("Adds one to `x`."), "{contents}");
+}
+
+#[test]
+fn definition_and_references() {
+    let replies = session(vec![
+        request(1, "textDocument/definition", at(5, 10)),
+        request(
+            2,
+            "textDocument/references",
+            json!({
+                "textDocument": { "uri": URI },
+                "position": { "line": 3, "character": 4 },
+                "context": { "includeDeclaration": true },
+            }),
+        ),
+        request(3, "textDocument/definition", at(3, 13)),
+    ]);
+
+    let definition = response(&replies, 1);
+    assert_eq!(definition["uri"], json!(URI));
+    assert_eq!(
+        definition["range"]["start"],
+        json!({ "line": 3, "character": 3 })
+    );
+
+    let references = response(&replies, 2).as_array().unwrap();
+    assert_eq!(references.len(), 3);
+
+    // `x` in the body resolves to the parameter.
+    assert_eq!(
+        response(&replies, 3)["range"]["start"],
+        json!({ "line": 3, "character": 7 })
+    );
+}
+
+#[test]
+fn formatting_skips_commented_declarations() {
+    let replies = session(vec![
+        notification(
+            "textDocument/didChange",
+            json!({
+                "textDocument": { "uri": URI, "version": 2 },
+                "contentChanges": [{ "text": "// keep\nconst  a=1 ;\n\nconst  b = // two\n  2;\n" }],
+            }),
+        ),
+        request(
+            1,
+            "textDocument/formatting",
+            json!({
+                "textDocument": { "uri": URI },
+                "options": { "tabSize": 2, "insertSpaces": true },
+            }),
+        ),
+    ]);
+
+    let edits = response(&replies, 1).as_array().unwrap();
+    assert_eq!(edits.len(), 1);
+    assert_eq!(edits[0]["range"]["start"]["line"], json!(1));
+    assert!(edits[0]["newText"]
+        .as_str()
+        .unwrap()
+        .starts_with("const a = 1"));
+}
+
+#[test]
+fn formatting_reads_comment_markers_in_strings_as_text() {
+    let replies = session(vec![
+        change(json!([{ "text": "const  url=\"http://example.com/*\" ;\n" }])),
+        request(
+            1,
+            "textDocument/formatting",
+            json!({
+                "textDocument": { "uri": URI },
+                "options": { "tabSize": 2, "insertSpaces": true },
+            }),
+        ),
+    ]);
+
+    let edits = response(&replies, 1).as_array().unwrap();
+    assert_eq!(edits.len(), 1);
+    assert!(edits[0]["newText"]
+        .as_str()
+        .unwrap()
+        .starts_with("const url = \"http://example.com/*\""));
+}
+
+#[test]
+fn completion_fills_holes_with_fitting_candidates() {
+    let replies = session(vec![
+        notification(
+            "textDocument/didChange",
+            json!({
+                "textDocument": { "uri": URI, "version": 2 },
+                "contentChanges": [{
+                    "text": "const greeting = \"hi\";\nfn inc(x: number): number -> x + 1;\nconst n: number = @;\n",
+                }],
+            }),
+        ),
+        request(1, "textDocument/completion", at(2, 19)),
+    ]);
+
+    let items = response(&replies, 1).as_array().unwrap();
+    let labels: Vec<&str> = items.iter().map(|i| i["label"].as_str().unwrap()).collect();
+
+    assert!(labels.contains(&"inc(…)"), "{labels:?}");
+    assert!(labels.contains(&"0"), "{labels:?}");
+    assert!(!labels.contains(&"greeting"), "{labels:?}");
+
+    let call = items
+        .iter()
+        .find(|i| i["label"] == json!("inc(…)"))
+        .unwrap();
+    assert_eq!(call["textEdit"]["newText"], json!("inc(${1:@})"));
+    assert_eq!(
+        call["textEdit"]["range"]["start"],
+        json!({ "line": 2, "character": 18 })
+    );
+}
+
+#[test]
+fn rename_follows_imports_across_documents() {
+    let open = |uri: &str, text: &str| {
+        notification(
+            "textDocument/didOpen",
+            json!({
+                "textDocument": {
+                    "uri": uri,
+                    "languageId": "serendipity",
+                    "version": 1,
+                    "text": text,
+                },
+            }),
+        )
+    };
+    let rename_at = |id: u64, new_name: &str| {
+        request(
+            id,
+            "textDocument/rename",
+            json!({
+                "textDocument": { "uri": "file:///main.sdp" },
+                "position": { "line": 2, "character": 12 },
+                "newName": new_name,
+            }),
+        )
+    };
+
+    let (replies, _) = run(&[
+        request(0, "initialize", json!({ "capabilities": {} })),
+        open(
+            "file:///lib.sdp",
+            "fn twice(x) -> x * 2;\n\nexport { twice };\n",
+        ),
+        open(
+            "file:///main.sdp",
+            "import { twice } = use(\"./lib.sdp\");\n\nmain print(twice(2));\n",
+        ),
+        rename_at(1, "double"),
+        rename_at(2, "print"),
+        request(99, "shutdown", Value::Null),
+        notification("exit", Value::Null),
+    ]);
+
+    let changes = &response(&replies, 1)["changes"];
+    for uri in ["file:///lib.sdp", "file:///main.sdp"] {
+        let edits = changes[uri].as_array().unwrap();
+        assert_eq!(edits.len(), 2, "{uri}: {edits:?}");
+        assert!(edits.iter().all(|e| e["newText"] == json!("double")));
+    }
+
+    // Renaming to `print` would capture the call to the prelude's `print`.
+    let refused = replies.iter().find(|r| r["id"] == json!(2)).unwrap();
+    assert_eq!(refused["error"]["code"], json!(-32803));
+}
+
+#[test]
+fn requests_before_initialize_fail() {
+    let (replies, exit_code) = run(&[
+        request(1, "textDocument/hover", at(0, 0)),
+        request(2, "shutdown", Value::Null),
+        notification("exit", Value::Null),
+    ]);
+
+    assert_eq!(replies[0]["error"]["code"], json!(-32002));
+    assert_eq!(exit_code, 1);
+}
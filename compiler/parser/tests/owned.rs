@@ -0,0 +1,158 @@
+use std::{sync::Arc, thread};
+
+use serendipity_parser::{
+    incremental::{parse_module, TextEdit},
+    node::NodeRef,
+    owned::OwnedModule,
+    structural::structural_eq,
+    Declaration,
+};
+
+const SOURCE: &str = "const a = 1 + 2;
+fn f(x) -> x * a;
+main print(f(a));
+";
+
+/// The names of the `const` and `fn` declarations of `module`, checking that each borrows from
+/// the module's own source.
+fn names(module: &OwnedModule) -> Vec<String> {
+    let source = module.source().as_bytes().as_ptr_range();
+
+    module
+        .module()
+        .unwrap()
+        .value
+        .declarations
+        .iter()
+        .filter_map(|declaration| match &declaration.value {
+            Declaration::Const { identifier, .. } | Declaration::Function { identifier, .. } => {
+                Some(identifier.value)
+            }
+            _ => None,
+        })
+        .map(|name| {
+            assert!(
+                source.contains(&name.as_ptr()),
+                "'{name}' does not point into the module's source"
+            );
+            name.to_string()
+        })
+        .collect()
+}
+
+fn assert_same_as_parse(module: &OwnedModule) {
+    let expected = parse_module(module.source());
+
+    assert!(structural_eq(
+        NodeRef::Module(module.module().unwrap()),
+        NodeRef::Module(expected.module.as_ref().unwrap()),
+    ));
+
+    let messages = |diagnostics: &[seglisp::Diagnostic]| {
+        diagnostics
+            .iter()
+            .map(|d| d.message.clone())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        messages(module.diagnostics()),
+        messages(&expected.diagnostics)
+    );
+}
+
+#[test]
+fn modules_can_be_moved_to_other_threads() {
+    let module = OwnedModule::parse(SOURCE);
+
+    let found = thread::spawn(move || {
+        assert_same_as_parse(&module);
+        names(&module)
+    })
+    .join()
+    .unwrap();
+
+    assert_eq!(found, ["a", "f"]);
+}
+
+#[test]
+fn modules_can_be_shared_between_threads() {
+    let module = Arc::new(OwnedModule::parse(SOURCE));
+
+    let threads: Vec<_> = (0..4)
+        .map(|_| {
+            let module = module.clone();
+            thread::spawn(move || names(&module))
+        })
+        .collect();
+
+    for thread in threads {
+        assert_eq!(thread.join().unwrap(), ["a", "f"]);
+    }
+}
+
+#[test]
+fn modules_outlive_the_text_they_were_parsed_from() {
+    let source = SOURCE.to_string();
+    let module = OwnedModule::parse(source.as_str());
+    drop(source);
+
+    assert_eq!(names(&module), ["a", "f"]);
+    assert_same_as_parse(&module);
+}
+
+#[test]
+fn static_modules_outlive_the_text_they_were_moved_from() {
+    let source = SOURCE.to_string();
+    let module = parse_module(&source).into_static(&source);
+    drop(source);
+
+    assert_eq!(module.source(), SOURCE);
+    assert_eq!(names(&module), ["a", "f"]);
+    assert_same_as_parse(&module);
+}
+
+#[test]
+fn copies_of_static_modules_outlive_the_original() {
+    let source = SOURCE.to_string();
+    let parsed = parse_module(&source);
+    let module = parsed.to_static(&source);
+    drop(parsed);
+    drop(source);
+
+    assert_eq!(names(&module), ["a", "f"]);
+}
+
+#[test]
+fn clones_outlive_the_original() {
+    let module = OwnedModule::parse(SOURCE);
+    let clone = module.clone();
+    drop(module);
+
+    assert_eq!(names(&clone), ["a", "f"]);
+    assert_same_as_parse(&clone);
+}
+
+#[test]
+fn edited_modules_borrow_only_from_the_edited_source() {
+    let mut module = OwnedModule::parse(SOURCE);
+    let previous = module.shared_source();
+    let start = SOURCE.find("f(x)").unwrap();
+
+    module
+        .apply_edits(&[TextEdit {
+            start,
+            end: start + 1,
+            text: "g".into(),
+        }])
+        .unwrap();
+
+    // The previous source lives on in `previous`, but the tree must not point into it.
+    assert_eq!(&*previous, SOURCE);
+    drop(previous);
+
+    assert_eq!(names(&module), ["a", "g"]);
+    assert_same_as_parse(&module);
+
+    let moved = thread::spawn(move || module).join().unwrap();
+    assert_eq!(names(&moved), ["a", "g"]);
+}
@@ -0,0 +1,238 @@
+use serendipity_parser::{
+    incremental::parse_module,
+    node::NodeRef,
+    patch::{apply, diff_sources, Anchor, Key, Operation, Patch, Step},
+    structural::structural_eq,
+};
+
+const BASE: &str = "// The numbers.
+const a = 1;
+main #[
+  let x = a;
+  print(x);
+];
+const c = 3;
+";
+
+fn assert_same_tree(actual: &str, expected: &str) {
+    let (actual_parse, expected_parse) = (parse_module(actual), parse_module(expected));
+
+    assert!(
+        structural_eq(
+            NodeRef::Module(actual_parse.module.as_ref().unwrap()),
+            NodeRef::Module(expected_parse.module.as_ref().unwrap()),
+        ),
+        "{actual:?} is not {expected:?}"
+    );
+}
+
+/// Diffs `old` against `new`, sends the patch through its text form, and applies it to `old`.
+fn round_trip(old: &str, new: &str) -> String {
+    let patch = diff_sources(old, new).unwrap();
+
+    let text = patch.to_string();
+    assert_eq!(text.parse::<Patch>(), Ok(patch.clone()), "{text}");
+
+    let patched = apply(old, &patch).unwrap();
+    assert_same_tree(&patched, new);
+    patched
+}
+
+#[test]
+fn identical_modules_need_no_patch() {
+    let patch = diff_sources(BASE, BASE).unwrap();
+
+    assert!(patch.is_empty());
+    assert_eq!(patch.to_string(), "sdp-patch 1\n");
+}
+
+#[test]
+fn changed_declarations_are_updated_in_place() {
+    let new = BASE.replace("const a = 1;", "const a = 1 + 1;");
+
+    assert_eq!(round_trip(BASE, &new), new);
+}
+
+#[test]
+fn inserted_and_deleted_declarations_round_trip() {
+    let inserted = BASE.replace("const c = 3;\n", "const c = 3;\nconst d = c * 2;\n");
+    assert_eq!(round_trip(BASE, &inserted), inserted);
+
+    let deleted = BASE.replace("const c = 3;\n", "");
+    assert_eq!(round_trip(BASE, &deleted), deleted);
+}
+
+#[test]
+fn statements_round_trip() {
+    let changed = BASE.replace("  print(x);\n", "  print(x + 1);\n  print(x);\n");
+    let patched = round_trip(BASE, &changed);
+    assert!(patched.starts_with("// The numbers.\n"));
+
+    let deleted = BASE
+        .replace("  let x = a;\n", "")
+        .replace("print(x)", "print(a)");
+    round_trip(BASE, &deleted);
+}
+
+#[test]
+fn moved_declarations_round_trip() {
+    let new = "// The numbers.
+const c = 3;
+const a = 1;
+main #[
+  let x = a;
+  print(x);
+];
+";
+
+    let patch = diff_sources(BASE, new).unwrap();
+    assert!(patch
+        .operations
+        .iter()
+        .any(|operation| matches!(operation, Operation::Move { .. })));
+
+    round_trip(BASE, new);
+}
+
+#[test]
+fn unrelated_patches_apply_in_either_order() {
+    let first = diff_sources(BASE, &BASE.replace("const a = 1;", "const a = 2;")).unwrap();
+    let second = diff_sources(
+        BASE,
+        &BASE
+            .replace("  print(x);\n", "  print(x);\n  print(c);\n")
+            .replace("const c = 3;\n", "const c = 3;\nconst d = 4;\n"),
+    )
+    .unwrap();
+
+    let expected = BASE
+        .replace("const a = 1;", "const a = 2;")
+        .replace("  print(x);\n", "  print(x);\n  print(c);\n")
+        .replace("const c = 3;\n", "const c = 3;\nconst d = 4;\n");
+
+    let one_way = apply(&apply(BASE, &first).unwrap(), &second).unwrap();
+    let other_way = apply(&apply(BASE, &second).unwrap(), &first).unwrap();
+
+    assert_same_tree(&one_way, &expected);
+    assert_same_tree(&other_way, &expected);
+}
+
+#[test]
+fn patches_apply_after_declarations_move_around_them() {
+    let patch = diff_sources(BASE, &BASE.replace("const c = 3;", "const c = 30;")).unwrap();
+    let shifted = BASE.replace("// The numbers.\n", "// The numbers.\nconst z = 0;\n");
+
+    let patched = apply(&shifted, &patch).unwrap();
+    assert_same_tree(&patched, &shifted.replace("const c = 3;", "const c = 30;"));
+}
+
+#[test]
+fn updates_to_changed_nodes_are_refused() {
+    let patch = diff_sources(BASE, &BASE.replace("const a = 1;", "const a = 2;")).unwrap();
+    let edited = BASE.replace("const a = 1;", "const a = 3;");
+
+    let error = apply(&edited, &patch).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "operation 1: 'declarations[0].value' has changed since the patch was made"
+    );
+}
+
+#[test]
+fn deletions_of_changed_nodes_are_refused() {
+    let patch = diff_sources(BASE, &BASE.replace("const c = 3;\n", "")).unwrap();
+    let edited = BASE.replace("const c = 3;", "const c = 4;");
+
+    let error = apply(&edited, &patch).unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("has changed since the patch was made"));
+}
+
+#[test]
+fn patches_are_written_one_operation_per_line() {
+    let key = |index, hash, name: Option<&str>| Key {
+        index,
+        name: name.map(String::from),
+        hash,
+    };
+    let patch = Patch {
+        operations: vec![
+            Operation::Update {
+                target: Anchor(vec![
+                    Step::Element(
+                        "declarations",
+                        key(1, 0x6c0f_3a28_e4d1_b9a7, Some("double")),
+                    ),
+                    Step::Field("body"),
+                ]),
+                hash: 0x94e1_b2c3_d4a5_f607,
+                text: "x * 2".into(),
+            },
+            Operation::Insert {
+                parent: Anchor(vec![
+                    Step::Element("declarations", key(0, 0x0b3c_2d1e_4f5a_6978, None)),
+                    Step::Field("body"),
+                ]),
+                field: "body",
+                after: Some(key(0, 0x1d, None)),
+                text: "print(\"a\\b\")\n\tprint(1)".into(),
+            },
+            Operation::Delete {
+                target: Anchor(vec![Step::Element(
+                    "declarations",
+                    key(2, 0x8a7b_6c5d_4e3f_2a1b, Some("unused")),
+                )]),
+            },
+            Operation::Move {
+                target: Anchor(vec![Step::Element(
+                    "declarations",
+                    key(3, 0x3e4f_5a6b_7c8d_9e0f, Some("helper")),
+                )]),
+                after: None,
+            },
+        ],
+    };
+
+    let text = "sdp-patch 1
+update $.declarations[1#6c0f3a28e4d1b9a7@double].body 94e1b2c3d4a5f607 \"x * 2\"
+insert $.declarations[0#0b3c2d1e4f5a6978].body body [0#000000000000001d] \"print(\\\"a\\\\b\\\")\\n\\tprint(1)\"
+delete $.declarations[2#8a7b6c5d4e3f2a1b@unused]
+move $.declarations[3#3e4f5a6b7c8d9e0f@helper] -
+";
+
+    assert_eq!(patch.to_string(), text);
+    assert_eq!(text.parse::<Patch>(), Ok(patch.clone()));
+    assert_eq!(
+        text.replace('\n', "\r\n").parse::<Patch>(),
+        Ok(patch),
+        "line endings do not matter"
+    );
+}
+
+#[test]
+fn malformed_patches_are_rejected() {
+    let error = |text: &str| text.parse::<Patch>().unwrap_err().to_string();
+
+    assert_eq!(error(""), "not a patch");
+    assert_eq!(
+        error("sdp-patch 2\n"),
+        "patch has format version 2, expected 1"
+    );
+    assert_eq!(
+        error("sdp-patch 1\nrename $ x\n"),
+        "invalid operation 'rename $ x'"
+    );
+    assert_eq!(
+        error("sdp-patch 1\ndelete $.declarations[x#00]\n"),
+        "invalid element 'x#00'"
+    );
+    assert_eq!(
+        error("sdp-patch 1\ndelete $.nonsense\n"),
+        "unknown field 'nonsense'"
+    );
+    assert_eq!(
+        error("sdp-patch 1\nupdate $ 00 \"unterminated\n"),
+        "invalid operation 'update $ 00 \"unterminated'"
+    );
+}
@@ -0,0 +1,112 @@
+use serendipity_parser::{
+    incremental::parse_module,
+    node::NodePath,
+    query::{
+        node_at_offset, nodes_at_offset, offset_of_char_position, offset_of_position, range_of_path,
+    },
+};
+
+const SOURCE: &str = "main f(xy, 2);\n";
+
+/// The path of the deepest node at `offset` in `SOURCE`.
+fn path_at(offset: usize) -> String {
+    let module = parse_module(SOURCE).module.unwrap();
+    node_at_offset(&module, offset).0.to_string()
+}
+
+#[test]
+fn the_chain_runs_from_the_module_to_the_deepest_node() {
+    let module = parse_module(SOURCE).module.unwrap();
+
+    let paths: Vec<_> = nodes_at_offset(&module, SOURCE.find("xy").unwrap())
+        .into_iter()
+        .map(|(path, _)| path.to_string())
+        .collect();
+
+    assert_eq!(
+        paths,
+        [
+            "",
+            "declarations[0]",
+            "declarations[0].body",
+            "declarations[0].body.parameters[0]",
+        ]
+    );
+}
+
+#[test]
+fn nodes_are_found_from_their_start_to_their_end() {
+    for offset in 7..=9 {
+        assert_eq!(path_at(offset), "declarations[0].body.parameters[0]");
+    }
+}
+
+#[test]
+fn a_cursor_just_after_an_identifier_finds_it() {
+    // Between `f` and `(`.
+    assert_eq!(path_at(6), "declarations[0].body.callee");
+    // Between `2` and `)`.
+    assert_eq!(path_at(12), "declarations[0].body.parameters[1]");
+}
+
+#[test]
+fn a_cursor_between_nodes_finds_their_parent() {
+    assert_eq!(path_at(10), "declarations[0].body");
+}
+
+#[test]
+fn ranges_are_found_by_path() {
+    let module = parse_module(SOURCE).module.unwrap();
+
+    let path: NodePath = "declarations[0].body.parameters[1]".parse().unwrap();
+    let range = range_of_path(&module, &path).unwrap();
+    assert_eq!(&SOURCE[range.0.absolute..range.1.absolute], "2");
+
+    let path: NodePath = "declarations[0].body.parameters[2]".parse().unwrap();
+    assert!(range_of_path(&module, &path).is_none());
+}
+
+#[test]
+fn positions_count_lines_from_zero() {
+    let source = "ab\ncd\n";
+
+    assert_eq!(offset_of_position(source, 0, 0), Some(0));
+    assert_eq!(offset_of_position(source, 1, 1), Some(4));
+    assert_eq!(offset_of_position(source, 1, 2), Some(5));
+    assert_eq!(offset_of_position(source, 1, 3), None);
+    assert_eq!(offset_of_position(source, 2, 0), Some(6));
+    assert_eq!(offset_of_position(source, 3, 0), None);
+}
+
+#[test]
+fn positions_count_utf16_code_units() {
+    // `é` is one UTF-16 unit and two bytes, `😀` two UTF-16 units and four bytes.
+    let source = "main \"é😀\" + x;\n";
+    let x = source.find('x').unwrap();
+
+    assert_eq!(offset_of_position(source, 0, 13), Some(x));
+    assert_eq!(
+        offset_of_position(source, 0, 7),
+        Some(source.find('😀').unwrap())
+    );
+    assert_eq!(
+        offset_of_position(source, 0, 8),
+        None,
+        "inside a surrogate pair"
+    );
+    assert_eq!(offset_of_position(source, 0, 15), Some(source.len() - 1));
+    assert_eq!(offset_of_position(source, 0, 16), None);
+}
+
+#[test]
+fn char_positions_count_characters() {
+    let source = "main \"é😀\" + x;\n";
+    let x = source.find('x').unwrap();
+
+    assert_eq!(offset_of_char_position(source, 0, 12), Some(x));
+    assert_eq!(
+        offset_of_char_position(source, 0, 14),
+        Some(source.len() - 1)
+    );
+    assert_eq!(offset_of_char_position(source, 0, 15), None);
+}
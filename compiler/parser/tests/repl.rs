@@ -0,0 +1,121 @@
+use std::{cell::RefCell, rc::Rc};
+
+use serendipity_parser::{
+    eval::Host,
+    repl::{is_incomplete, Repl, Reply},
+};
+
+/// Records what the session prints.
+#[derive(Clone, Default)]
+struct Output(Rc<RefCell<Vec<String>>>);
+
+impl Host for Output {
+    fn print(&mut self, text: &str) {
+        self.0.borrow_mut().push(text.to_string());
+    }
+
+    fn read_line(&mut self, _: Option<&str>) -> String {
+        String::new()
+    }
+}
+
+fn session() -> (Repl<Output>, Output) {
+    let output = Output::default();
+    (Repl::new(output.clone()), output)
+}
+
+fn output(text: &str) -> Reply {
+    Reply::Output(text.into())
+}
+
+#[test]
+fn unclosed_delimiters_are_incomplete() {
+    assert!(is_incomplete("fn f(x) -> ("));
+    assert!(is_incomplete("main #[\n  let a = [1,"));
+    assert!(is_incomplete("const s = \"two\nlines"));
+    assert!(is_incomplete("/* a comment"));
+
+    assert!(!is_incomplete("f(1)"));
+    assert!(!is_incomplete("const s = \"(\""));
+    assert!(!is_incomplete("const a = 1 // (\n"));
+    assert!(!is_incomplete("/* ( */ 1"));
+    // A stray closing delimiter is left for the reader to report.
+    assert!(!is_incomplete("f(1))"));
+}
+
+#[test]
+fn input_continues_over_lines_until_it_is_complete() {
+    let (mut repl, _) = session();
+
+    assert_eq!(repl.prompt(), "sdp> ");
+    assert_eq!(repl.feed("fn add(x, y) -> ("), Reply::Incomplete);
+    assert_eq!(repl.prompt(), "...> ");
+    assert_eq!(repl.feed("  x + y"), Reply::Incomplete);
+    assert_eq!(repl.feed(");"), output(""));
+    assert_eq!(repl.prompt(), "sdp> ");
+
+    assert_eq!(repl.feed("add(1, 2)"), output("3"));
+}
+
+#[test]
+fn let_bindings_persist_across_inputs() {
+    let (mut repl, _) = session();
+
+    assert_eq!(repl.feed("let x = 40;"), output(""));
+    assert_eq!(repl.feed("x + 2"), output("42"));
+    assert_eq!(repl.feed(":type x"), output("number"));
+}
+
+#[test]
+fn declarations_persist_across_inputs() {
+    let (mut repl, _) = session();
+
+    assert_eq!(repl.feed("const greeting = \"hi\";"), output(""));
+    assert_eq!(repl.feed("fn twice(s) -> s + s;"), output(""));
+    assert_eq!(repl.feed("twice(greeting)"), output("\"hihi\""));
+}
+
+#[test]
+fn type_shows_inferred_types() {
+    let (mut repl, _) = session();
+
+    assert_eq!(repl.feed(":type 1 + 2"), output("number"));
+    assert_eq!(repl.feed(":type (1, \"a\")"), output("(number, string)"));
+    assert_eq!(
+        repl.feed(":type fn (x: string) -> x"),
+        output("fn(string) -> string")
+    );
+    assert_eq!(
+        repl.feed(":type print"),
+        output("fn(_) -> none"),
+        "the prelude is typed"
+    );
+}
+
+#[test]
+fn procedures_run_against_the_host() {
+    let (mut repl, printed) = session();
+
+    assert_eq!(repl.feed("print(\"hello\")"), output(""));
+    assert_eq!(repl.feed("main #[ print(1); print(2); ];"), output(""));
+
+    assert_eq!(*printed.0.borrow(), ["hello", "1", "2"]);
+}
+
+#[test]
+fn errors_are_reported_and_the_session_continues() {
+    let (mut repl, _) = session();
+
+    let Reply::Output(message) = repl.feed("undefined_name") else {
+        panic!("expected output");
+    };
+    assert!(message.starts_with("error: "), "{message}");
+
+    let Reply::Output(message) = repl.feed(":frobnicate") else {
+        panic!("expected output");
+    };
+    assert!(message.contains("unknown command"), "{message}");
+
+    assert_eq!(repl.feed("1"), output("1"));
+    assert_eq!(repl.feed(":quit"), Reply::Quit);
+}
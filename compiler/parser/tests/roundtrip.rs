@@ -0,0 +1,428 @@
+//! Property tests for the parser and printer.
+//!
+//! Random well-formed modules are generated in the S-expression form read by `sexpr`, which can
+//! build any tree, including the ones the parser would never produce from a hand-written program.
+//! Each is printed as source and parsed again, and must come back as the same tree. Source is
+//! also mutated at random to check that malformed input never panics the parser.
+//!
+//! The generator only builds trees the surface syntax can express: there is no `as` expression or
+//! `else` branch on an `if` statement yet, tuples never have one element, and statements nested
+//! in `if`, `for`, and `loop` are ones that cannot be mistaken for a continuation of the
+//! expression before them.
+
+use seglisp::DiagnosticSeverity;
+use serendipity_parser::{
+    incremental::parse_module, node::NodeRef, printer, sexpr::read_module,
+    structural::structural_eq, with_checked_module,
+};
+
+/// The number of modules generated, and of mutations made to each source.
+const CASES: u64 = 256;
+
+const NAMES: &[&str] = &["a", "b", "x", "y", "item", "total"];
+const TYPE_NAMES: &[&str] = &["T", "U", "number", "string", "Box"];
+const NUMBERS: &[&str] = &["0", "7", "42"];
+const STRINGS: &[&str] = &["\"\"", "\"hello\"", "\"two words\""];
+const COMPARE_OPS: &[&str] = &["==", "!=", "<=", ">=", "<", ">"];
+const ARITHMETIC_OPS: &[&str] = &["+", "-", "*", "/", "%"];
+
+/// Writes random trees as S-expressions. A small xorshift generator keeps every case
+/// reproducible from its seed.
+struct Gen {
+    state: u64,
+}
+
+impl Gen {
+    fn new(seed: u64) -> Self {
+        Gen {
+            state: seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1,
+        }
+    }
+
+    fn next(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn chance(&mut self, percent: usize) -> bool {
+        self.below(100) < percent
+    }
+
+    fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+        items[self.below(items.len())]
+    }
+
+    /// Between `min` and `max` items made by `item`.
+    fn many(
+        &mut self,
+        min: usize,
+        max: usize,
+        mut item: impl FnMut(&mut Self) -> String,
+    ) -> Vec<String> {
+        let count = min + self.below(max - min + 1);
+        (0..count).map(|_| item(self)).collect()
+    }
+
+    /// `(head items...)`, with between `min` and `max` items made by `item`.
+    fn form(
+        &mut self,
+        head: &str,
+        min: usize,
+        max: usize,
+        item: impl FnMut(&mut Self) -> String,
+    ) -> String {
+        let mut items = vec![head.to_string()];
+        items.extend(self.many(min, max, item));
+        list(items)
+    }
+
+    fn module(&mut self) -> String {
+        self.form("module", 1, 5, |g| g.declaration())
+    }
+
+    fn declaration(&mut self) -> String {
+        let name = self.pick(NAMES).to_string();
+        let type_name = self.pick(TYPE_NAMES).to_string();
+
+        match self.below(7) {
+            0 => format!("(main {})", self.expression(3)),
+            1 => {
+                let mut items = vec!["const".into(), name];
+                items.extend(self.maybe_constraint());
+                items.push(self.expression(3));
+                list(items)
+            }
+            2 => self.function(Some(&name)),
+            3 => format!("(import {} \"./{name}\")", self.pattern(2)),
+            4 => self.form("export", 0, 3, |g| g.record_element(1)),
+            5 => {
+                let mut items = vec!["type".into(), type_name];
+                items.extend(self.maybe_generics());
+                items.push(self.type_(2));
+                list(items)
+            }
+            _ => {
+                let mut items = vec!["interface".into(), type_name];
+                items.extend(self.maybe_generics());
+                items.extend(self.maybe_constraint());
+                items.extend(self.many(0, 3, |g| {
+                    format!("(field {} {})", g.pick(NAMES), g.constraint())
+                }));
+                list(items)
+            }
+        }
+    }
+
+    fn function(&mut self, name: Option<&str>) -> String {
+        let mut items = vec!["fn".to_string()];
+        items.extend(name.map(str::to_string));
+        items.extend(self.maybe_generics());
+
+        let parameters = self.many(0, 3, |g| {
+            let mut param = vec!["param".into(), g.pick(NAMES).into()];
+            param.extend(g.maybe_constraint());
+            list(param)
+        });
+        items.push(list(parameters));
+
+        items.extend(self.maybe_constraint());
+        items.push(self.expression(2));
+        list(items)
+    }
+
+    fn maybe_generics(&mut self) -> Option<String> {
+        self.chance(30).then(|| {
+            self.form("generics", 1, 2, |g| {
+                let mut generic = vec!["generic".into(), g.pick(TYPE_NAMES).into()];
+                generic.extend(g.maybe_constraint());
+                list(generic)
+            })
+        })
+    }
+
+    fn maybe_constraint(&mut self) -> Option<String> {
+        self.chance(50).then(|| self.constraint())
+    }
+
+    fn constraint(&mut self) -> String {
+        format!("(: {})", self.type_(2))
+    }
+
+    fn type_(&mut self, depth: usize) -> String {
+        if depth == 0 || self.chance(40) {
+            return match self.below(6) {
+                0 => "*".into(),
+                1 => "!".into(),
+                2 => "_".into(),
+                _ => self.pick(TYPE_NAMES).into(),
+            };
+        }
+
+        match self.below(4) {
+            0 => {
+                let name = self.pick(TYPE_NAMES);
+                self.form(name, 1, 2, |g| g.type_(depth - 1))
+            }
+            1 => format!("(| {} {})", self.type_(depth - 1), self.type_(depth - 1)),
+            2 => self.form("tuple", 2, 3, |g| g.type_(depth - 1)),
+            _ => {
+                let parameters = list(self.many(0, 2, |g| g.type_(depth - 1)));
+                format!("(fn {parameters} {})", self.type_(depth - 1))
+            }
+        }
+    }
+
+    fn pattern(&mut self, depth: usize) -> String {
+        if depth == 0 || self.chance(40) {
+            return self.pick(NAMES).into();
+        }
+
+        if self.chance(50) {
+            self.form("tuple", 2, 3, |g| g.pattern(depth - 1))
+        } else {
+            self.form("record", 0, 3, |g| match g.below(3) {
+                0 => g.pick(NAMES).into(),
+                1 => format!("(: {} {})", g.pick(NAMES), g.pattern(depth - 1)),
+                _ => format!("(... {})", g.pick(NAMES)),
+            })
+        }
+    }
+
+    fn atom(&mut self) -> String {
+        match self.below(7) {
+            0 => self.pick(NUMBERS).into(),
+            1 => self.pick(STRINGS).into(),
+            2 => self.pick(&["true", "false"]).into(),
+            3 => "@".into(),
+            4 => "none".into(),
+            _ => self.pick(NAMES).into(),
+        }
+    }
+
+    fn expression(&mut self, depth: usize) -> String {
+        if depth == 0 || self.chance(30) {
+            return self.atom();
+        }
+        let d = depth - 1;
+
+        match self.below(16) {
+            0 => {
+                let operator = self.pick(&["!", "-"]);
+                format!("({operator} {})", self.operand(d))
+            }
+            1 => format!(
+                "({} {} {})",
+                self.pick(COMPARE_OPS),
+                self.expression(d),
+                self.expression(d)
+            ),
+            2 => format!(
+                "({} {} {})",
+                self.pick(ARITHMETIC_OPS),
+                self.expression(d),
+                self.expression(d)
+            ),
+            3 => format!("(index {} {})", self.expression(d), self.expression(d)),
+            4 => {
+                let name = self.chance(30).then(|| self.pick(NAMES));
+                self.function(name)
+            }
+            5 => {
+                let callee = self.expression(d);
+                self.form(&format!("call {callee}"), 0, 3, |g| g.expression(d))
+            }
+            6 => {
+                let bindings = list(self.many(0, 2, |g| {
+                    format!("(= {} {})", g.pick(NAMES), g.expression(d))
+                }));
+                format!("(with {bindings} {})", self.expression(d))
+            }
+            7 if self.chance(20) => "(tuple)".into(),
+            7 => self.form("tuple", 2, 3, |g| g.expression(d)),
+            8 => self.form("list", 0, 3, |g| g.expression(d)),
+            9 | 10 => self.procedure(d),
+            11 => format!(
+                "(if {} {} {})",
+                self.expression(d),
+                self.expression(d),
+                self.expression(d)
+            ),
+            12 => self.form("record", 0, 3, |g| g.record_element(d)),
+            _ => format!("(. {} {})", self.operand(d), self.pick(NAMES)),
+        }
+    }
+
+    /// An expression that can be written right after a sigil. `1.x` would read as a number, and
+    /// `@` could run into the sigil before it, so neither is used.
+    fn operand(&mut self, depth: usize) -> String {
+        let expr = self.expression(depth);
+        if expr == "@" || expr.starts_with(|c: char| c.is_ascii_digit()) {
+            self.pick(NAMES).into()
+        } else {
+            expr
+        }
+    }
+
+    fn record_element(&mut self, depth: usize) -> String {
+        match self.below(3) {
+            0 => self.pick(NAMES).into(),
+            1 => format!("(: {} {})", self.pick(NAMES), self.expression(depth)),
+            _ => format!("(... {})", self.pick(NAMES)),
+        }
+    }
+
+    fn procedure(&mut self, depth: usize) -> String {
+        self.form("proc", 0, 3, |g| g.statement(depth, false))
+    }
+
+    /// A statement. A `nested` statement follows an expression in an `if`, `for`, or `loop`, so
+    /// it must not start with anything that would continue that expression, like `(` or `-`.
+    fn statement(&mut self, depth: usize, nested: bool) -> String {
+        if depth == 0 {
+            return self.pick(&["break", "continue", "pass"]).into();
+        }
+        let d = depth - 1;
+
+        match self.below(10) {
+            0 => format!("(let {} {})", self.pick(NAMES), self.expression(d)),
+            1 => format!("(set {} {})", self.pick(NAMES), self.expression(d)),
+            2 => format!("(if {} {})", self.expression(d), self.statement(d, true)),
+            3 => format!(
+                "(for {} {} {})",
+                self.pick(NAMES),
+                self.expression(d),
+                self.statement(d, true)
+            ),
+            4 => format!("(loop {})", self.statement(d, true)),
+            5 => format!("(do {})", self.expression(d)),
+            6 => self.pick(&["break", "continue", "pass"]).into(),
+            _ if nested => self.procedure(d),
+            _ => loop {
+                // An `if` expression would read back as an `if` statement.
+                let expr = self.expression(d);
+                if !expr.starts_with("(if ") {
+                    break expr;
+                }
+            },
+        }
+    }
+}
+
+fn list(items: Vec<String>) -> String {
+    format!("({})", items.join(" "))
+}
+
+#[test]
+fn printed_modules_parse_back_to_the_same_tree() {
+    for seed in 0..CASES {
+        let text = Gen::new(seed).module();
+        let generated = read_module(&text).unwrap_or_else(|e| panic!("seed {seed}: {e}\n{text}"));
+
+        let source = printer::print_module(&generated.value);
+        let parsed = parse_module(&source);
+        let errors: Vec<_> = parsed
+            .diagnostics
+            .iter()
+            .filter(|d| matches!(d.severity, DiagnosticSeverity::Error))
+            .map(|d| d.message.clone())
+            .collect();
+
+        let Some(module) = parsed.module.as_ref().filter(|_| errors.is_empty()) else {
+            panic!(
+                "seed {seed}: the printed module does not parse: {errors:?}\n{text}\n\n{source}"
+            );
+        };
+        assert!(
+            structural_eq(NodeRef::Module(&generated), NodeRef::Module(module)),
+            "seed {seed}: the printed module parses to a different tree\n{text}\n\n{source}"
+        );
+    }
+}
+
+const SAMPLES: &[&str] = &[
+    include_str!("../../../cli/slipr/examples/hello_world.sdp"),
+    include_str!("../../../cli/slipr/examples/2param.sdp"),
+    include_str!("../../../cli/slipr/examples/break.sdp"),
+    include_str!("../../../cli/slipr/examples/echo.sdp"),
+    include_str!("../../../cli/slipr/examples/if.sdp"),
+    include_str!("../../../cli/slipr/examples/iter.sdp"),
+    include_str!("../../../cli/slipr/examples/recur.sdp"),
+    include_str!("../../../cli/slipr/examples/utils/iteration.sdp"),
+    include_str!("../../../lib/core/lib.sdp"),
+];
+
+/// Mutated sources are cut back to this many bytes, since duplication can double them.
+const MAX_LEN: usize = 4 * 1024;
+
+/// Fragments that are likely to leave source almost, but not quite, well-formed.
+const FRAGMENTS: &[&str] = &[
+    "(", ")", "[", "]", "{", "}", "#[", ",", ";", ":", "=", "->", ".", "...", "@", "\"", "fn",
+    "if", "then", "else", "let", "with", "export", "import", "use", "main", "-", "!", "==", "1",
+    "x", " ", "\n",
+];
+
+/// Changes `source` in one random place, keeping it valid UTF-8.
+fn mutate(g: &mut Gen, source: &str) -> String {
+    let boundary = |g: &mut Gen| {
+        let mut at = g.below(source.len() + 1);
+        while !source.is_char_boundary(at) {
+            at -= 1;
+        }
+        at
+    };
+    let (a, b) = (boundary(g), boundary(g));
+    let (start, end) = (a.min(b), a.max(b));
+
+    match g.below(3) {
+        0 => format!("{}{}", &source[..start], &source[end..]),
+        1 => format!("{}{}", &source[..end], &source[start..]),
+        _ => format!(
+            "{}{}{}",
+            &source[..start],
+            g.pick(FRAGMENTS),
+            &source[start..]
+        ),
+    }
+}
+
+#[test]
+fn mutated_source_never_panics() {
+    let generated = (0..8).map(|seed| {
+        let text = Gen::new(seed).module();
+        printer::print_module(&read_module(&text).unwrap().value)
+    });
+
+    for (index, sample) in SAMPLES
+        .iter()
+        .map(|s| s.to_string())
+        .chain(generated)
+        .enumerate()
+    {
+        let mut g = Gen::new(index as u64);
+        let mut source = sample;
+
+        // Mutations accumulate, so later cases drift further from well-formed source.
+        for _ in 0..CASES {
+            source = mutate(&mut g, &source);
+            if source.len() > MAX_LEN {
+                let mut end = MAX_LEN;
+                while !source.is_char_boundary(end) {
+                    end -= 1;
+                }
+                source.truncate(end);
+            }
+
+            let result = std::panic::catch_unwind(|| {
+                parse_module(&source);
+                with_checked_module(source.as_bytes(), |_| ());
+            });
+            assert!(result.is_ok(), "the parser panicked on:\n{source}");
+        }
+    }
+}
@@ -0,0 +1,119 @@
+#![cfg(feature = "serde")]
+
+use serde_json::Value;
+use serendipity_parser::json;
+
+const SAMPLES: &[(&str, &str)] = &[
+    (
+        "hello_world.sdp",
+        include_str!("../../../cli/slipr/examples/hello_world.sdp"),
+    ),
+    (
+        "2param.sdp",
+        include_str!("../../../cli/slipr/examples/2param.sdp"),
+    ),
+    (
+        "break.sdp",
+        include_str!("../../../cli/slipr/examples/break.sdp"),
+    ),
+    (
+        "echo.sdp",
+        include_str!("../../../cli/slipr/examples/echo.sdp"),
+    ),
+    ("if.sdp", include_str!("../../../cli/slipr/examples/if.sdp")),
+    (
+        "iter.sdp",
+        include_str!("../../../cli/slipr/examples/iter.sdp"),
+    ),
+    (
+        "recur.sdp",
+        include_str!("../../../cli/slipr/examples/recur.sdp"),
+    ),
+    (
+        "utils/iteration.sdp",
+        include_str!("../../../cli/slipr/examples/utils/iteration.sdp"),
+    ),
+    ("core/lib.sdp", include_str!("../../../lib/core/lib.sdp")),
+    ("types", include_str!("fixtures/types.sdp")),
+    ("unparsable", "fn (x -> "),
+    (
+        "lints",
+        "import { a } = use \"./a\";\nconst b = 1;\nmain #[ let c = b; ];\n",
+    ),
+];
+
+#[test]
+fn checked_in_schema_is_up_to_date() {
+    let checked_in: Value =
+        serde_json::from_str(include_str!("../schema/parse-output.schema.json")).unwrap();
+
+    assert!(
+        checked_in == json::schema(),
+        "schema/parse-output.schema.json is out of date; regenerate it with \
+         `cargo run --features serde --bin gen_schema > schema/parse-output.schema.json`"
+    );
+}
+
+/// The diagnostics of a sample's parse output.
+fn diagnostics(name: &str) -> Vec<Value> {
+    let (_, source) = SAMPLES.iter().find(|(n, _)| *n == name).unwrap();
+    let output: Value = serde_json::from_str(&json::to_json(source, false)).unwrap();
+    output["diagnostics"].as_array().unwrap().clone()
+}
+
+#[test]
+fn diagnostic_samples_report_diagnostics() {
+    let unparsable = diagnostics("unparsable");
+    assert!(unparsable
+        .iter()
+        .any(|d| d["severity"] == "Error" && d["phase"] == "Parse"));
+
+    let lints = diagnostics("lints");
+    assert!(lints
+        .iter()
+        .any(|d| d["phase"] == "Lint" && d["location"].get("Range").is_some()));
+}
+
+#[test]
+fn malformed_diagnostics_do_not_validate() {
+    let validator = jsonschema::validator_for(&json::schema()).unwrap();
+    let mut output: Value = serde_json::from_str(&json::to_json("const b = ();", false)).unwrap();
+    assert!(validator.is_valid(&output));
+
+    let diagnostic = output["diagnostics"][0].clone();
+    let with = |field: &str, value: Value| {
+        let mut diagnostic = diagnostic.clone();
+        diagnostic[field] = value;
+        diagnostic
+    };
+
+    for malformed in [
+        with("severity", "Loud".into()),
+        with("phase", "Lunch".into()),
+        with("location", serde_json::json!({ "Range": [] })),
+        with(
+            "location",
+            serde_json::json!({ "Range": diagnostic["location"]["Range"], "x": 1 }),
+        ),
+        with("subject", "x".into()),
+        with("extra", true.into()),
+    ] {
+        output["diagnostics"][0] = malformed.clone();
+        assert!(!validator.is_valid(&output), "{malformed} validates");
+    }
+}
+
+#[test]
+fn parse_outputs_validate() {
+    let validator = jsonschema::validator_for(&json::schema()).unwrap();
+
+    for (name, source) in SAMPLES {
+        let output: Value = serde_json::from_str(&json::to_json(source, false)).unwrap();
+        let errors: Vec<_> = validator
+            .iter_errors(&output)
+            .map(|error| format!("{} at {}", error, error.instance_path))
+            .collect();
+
+        assert!(errors.is_empty(), "{name}:\n{}", errors.join("\n"));
+    }
+}
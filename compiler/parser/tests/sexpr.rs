@@ -0,0 +1,85 @@
+use serendipity_parser::{
+    incremental::parse_module,
+    node::NodeRef,
+    sexpr::{print_expression, print_module, read_expression, read_module},
+    structural::structural_eq,
+};
+
+const SAMPLES: &[(&str, &str)] = &[
+    (
+        "hello_world.sdp",
+        include_str!("../../../cli/slipr/examples/hello_world.sdp"),
+    ),
+    (
+        "2param.sdp",
+        include_str!("../../../cli/slipr/examples/2param.sdp"),
+    ),
+    (
+        "break.sdp",
+        include_str!("../../../cli/slipr/examples/break.sdp"),
+    ),
+    ("if.sdp", include_str!("../../../cli/slipr/examples/if.sdp")),
+    (
+        "recur.sdp",
+        include_str!("../../../cli/slipr/examples/recur.sdp"),
+    ),
+    (
+        "utils/iteration.sdp",
+        include_str!("../../../cli/slipr/examples/utils/iteration.sdp"),
+    ),
+    ("core/lib.sdp", include_str!("../../../lib/core/lib.sdp")),
+    ("types", include_str!("fixtures/types.sdp")),
+];
+
+#[test]
+fn modules_round_trip() {
+    for (name, source) in SAMPLES {
+        let parsed = parse_module(source);
+        let module = parsed.module.as_ref().unwrap();
+
+        let printed = print_module(&module.value);
+        let read = read_module(&printed).unwrap_or_else(|e| panic!("{name}: {e}\n{printed}"));
+
+        assert!(
+            structural_eq(NodeRef::Module(module), NodeRef::Module(&read)),
+            "{name} changed in the round trip:\n{printed}"
+        );
+        assert_eq!(print_module(&read.value), printed, "{name}");
+    }
+}
+
+#[test]
+fn prints_the_canonical_form() {
+    let parsed = parse_module("fn f(x, y: number) -> x + y;\nmain f(1, \"a\\n\");\n");
+    let module = parsed.module.unwrap();
+
+    assert_eq!(
+        print_module(&module.value),
+        "(module\n  (fn f ((param x) (param y (: number))) (+ x y))\n  (main (call f 1 \"a\\n\")))\n"
+    );
+}
+
+#[test]
+fn reads_hand_written_expressions() {
+    let expr = read_expression(
+        "; a comment
+        (with ((= x (- 1)))
+          (if (<= x 0) (list x \"neg\") (fn ((param y)) (. y name))))",
+    )
+    .unwrap();
+
+    assert_eq!(
+        print_expression(&expr.value),
+        "(with ((= x (- 1))) (if (<= x 0) (list x \"neg\") (fn ((param y)) (. y name))))"
+    );
+    assert_eq!(expr.range.0.line, 1);
+}
+
+#[test]
+fn reports_where_reading_failed() {
+    let error = read_module("(module\n  (main (call f 1))\n  (main (frobnicate 2)))").unwrap_err();
+    assert_eq!(error.0, "3:9: unknown expression `(frobnicate ...)`");
+
+    let error = read_expression("(+ 1 2").unwrap_err();
+    assert_eq!(error.0, "1:1: unclosed `(`");
+}
@@ -0,0 +1,224 @@
+//! Long chains of operators, and brackets nested as deeply as the parser allows, must parse on a
+//! stack no larger than the one a WASM build gets. A stack overflow aborts the test binary rather
+//! than failing a single test, so parses that might overflow are run in a child process.
+
+use std::process::Command;
+
+use seglisp::{parse::ParseHost, DiagnosticLocation};
+use serendipity_parser::{
+    incremental::{parse_module, MAX_NESTING},
+    sexpr::print_expression,
+    with_checked_module, Declaration, Expression, Module,
+};
+
+/// The default stack size of a Rust program built for `wasm32-unknown-unknown`.
+const WASM_STACK: usize = 1024 * 1024;
+
+const LENGTH: usize = 1_000;
+
+/// Runs `f` on a thread with a WASM-sized stack.
+fn on_wasm_stack<R: Send + 'static>(f: impl FnOnce() -> R + Send + 'static) -> R {
+    std::thread::Builder::new()
+        .stack_size(WASM_STACK)
+        .spawn(f)
+        .unwrap()
+        .join()
+        .expect("the parser panicked")
+}
+
+/// Parses `source` on a thread with a WASM-sized stack, and checks the body of its `main`.
+fn parse_main(source: String, check: fn(&Expression) -> bool) {
+    let matched = on_wasm_stack(move || {
+        let parsed = parse_module(&source);
+        let module = parsed.module.expect("the module does not parse");
+        match &module.value.declarations[0].value {
+            Declaration::Main { body, .. } => check(&body.value),
+            _ => false,
+        }
+    });
+
+    assert!(matched, "the source parsed to the wrong tree");
+}
+
+/// `main` of `1` inside `depth` pairs of `open` and `close`.
+fn nested(open: &str, close: &str, depth: usize) -> String {
+    format!("main {}1{};", open.repeat(depth), close.repeat(depth))
+}
+
+#[test]
+fn long_arithmetic_chains() {
+    parse_main(format!("main 1{};", " + 1".repeat(LENGTH)), |body| {
+        matches!(body, Expression::Arithmetic { .. })
+    });
+    parse_main(format!("main 1{};", " * 2 - 1".repeat(LENGTH)), |body| {
+        matches!(body, Expression::Arithmetic { .. })
+    });
+}
+
+#[test]
+fn chains_associate_to_the_left() {
+    let parsed = parse_module("main 10 - 2 - 3 * 4 / 2 < 1 + 1;");
+    let module = parsed.module.unwrap();
+    let Declaration::Main { body, .. } = &module.value.declarations[0].value else {
+        panic!("not a main declaration");
+    };
+
+    assert_eq!(
+        print_expression(&body.value),
+        "(< (- (- 10 2) (/ (* 3 4) 2)) (+ 1 1))"
+    );
+}
+
+#[test]
+fn long_comparison_chains() {
+    parse_main(format!("main 1{};", " < 1".repeat(LENGTH)), |body| {
+        matches!(body, Expression::Compare { .. })
+    });
+}
+
+#[test]
+fn long_unary_chains() {
+    parse_main(format!("main {}1;", "- ".repeat(LENGTH)), |body| {
+        matches!(body, Expression::Unary { .. })
+    });
+}
+
+#[test]
+fn long_postfix_chains() {
+    parse_main(format!("main f{};", "(1)[0]".repeat(LENGTH)), |body| {
+        matches!(body, Expression::Accessor { .. })
+    });
+}
+
+#[test]
+fn nesting_up_to_the_limit() {
+    parse_main(nested("(", ")", MAX_NESTING), |body| {
+        matches!(body, Expression::Number("1"))
+    });
+    parse_main(nested("[", "]", MAX_NESTING), |body| {
+        matches!(body, Expression::List { .. })
+    });
+    parse_main(nested("#[", "]", MAX_NESTING), |body| {
+        matches!(body, Expression::Procedure { .. })
+    });
+}
+
+#[test]
+fn deeper_nesting_is_refused() {
+    for (open, close) in [("(", ")"), ("[", "]"), ("{", "}")] {
+        let source = nested(open, close, 100_000);
+        let (module, messages, range) = on_wasm_stack(move || {
+            let parsed = parse_module(&source);
+            let range = parsed.diagnostics.first().and_then(|d| match &d.location {
+                DiagnosticLocation::Range((start, end)) => {
+                    Some((start.line, start.column, end.absolute - start.absolute))
+                }
+                _ => None,
+            });
+            let messages: Vec<_> = parsed.diagnostics.into_iter().map(|d| d.message).collect();
+            (parsed.module.is_some(), messages, range)
+        });
+
+        assert!(!module, "{open}: a module was read");
+        assert_eq!(
+            messages,
+            [format!(
+                "brackets are nested more than {MAX_NESTING} levels deep"
+            )]
+        );
+        assert_eq!(range, Some((0, 5 + MAX_NESTING, 1)), "{open}");
+    }
+
+    let source = nested("((", "))", MAX_NESTING);
+    let (module, messages) = on_wasm_stack(move || {
+        with_checked_module(source.as_bytes(), |document| {
+            let messages: Vec<_> = document
+                .diagnostics
+                .into_iter()
+                .map(|d| d.message)
+                .collect();
+            (document.result.is_ok(), messages)
+        })
+    });
+    assert!(!module, "with_checked_module read a module");
+    assert_eq!(
+        messages,
+        [format!(
+            "brackets are nested more than {MAX_NESTING} levels deep"
+        )]
+    );
+}
+
+/// Whether `main` of `1` inside `depth` parentheses reads and parses, without the nesting check, on
+/// a WASM-sized stack. The parse runs in a child process, which aborts if the stack overflows.
+fn fits(depth: usize) -> bool {
+    Command::new(std::env::current_exe().unwrap())
+        .args([
+            "--exact",
+            "parse_unchecked",
+            "--ignored",
+            "--test-threads",
+            "1",
+        ])
+        .env("SDP_NESTING_DEPTH", depth.to_string())
+        .output()
+        .expect("failed to run the test binary")
+        .status
+        .success()
+}
+
+#[test]
+#[ignore = "run in a child process by `fits`"]
+fn parse_unchecked() {
+    let Ok(depth) = std::env::var("SDP_NESTING_DEPTH") else {
+        return;
+    };
+    let source = nested("(", ")", depth.parse().unwrap());
+
+    on_wasm_stack(move || {
+        let read = seglisp::read_str(&Default::default(), &source);
+        assert!(read.parse::<Module>(&ParseHost::default()).result.is_ok());
+    });
+}
+
+#[test]
+fn the_limit_leaves_room_on_the_stack() {
+    assert!(
+        fits(2 * MAX_NESTING),
+        "nesting {} levels deep overflows a WASM-sized stack; lower MAX_NESTING",
+        2 * MAX_NESTING
+    );
+}
+
+/// Prints how deeply parentheses can nest on a WASM-sized stack, to choose [`MAX_NESTING`] by:
+///
+/// ```sh
+/// cargo test --release --test stack -- --ignored --nocapture deepest_nesting
+/// ```
+#[test]
+#[ignore = "a measurement, not a check"]
+fn deepest_nesting() {
+    let (mut fitting, mut overflowing) = (1, 1 << 20);
+    while overflowing - fitting > 1 {
+        let depth = (fitting + overflowing) / 2;
+        if fits(depth) {
+            fitting = depth;
+        } else {
+            overflowing = depth;
+        }
+    }
+
+    eprintln!("parentheses nest up to {fitting} levels deep on a 1 MiB stack");
+}
+
+#[test]
+fn brackets_in_strings_and_comments_do_not_nest() {
+    let brackets = "([{".repeat(LENGTH);
+
+    parse_main(format!("main \"{brackets}\";"), |body| {
+        matches!(body, Expression::String(_))
+    });
+    parse_main(format!("// {brackets}\nmain /* {brackets} */ 1;"), |body| {
+        matches!(body, Expression::Number("1"))
+    });
+}
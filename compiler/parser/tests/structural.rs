@@ -0,0 +1,182 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashSet},
+    hash::{Hash, Hasher},
+};
+
+use serendipity_parser::{
+    incremental::{parse_module, ParsedModule},
+    node::NodeRef,
+    structural::{content_hash, diff, structural_eq, Structural},
+};
+
+fn parse(source: &str) -> ParsedModule<'_> {
+    let parsed = parse_module(source);
+    assert!(parsed.diagnostics.is_empty(), "{source:?} does not parse");
+    parsed
+}
+
+fn module<'a, 'ast>(parsed: &'a ParsedModule<'ast>) -> NodeRef<'a, 'ast> {
+    NodeRef::Module(parsed.module.as_ref().unwrap())
+}
+
+fn hash(node: NodeRef) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    Structural(node).hash(&mut hasher);
+    hasher.finish()
+}
+
+/// The value of the first declaration of `parsed`, or its body if it is `main`.
+fn first_value<'a, 'ast>(parsed: &'a ParsedModule<'ast>) -> NodeRef<'a, 'ast> {
+    module(parsed)
+        .descendant(&"declarations[0]".parse().unwrap())
+        .unwrap()
+        .children()
+        .into_iter()
+        .find(|(segment, _)| segment.field == "value" || segment.field == "body")
+        .unwrap()
+        .1
+}
+
+/// The first difference between the modules `a` and `b`, as displayed.
+fn difference(a: &str, b: &str) -> Option<String> {
+    diff(module(&parse(a)), module(&parse(b))).map(|d| d.to_string())
+}
+
+#[test]
+fn spans_whitespace_and_comments_are_ignored() {
+    let a = parse("const a = 1 + f(2, 3);\nmain print(a);\n");
+    let b = parse("// The answer.\nconst a =\n    1+f( 2,3 ) ;\n\n\nmain   print(a) ;");
+
+    assert!(structural_eq(module(&a), module(&b)));
+    assert_eq!(Structural(module(&a)), Structural(module(&b)));
+    assert_eq!(hash(module(&a)), hash(module(&b)));
+    assert_eq!(difference("const a = 1;", "  const   a=1 ;"), None);
+}
+
+#[test]
+fn declarations_hash_the_same_wherever_they_are() {
+    let a = parse("const a = [1, 2];\nfn f(x) -> x * 2;\n");
+    let b = parse("fn f(x) ->\n  x * 2;\n\nconst a = [1,2];\n");
+    let (a, b) = (
+        &a.module.as_ref().unwrap().value.declarations,
+        &b.module.as_ref().unwrap().value.declarations,
+    );
+
+    assert_eq!(content_hash(&a[0]), content_hash(&b[1]));
+    assert_eq!(content_hash(&a[1]), content_hash(&b[0]));
+    assert_ne!(content_hash(&a[0]), content_hash(&a[1]));
+}
+
+#[test]
+fn different_trees_hash_differently() {
+    let sources = [
+        "const a = 1;",
+        "const a = 2;",
+        "const b = 1;",
+        "const a = \"1\";",
+        "const a = x;",
+        "const a = true;",
+        "const a = 1 + 2;",
+        "const a = 2 + 1;",
+        "const a = 1 - 2;",
+        "const a = (1, 2);",
+        "const a = [1, 2];",
+        "const a = [1, 2, 3];",
+        "const a = [[1], 2];",
+        "const a = f(1, 2);",
+        "const a: number = 1;",
+        "fn a() -> 1;",
+        "main 1;",
+    ];
+    let parsed: Vec<_> = sources.iter().map(|source| parse(source)).collect();
+
+    let mut hashes = HashSet::new();
+    let mut content_hashes = HashSet::new();
+    let mut nodes = HashSet::new();
+    for module in &parsed {
+        let module = module.module.as_ref().unwrap();
+        hashes.insert(hash(NodeRef::Module(module)));
+        content_hashes.insert(content_hash(&module.value.declarations[0]));
+        nodes.insert(Structural(NodeRef::Module(module)));
+    }
+
+    assert_eq!(hashes.len(), sources.len());
+    assert_eq!(content_hashes.len(), sources.len());
+    assert_eq!(nodes.len(), sources.len());
+
+    for (i, a) in parsed.iter().enumerate() {
+        for (j, b) in parsed.iter().enumerate() {
+            assert_eq!(structural_eq(module(a), module(b)), i == j);
+        }
+    }
+}
+
+#[test]
+fn equal_trees_collapse_in_a_set() {
+    let parsed: Vec<_> = [
+        "const a = 1;",
+        "const a=1;",
+        "\nconst a = 1 ;\n",
+        "const a = 2;",
+    ]
+    .into_iter()
+    .map(parse)
+    .collect();
+
+    let nodes: HashSet<_> = parsed.iter().map(|p| Structural(module(p))).collect();
+    assert_eq!(nodes.len(), 2);
+}
+
+#[test]
+fn diff_reports_the_first_differing_node() {
+    assert_eq!(
+        difference(
+            "const a = 1 + 2;\nconst b = x;",
+            "const a = 1 + 3;\nconst b = y;"
+        )
+        .as_deref(),
+        Some("at declarations[0].value.right: Expression::Number `2` != Expression::Number `3`")
+    );
+    assert_eq!(
+        difference("const a = 1 + 2;", "const a = 1 - 2;").as_deref(),
+        Some(
+            "at declarations[0].value: Expression::Arithmetic `Add` != Expression::Arithmetic \
+             `Subtract`"
+        )
+    );
+    assert_eq!(
+        difference("const a = x;", "fn a() -> x;").as_deref(),
+        Some("at declarations[0]: Declaration::Const != Declaration::Function")
+    );
+}
+
+#[test]
+fn diff_reports_missing_nodes() {
+    assert_eq!(
+        difference("const a = 1;", "const a = 1;\nconst b = 2;").as_deref(),
+        Some("at declarations[1]: nothing != Declaration::Const")
+    );
+    assert_eq!(
+        difference("const a = [1, 2];", "const a = [1];").as_deref(),
+        Some("at declarations[0].value.elements[1]: Expression::Number `2` != nothing")
+    );
+
+    let a = parse("const a: number = 1;");
+    let b = parse("const a = 1;");
+    let difference = diff(module(&a), module(&b)).unwrap();
+    assert_eq!(difference.path.to_string(), "declarations[0].type_");
+    assert!(difference.left.is_some());
+    assert_eq!(difference.right, None);
+}
+
+#[test]
+fn diff_paths_are_relative_to_the_nodes_compared() {
+    let a = parse("const a = f(1, 2);");
+    let b = parse("main f(1, 3);");
+    let difference = diff(first_value(&a), first_value(&b)).unwrap();
+    assert_eq!(difference.path.to_string(), "parameters[1]");
+    assert_eq!(
+        difference.to_string(),
+        "at parameters[1]: Expression::Number `2` != Expression::Number `3`"
+    );
+}
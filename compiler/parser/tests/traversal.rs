@@ -0,0 +1,210 @@
+use std::ops::ControlFlow;
+
+use seglisp::parse::ParseNode;
+use serendipity_parser::{
+    fold::{self, Fold, TryFold},
+    incremental::parse_module,
+    visit::{self, Visit},
+    visit_mut::{self, VisitMut},
+    Expression, Module, Verbatim,
+};
+
+const SOURCE: &str = "fn f(x: T) -> g(x, 1 + y);\nmain f(2);\n";
+
+/// Every expression and identifier of `SOURCE`, in the order a traversal reaches them.
+const ORDER: &[&str] = &[
+    "ident:f", "ident:x", "ident:T", "call", "g", "x", "arith", "1", "y", "call", "f", "2",
+];
+
+/// The names `Renamer` gives to the `Expression::Name`s it reaches, in order.
+const NEW_NAMES: &[&str] = &["a", "b", "c", "d"];
+
+fn label(expression: &Expression) -> String {
+    match expression {
+        Expression::Name(name) | Expression::Number(name) => name.to_string(),
+        Expression::Call { .. } => "call".into(),
+        Expression::Arithmetic { .. } => "arith".into(),
+        _ => "other".into(),
+    }
+}
+
+fn parse(source: &str) -> ParseNode<Module<'_>> {
+    parse_module(source)
+        .module
+        .expect("the module does not parse")
+}
+
+/// Records every expression and identifier it visits, and stops at the first `Name` called
+/// `stop_at`.
+#[derive(Default)]
+struct Recorder {
+    seen: Vec<String>,
+    stop_at: Option<&'static str>,
+}
+
+impl<'ast> Visit<'ast> for Recorder {
+    fn visit_expression(&mut self, node: &'ast ParseNode<Expression<'ast>>) -> ControlFlow<()> {
+        self.seen.push(label(&node.value));
+
+        match (&node.value, self.stop_at) {
+            (Expression::Name(name), Some(stop_at)) if *name == stop_at => ControlFlow::Break(()),
+            _ => visit::walk_expression(self, node),
+        }
+    }
+
+    fn visit_identifier(&mut self, node: &'ast Verbatim<'ast>) -> ControlFlow<()> {
+        self.seen.push(format!("ident:{}", node.value));
+        ControlFlow::Continue(())
+    }
+}
+
+fn record(module: &ParseNode<Module>) -> Vec<String> {
+    let mut recorder = Recorder::default();
+    assert!(recorder.visit_module(module).is_continue());
+    recorder.seen
+}
+
+#[test]
+fn visit_reaches_nodes_in_source_order() {
+    assert_eq!(record(&parse(SOURCE)), ORDER);
+}
+
+#[test]
+fn visit_stops_at_break() {
+    let module = parse(SOURCE);
+    let mut recorder = Recorder {
+        stop_at: Some("x"),
+        ..Default::default()
+    };
+
+    assert!(recorder.visit_module(&module).is_break());
+    assert_eq!(recorder.seen, ORDER[..6]);
+}
+
+/// Renames every `Expression::Name` it reaches after `NEW_NAMES`, and stops once it has renamed
+/// `limit` of them.
+struct Renamer {
+    renamed: usize,
+    limit: usize,
+}
+
+impl Renamer {
+    fn new(limit: usize) -> Self {
+        Renamer { renamed: 0, limit }
+    }
+
+    fn rename(&mut self, expression: &mut Expression) -> ControlFlow<()> {
+        if self.renamed == self.limit {
+            return ControlFlow::Break(());
+        }
+
+        if let Expression::Name(name) = expression {
+            *name = NEW_NAMES[self.renamed];
+            self.renamed += 1;
+        }
+
+        ControlFlow::Continue(())
+    }
+}
+
+impl<'ast> VisitMut<'ast> for Renamer {
+    fn visit_expression_mut(&mut self, node: &mut ParseNode<Expression<'ast>>) -> ControlFlow<()> {
+        self.rename(&mut node.value)?;
+        visit_mut::walk_expression_mut(self, node)
+    }
+}
+
+impl<'ast> Fold<'ast> for Renamer {
+    fn fold_expression(
+        &mut self,
+        node: ParseNode<Expression<'ast>>,
+    ) -> ParseNode<Expression<'ast>> {
+        let mut node = fold::walk_expression(self, node);
+        let _ = self.rename(&mut node.value);
+        node
+    }
+}
+
+#[test]
+fn visit_mut_reaches_nodes_in_source_order() {
+    let mut module = parse(SOURCE);
+
+    assert!(Renamer::new(usize::MAX)
+        .visit_module_mut(&mut module)
+        .is_continue());
+    assert_eq!(
+        record(&module),
+        ["ident:f", "ident:x", "ident:T", "call", "a", "b", "arith", "1", "c", "call", "d", "2"]
+    );
+}
+
+#[test]
+fn visit_mut_stops_at_break() {
+    let mut module = parse(SOURCE);
+
+    assert!(Renamer::new(2).visit_module_mut(&mut module).is_break());
+    assert_eq!(
+        record(&module),
+        ["ident:f", "ident:x", "ident:T", "call", "a", "b", "arith", "1", "y", "call", "f", "2"]
+    );
+}
+
+#[test]
+fn fold_reaches_nodes_in_source_order_and_keeps_spans() {
+    let module = parse(SOURCE);
+    let span = (module.range.0.absolute, module.range.1.absolute);
+
+    let folded = Renamer::new(usize::MAX).fold_module(module);
+
+    assert_eq!((folded.range.0.absolute, folded.range.1.absolute), span);
+    assert_eq!(
+        record(&folded),
+        ["ident:f", "ident:x", "ident:T", "call", "a", "b", "arith", "1", "c", "call", "d", "2"]
+    );
+}
+
+/// Records the expressions it folds, and fails at the first hole.
+#[derive(Default)]
+struct HoleFinder {
+    seen: Vec<String>,
+}
+
+impl<'ast> TryFold<'ast> for HoleFinder {
+    type Error = usize;
+
+    fn try_fold_expression(
+        &mut self,
+        node: ParseNode<Expression<'ast>>,
+    ) -> Result<ParseNode<Expression<'ast>>, usize> {
+        self.seen.push(label(&node.value));
+
+        match node.value {
+            Expression::Hole => Err(node.range.0.absolute),
+            _ => fold::try_walk_expression(self, node),
+        }
+    }
+}
+
+#[test]
+fn try_fold_succeeds_without_errors() {
+    let mut finder = HoleFinder::default();
+
+    let folded = finder.try_fold_module(parse(SOURCE));
+
+    assert!(folded.is_ok());
+    assert_eq!(
+        finder.seen,
+        ["call", "g", "x", "arith", "1", "y", "call", "f", "2"]
+    );
+}
+
+#[test]
+fn try_fold_stops_at_the_first_error() {
+    let source = "main g(x, @, y, @);\n";
+    let mut finder = HoleFinder::default();
+
+    let folded = finder.try_fold_module(parse(source));
+
+    assert_eq!(folded.err(), source.find('@'));
+    assert_eq!(finder.seen, ["call", "g", "x", "other"]);
+}
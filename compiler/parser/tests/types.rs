@@ -0,0 +1,90 @@
+use serendipity_parser::{incremental::parse_module, types::TypeEnv, Declaration};
+
+/// Infers the type of `expression` with `declarations` in scope.
+fn type_of(declarations: &str, expression: &str) -> String {
+    let source = format!("{declarations}\nmain {expression};\n");
+    let module = parse_module(&source)
+        .module
+        .expect("the source does not parse");
+
+    let (main, declarations) = module.value.declarations.split_last().unwrap();
+    let Declaration::Main { body, .. } = &main.value else {
+        panic!("the last declaration is not `main`");
+    };
+
+    let mut env = TypeEnv::for_declarations(declarations.iter().map(|d| &d.value));
+    env.infer(&body.value).to_string()
+}
+
+/// The type of the top-level binding `name` in `source`.
+fn binding_type(source: &str, name: &str) -> String {
+    let module = parse_module(source).module.unwrap();
+    let env = TypeEnv::for_declarations(module.value.declarations.iter().map(|d| &d.value));
+
+    env.lookup(name)
+        .unwrap_or_else(|| panic!("'{name}' is not bound"))
+        .to_string()
+}
+
+#[test]
+fn literals_and_operators() {
+    assert_eq!(type_of("", "1 + 2"), "number");
+    assert_eq!(type_of("", "\"a\" + 1"), "string");
+    assert_eq!(type_of("", "1 < 2"), "boolean");
+    assert_eq!(type_of("", "-1"), "number");
+    assert_eq!(type_of("", "none"), "none");
+    assert_eq!(type_of("", "unbound"), "_");
+}
+
+#[test]
+fn compound_values() {
+    assert_eq!(type_of("", "(1, \"a\")"), "(number, string)");
+    assert_eq!(type_of("", "[1, \"a\", 2]"), "List[number | string]");
+    assert_eq!(type_of("", "[1, 2][0]"), "number");
+    assert_eq!(
+        type_of("", "{ a: 1, b: \"x\" }"),
+        "{ a: number, b: string }"
+    );
+    assert_eq!(type_of("", "if true then 1 else \"a\""), "number | string");
+    assert_eq!(type_of("", "with (y = 1) y * 2"), "number");
+}
+
+#[test]
+fn functions_and_calls() {
+    let add = "fn add(x: number, y: number): number -> x + y;";
+
+    assert_eq!(type_of(add, "add"), "fn(number, number) -> number");
+    assert_eq!(type_of(add, "add(1, 2)"), "number");
+    assert_eq!(type_of(add, "add(1)"), "fn(number) -> number");
+    assert_eq!(
+        type_of("", "fn (s: string) -> s + \"!\""),
+        "fn(string) -> string"
+    );
+}
+
+#[test]
+fn interfaces_describe_fields() {
+    let declarations = "interface Named { name: string };\nconst n: Named = @;";
+
+    assert_eq!(type_of(declarations, "n"), "Named");
+    assert_eq!(type_of(declarations, "n.name"), "string");
+}
+
+#[test]
+fn the_examples_are_typed_from_their_declarations() {
+    assert_eq!(
+        binding_type(include_str!("../../../cli/slipr/examples/2param.sdp"), "f"),
+        "fn(_, _) -> number"
+    );
+    assert_eq!(
+        binding_type(
+            include_str!("../../../cli/slipr/examples/break.sdp"),
+            "naturals"
+        ),
+        "Seq[natural]"
+    );
+    assert_eq!(
+        binding_type(include_str!("../../../lib/core/lib.sdp"), "prompt"),
+        "fn(string | none) -> string"
+    );
+}